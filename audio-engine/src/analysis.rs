@@ -1,36 +0,0 @@
-// This module will responsible for analyzing audio data 
-// and generating min/max peak data for UI visualization.
-
-pub struct WaveformAnalyzer {
-    // Cache of computed peaks
-    // Key: Asset ID, Value: Vec of min/max pairs
-    cache: std::collections::HashMap<String, Vec<(f32, f32)>>,
-}
-
-impl WaveformAnalyzer {
-    pub fn new() -> Self {
-        Self {
-            cache: std::collections::HashMap::new(),
-        }
-    }
-
-    // In a real app, this would happen in a background thread/worker
-    pub fn analyze(&mut self, asset_id: &str, pcm: &[f32], samples_per_pixel: usize) {
-        let mut peaks = Vec::new();
-        
-        // Chunk the PCM data
-        for chunk in pcm.chunks(samples_per_pixel) {
-            let mut min = 0.0f32;
-            let mut max = 0.0f32;
-            
-            for sample in chunk {
-                if *sample < min { min = *sample; }
-                if *sample > max { max = *sample; }
-            }
-            
-            peaks.push((min, max));
-        }
-        
-        self.cache.insert(asset_id.to_string(), peaks);
-    }
-}
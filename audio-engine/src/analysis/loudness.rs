@@ -0,0 +1,288 @@
+// Loudness after ITU-R BS.1770-4 / EBU R128: K-weighted mean square in
+// 400 ms blocks (momentary), 3 s windows (short-term), gated integration and
+// loudness range (EBU Tech 3342), plus 4x oversampled true peak.
+
+use serde::Serialize;
+use std::f64::consts::PI;
+
+// Blocks are built from 100 ms steps: 4 make a momentary block (75% overlap)
+// and 30 a short-term window
+const STEP_SECONDS: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0; // Integrated
+const RANGE_GATE: f64 = -20.0;    // Loudness range
+const RANGE_LOW: f64 = 0.10;      // Percentiles spanned by the range
+const RANGE_HIGH: f64 = 0.95;
+
+// True peak: 4x oversampling through a windowed-sinc interpolator
+const OVERSAMPLING: usize = 4;
+const TAPS_PER_PHASE: usize = 16;
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy > 0.0 { -0.691 + 10.0 * energy.log10() } else { f64::NEG_INFINITY }
+}
+
+fn lufs_to_energy(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+// Channel weights for L, R, C, LFE, Ls, Rs. LFE is left out, surrounds count more.
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+// The two K-weighting stages for any sample rate, from the analog prototypes
+// behind the 48 kHz coefficients in the standard
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    // Stage 1: high shelf, +4 dB above ~1.7 kHz (head diffraction)
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    // Stage 2: RLB high-pass at ~38 Hz
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+/// Measured loudness of a whole programme. Levels with nothing above the
+/// gates are -inf (null in JSON).
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct LoudnessStats {
+    pub integrated: f64,        // LUFS
+    pub range: f64,             // LU
+    pub momentary_max: f64,     // LUFS
+    pub short_term_max: f64,    // LUFS
+    pub true_peak: f64,         // dBTP
+}
+
+/// Streaming loudness meter over interleaved samples. Keeps every block for
+/// the gated values, so it's meant for offline use rather than the audio thread.
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    step_len: usize,
+    step_pos: usize,
+    step_energy: f64,      // Weighted sum of squares of the step in progress
+    steps: Vec<f64>,       // Mean weighted energy of each finished step
+    blocks: Vec<f64>,      // Energy of every 400 ms block
+    short_terms: Vec<f64>, // Energy of every 3 s window
+    true_peak: TruePeakMeter,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let sample_rate = sample_rate.max(1.0) as f64;
+        Self {
+            channels,
+            weights: (0..channels).map(|c| channel_weight(c, channels)).collect(),
+            filters: vec![k_weighting(sample_rate); channels],
+            step_len: ((sample_rate * STEP_SECONDS).round() as usize).max(1),
+            step_pos: 0,
+            step_energy: 0.0,
+            steps: Vec::new(),
+            blocks: Vec::new(),
+            short_terms: Vec::new(),
+            true_peak: TruePeakMeter::new(channels),
+        }
+    }
+
+    pub fn process(&mut self, interleaved: &[f32]) {
+        self.true_peak.process(interleaved);
+        for frame in interleaved.chunks_exact(self.channels) {
+            for (c, sample) in frame.iter().enumerate() {
+                let [shelf, high_pass] = &mut self.filters[c];
+                let y = high_pass.process(shelf.process(*sample as f64));
+                self.step_energy += self.weights[c] * y * y;
+            }
+            self.step_pos += 1;
+            if self.step_pos == self.step_len {
+                self.finish_step();
+            }
+        }
+    }
+
+    fn finish_step(&mut self) {
+        self.steps.push(self.step_energy / self.step_len as f64);
+        self.step_energy = 0.0;
+        self.step_pos = 0;
+
+        let n = self.steps.len();
+        if n >= MOMENTARY_STEPS {
+            self.blocks.push(self.window_energy(MOMENTARY_STEPS));
+        }
+        if n >= SHORT_TERM_STEPS {
+            self.short_terms.push(self.window_energy(SHORT_TERM_STEPS));
+        }
+    }
+
+    fn window_energy(&self, steps: usize) -> f64 {
+        let n = self.steps.len();
+        if n < steps { return 0.0; }
+        self.steps[n - steps..].iter().sum::<f64>() / steps as f64
+    }
+
+    /// Last 400 ms
+    pub fn momentary(&self) -> f64 {
+        energy_to_lufs(self.window_energy(MOMENTARY_STEPS))
+    }
+
+    /// Last 3 s
+    pub fn short_term(&self) -> f64 {
+        energy_to_lufs(self.window_energy(SHORT_TERM_STEPS))
+    }
+
+    /// Gated loudness of everything so far
+    pub fn integrated(&self) -> f64 {
+        let gated = gate(&self.blocks, RELATIVE_GATE);
+        if gated.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+        energy_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64)
+    }
+
+    /// Spread between the 10th and 95th percentile of gated short-term loudness
+    pub fn loudness_range(&self) -> f64 {
+        let mut levels: Vec<f64> = gate(&self.short_terms, RANGE_GATE).into_iter().map(energy_to_lufs).collect();
+        if levels.is_empty() {
+            return 0.0;
+        }
+        levels.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| levels[((levels.len() - 1) as f64 * p).round() as usize];
+        percentile(RANGE_HIGH) - percentile(RANGE_LOW)
+    }
+
+    /// Highest inter-sample peak so far, dBTP
+    pub fn true_peak(&self) -> f64 {
+        self.true_peak.peak_db()
+    }
+
+    pub fn stats(&self) -> LoudnessStats {
+        let loudest = |energies: &[f64]| energy_to_lufs(energies.iter().copied().fold(0.0, f64::max));
+        LoudnessStats {
+            integrated: self.integrated(),
+            range: self.loudness_range(),
+            momentary_max: loudest(&self.blocks),
+            short_term_max: loudest(&self.short_terms),
+            true_peak: self.true_peak(),
+        }
+    }
+}
+
+// Blocks above the absolute gate and `relative` LU under their mean
+fn gate(energies: &[f64], relative: f64) -> Vec<f64> {
+    let floor = lufs_to_energy(ABSOLUTE_GATE);
+    let loud: Vec<f64> = energies.iter().copied().filter(|e| *e > floor).collect();
+    if loud.is_empty() {
+        return loud;
+    }
+    let mean = loud.iter().sum::<f64>() / loud.len() as f64;
+    let threshold = lufs_to_energy(energy_to_lufs(mean) + relative);
+    loud.into_iter().filter(|e| *e > threshold).collect()
+}
+
+/// Loudness and true peak of interleaved samples
+pub fn measure(interleaved: &[f32], channels: usize, sample_rate: f32) -> LoudnessStats {
+    let mut meter = LoudnessMeter::new(sample_rate, channels);
+    meter.process(interleaved);
+    meter.stats()
+}
+
+/// Peak of the signal between samples, estimated by 4x oversampling
+pub struct TruePeakMeter {
+    channels: usize,
+    kernel: Vec<[f32; TAPS_PER_PHASE]>, // Per phase
+    history: Vec<[f32; TAPS_PER_PHASE]>, // Recent input per channel, newest last
+    peak: f32,
+}
+
+impl TruePeakMeter {
+    pub fn new(channels: usize) -> Self {
+        let len = OVERSAMPLING * TAPS_PER_PHASE;
+        let centre = (len - 1) as f64 / 2.0;
+        // Blackman-windowed sinc, low-pass at the original Nyquist
+        let tap = |i: usize| {
+            let x = (i as f64 - centre) / OVERSAMPLING as f64;
+            let sinc = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            let w = 2.0 * PI * i as f64 / (len - 1) as f64;
+            sinc * (0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos())
+        };
+        let kernel = (0..OVERSAMPLING)
+            .map(|phase| {
+                let mut taps = [0.0; TAPS_PER_PHASE];
+                for (k, t) in taps.iter_mut().enumerate() {
+                    *t = tap(k * OVERSAMPLING + phase) as f32;
+                }
+                taps
+            })
+            .collect();
+        Self {
+            channels: channels.max(1),
+            kernel,
+            history: vec![[0.0; TAPS_PER_PHASE]; channels.max(1)],
+            peak: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, interleaved: &[f32]) {
+        for frame in interleaved.chunks_exact(self.channels) {
+            for (c, sample) in frame.iter().enumerate() {
+                let history = &mut self.history[c];
+                history.rotate_left(1);
+                history[TAPS_PER_PHASE - 1] = *sample;
+                // The input samples themselves always count
+                self.peak = self.peak.max(sample.abs());
+                for taps in &self.kernel {
+                    let y: f32 = taps.iter().rev().zip(history.iter()).map(|(t, x)| t * x).sum();
+                    self.peak = self.peak.max(y.abs());
+                }
+            }
+        }
+    }
+
+    pub fn peak_db(&self) -> f64 {
+        if self.peak > 0.0 { 20.0 * (self.peak as f64).log10() } else { f64::NEG_INFINITY }
+    }
+}
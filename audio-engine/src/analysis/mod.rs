@@ -0,0 +1,46 @@
+pub mod loudness;
+pub mod waveform;
+
+use std::collections::HashMap;
+use waveform::PeakPyramid;
+
+// Analyzes audio data and keeps the min/max/RMS peak pyramids
+// used to draw waveforms
+
+pub struct WaveformAnalyzer {
+    // Key: Asset ID
+    cache: HashMap<String, PeakPyramid>,
+}
+
+impl Default for WaveformAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaveformAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Build the pyramid for an asset from its channels (left, right)
+    pub fn analyze(&mut self, asset_id: &str, channels: &[&[f32]]) -> &PeakPyramid {
+        self.cache.insert(asset_id.to_string(), PeakPyramid::new(channels));
+        &self.cache[asset_id]
+    }
+
+    /// Keep a pyramid made elsewhere, e.g. fetched from the backend
+    pub fn insert(&mut self, asset_id: &str, pyramid: PeakPyramid) {
+        self.cache.insert(asset_id.to_string(), pyramid);
+    }
+
+    pub fn get(&self, asset_id: &str) -> Option<&PeakPyramid> {
+        self.cache.get(asset_id)
+    }
+
+    pub fn remove(&mut self, asset_id: &str) -> Option<PeakPyramid> {
+        self.cache.remove(asset_id)
+    }
+}
@@ -0,0 +1,260 @@
+// Waveform overview: min, max and RMS per channel at power-of-two zoom
+// levels. Level 0 holds one peak per BASE_FRAMES frames, every level above
+// merges pairs from the one below, up to a single peak for the whole sound.
+// Drawing at any zoom reads a few peaks per pixel from the closest level.
+//
+// Binary form, little-endian:
+//   "PEAK" | version u8 | channels u8 | log2 of level 0 frames u8 | 0 u8 | frames u64
+//   then every level from 0 up, each peak per channel in turn:
+//   min i16 | max i16 | rms u16   (full scale = 1.0, min rounded down, max up)
+
+use serde::Serialize;
+
+const MAGIC: &[u8; 4] = b"PEAK";
+const VERSION: u8 = 1;
+const HEADER_BYTES: usize = 16;
+const PEAK_BYTES: usize = 6;
+
+// Level 0 spans 2^BASE_SHIFT frames per peak
+pub const BASE_SHIFT: u8 = 8;
+pub const BASE_FRAMES: usize = 1 << BASE_SHIFT;
+
+// Stereo at most, more channels are left out
+pub const MAX_CHANNELS: usize = 2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Peak {
+    pub min: f32,
+    pub max: f32,
+    pub rms: f32,
+}
+
+impl Peak {
+    // Silence for an empty slice
+    fn of(samples: &[f32]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut peak = Self { min: f32::INFINITY, max: f32::NEG_INFINITY, rms: 0.0 };
+        let mut energy = 0.0f64;
+        for &s in samples {
+            peak.min = peak.min.min(s);
+            peak.max = peak.max.max(s);
+            energy += s as f64 * s as f64;
+        }
+        peak.rms = (energy / samples.len() as f64).sqrt() as f32;
+        peak
+    }
+}
+
+// Peaks merged across buckets of different lengths, RMS weighted by frames
+struct Merge {
+    min: f32,
+    max: f32,
+    energy: f64,
+    frames: u64,
+}
+
+impl Merge {
+    fn new() -> Self {
+        Self { min: f32::INFINITY, max: f32::NEG_INFINITY, energy: 0.0, frames: 0 }
+    }
+
+    fn add(&mut self, peak: Peak, frames: u64) {
+        self.min = self.min.min(peak.min);
+        self.max = self.max.max(peak.max);
+        self.energy += peak.rms as f64 * peak.rms as f64 * frames as f64;
+        self.frames += frames;
+    }
+
+    fn finish(self) -> Peak {
+        if self.frames == 0 {
+            return Peak::default();
+        }
+        Peak { min: self.min, max: self.max, rms: (self.energy / self.frames as f64).sqrt() as f32 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeakPyramid {
+    channels: usize,
+    frames: u64,
+    base_shift: u8,
+    levels: Vec<Vec<Peak>>, // Level k: peaks of every channel in turn
+}
+
+// Peaks per channel at each level, finest first
+fn level_sizes(frames: u64, base_shift: u8) -> Vec<u64> {
+    let mut sizes = Vec::new();
+    if frames == 0 {
+        return sizes;
+    }
+    let mut count = frames.div_ceil(1 << base_shift);
+    loop {
+        sizes.push(count);
+        if count == 1 {
+            return sizes;
+        }
+        count = count.div_ceil(2);
+    }
+}
+
+impl PeakPyramid {
+    /// Build from planar audio, one slice per channel. Shorter channels are
+    /// silent past their end.
+    pub fn new(channels: &[&[f32]]) -> Self {
+        let channels = &channels[..channels.len().min(MAX_CHANNELS)];
+        let frames = channels.iter().map(|c| c.len()).max().unwrap_or(0) as u64;
+        let mut pyramid = Self { channels: channels.len(), frames, base_shift: BASE_SHIFT, levels: Vec::new() };
+
+        let sizes = level_sizes(frames, BASE_SHIFT);
+        let Some(&base_count) = sizes.first() else { return pyramid };
+        let mut base = Vec::with_capacity(base_count as usize * channels.len());
+        for i in 0..base_count as usize {
+            for channel in channels {
+                let start = (i * BASE_FRAMES).min(channel.len());
+                let end = ((i + 1) * BASE_FRAMES).min(channel.len());
+                base.push(Peak::of(&channel[start..end]));
+            }
+        }
+        pyramid.levels.push(base);
+
+        for (level, &count) in sizes.iter().enumerate().skip(1) {
+            let below = &pyramid.levels[level - 1];
+            let mut peaks = Vec::with_capacity(count as usize * pyramid.channels);
+            for i in 0..count as usize {
+                for c in 0..pyramid.channels {
+                    let mut merge = Merge::new();
+                    for j in [2 * i, 2 * i + 1] {
+                        if let Some(&peak) = below.get(j * pyramid.channels + c) {
+                            merge.add(peak, pyramid.bucket_frames(level - 1, j as u64));
+                        }
+                    }
+                    peaks.push(merge.finish());
+                }
+            }
+            pyramid.levels.push(peaks);
+        }
+        pyramid
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn frames_per_peak(&self, level: usize) -> u64 {
+        1 << (self.base_shift as usize + level)
+    }
+
+    /// Peaks of one channel at one level
+    pub fn level(&self, level: usize, channel: usize) -> Vec<Peak> {
+        match self.levels.get(level) {
+            Some(peaks) if channel < self.channels => {
+                peaks.iter().skip(channel).step_by(self.channels).copied().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Frames covered by a bucket, the last one of a level may be short
+    fn bucket_frames(&self, level: usize, index: u64) -> u64 {
+        let size = self.frames_per_peak(level);
+        size.min(self.frames.saturating_sub(index * size))
+    }
+
+    /// One peak per pixel for frames `start..end` of `channel`. Each pixel
+    /// merges every stored peak it touches, from the coarsest level still
+    /// finer than a pixel, so narrow transients never fall between pixels.
+    /// Pixels past the end are silent.
+    pub fn query(&self, channel: usize, start: u64, end: u64, pixels: usize) -> Vec<Peak> {
+        if pixels == 0 {
+            return Vec::new();
+        }
+        if channel >= self.channels || self.levels.is_empty() || end <= start {
+            return vec![Peak::default(); pixels];
+        }
+        let span = (end - start) as f64 / pixels as f64;
+        let level = (0..self.levels.len())
+            .take_while(|&level| self.frames_per_peak(level) as f64 <= span)
+            .last()
+            .unwrap_or(0);
+        let size = self.frames_per_peak(level);
+        let peaks = &self.levels[level];
+        let count = (peaks.len() / self.channels) as u64;
+
+        (0..pixels)
+            .map(|p| {
+                let from = start + (p as f64 * span) as u64;
+                let to = (start + ((p + 1) as f64 * span) as u64).max(from + 1).min(self.frames);
+                if from >= to {
+                    return Peak::default();
+                }
+                let mut merge = Merge::new();
+                for bucket in from / size..((to - 1) / size + 1).min(count) {
+                    let peak = peaks[bucket as usize * self.channels + channel];
+                    merge.add(peak, self.bucket_frames(level, bucket));
+                }
+                merge.finish()
+            })
+            .collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let peaks: usize = self.levels.iter().map(|level| level.len()).sum();
+        let mut out = Vec::with_capacity(HEADER_BYTES + peaks * PEAK_BYTES);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&[VERSION, self.channels as u8, self.base_shift, 0]);
+        out.extend_from_slice(&self.frames.to_le_bytes());
+        for peak in self.levels.iter().flatten() {
+            let min = (peak.min.clamp(-1.0, 1.0) * 32767.0).floor() as i16;
+            let max = (peak.max.clamp(-1.0, 1.0) * 32767.0).ceil() as i16;
+            let rms = (peak.rms.clamp(0.0, 1.0) * 65535.0).round() as u16;
+            out.extend_from_slice(&min.to_le_bytes());
+            out.extend_from_slice(&max.to_le_bytes());
+            out.extend_from_slice(&rms.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_BYTES || &bytes[..4] != MAGIC {
+            return Err("Not a peak file".to_string());
+        }
+        if bytes[4] != VERSION {
+            return Err(format!("Unknown peak file version {}", bytes[4]));
+        }
+        let channels = bytes[5] as usize;
+        let base_shift = bytes[6];
+        if !(1..=MAX_CHANNELS).contains(&channels) || base_shift > 32 {
+            return Err("Corrupt peak file header".to_string());
+        }
+        let frames = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+
+        // Check the size before allocating anything
+        let sizes = level_sizes(frames, base_shift);
+        let expected = sizes.iter()
+            .try_fold(0u64, |sum, &n| sum.checked_add(n.checked_mul((channels * PEAK_BYTES) as u64)?))
+            .and_then(|body| body.checked_add(HEADER_BYTES as u64));
+        if expected != Some(bytes.len() as u64) {
+            return Err("Peak file is truncated or too long".to_string());
+        }
+
+        let mut data = bytes[HEADER_BYTES..].chunks_exact(PEAK_BYTES).map(|b| Peak {
+            min: i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0,
+            max: i16::from_le_bytes([b[2], b[3]]) as f32 / 32767.0,
+            rms: u16::from_le_bytes([b[4], b[5]]) as f32 / 65535.0,
+        });
+        let levels = sizes.iter()
+            .map(|&n| data.by_ref().take(n as usize * channels).collect())
+            .collect();
+        Ok(Self { channels, frames, base_shift, levels })
+    }
+}
@@ -0,0 +1,327 @@
+use shared::{AutomationLane, AutomationMode, AutomationPoint, CurveType, ParamAddress, ParamScale, RecordedAutomation};
+
+// Render buffers start at this size and only grow if a block is longer
+const INITIAL_BLOCK_SIZE: usize = 8192;
+
+// Recorded points reserved per armed lane, about 10 s of constant fader moves
+const RECORD_CAPACITY: usize = 4096;
+
+// Recorded passes are thinned to stay within this distance (normalized)
+const RECORD_TOLERANCE: f32 = 0.002;
+
+// Effect and filter lanes are applied this often within a block
+pub const AUTOMATION_STEP: usize = 16;
+
+/// Lanes set every `AUTOMATION_STEP` samples while processing, the levels
+/// read their curves per sample and instruments take one value per block
+pub fn is_stepped(address: &ParamAddress) -> bool {
+    matches!(address, ParamAddress::TrackFilter | ParamAddress::Effect { .. } | ParamAddress::BusEffect { .. })
+}
+
+// Capture state of one lane in a write mode. Buffers are reserved when the
+// lane is armed, recording and finishing a pass don't allocate.
+struct LaneRecorder {
+    active: bool,
+    value: f32,           // Control value, normalized
+    start: f64,
+    last: (f64, f32),     // Last captured point
+    block_time: f64,      // Start of the previous block
+    pass: AutomationLane, // Points captured so far
+    keep: Vec<bool>,      // Thinning scratch
+    changed: Option<(f64, f64)>, // Span of the lane rewritten since `take_recorded`
+}
+
+impl LaneRecorder {
+    fn new() -> Self {
+        Self {
+            active: false,
+            value: 0.0,
+            start: 0.0,
+            last: (0.0, 0.0),
+            block_time: 0.0,
+            pass: AutomationLane::new(String::new()),
+            keep: Vec::new(),
+            changed: None,
+        }
+    }
+
+    fn arm(&mut self) {
+        if self.pass.points.capacity() < RECORD_CAPACITY {
+            self.pass.points.reserve(RECORD_CAPACITY);
+            self.keep.reserve(RECORD_CAPACITY);
+        }
+    }
+
+    fn begin(&mut self, time: f64, value: f32) {
+        self.active = true;
+        self.value = value;
+        self.start = time;
+        self.block_time = time;
+        self.pass.points.clear();
+        self.push(time, value);
+    }
+
+    fn push(&mut self, time: f64, value: f32) {
+        let points = &self.pass.points;
+        if !points.is_empty() && points.len() == points.capacity() {
+            // Full: thin what's there rather than grow, at worst drop the last point
+            self.pass.thin_with(RECORD_TOLERANCE, &mut self.keep);
+            if self.pass.points.len() == self.pass.points.capacity() {
+                self.pass.points.pop();
+            }
+        }
+        self.pass.points.push(AutomationPoint { time, value, curve: CurveType::Linear });
+        self.last = (time, value);
+    }
+}
+
+/// Automation lanes with their parsed targets. Each block every lane is
+/// rendered to one value per sample, in real units (gains as linear factors).
+/// Buffers are allocated when the lanes are set, not on the audio thread.
+///
+/// Lanes in a write mode also capture control changes (`write`) during
+/// playback. A lane being written isn't rendered, so the control wins.
+/// Finished passes are merged into the lane, `take_recorded` reports them.
+pub struct AutomationSet {
+    lanes: Vec<AutomationLane>,
+    targets: Vec<Option<ParamAddress>>,
+    buffers: Vec<Vec<f32>>,
+    samples: usize, // Length rendered this block, 0 = nothing rendered
+    recorders: Vec<LaneRecorder>,
+}
+
+impl AutomationSet {
+    /// Lanes whose target doesn't parse, or fails `accept`, are kept but never rendered
+    pub fn new(lanes: Vec<AutomationLane>, accept: impl Fn(&ParamAddress) -> bool) -> Self {
+        let targets = lanes.iter()
+            .map(|lane| ParamAddress::parse(&lane.target).filter(|a| accept(a)))
+            .collect();
+        let buffers = lanes.iter().map(|_| vec![0.0; INITIAL_BLOCK_SIZE]).collect();
+        let recorders = lanes.iter().map(|_| LaneRecorder::new()).collect();
+        let mut set = Self { lanes, targets, buffers, samples: 0, recorders };
+        for index in 0..set.lanes.len() {
+            if set.lanes[index].mode != AutomationMode::Read {
+                set.arm(index);
+            }
+        }
+        set
+    }
+
+    pub fn empty() -> Self {
+        Self::new(Vec::new(), |_| true)
+    }
+
+    pub fn lanes(&self) -> &[AutomationLane] {
+        &self.lanes
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    fn index_of(&self, address: ParamAddress) -> Option<usize> {
+        self.targets.iter().position(|t| *t == Some(address))
+    }
+
+    // Room for a pass in the recorder and for merging it into the lane
+    fn arm(&mut self, index: usize) {
+        self.recorders[index].arm();
+        let points = &mut self.lanes[index].points;
+        if points.capacity() < points.len() + RECORD_CAPACITY {
+            points.reserve(RECORD_CAPACITY);
+        }
+    }
+
+    /// Change a lane's mode, adding the lane if it doesn't exist yet.
+    /// Leaving a write mode ends the pass at `time`.
+    pub fn set_mode(&mut self, address: ParamAddress, mode: AutomationMode, time: f64) {
+        let index = match self.index_of(address) {
+            Some(index) => index,
+            None => {
+                self.lanes.push(AutomationLane::new(address.to_string()));
+                self.targets.push(Some(address));
+                self.buffers.push(vec![0.0; INITIAL_BLOCK_SIZE]);
+                self.recorders.push(LaneRecorder::new());
+                self.lanes.len() - 1
+            }
+        };
+        if mode == AutomationMode::Read {
+            self.finish_pass(index, time);
+        } else {
+            self.arm(index);
+        }
+        self.lanes[index].mode = mode;
+    }
+
+    /// A control moved to `value` (normalized). Returns true if it was captured.
+    pub fn write(&mut self, address: ParamAddress, value: f32, time: f64, playing: bool) -> bool {
+        let Some(index) = self.index_of(address) else { return false };
+        if !playing || self.lanes[index].mode == AutomationMode::Read {
+            return false;
+        }
+        let recorder = &mut self.recorders[index];
+        if recorder.active {
+            recorder.value = value;
+        } else {
+            recorder.begin(time, value);
+        }
+        true
+    }
+
+    /// The control was let go: a touch pass ends, other modes keep going
+    pub fn release(&mut self, address: ParamAddress, time: f64) {
+        if let Some(index) = self.index_of(address) {
+            if self.lanes[index].mode == AutomationMode::Touch {
+                self.finish_pass(index, time);
+            }
+        }
+    }
+
+    /// Capture this block's control values, `time` is the block start in seconds.
+    /// Call before `render` while the transport runs.
+    pub fn record(&mut self, time: f64) {
+        for index in 0..self.lanes.len() {
+            let mode = self.lanes[index].mode;
+            if mode == AutomationMode::Read { continue; }
+            let Some(target) = self.targets[index] else { continue };
+
+            let recorder = &mut self.recorders[index];
+            if recorder.active && time < recorder.block_time {
+                // Jumped back (loop): close this pass, carry on writing from here
+                let value = recorder.value;
+                self.finish_pass(index, self.recorders[index].block_time);
+                self.recorders[index].begin(time, value);
+                continue;
+            }
+
+            if !recorder.active {
+                // Write mode takes over from the first block, starting at what was playing
+                if mode == AutomationMode::Write {
+                    let lane = &self.lanes[index];
+                    let value = if lane.points.is_empty() {
+                        target.range().normalize(target.range().default)
+                    } else {
+                        lane.get_value_at(time)
+                    };
+                    recorder.begin(time, value);
+                }
+                continue;
+            }
+
+            if recorder.value != recorder.last.1 {
+                // Hold the old value up to the previous block, then move
+                if recorder.last.0 < recorder.block_time {
+                    let held = (recorder.block_time, recorder.last.1);
+                    recorder.push(held.0, held.1);
+                }
+                let value = recorder.value;
+                recorder.push(time, value);
+            }
+            recorder.block_time = time;
+        }
+    }
+
+    /// End every pass (transport stopped or jumped)
+    pub fn finish(&mut self, time: f64) {
+        for index in 0..self.lanes.len() {
+            self.finish_pass(index, time);
+        }
+    }
+
+    fn finish_pass(&mut self, index: usize, time: f64) {
+        let recorder = &mut self.recorders[index];
+        if !recorder.active { return; }
+        recorder.active = false;
+
+        let (start, end) = (recorder.start, time.max(recorder.last.0));
+        let value = recorder.value;
+        recorder.push(end, value);
+        recorder.pass.thin_with(RECORD_TOLERANCE, &mut recorder.keep);
+        let lane = &mut self.lanes[index];
+        let (from, to) = lane.delete_span(start, end);
+        lane.merge_recording(start, end, &recorder.pass.points);
+        recorder.pass.points.clear();
+
+        recorder.changed = Some(match recorder.changed {
+            Some((a, b)) => (a.min(from), b.max(to)),
+            None => (from, to),
+        });
+    }
+
+    /// What passes rewrote since the last call, one entry per lane covering
+    /// all of its passes, without a track id. Allocates, call it from the
+    /// control side rather than the audio callback.
+    pub fn take_recorded(&mut self) -> Vec<RecordedAutomation> {
+        self.recorders.iter_mut().zip(self.lanes.iter())
+            .filter_map(|(recorder, lane)| {
+                let (start, end) = recorder.changed.take()?;
+                Some(RecordedAutomation {
+                    track_id: None,
+                    target: lane.target.clone(),
+                    start,
+                    end,
+                    points: lane.points.iter().filter(|p| p.time >= start && p.time <= end).copied().collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Render `samples` values per lane, starting at `start` seconds and
+    /// advancing `step` seconds per sample
+    pub fn render(&mut self, start: f64, step: f64, samples: usize) {
+        self.samples = samples;
+        for (((lane, target), buf), recorder) in self.lanes.iter_mut().zip(self.targets.iter()).zip(self.buffers.iter_mut()).zip(self.recorders.iter()) {
+            let Some(target) = target else { continue };
+            if lane.points.is_empty() || recorder.active { continue; }
+            if buf.len() < samples {
+                buf.resize(samples, 0.0);
+            }
+
+            let out = &mut buf[..samples];
+            lane.render(start, step, out);
+
+            let range = target.range();
+            let is_gain = range.scale == ParamScale::Decibel;
+            for v in out.iter_mut() {
+                let real = range.denormalize(*v);
+                *v = if is_gain { shared::db_to_linear(real) } else { real };
+            }
+        }
+    }
+
+    /// Forget the last render (transport stopped), so no stale curves are applied
+    pub fn clear(&mut self) {
+        self.samples = 0;
+    }
+
+    /// Per-sample values of lane `index` for the current block
+    pub fn lane_curve(&self, index: usize) -> Option<(ParamAddress, &[f32])> {
+        if self.samples == 0 || self.lanes[index].points.is_empty() || self.recorders[index].active {
+            return None;
+        }
+        let target = self.targets[index]?;
+        Some((target, &self.buffers[index][..self.samples]))
+    }
+
+    /// Target and value at the end of the block, for parameters set once per block
+    pub fn block_value(&self, index: usize) -> Option<(ParamAddress, f32)> {
+        self.lane_curve(index).and_then(|(target, curve)| curve.last().map(|v| (target, *v)))
+    }
+
+    /// Target and value `sample` samples into the block
+    pub fn value_at(&self, index: usize, sample: usize) -> Option<(ParamAddress, f32)> {
+        self.lane_curve(index).and_then(|(target, curve)| curve.get(sample).map(|v| (target, *v)))
+    }
+
+    /// Per-sample values for `address`, if a lane automates it
+    pub fn curve(&self, address: ParamAddress) -> Option<&[f32]> {
+        (0..self.lanes.len())
+            .filter_map(|i| self.lane_curve(i))
+            .find(|(target, _)| *target == address)
+            .map(|(_, curve)| curve)
+    }
+}
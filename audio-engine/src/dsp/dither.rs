@@ -0,0 +1,69 @@
+use serde::Deserialize;
+use super::rng::Rng;
+
+// Same noise on every render, so exports of the same project are identical
+const DITHER_SEED: u64 = 0x5EED_D17E;
+
+// Lipshitz et al., "Minimally audible noise shaping" (E-weighted, 44.1 kHz):
+// moves the requantization noise up to where hearing is least sensitive
+const SHAPING: [f32; 5] = [2.033, -2.165, 1.959, -1.590, 0.6149];
+
+/// Noise added before reducing the word length
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Dither {
+    None,    // Plain rounding, distortion on quiet material
+    #[default]
+    Tpdf,    // Triangular, +-1 LSB: flat noise, no distortion
+    Shaped,  // TPDF with error feedback, quieter in the midrange
+}
+
+/// Float to integer PCM for one interleaved stream
+pub struct Ditherer {
+    kind: Dither,
+    scale: f32,     // Full scale in LSBs
+    min: i32,
+    max: i32,
+    rng: Rng,
+    errors: Vec<[f32; SHAPING.len()]>, // Recent quantization errors, per channel
+}
+
+impl Ditherer {
+    pub fn new(kind: Dither, bits: u16, channels: usize) -> Self {
+        let full_scale = 1i64 << (bits.clamp(2, 32) - 1);
+        Self {
+            kind,
+            scale: full_scale as f32,
+            min: (-full_scale) as i32,
+            max: (full_scale - 1) as i32,
+            rng: Rng::new(DITHER_SEED),
+            errors: vec![[0.0; SHAPING.len()]; channels.max(1)],
+        }
+    }
+
+    /// Quantize `sample` (-1.0..1.0) of `channel`
+    pub fn quantize(&mut self, sample: f32, channel: usize) -> i32 {
+        let x = if sample.is_finite() { sample * self.scale } else { 0.0 };
+        let value = match self.kind {
+            Dither::None => x.round(),
+            Dither::Tpdf => (x + self.tpdf()).round(),
+            Dither::Shaped => {
+                let errors = &self.errors[channel];
+                let wanted = x - SHAPING.iter().zip(errors).map(|(c, e)| c * e).sum::<f32>();
+                let value = (wanted + self.tpdf()).round();
+                // Clipped samples would feed back a huge error, cap it
+                let error = (value - wanted).clamp(-1.5, 1.5);
+                let errors = &mut self.errors[channel];
+                errors.rotate_right(1);
+                errors[0] = error;
+                value
+            }
+        };
+        // f32 -> i64 saturates, then clip to the word length
+        (value as i64).clamp(self.min as i64, self.max as i64) as i32
+    }
+
+    fn tpdf(&mut self) -> f32 {
+        (self.rng.next_bipolar() + self.rng.next_bipolar()) * 0.5
+    }
+}
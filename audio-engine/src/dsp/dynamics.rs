@@ -1,5 +1,3 @@
-use super::DspProcessor;
-
 pub struct EnvelopeFollower {
     attack_coef: f32,
     release_coef: f32,
@@ -1,97 +1,167 @@
-use super::DspProcessor;
+pub use shared::EnvelopeParams;
+
+// Curve amount of 1.0 maps to this exponent steepness
+const CURVE_STEEPNESS: f32 = 8.0;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum AdsrStage {
+pub enum EnvelopeStage {
     Idle,
+    Delay,
     Attack,
+    Hold,
     Decay,
     Sustain,
     Release,
 }
 
-pub struct AdsrEnvelope {
-    pub attack_ms: f32,
-    pub decay_ms: f32,
-    pub sustain_level: f32,
-    pub release_ms: f32,
-    
+/// Map linear segment progress (0-1) onto the curve shape
+#[inline]
+fn shape(t: f32, curve: f32) -> f32 {
+    let k = curve.clamp(-1.0, 1.0) * CURVE_STEEPNESS;
+    if k.abs() < 1e-3 {
+        return t;
+    }
+    (1.0 - (-k * t).exp()) / (1.0 - (-k).exp())
+}
+
+/// Delay-Attack-Hold-Decay-Sustain-Release envelope with curved segments.
+/// Stage lengths are read every sample so parameter changes apply immediately,
+/// even on notes that are already sounding.
+pub struct Envelope {
+    pub params: EnvelopeParams,
+
     sample_rate: f32,
-    stage: AdsrStage,
-    current_level: f32,
-    
-    // Increments per sample
-    attack_inc: f32,
-    decay_inc: f32,
-    release_dec: f32,
+    stage: EnvelopeStage,
+    level: f32,
+
+    // Segment state
+    stage_pos: f32,   // Linear progress through the current stage (0-1)
+    stage_start: f32, // Level when the stage began
+    peak: f32,        // Attack target, velocity is folded in so retriggers stay continuous
 }
 
-impl AdsrEnvelope {
+impl Envelope {
     pub fn new(sample_rate: f32) -> Self {
+        Self::with_params(EnvelopeParams::default(), sample_rate)
+    }
+
+    pub fn with_params(params: EnvelopeParams, sample_rate: f32) -> Self {
         Self {
-            attack_ms: 10.0,
-            decay_ms: 100.0,
-            sustain_level: 0.7,
-            release_ms: 200.0,
+            params,
             sample_rate,
-            stage: AdsrStage::Idle,
-            current_level: 0.0,
-            attack_inc: 0.0,
-            decay_inc: 0.0,
-            release_dec: 0.0,
+            stage: EnvelopeStage::Idle,
+            level: 0.0,
+            stage_pos: 0.0,
+            stage_start: 0.0,
+            peak: 1.0,
         }
     }
-    
-    fn calc_rates(&mut self) {
-        self.attack_inc = 1.0 / (self.attack_ms * 0.001 * self.sample_rate);
-        let decay_samples = self.decay_ms * 0.001 * self.sample_rate;
-        self.decay_inc = (1.0 - self.sustain_level) / decay_samples;
-        self.release_dec = self.sustain_level / (self.release_ms * 0.001 * self.sample_rate);
-    }
 
-    pub fn trigger(&mut self) {
-        self.calc_rates(); // Recalc in case params changed
-        self.stage = AdsrStage::Attack;
+    /// Start the envelope. Velocity is 0.0 - 1.0.
+    /// Retriggering starts from the current level to avoid clicks, the new
+    /// velocity only changes where the attack heads.
+    pub fn trigger(&mut self, velocity: f32) {
+        let sens = self.params.velocity_sens.clamp(0.0, 1.0);
+        self.peak = 1.0 - sens + sens * velocity.clamp(0.0, 1.0);
+        self.enter(EnvelopeStage::Delay);
     }
 
     pub fn release(&mut self) {
-        self.stage = AdsrStage::Release;
-        self.calc_rates(); // Ensure release rate is correct
+        if self.stage != EnvelopeStage::Idle {
+            self.enter(EnvelopeStage::Release);
+        }
+    }
+
+    /// Jump straight to silence (voice stealing, all-notes-off)
+    pub fn reset(&mut self) {
+        self.stage = EnvelopeStage::Idle;
+        self.level = 0.0;
+        self.stage_pos = 0.0;
+    }
+
+    fn enter(&mut self, stage: EnvelopeStage) {
+        self.stage = stage;
+        self.stage_pos = 0.0;
+        self.stage_start = self.level;
+    }
+
+    // Advance the linear position through a stage of `ms` length.
+    // Returns true once the stage is complete.
+    #[inline]
+    fn advance(&mut self, ms: f32) -> bool {
+        let samples = ms * 0.001 * self.sample_rate;
+        if samples < 1.0 {
+            self.stage_pos = 1.0;
+        } else {
+            // Half a step of slack so rounding in the sum doesn't add a sample
+            let step = 1.0 / samples;
+            self.stage_pos += step;
+            if self.stage_pos + step * 0.5 >= 1.0 {
+                self.stage_pos = 1.0;
+            }
+        }
+        self.stage_pos >= 1.0
     }
 
     pub fn process(&mut self) -> f32 {
+        let p = self.params;
         match self.stage {
-            AdsrStage::Idle => {
-                self.current_level = 0.0;
+            EnvelopeStage::Idle => {
+                self.level = 0.0;
+            },
+            EnvelopeStage::Delay => {
+                // Hold whatever level we were retriggered from
+                if self.advance(p.delay_ms) {
+                    self.enter(EnvelopeStage::Attack);
+                }
+            },
+            EnvelopeStage::Attack => {
+                let done = self.advance(p.attack_ms);
+                self.level = self.stage_start + (self.peak - self.stage_start) * shape(self.stage_pos, p.attack_curve);
+                if done {
+                    self.level = self.peak;
+                    self.enter(EnvelopeStage::Hold);
+                }
             },
-            AdsrStage::Attack => {
-                self.current_level += self.attack_inc;
-                if self.current_level >= 1.0 {
-                    self.current_level = 1.0;
-                    self.stage = AdsrStage::Decay;
+            EnvelopeStage::Hold => {
+                if self.advance(p.hold_ms) {
+                    self.enter(EnvelopeStage::Decay);
                 }
             },
-            AdsrStage::Decay => {
-                self.current_level -= self.decay_inc;
-                if self.current_level <= self.sustain_level {
-                    self.current_level = self.sustain_level;
-                    self.stage = AdsrStage::Sustain;
+            EnvelopeStage::Decay => {
+                let sustain = p.sustain_level.clamp(0.0, 1.0) * self.peak;
+                let done = self.advance(p.decay_ms);
+                self.level = self.stage_start + (sustain - self.stage_start) * shape(self.stage_pos, p.decay_curve);
+                if done {
+                    self.level = sustain;
+                    if p.looping {
+                        self.enter(EnvelopeStage::Attack);
+                    } else {
+                        self.enter(EnvelopeStage::Sustain);
+                    }
                 }
             },
-            AdsrStage::Sustain => {
-                self.current_level = self.sustain_level;
+            EnvelopeStage::Sustain => {
+                // Follows the parameter so sustain changes apply to held notes
+                self.level = p.sustain_level.clamp(0.0, 1.0) * self.peak;
             },
-            AdsrStage::Release => {
-                self.current_level -= self.release_dec;
-                if self.current_level <= 0.0 {
-                    self.current_level = 0.0;
-                    self.stage = AdsrStage::Idle;
+            EnvelopeStage::Release => {
+                let done = self.advance(p.release_ms);
+                self.level = self.stage_start * (1.0 - shape(self.stage_pos, p.release_curve));
+                if done {
+                    self.level = 0.0;
+                    self.stage = EnvelopeStage::Idle;
                 }
             }
         }
-        self.current_level
+        self.level
     }
-    
+
+    pub fn stage(&self) -> EnvelopeStage {
+        self.stage
+    }
+
     pub fn is_active(&self) -> bool {
-        self.stage != AdsrStage::Idle
+        self.stage != EnvelopeStage::Idle
     }
 }
@@ -1,12 +1,18 @@
 use super::{DspProcessor, PI_2};
+use super::smooth::{SmoothedParam, DEFAULT_SMOOTHING_MS};
 
-#[derive(Clone, Copy, Debug)]
+// Coefficients are recalculated this often while a smoothed filter is gliding
+const SMOOTH_UPDATE_INTERVAL: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FilterType {
     LowPass,
     HighPass,
     Peaking,
     LowShelf,
     HighShelf,
+    Notch,
+    BandPass, // Constant 0 dB peak gain
 }
 
 #[derive(Clone, Debug)]
@@ -43,12 +49,33 @@ impl Biquad {
     }
 
     pub fn set_params(&mut self, freq: f32, q: f32, gain_db: f32) {
-        self.freq = freq;
-        self.q = q;
+        // Keep the pole inside the unit circle for any knob position
+        self.freq = freq.clamp(1.0, self.sample_rate * 0.499);
+        self.q = q.max(0.01);
         self.gain_db = gain_db;
         self.calc_coeffs();
     }
 
+    pub fn set_type(&mut self, filter_type: FilterType) {
+        self.filter_type = filter_type;
+        self.calc_coeffs();
+    }
+
+    /// Magnitude response at `freq` Hz (linear)
+    pub fn magnitude(&self, freq: f32) -> f32 {
+        let w = PI_2 * freq / self.sample_rate;
+        let (sin1, cos1) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+
+        // H(e^jw) = (b0 + b1 e^-jw + b2 e^-2jw) / (1 + a1 e^-jw + a2 e^-2jw)
+        let num_re = self.b0 + self.b1 * cos1 + self.b2 * cos2;
+        let num_im = -(self.b1 * sin1 + self.b2 * sin2);
+        let den_re = 1.0 + self.a1 * cos1 + self.a2 * cos2;
+        let den_im = -(self.a1 * sin1 + self.a2 * sin2);
+
+        ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im).max(1e-20)).sqrt()
+    }
+
     fn calc_coeffs(&mut self) {
         let w0 = PI_2 * self.freq / self.sample_rate;
         let cos_w0 = w0.cos();
@@ -122,6 +149,36 @@ impl Biquad {
                 self.a1 = a1 * a0_inv;
                 self.a2 = a2 * a0_inv;
             },
+            FilterType::Notch => {
+                let b0 = 1.0;
+                let b1 = -2.0 * cos_w0;
+                let b2 = 1.0;
+                let a0 = 1.0 + alpha;
+                let a1 = -2.0 * cos_w0;
+                let a2 = 1.0 - alpha;
+
+                a0_inv = 1.0 / a0;
+                self.b0 = b0 * a0_inv;
+                self.b1 = b1 * a0_inv;
+                self.b2 = b2 * a0_inv;
+                self.a1 = a1 * a0_inv;
+                self.a2 = a2 * a0_inv;
+            },
+            FilterType::BandPass => {
+                let b0 = alpha;
+                let b1 = 0.0;
+                let b2 = -alpha;
+                let a0 = 1.0 + alpha;
+                let a1 = -2.0 * cos_w0;
+                let a2 = 1.0 - alpha;
+
+                a0_inv = 1.0 / a0;
+                self.b0 = b0 * a0_inv;
+                self.b1 = b1 * a0_inv;
+                self.b2 = b2 * a0_inv;
+                self.a1 = a1 * a0_inv;
+                self.a2 = a2 * a0_inv;
+            },
             FilterType::HighShelf => {
                 let a = 10.0f32.powf(self.gain_db / 40.0);
                 let sqrt_a = a.sqrt();
@@ -163,3 +220,84 @@ impl DspProcessor for Biquad {
         self.z2 = 0.0;
     }
 }
+
+/// Biquad whose frequency, Q and gain glide to new settings, for knob moves
+/// and automation. Frequency is smoothed in octaves so sweeps sound even.
+#[derive(Clone, Debug)]
+pub struct SmoothedBiquad {
+    biquad: Biquad,
+    octave: SmoothedParam, // log2(freq)
+    q: SmoothedParam,
+    gain_db: SmoothedParam,
+    countdown: u32,
+}
+
+impl SmoothedBiquad {
+    pub fn new(filter_type: FilterType, freq: f32, q: f32, sample_rate: f32) -> Self {
+        Self {
+            biquad: Biquad::new(filter_type, freq, q, sample_rate),
+            octave: SmoothedParam::exponential(freq.max(1.0).log2(), DEFAULT_SMOOTHING_MS, sample_rate),
+            q: SmoothedParam::exponential(q, DEFAULT_SMOOTHING_MS, sample_rate),
+            gain_db: SmoothedParam::exponential(0.0, DEFAULT_SMOOTHING_MS, sample_rate),
+            countdown: 0,
+        }
+    }
+
+    pub fn set_params(&mut self, freq: f32, q: f32, gain_db: f32) {
+        let nyquist = self.biquad.sample_rate * 0.499;
+        self.octave.set_target(freq.clamp(1.0, nyquist).log2());
+        self.q.set_target(q.max(0.01));
+        self.gain_db.set_target(gain_db);
+
+        // Nothing to glide (first setup), apply right away
+        if !self.is_smoothing() {
+            self.apply_current();
+        }
+    }
+
+    pub fn set_type(&mut self, filter_type: FilterType) {
+        if self.biquad.filter_type != filter_type {
+            self.biquad.set_type(filter_type);
+        }
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.octave.is_smoothing() || self.q.is_smoothing() || self.gain_db.is_smoothing()
+    }
+
+    /// Magnitude response at `freq` Hz (linear) for the target settings
+    pub fn magnitude(&self, freq: f32) -> f32 {
+        if !self.is_smoothing() {
+            return self.biquad.magnitude(freq);
+        }
+        let mut target = self.biquad.clone();
+        target.set_params(self.octave.target().exp2(), self.q.target(), self.gain_db.target());
+        target.magnitude(freq)
+    }
+
+    fn apply_current(&mut self) {
+        self.biquad.set_params(self.octave.current().exp2(), self.q.current(), self.gain_db.current());
+    }
+}
+
+impl DspProcessor for SmoothedBiquad {
+    #[inline]
+    fn process_sample(&mut self, input: f32) -> f32 {
+        if self.countdown == 0 {
+            self.countdown = SMOOTH_UPDATE_INTERVAL;
+            let gliding = self.is_smoothing();
+            self.octave.skip(SMOOTH_UPDATE_INTERVAL);
+            self.q.skip(SMOOTH_UPDATE_INTERVAL);
+            self.gain_db.skip(SMOOTH_UPDATE_INTERVAL);
+            if gliding {
+                self.apply_current();
+            }
+        }
+        self.countdown -= 1;
+        self.biquad.process_sample(input)
+    }
+
+    fn reset(&mut self) {
+        self.biquad.reset();
+    }
+}
@@ -0,0 +1,192 @@
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+// Kernel resolution of the sinc tables, per source sample
+const SINC_PHASES: usize = 512;
+
+// Widest the sinc kernel gets when reading faster than 1x (anti-aliasing)
+const MAX_SINC_SCALE: f64 = 4.0;
+
+/// Resampling quality for variable-speed reads (clips, grains, samplers).
+/// Better modes cost more per sample, so real-time playback can use a cheaper
+/// one than offline export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    Linear,  // 2 points, cheapest, dull highs and audible aliasing
+    #[default]
+    Hermite, // 4-point cubic Hermite, the real-time default
+    Sinc8,   // 8-point windowed sinc
+    Sinc32,  // 32-point windowed sinc, for export
+}
+
+/// Quality used for offline rendering
+pub const EXPORT_INTERPOLATION: Interpolation = Interpolation::Sinc32;
+
+impl Interpolation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "linear" => Some(Self::Linear),
+            "hermite" | "cubic" => Some(Self::Hermite),
+            "sinc8" => Some(Self::Sinc8),
+            "sinc32" | "sinc" => Some(Self::Sinc32),
+            _ => None,
+        }
+    }
+
+    /// Build lookup tables now instead of on the first read (audio thread)
+    pub fn prepare(self) {
+        if let Some(table) = self.sinc_table() {
+            table.get_or_init(|| SincTable::new(self.taps(), self.cutoff()));
+        }
+    }
+
+    /// Whether reads can start without building a table first
+    pub fn is_prepared(self) -> bool {
+        self.sinc_table().is_none_or(|table| table.get().is_some())
+    }
+
+    /// Sample of `buf` at fractional `pos`, silent outside the buffer.
+    /// `rate` is the read speed in source samples per output sample; the sinc
+    /// modes widen their kernel above 1x so pitching up doesn't alias.
+    #[inline]
+    pub fn read(self, buf: &[f32], pos: f64, rate: f64) -> f32 {
+        match self {
+            Self::Linear => read_linear(buf, pos),
+            Self::Hermite => read_hermite(buf, pos),
+            Self::Sinc8 | Self::Sinc32 => {
+                let table = self.sinc_table().map(|t| t.get_or_init(|| SincTable::new(self.taps(), self.cutoff())));
+                match table {
+                    Some(table) => table.read(buf, pos, rate),
+                    None => read_hermite(buf, pos),
+                }
+            }
+        }
+    }
+
+    fn taps(self) -> usize {
+        match self {
+            Self::Linear => 2,
+            Self::Hermite => 4,
+            Self::Sinc8 => 8,
+            Self::Sinc32 => 32,
+        }
+    }
+
+    // Passband as a share of Nyquist when reading faster than 1x, short
+    // kernels need more room to roll off
+    fn cutoff(self) -> f64 {
+        match self {
+            Self::Sinc8 => 0.85,
+            _ => 0.95,
+        }
+    }
+
+    fn sinc_table(self) -> Option<&'static OnceLock<SincTable>> {
+        static SINC8: OnceLock<SincTable> = OnceLock::new();
+        static SINC32: OnceLock<SincTable> = OnceLock::new();
+        match self {
+            Self::Sinc8 => Some(&SINC8),
+            Self::Sinc32 => Some(&SINC32),
+            _ => None,
+        }
+    }
+}
+
+#[inline]
+fn sample_at(buf: &[f32], index: i64) -> f32 {
+    if index < 0 { return 0.0; }
+    buf.get(index as usize).copied().unwrap_or(0.0)
+}
+
+// Linear interpolation, silent outside the buffer
+#[inline]
+pub fn read_linear(buf: &[f32], pos: f64) -> f32 {
+    if !(0.0..buf.len() as f64).contains(&pos) { return 0.0; }
+    let idx = pos as usize;
+    let frac = (pos - idx as f64) as f32;
+    let s0 = buf.get(idx).copied().unwrap_or(0.0);
+    let s1 = buf.get(idx + 1).copied().unwrap_or(0.0);
+    s0 + (s1 - s0) * frac
+}
+
+// 4-point, 3rd-order Hermite (Catmull-Rom)
+#[inline]
+pub fn read_hermite(buf: &[f32], pos: f64) -> f32 {
+    if !(-1.0..buf.len() as f64 + 1.0).contains(&pos) { return 0.0; }
+    let base = pos.floor();
+    let i = base as i64;
+    let t = (pos - base) as f32;
+    let xm1 = sample_at(buf, i - 1);
+    let x0 = sample_at(buf, i);
+    let x1 = sample_at(buf, i + 1);
+    let x2 = sample_at(buf, i + 2);
+
+    let c1 = 0.5 * (x1 - xm1);
+    let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+    let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+    ((c3 * t + c2) * t + c1) * t + x0
+}
+
+// Blackman-windowed sinc, one side of the kernel sampled at SINC_PHASES per
+// sample. Reads interpolate between phases, so any fractional offset and any
+// kernel stretch can be looked up from the same table. Unstretched it is
+// zero on every other sample, so whole positions read back exactly.
+struct SincTable {
+    half_width: usize,
+    cutoff: f64,
+    table: Vec<f32>,
+}
+
+impl SincTable {
+    fn new(taps: usize, cutoff: f64) -> Self {
+        let half_width = taps / 2;
+        let len = half_width * SINC_PHASES + 2;
+        let table = (0..len).map(|i| {
+            let x = i as f64 / SINC_PHASES as f64;
+            if x >= half_width as f64 { return 0.0; }
+            let sinc = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            // Blackman over the full kernel width, centred on 0
+            let w = 0.5 + 0.5 * x / half_width as f64;
+            let window = 0.42 - 0.5 * (2.0 * PI * w).cos() + 0.08 * (4.0 * PI * w).cos();
+            (sinc * window) as f32
+        }).collect();
+        Self { half_width, cutoff, table }
+    }
+
+    #[inline]
+    fn kernel(&self, x: f64) -> f32 {
+        let u = x.abs() * SINC_PHASES as f64;
+        let i = u as usize;
+        if i + 1 >= self.table.len() { return 0.0; }
+        let frac = (u - i as f64) as f32;
+        self.table[i] + (self.table[i + 1] - self.table[i]) * frac
+    }
+
+    fn read(&self, buf: &[f32], pos: f64, rate: f64) -> f32 {
+        if !pos.is_finite() { return 0.0; }
+        // Stretch the kernel when reading faster than 1x to lower its cutoff.
+        // The margin below the new Nyquist eases in up to 2x so the kernel
+        // doesn't jump as the rate passes 1x.
+        let rate = rate.abs();
+        let scale = if rate > 1.0 {
+            let margin = 1.0 + (1.0 / self.cutoff - 1.0) * (rate - 1.0).min(1.0);
+            (rate * margin).min(MAX_SINC_SCALE)
+        } else {
+            1.0
+        };
+        let reach = self.half_width as f64 * scale;
+        if pos + reach < 0.0 || pos - reach >= buf.len() as f64 { return 0.0; }
+        let first = (pos - reach).floor() as i64 + 1;
+        let last = (pos + reach).floor() as i64;
+
+        let mut sum = 0.0;
+        let mut weight = 0.0;
+        for i in first..=last {
+            let k = self.kernel((pos - i as f64) / scale);
+            sum += sample_at(buf, i) * k;
+            weight += k;
+        }
+        // Normalise so DC passes at unity whatever the fractional position
+        if weight.abs() > 1e-6 { sum / weight } else { 0.0 }
+    }
+}
@@ -1,7 +1,14 @@
 pub mod filter;
 pub mod delay;
+pub mod dither;
 pub mod dynamics;
 pub mod envelope;
+pub mod fft;
+pub mod interpolation;
+pub mod resample;
+pub mod rng;
+pub mod smooth;
+pub mod stretch;
 
 use std::f32::consts::PI;
 
@@ -0,0 +1,200 @@
+// Offline sample-rate conversion: polyphase Kaiser-windowed sinc. The rate
+// ratio is reduced to L/M; every output sample sits at one of L fractional
+// offsets between input samples, each with its own precomputed filter.
+// Ratios too awkward for an exact bank (e.g. 44100 -> 44101) interpolate
+// between MAX_PHASES phases instead.
+
+use std::f64::consts::PI;
+
+// Kernel half-width in zero crossings of the sinc. 64 puts the transition
+// band within the top 10% under Nyquist.
+const ZERO_CROSSINGS: usize = 64;
+
+// Passband edge as a share of the lower Nyquist
+const CUTOFF: f64 = 0.95;
+
+// Kaiser beta for about 100 dB of stopband attenuation
+const KAISER_BETA: f64 = 10.0;
+
+const MAX_PHASES: usize = 1024;
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// Zeroth-order modified Bessel function of the first kind, by its series
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..50 {
+        term *= half / k as f64;
+        let t2 = term * term;
+        sum += t2;
+        if t2 < sum * 1e-16 {
+            break;
+        }
+    }
+    sum
+}
+
+pub struct Resampler {
+    from: u32,
+    to: u32,
+    up: u64,          // L: output steps per input step, reduced
+    down: u64,        // M
+    taps: usize,      // Filter length per phase, 2 * half-width
+    phases: usize,    // Rows in the bank (one extra when interpolating)
+    exact: bool,      // One row per possible offset, no interpolation
+    table: Vec<f32>,  // phases * taps
+}
+
+impl Resampler {
+    pub fn new(from: u32, to: u32) -> Self {
+        let from = from.max(1);
+        let to = to.max(1);
+        let g = gcd(from as u64, to as u64);
+        let up = to as u64 / g;
+        let down = from as u64 / g;
+
+        // Downsampling lowers the cutoff and widens the kernel to match
+        let ratio = (to as f64 / from as f64).min(1.0);
+        let cutoff = CUTOFF * ratio;
+        let reach = ZERO_CROSSINGS as f64 / ratio;
+        let half = reach.ceil() as usize;
+        let taps = 2 * half;
+
+        let exact = up as usize <= MAX_PHASES;
+        let rows = if exact { up as usize } else { MAX_PHASES + 1 };
+        let spacing = if exact { up as f64 } else { MAX_PHASES as f64 };
+
+        let norm = bessel_i0(KAISER_BETA);
+        let mut table = Vec::with_capacity(rows * taps);
+        for row in 0..rows {
+            let frac = row as f64 / spacing;
+            let start = table.len();
+            // Tap j weighs input sample floor(pos) + j - (half - 1)
+            for j in 0..taps {
+                let x = frac - (j as f64 - (half as f64 - 1.0));
+                let t = x / reach;
+                let h = if t.abs() >= 1.0 {
+                    0.0
+                } else {
+                    let sinc = if x == 0.0 { 1.0 } else { (PI * cutoff * x).sin() / (PI * cutoff * x) };
+                    cutoff * sinc * bessel_i0(KAISER_BETA * (1.0 - t * t).sqrt()) / norm
+                };
+                table.push(h);
+            }
+            // Unity gain at DC for every phase
+            let sum: f64 = table[start..].iter().sum();
+            if sum.abs() > 1e-12 {
+                table[start..].iter_mut().for_each(|h| *h /= sum);
+            }
+        }
+
+        Self {
+            from,
+            to,
+            up,
+            down,
+            taps,
+            phases: rows,
+            exact,
+            table: table.into_iter().map(|h| h as f32).collect(),
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.from == self.to
+    }
+
+    /// Output samples for `input_len` input samples, rounded up
+    pub fn output_len(&self, input_len: usize) -> usize {
+        ((input_len as u64 * self.up).div_ceil(self.down)) as usize
+    }
+
+    /// Convert one channel. Output sample n lines up with input time
+    /// n * from / to, so starts and lengths keep their place in seconds.
+    pub fn process(&self, input: &[f32]) -> Vec<f32> {
+        if self.is_identity() {
+            return input.to_vec();
+        }
+        let len = self.output_len(input.len());
+        let mut output = Vec::with_capacity(len);
+        let half = (self.taps / 2) as i64;
+        let step = self.down / self.up;
+        let step_rem = self.down % self.up;
+        let mut row = vec![0.0f32; self.taps];
+
+        let mut pos: i64 = 0; // Input index at or before the output time
+        let mut rem: u64 = 0; // Fraction past `pos`, in 1/L
+        for _ in 0..len {
+            let taps = if self.exact {
+                let start = rem as usize * self.taps;
+                &self.table[start..start + self.taps]
+            } else {
+                let p = rem as f64 / self.up as f64 * (self.phases - 1) as f64;
+                let i = (p as usize).min(self.phases - 2);
+                let blend = (p - i as f64) as f32;
+                let a = &self.table[i * self.taps..(i + 1) * self.taps];
+                let b = &self.table[(i + 1) * self.taps..(i + 2) * self.taps];
+                for ((r, a), b) in row.iter_mut().zip(a).zip(b) {
+                    *r = a + (b - a) * blend;
+                }
+                &row[..]
+            };
+
+            let first = pos - (half - 1);
+            let sum = if first >= 0 && (first as usize + self.taps) <= input.len() {
+                let window = &input[first as usize..first as usize + self.taps];
+                window.iter().zip(taps).map(|(x, h)| x * h).sum()
+            } else {
+                // Near the edges, silence outside the buffer
+                let mut sum = 0.0;
+                for (j, h) in taps.iter().enumerate() {
+                    let i = first + j as i64;
+                    if i >= 0 && (i as usize) < input.len() {
+                        sum += input[i as usize] * h;
+                    }
+                }
+                sum
+            };
+            output.push(sum);
+
+            pos += step as i64;
+            rem += step_rem;
+            if rem >= self.up {
+                rem -= self.up;
+                pos += 1;
+            }
+        }
+        output
+    }
+
+    /// Convert interleaved audio channel by channel
+    pub fn process_interleaved(&self, input: &[f32], channels: usize) -> Vec<f32> {
+        if self.is_identity() || channels == 0 {
+            return input.to_vec();
+        }
+        let converted: Vec<Vec<f32>> = (0..channels)
+            .map(|c| {
+                let channel: Vec<f32> = input.iter().skip(c).step_by(channels).copied().collect();
+                self.process(&channel)
+            })
+            .collect();
+        let frames = converted.first().map(|c| c.len()).unwrap_or(0);
+        let mut output = Vec::with_capacity(frames * channels);
+        for i in 0..frames {
+            output.extend(converted.iter().map(|c| c[i]));
+        }
+        output
+    }
+}
+
+/// Convert one channel from `from` Hz to `to` Hz
+pub fn resample(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to {
+        return input.to_vec();
+    }
+    Resampler::new(from, to).process(input)
+}
@@ -0,0 +1,196 @@
+// Default glide for knob moves and automation
+pub const DEFAULT_SMOOTHING_MS: f32 = 20.0;
+
+// Exponential ramps count as settled within this distance of the target
+const SETTLE_EPSILON: f32 = 1e-5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SmoothingMode {
+    Linear,      // Constant step, lands exactly on the target after the ramp time
+    Exponential, // One-pole glide, ~60 dB closer to the target after the ramp time
+}
+
+/// A parameter that glides to new values instead of jumping.
+/// Call `next_value()` once per sample (or `skip()` per sub-block). Targets set
+/// before the first sample is rendered apply immediately, so freshly built
+/// nodes start at their configured values instead of ramping from defaults.
+#[derive(Clone, Debug)]
+pub struct SmoothedParam {
+    current: f32,
+    target: f32,
+    mode: SmoothingMode,
+    ramp_samples: f32,
+
+    // Linear state
+    step: f32,
+    remaining: u32,
+
+    // Exponential state
+    pole: f32,
+
+    primed: bool, // Has rendered at least one sample
+}
+
+impl SmoothedParam {
+    pub fn new(value: f32, time_ms: f32, sample_rate: f32, mode: SmoothingMode) -> Self {
+        let mut param = Self {
+            current: value,
+            target: value,
+            mode,
+            ramp_samples: 0.0,
+            step: 0.0,
+            remaining: 0,
+            pole: 0.0,
+            primed: false,
+        };
+        param.set_time(time_ms, sample_rate);
+        param
+    }
+
+    pub fn linear(value: f32, time_ms: f32, sample_rate: f32) -> Self {
+        Self::new(value, time_ms, sample_rate, SmoothingMode::Linear)
+    }
+
+    pub fn exponential(value: f32, time_ms: f32, sample_rate: f32) -> Self {
+        Self::new(value, time_ms, sample_rate, SmoothingMode::Exponential)
+    }
+
+    pub fn set_time(&mut self, time_ms: f32, sample_rate: f32) {
+        self.ramp_samples = (time_ms * 0.001 * sample_rate).max(1.0);
+        // ln(1000) time constants per ramp = -60 dB remaining
+        self.pole = (-(1000.0f32).ln() / self.ramp_samples).exp();
+    }
+
+    pub fn set_target(&mut self, value: f32) {
+        if !self.primed {
+            self.set_immediate(value);
+            return;
+        }
+        if value == self.target { return; }
+        self.target = value;
+        if self.mode == SmoothingMode::Linear {
+            self.remaining = self.ramp_samples as u32;
+            self.step = (self.target - self.current) / self.remaining.max(1) as f32;
+        }
+    }
+
+    /// Jump straight to `value` (initial setup, resets)
+    pub fn set_immediate(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.remaining = 0;
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.current != self.target
+    }
+
+    #[inline]
+    pub fn next_value(&mut self) -> f32 {
+        self.primed = true;
+        if self.current != self.target {
+            match self.mode {
+                SmoothingMode::Linear => {
+                    if self.remaining <= 1 {
+                        self.current = self.target;
+                        self.remaining = 0;
+                    } else {
+                        self.current += self.step;
+                        self.remaining -= 1;
+                    }
+                },
+                SmoothingMode::Exponential => {
+                    self.current = self.target + (self.current - self.target) * self.pole;
+                    if (self.current - self.target).abs() <= SETTLE_EPSILON * self.target.abs().max(1.0) {
+                        self.current = self.target;
+                    }
+                }
+            }
+        }
+        self.current
+    }
+
+    /// Advance `n` samples at once
+    pub fn skip(&mut self, n: u32) -> f32 {
+        self.primed = true;
+        if n == 0 || self.current == self.target {
+            return self.current;
+        }
+        match self.mode {
+            SmoothingMode::Linear => {
+                if n >= self.remaining {
+                    self.current = self.target;
+                    self.remaining = 0;
+                } else {
+                    self.current += self.step * n as f32;
+                    self.remaining -= n;
+                }
+            },
+            SmoothingMode::Exponential => {
+                self.current = self.target + (self.current - self.target) * self.pole.powi(n as i32);
+                if (self.current - self.target).abs() <= SETTLE_EPSILON * self.target.abs().max(1.0) {
+                    self.current = self.target;
+                }
+            }
+        }
+        self.current
+    }
+}
+
+/// Equal-power pan with smoothed channel gains
+#[derive(Clone, Debug)]
+pub struct SmoothedPan {
+    left: SmoothedParam,
+    right: SmoothedParam,
+}
+
+impl SmoothedPan {
+    pub fn new(pan: f32, sample_rate: f32) -> Self {
+        let (l, r) = Self::gains(pan);
+        Self {
+            left: SmoothedParam::linear(l, DEFAULT_SMOOTHING_MS, sample_rate),
+            right: SmoothedParam::linear(r, DEFAULT_SMOOTHING_MS, sample_rate),
+        }
+    }
+
+    fn gains(pan: f32) -> (f32, f32) {
+        let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::PI / 4.0;
+        (angle.cos(), angle.sin())
+    }
+
+    /// -1.0 (L) to 1.0 (R)
+    pub fn set_pan(&mut self, pan: f32) {
+        let (l, r) = Self::gains(pan);
+        self.left.set_target(l);
+        self.right.set_target(r);
+    }
+
+    /// Pan following a per-sample curve (automation) instead of the smoother
+    pub fn process_curve(&mut self, pans: &[f32], left: &mut [f32], right: &mut [f32]) {
+        for ((l, r), pan) in left.iter_mut().zip(right.iter_mut()).zip(pans.iter()) {
+            let (gain_l, gain_r) = Self::gains(*pan);
+            *l *= gain_l;
+            *r *= gain_r;
+        }
+        if let Some(&last) = pans.last() {
+            let (l, r) = Self::gains(last);
+            self.left.set_immediate(l);
+            self.right.set_immediate(r);
+        }
+    }
+
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            *l *= self.left.next_value();
+            *r *= self.right.next_value();
+        }
+    }
+}
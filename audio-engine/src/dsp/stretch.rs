@@ -0,0 +1,138 @@
+use super::PI_2;
+use super::interpolation::Interpolation;
+
+// Grain length, ~40 ms keeps transients tight without a hollow low end
+const FRAME_SECONDS: f32 = 0.04;
+
+// The similarity search compares every Nth sample of the overlap
+const SEARCH_STRIDE: usize = 8;
+
+/// WSOLA time-stretch and pitch-shift over an in-memory source.
+///
+/// The read position advances `speed` source samples per output sample
+/// (tempo), while each grain is read `pitch` times faster (transpose), so the
+/// two are independent. Grains overlap by half and each one is nudged to
+/// where it best lines up with the previous grain's continuation, which avoids
+/// the phasing of plain overlap-add. Deterministic, so real-time playback and
+/// offline export render the same audio. Does not allocate after `new`.
+#[derive(Clone, Debug)]
+pub struct TimeStretcher {
+    hop: usize, // Output samples per grain, half a grain
+    tolerance: usize, // Search range around the nominal position
+    window: Vec<f32>,
+    out_l: Vec<f32>, // Overlap-add accumulator, one grain long
+    out_r: Vec<f32>,
+    out_pos: usize,
+    position: f64, // Source position of the next output sample
+    prev_grain: Option<f64>, // Where the last grain started in the source
+}
+
+impl TimeStretcher {
+    pub fn new(sample_rate: f32) -> Self {
+        let hop = ((sample_rate * FRAME_SECONDS * 0.5) as usize).max(16);
+        let frame = hop * 2;
+        // Periodic Hann: the overlapping halves sum to exactly 1
+        let window = (0..frame)
+            .map(|i| 0.5 - 0.5 * (PI_2 * i as f32 / frame as f32).cos())
+            .collect();
+        Self {
+            hop,
+            tolerance: frame / 8,
+            window,
+            out_l: vec![0.0; frame],
+            out_r: vec![0.0; frame],
+            out_pos: hop,
+            position: 0.0,
+            prev_grain: None,
+        }
+    }
+
+    /// Source position the next output sample belongs to
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// Output samples per grain, jumps shorter than this are followed smoothly
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Restart at `position` without overlap from earlier audio (seek, loop, clip start)
+    pub fn reset(&mut self, position: f64) {
+        self.position = position;
+        self.prev_grain = None;
+        self.out_l.fill(0.0);
+        self.out_r.fill(0.0);
+        self.out_pos = self.hop;
+    }
+
+    /// Next stereo output sample
+    pub fn next_frame(&mut self, src_l: &[f32], src_r: &[f32], speed: f32, pitch: f32, interpolation: Interpolation) -> (f32, f32) {
+        if self.out_pos >= self.hop {
+            if self.prev_grain.is_none() {
+                // Lead-in grain, so the first hop isn't a fade in
+                let lead_in = self.position - self.hop as f64 * speed as f64;
+                self.add_grain(src_l, src_r, lead_in, pitch, interpolation);
+            }
+            self.add_grain(src_l, src_r, self.position, pitch, interpolation);
+            self.out_pos = 0;
+        }
+        let out = (self.out_l[self.out_pos], self.out_r[self.out_pos]);
+        self.out_pos += 1;
+        self.position += speed as f64;
+        out
+    }
+
+    // Move the accumulator on by one hop and add a grain near `nominal`
+    fn add_grain(&mut self, src_l: &[f32], src_r: &[f32], nominal: f64, pitch: f32, interpolation: Interpolation) {
+        let hop = self.hop;
+        self.out_l.copy_within(hop.., 0);
+        self.out_r.copy_within(hop.., 0);
+        self.out_l[hop..].fill(0.0);
+        self.out_r[hop..].fill(0.0);
+
+        let pitch = pitch as f64;
+        let start = match self.prev_grain {
+            Some(prev) => self.best_start(src_l, src_r, nominal, prev + hop as f64 * pitch, pitch),
+            None => nominal,
+        };
+
+        for (i, w) in self.window.iter().enumerate() {
+            let pos = start + i as f64 * pitch;
+            self.out_l[i] += interpolation.read(src_l, pos, pitch) * w;
+            self.out_r[i] += interpolation.read(src_r, pos, pitch) * w;
+        }
+        self.prev_grain = Some(start);
+    }
+
+    // Grain start within the tolerance of `nominal` whose first half best
+    // matches `natural`, the audio that would have followed the previous grain
+    fn best_start(&self, src_l: &[f32], src_r: &[f32], nominal: f64, natural: f64, pitch: f64) -> f64 {
+        let mono = |pos: f64| {
+            if pos < 0.0 { return 0.0; }
+            let idx = pos as usize;
+            src_l.get(idx).copied().unwrap_or(0.0) + src_r.get(idx).copied().unwrap_or(0.0)
+        };
+
+        let score = |candidate: f64| {
+            (0..self.hop).step_by(SEARCH_STRIDE)
+                .map(|i| {
+                    let step = i as f64 * pitch;
+                    mono(natural + step) * mono(candidate + step)
+                })
+                .sum::<f32>()
+        };
+
+        // Stay on the nominal position unless something lines up better (silence)
+        let tolerance = self.tolerance as i64;
+        let mut best = (nominal, score(nominal));
+        for offset in (-tolerance..=tolerance).filter(|o| *o != 0) {
+            let candidate = nominal + offset as f64;
+            let value = score(candidate);
+            if value > best.1 {
+                best = (candidate, value);
+            }
+        }
+        best.0
+    }
+}
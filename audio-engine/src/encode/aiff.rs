@@ -0,0 +1,85 @@
+use super::Pcm;
+
+// AIFF-C version 1, the only one there is
+const AIFC_VERSION: u32 = 0xA280_5140;
+
+// Sample rate as an 80-bit IEEE 754 extended float, as COMM wants it
+fn extended(rate: u32) -> [u8; 10] {
+    let mut out = [0u8; 10];
+    if rate == 0 {
+        return out;
+    }
+    let shift = rate.leading_zeros();
+    let exponent = 16383 + 31 - shift as u16;
+    let mantissa = (rate as u64) << (32 + shift);
+    out[..2].copy_from_slice(&exponent.to_be_bytes());
+    out[2..].copy_from_slice(&mantissa.to_be_bytes());
+    out
+}
+
+fn chunk_header(out: &mut Vec<u8>, id: &[u8; 4], size: u32) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&size.to_be_bytes());
+}
+
+/// Complete AIFF file, big-endian samples. Floats need AIFF-C ("fl32").
+pub fn write(pcm: &Pcm, channels: u16, sample_rate: u32) -> Result<Vec<u8>, String> {
+    let depth = pcm.bit_depth();
+    let frames = u32::try_from(pcm.len() / channels.max(1) as usize)
+        .map_err(|_| "Too long for AIFF".to_string())?;
+    let data_len = pcm.len() * depth.bytes();
+
+    let mut comm = Vec::with_capacity(40);
+    comm.extend_from_slice(&channels.to_be_bytes());
+    comm.extend_from_slice(&frames.to_be_bytes());
+    comm.extend_from_slice(&depth.bits().to_be_bytes());
+    comm.extend_from_slice(&extended(sample_rate));
+    if depth.is_float() {
+        comm.extend_from_slice(b"fl32");
+        // Pascal string, padded to an even length
+        let name = b"IEEE 32-bit float";
+        comm.push(name.len() as u8);
+        comm.extend_from_slice(name);
+        if (name.len() + 1) % 2 == 1 {
+            comm.push(0);
+        }
+    }
+
+    let ssnd_len = 8 + data_len + data_len % 2;
+    let fver_len = if depth.is_float() { 12 } else { 0 };
+    let form_size = u32::try_from(4 + fver_len + 8 + comm.len() + 8 + ssnd_len)
+        .map_err(|_| "Too long for AIFF".to_string())?;
+
+    let mut out = Vec::with_capacity(form_size as usize + 8);
+    chunk_header(&mut out, b"FORM", form_size);
+    if depth.is_float() {
+        out.extend_from_slice(b"AIFC");
+        chunk_header(&mut out, b"FVER", 4);
+        out.extend_from_slice(&AIFC_VERSION.to_be_bytes());
+    } else {
+        out.extend_from_slice(b"AIFF");
+    }
+    chunk_header(&mut out, b"COMM", comm.len() as u32);
+    out.extend_from_slice(&comm);
+
+    chunk_header(&mut out, b"SSND", (8 + data_len) as u32);
+    out.extend_from_slice(&0u32.to_be_bytes()); // Offset
+    out.extend_from_slice(&0u32.to_be_bytes()); // Block size
+    match pcm {
+        Pcm::Float(samples) => {
+            for s in samples.iter() {
+                out.extend_from_slice(&s.to_be_bytes());
+            }
+        }
+        Pcm::Int(samples, depth) => {
+            let bytes = depth.bytes();
+            for s in samples {
+                out.extend_from_slice(&s.to_be_bytes()[4 - bytes..]);
+            }
+        }
+    }
+    if data_len % 2 == 1 {
+        out.push(0);
+    }
+    Ok(out)
+}
@@ -0,0 +1,136 @@
+// Audio file writers for export: WAV (RF64 past 4 GiB), AIFF/AIFF-C and FLAC
+
+pub mod aiff;
+pub mod flac;
+pub mod wav;
+
+use serde::Deserialize;
+use crate::dsp::dither::{Dither, Ditherer};
+
+/// Sample format of the written file
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BitDepth {
+    #[serde(rename = "16")]
+    Int16,
+    #[serde(rename = "24")]
+    Int24,
+    #[serde(rename = "32")]
+    Int32,
+    #[default]
+    #[serde(rename = "float")]
+    Float32,
+}
+
+impl BitDepth {
+    pub fn bits(self) -> u16 {
+        match self {
+            Self::Int16 => 16,
+            Self::Int24 => 24,
+            Self::Int32 | Self::Float32 => 32,
+        }
+    }
+
+    pub fn bytes(self) -> usize {
+        self.bits() as usize / 8
+    }
+
+    pub fn is_float(self) -> bool {
+        self == Self::Float32
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    #[default]
+    Wav,
+    Aiff,
+    Flac, // Lossless, 16 and 24-bit only
+}
+
+impl Container {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Aiff => "aiff",
+            Self::Flac => "flac",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Wav => "audio/wav",
+            Self::Aiff => "audio/aiff",
+            Self::Flac => "audio/flac",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExportFormat {
+    pub container: Container,
+    pub bit_depth: BitDepth,
+    pub dither: Dither, // 16 and 24-bit only, 32-bit words are finer than a float
+}
+
+impl ExportFormat {
+    /// Whether the container can hold the sample format
+    pub fn check(&self) -> Result<(), String> {
+        if self.container == Container::Flac && !matches!(self.bit_depth, BitDepth::Int16 | BitDepth::Int24) {
+            return Err("FLAC export needs 16 or 24-bit samples".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Interleaved samples in the file's word format
+pub enum Pcm<'a> {
+    Int(Vec<i32>, BitDepth),
+    Float(&'a [f32]),
+}
+
+impl Pcm<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Int(samples, _) => samples.len(),
+            Self::Float(samples) => samples.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn bit_depth(&self) -> BitDepth {
+        match self {
+            Self::Int(_, depth) => *depth,
+            Self::Float(_) => BitDepth::Float32,
+        }
+    }
+}
+
+/// Encode interleaved float samples into a complete file
+pub fn encode(samples: &[f32], channels: u16, sample_rate: u32, format: &ExportFormat) -> Result<Vec<u8>, String> {
+    if channels == 0 {
+        return Err("No channels to encode".to_string());
+    }
+    format.check()?;
+
+    let pcm = match format.bit_depth {
+        BitDepth::Float32 => Pcm::Float(samples),
+        depth => {
+            let dither = if depth == BitDepth::Int32 { Dither::None } else { format.dither };
+            let mut ditherer = Ditherer::new(dither, depth.bits(), channels as usize);
+            let words = samples.iter().enumerate()
+                .map(|(i, s)| ditherer.quantize(*s, i % channels as usize))
+                .collect();
+            Pcm::Int(words, depth)
+        }
+    };
+
+    match format.container {
+        Container::Wav => Ok(wav::write(&pcm, channels, sample_rate)),
+        Container::Aiff => aiff::write(&pcm, channels, sample_rate),
+        Container::Flac => flac::write(&pcm, channels, sample_rate),
+    }
+}
@@ -0,0 +1,114 @@
+use super::{BitDepth, Pcm};
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+// KSDATAFORMAT_SUBTYPE_* GUIDs share everything but the first two bytes
+const SUBFORMAT_TAIL: [u8; 14] = [0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71];
+
+// RF64 puts this in every 32-bit size field and the real size in "ds64"
+const RF64_SIZE: u32 = u32::MAX;
+
+fn chunk_header(out: &mut Vec<u8>, id: &[u8; 4], size: u32) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&size.to_le_bytes());
+}
+
+// "fmt " chunk. Plain PCM/float for mono and stereo up to 16-bit ints and
+// 32-bit floats, WAVE_FORMAT_EXTENSIBLE for wider ints and more channels.
+fn fmt_chunk(depth: BitDepth, channels: u16, sample_rate: u32) -> Vec<u8> {
+    let tag = if depth.is_float() { FORMAT_IEEE_FLOAT } else { FORMAT_PCM };
+    let extensible = channels > 2 || (!depth.is_float() && depth.bits() > 16);
+    let block_align = channels * depth.bits() / 8;
+
+    let mut body = Vec::with_capacity(40);
+    body.extend_from_slice(&(if extensible { FORMAT_EXTENSIBLE } else { tag }).to_le_bytes());
+    body.extend_from_slice(&channels.to_le_bytes());
+    body.extend_from_slice(&sample_rate.to_le_bytes());
+    body.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    body.extend_from_slice(&block_align.to_le_bytes());
+    body.extend_from_slice(&depth.bits().to_le_bytes());
+    if extensible {
+        let mask: u32 = match channels {
+            1 => 0x4, // Front centre
+            2 => 0x3, // Front left and right
+            _ => 0,   // Unassigned
+        };
+        body.extend_from_slice(&22u16.to_le_bytes());
+        body.extend_from_slice(&depth.bits().to_le_bytes()); // Valid bits
+        body.extend_from_slice(&mask.to_le_bytes());
+        body.extend_from_slice(&tag.to_le_bytes());
+        body.extend_from_slice(&SUBFORMAT_TAIL);
+    } else if depth.is_float() {
+        // Non-PCM formats carry an (empty) extension size
+        body.extend_from_slice(&0u16.to_le_bytes());
+    }
+
+    let mut chunk = Vec::with_capacity(8 + body.len());
+    chunk_header(&mut chunk, b"fmt ", body.len() as u32);
+    chunk.extend_from_slice(&body);
+    chunk
+}
+
+/// Everything up to the sample data of a WAV file holding `data_len` bytes.
+/// Switches to RF64 when the file would pass 4 GiB.
+pub fn header(sample_rate: u32, channels: u16, depth: BitDepth, data_len: u64) -> Vec<u8> {
+    let channels = channels.max(1);
+    let fmt = fmt_chunk(depth, channels, sample_rate);
+    let frames = data_len / (channels as u64 * depth.bytes() as u64);
+    let padded = data_len + data_len % 2;
+
+    // Non-PCM files need the frame count in a "fact" chunk
+    let fact_len = if depth.is_float() { 12 } else { 0 };
+    let riff_size = 4 + fmt.len() as u64 + fact_len + 8 + padded;
+    let rf64 = riff_size > u32::MAX as u64;
+
+    let mut out = Vec::with_capacity(80);
+    if rf64 {
+        chunk_header(&mut out, b"RF64", RF64_SIZE);
+        out.extend_from_slice(b"WAVE");
+        chunk_header(&mut out, b"ds64", 28);
+        out.extend_from_slice(&(riff_size + 36).to_le_bytes());
+        out.extend_from_slice(&data_len.to_le_bytes());
+        out.extend_from_slice(&frames.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes()); // No other long chunks
+    } else {
+        chunk_header(&mut out, b"RIFF", riff_size as u32);
+        out.extend_from_slice(b"WAVE");
+    }
+    out.extend_from_slice(&fmt);
+    if depth.is_float() {
+        chunk_header(&mut out, b"fact", 4);
+        out.extend_from_slice(&(frames.min(u32::MAX as u64) as u32).to_le_bytes());
+    }
+    chunk_header(&mut out, b"data", if rf64 { RF64_SIZE } else { data_len as u32 });
+    out
+}
+
+/// Complete WAV file, little-endian samples
+pub fn write(pcm: &Pcm, channels: u16, sample_rate: u32) -> Vec<u8> {
+    let depth = pcm.bit_depth();
+    let data_len = (pcm.len() * depth.bytes()) as u64;
+    let mut out = header(sample_rate, channels, depth, data_len);
+    out.reserve(data_len as usize + 1);
+
+    match pcm {
+        Pcm::Float(samples) => {
+            for s in samples.iter() {
+                out.extend_from_slice(&s.to_le_bytes());
+            }
+        }
+        Pcm::Int(samples, depth) => {
+            let bytes = depth.bytes();
+            for s in samples {
+                out.extend_from_slice(&s.to_le_bytes()[..bytes]);
+            }
+        }
+    }
+    // Chunks are word aligned
+    if data_len % 2 == 1 {
+        out.push(0);
+    }
+    out
+}
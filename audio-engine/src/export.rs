@@ -1,11 +1,311 @@
-use crate::mixer::Mixer;
+use crate::analysis::loudness::{self, LoudnessStats};
+use crate::dsp::interpolation::EXPORT_INTERPOLATION;
+use crate::encode::{self, wav, BitDepth, ExportFormat};
+use crate::mixer::{AssetCache, Mixer};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use shared::{BusData, ClipData, Effect, EnvelopeParams, Instrument, Project, TimeRange, TrackData};
+
+// Longest a single effect or voice tail may add to a render
+const MAX_TAIL_SECONDS: f32 = 30.0;
+
+// A decaying tail counts as gone below this level (-60 dB)
+const TAIL_FLOOR: f32 = 0.001;
+
+// Measured tails end once the output stays under this level (-80 dB)...
+const TAIL_THRESHOLD: f32 = 0.0001;
+
+// ...for this long, or for the longest echo gap if that's longer
+const TAIL_HOLD_SECONDS: f32 = 0.5;
+
+// Tails are measured in blocks this long
+const TAIL_BLOCK: u64 = 4096;
+
+// Name of the stem with everything played straight into master
+const DIRECT_STEM_NAME: &str = "Master";
+
+/// What each stem file holds
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StemGroup {
+    #[default]
+    Tracks, // One per track
+    Buses,  // One per bus, plus the tracks routed straight to master
+}
+
+/// How a render ends once the range is over
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TailMode {
+    #[default]
+    Estimated, // The longest tail the effect and envelope settings allow
+    Auto,      // Keep going until the output has decayed under -80 dB
+    None,      // Stop dead at the end of the range
+}
+
+/// Which part of the project a render covers
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    // Timeline range; the whole project when None. Nothing starts playing
+    // after its end, clips running past it are cut there.
+    pub range: Option<TimeRange>,
+    // Samples played and thrown away before the range, so delays, reverbs
+    // and compressors are already running at its start
+    pub pre_roll: u64,
+    // Only these tracks (and the buses they feed), all when None
+    pub tracks: Option<Vec<u32>>,
+    pub tail: TailMode,
+    // Lets another thread follow the render and stop it
+    pub progress: Option<Arc<RenderProgress>>,
+}
+
+/// How far a render has got, shared with whoever is waiting for it. A
+/// cancelled render stops at the next block and returns what it has.
+#[derive(Debug, Default)]
+pub struct RenderProgress {
+    done: AtomicU64,  // Samples rendered
+    total: AtomicU64, // Samples planned, measured tails are added as they go
+    cancelled: AtomicBool,
+}
+
+impl RenderProgress {
+    /// 0 to 1
+    pub fn fraction(&self) -> f32 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.0;
+        }
+        (self.done.load(Ordering::Relaxed) as f64 / total as f64).min(1.0) as f32
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn plan(&self, samples: u64) {
+        self.total.fetch_add(samples, Ordering::Relaxed);
+    }
+
+    fn advance(&self, samples: u64) {
+        self.done.fetch_add(samples, Ordering::Relaxed);
+    }
+}
+
+impl RenderOptions {
+    // The project as the render hears it
+    fn prepare(&self, project: &Project) -> Project {
+        let mut project = project.clone();
+        if let Some(ids) = &self.tracks {
+            project.tracks.retain(|track| ids.contains(&track.id));
+        }
+        if let Some(range) = self.range {
+            for track in &mut project.tracks {
+                trim_clips(track, range.end);
+            }
+        }
+        project
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StemOptions {
+    pub group: StemGroup,
+    pub include_master: bool, // Master gain, automation and clipper
+    pub include_sends: bool,  // Track stems: sends and the bus returns they feed
+    pub render: RenderOptions,
+}
+
+impl Default for StemOptions {
+    fn default() -> Self {
+        Self { group: StemGroup::Tracks, include_master: false, include_sends: true, render: RenderOptions::default() }
+    }
+}
+
+/// One rendered stem, interleaved stereo. All stems of an export start at the
+/// start of the range and have the same length.
+pub struct Stem {
+    pub name: String,
+    pub samples: Vec<f32>,
+}
+
+/// Loudness target for an export
+#[derive(Clone, Copy, Debug)]
+pub struct Normalize {
+    pub target_lufs: f64,
+    pub true_peak_ceiling: f64, // dBTP, wins over the target
+}
 
 pub struct AudioExporter;
 
 impl AudioExporter {
+    /// Render a whole project offline: a headless mixer is hydrated with the
+    /// project and its assets, then played from the start to the end of the
+    /// last clip plus effect and release tails.
+    pub fn render_project(project: &Project, assets: AssetCache, sample_rate: f32) -> Vec<f32> {
+        Self::render_range(project, assets, sample_rate, &RenderOptions::default())
+    }
+
+    /// Render part of a project, some of its tracks, or both
+    pub fn render_range(project: &Project, assets: AssetCache, sample_rate: f32, options: &RenderOptions) -> Vec<f32> {
+        let mut mixer = Mixer::new(sample_rate);
+        mixer.samples.extend(assets);
+        let project = options.prepare(project);
+        let span = Span::new(&project, &mixer.samples, sample_rate, options);
+
+        mixer.load_project(&project, sample_rate);
+        let progress = options.progress.as_deref();
+        if let Some(progress) = progress {
+            progress.plan(span.pre_roll + span.length);
+        }
+        Self::render_span(&mut mixer, &span, progress)
+    }
+
+    /// Samples from the timeline start to the end of the last clip, plus the
+    /// longest tail any track can leave behind (releases, delays, buses)
+    pub fn project_length(project: &Project, assets: &AssetCache, sample_rate: f32) -> u64 {
+        Self::content_end(project) + estimated_tail(project, assets, sample_rate)
+    }
+
+    /// End of the last clip, in samples
+    pub fn content_end(project: &Project) -> u64 {
+        project.tracks.iter()
+            .flat_map(|track| &track.clips)
+            .map(|clip| match clip {
+                ClipData::Audio { start, duration, .. } | ClipData::Midi { start, duration, .. } => start + duration,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Render one stem per track or per bus. Muted tracks and buses get none.
+    pub fn render_stems(project: &Project, assets: AssetCache, sample_rate: f32, options: StemOptions) -> Vec<Stem> {
+        let mut mixer = Mixer::new(sample_rate);
+        mixer.samples.extend(assets);
+        mixer.master_bypass = !options.include_master;
+
+        // The whole mix decides the length, so every stem lines up
+        let project = options.render.prepare(project);
+        let span = Span::new(&project, &mixer.samples, sample_rate, &options.render);
+
+        let stems = match options.group {
+            StemGroup::Tracks => project.tracks.iter()
+                .filter(|track| !track.muted)
+                .map(|track| (track.name.clone(), track_stem(&project, track.id, options.include_sends)))
+                .collect::<Vec<_>>(),
+            StemGroup::Buses => std::iter::once((DIRECT_STEM_NAME.to_string(), bus_stem(&project, None)))
+                .chain(project.buses.iter()
+                    .filter(|bus| !bus.muted)
+                    .map(|bus| (bus.name.clone(), bus_stem(&project, Some(bus.id)))))
+                .collect(),
+        };
+
+        let progress = options.render.progress.as_deref();
+        if let Some(progress) = progress {
+            progress.plan(stems.len() as u64 * (span.pre_roll + span.length));
+        }
+        let mut stems: Vec<Stem> = stems.into_iter()
+            .take_while(|_| !progress.is_some_and(RenderProgress::is_cancelled))
+            .map(|(name, stem_project)| {
+                mixer.load_project(&stem_project, sample_rate);
+                Stem { name, samples: Self::render_span(&mut mixer, &span, progress) }
+            })
+            .collect();
+
+        // Measured tails differ per stem, the longest one counts for all
+        let longest = stems.iter().map(|stem| stem.samples.len()).max().unwrap_or(0);
+        for stem in &mut stems {
+            stem.samples.resize(longest, 0.0);
+        }
+        stems
+    }
+
+    // Pre-roll, the range, then the tail
+    fn render_span(mixer: &mut Mixer, span: &Span, progress: Option<&RenderProgress>) -> Vec<f32> {
+        mixer.seek(span.start - span.pre_roll);
+        mixer.set_playing(true);
+        Self::render_watched(mixer, span.pre_roll, progress);
+
+        let mut out = Self::render_watched(mixer, span.length, progress);
+        if let Some(hold) = span.measure_tail {
+            Self::render_tail(mixer, &mut out, hold, span.max_tail, progress);
+        }
+        mixer.set_playing(false);
+        out
+    }
+
+    // Keep rendering until the output has stayed quiet for `hold` samples,
+    // then cut right after the last sample above the threshold
+    fn render_tail(mixer: &mut Mixer, out: &mut Vec<f32>, hold: u64, max_tail: u64, progress: Option<&RenderProgress>) {
+        let mut keep = out.len();
+        let mut quiet = 0;
+        let mut rendered = 0;
+        while quiet < hold && rendered < max_tail && !progress.is_some_and(RenderProgress::is_cancelled) {
+            let len = TAIL_BLOCK.min(max_tail - rendered);
+            if let Some(progress) = progress {
+                progress.plan(len);
+            }
+            let block = Self::render_watched(mixer, len, progress);
+            for (i, frame) in block.chunks_exact(2).enumerate() {
+                if frame.iter().any(|s| s.abs() > TAIL_THRESHOLD) {
+                    quiet = 0;
+                    keep = out.len() + 2 * (i + 1);
+                } else {
+                    quiet += 1;
+                }
+            }
+            rendered += (block.len() / 2) as u64;
+            out.extend_from_slice(&block);
+        }
+        out.truncate(keep);
+    }
+
+    /// Gain in dB that brings `stats` to the target loudness without the true
+    /// peak passing the ceiling. Gain only, no limiting: a mix already close
+    /// to the ceiling comes out quieter than the target.
+    pub fn normalization_gain(stats: &LoudnessStats, normalize: &Normalize) -> f64 {
+        if !stats.integrated.is_finite() {
+            return 0.0; // Silence, nothing to measure
+        }
+        let gain = normalize.target_lufs - stats.integrated;
+        if stats.true_peak.is_finite() { gain.min(normalize.true_peak_ceiling - stats.true_peak) } else { gain }
+    }
+
+    /// Normalize interleaved stereo in place, returns the gain in dB
+    pub fn normalize(samples: &mut [f32], sample_rate: f32, normalize: &Normalize) -> f64 {
+        let stats = loudness::measure(samples, 2, sample_rate);
+        let gain_db = Self::normalization_gain(&stats, normalize);
+        apply_gain(samples, gain_db);
+        gain_db
+    }
+
+    /// Normalize stems by their sum, so they keep their balance and still add
+    /// up to a normalized mix. Returns the gain in dB.
+    pub fn normalize_stems(stems: &mut [Stem], sample_rate: f32, normalize: &Normalize) -> f64 {
+        let len = stems.first().map(|stem| stem.samples.len()).unwrap_or(0);
+        let mut sum = vec![0.0f32; len];
+        for stem in stems.iter() {
+            sum.iter_mut().zip(&stem.samples).for_each(|(s, x)| *s += x);
+        }
+        let gain_db = Self::normalization_gain(&loudness::measure(&sum, 2, sample_rate), normalize);
+        for stem in stems.iter_mut() {
+            apply_gain(&mut stem.samples, gain_db);
+        }
+        gain_db
+    }
+
     // Render the project to a Vec<f32> (interleaved stereo)
     // This is a blocking operation in WASM usually, or chunked
     pub fn render(mixer: &mut Mixer, duration_samples: u64) -> Vec<f32> {
+        Self::render_watched(mixer, duration_samples, None)
+    }
+
+    // `render`, reporting to `progress` and stopping early when it's cancelled
+    fn render_watched(mixer: &mut Mixer, duration_samples: u64, progress: Option<&RenderProgress>) -> Vec<f32> {
         let mut result = Vec::with_capacity((duration_samples * 2) as usize);
         let block_size = 128;
         
@@ -13,54 +313,246 @@ impl AudioExporter {
         let mut output_buf_r = vec![0.0; block_size];
         
         let mut rendered = 0;
+
+        // Offline there's time for the best resampling
+        let realtime_interpolation = mixer.interpolation;
+        mixer.set_interpolation(EXPORT_INTERPOLATION);
         
-        while rendered < duration_samples {
-            // Setup output buffers
-            let mut output = vec![&mut output_buf_l[..], &mut output_buf_r[..]];
+        while rendered < duration_samples && !progress.is_some_and(RenderProgress::is_cancelled) {
+            // The last block is cut short, so the mixer stops exactly at the end
+            let len = (duration_samples - rendered).min(block_size as u64) as usize;
+            let mut output = vec![&mut output_buf_l[..len], &mut output_buf_r[..len]];
             
             // Mixer process (advances internal state)
             mixer.process(&mut output);
             
             // Interleave
-            for i in 0..block_size {
-                if rendered + (i as u64) < duration_samples {
-                    result.push(output_buf_l[i]);
-                    result.push(output_buf_r[i]);
-                }
+            for i in 0..len {
+                result.push(output_buf_l[i]);
+                result.push(output_buf_r[i]);
             }
             
-            rendered += block_size as u64;
+            rendered += len as u64;
+            if let Some(progress) = progress {
+                progress.advance(len as u64);
+            }
         }
-        
+
+        mixer.set_interpolation(realtime_interpolation);
         result
     }
     
-    // Helper to create WAV header (simplified)
+    /// WAV header for `data_len` bytes of interleaved 32-bit float samples
     pub fn create_wav_header(sample_rate: u32, num_channels: u16, data_len: u32) -> Vec<u8> {
-        let mut header = Vec::new();
-        // RIFF
-        header.extend_from_slice(b"RIFF");
-        header.extend_from_slice(&(36 + data_len).to_le_bytes());
-        header.extend_from_slice(b"WAVE");
-        
-        // fmt
-        header.extend_from_slice(b"fmt ");
-        header.extend_from_slice(&16u32.to_le_bytes()); // Subchunk1Size
-        header.extend_from_slice(&1u16.to_le_bytes());  // AudioFormat (PCM)
-        header.extend_from_slice(&num_channels.to_le_bytes());
-        header.extend_from_slice(&sample_rate.to_le_bytes());
-        
-        let byte_rate = sample_rate * num_channels as u32 * 4; // 32-bit float
-        let block_align = num_channels * 4;
-        
-        header.extend_from_slice(&byte_rate.to_le_bytes());
-        header.extend_from_slice(&block_align.to_le_bytes());
-        header.extend_from_slice(&32u16.to_le_bytes()); // BitsPerSample
-        
-        // data
-        header.extend_from_slice(b"data");
-        header.extend_from_slice(&data_len.to_le_bytes());
-        
-        header
+        wav::header(sample_rate, num_channels, BitDepth::Float32, data_len as u64)
+    }
+
+    /// Encode interleaved stereo from `render` into a file
+    pub fn encode(samples: &[f32], sample_rate: u32, format: &ExportFormat) -> Result<Vec<u8>, String> {
+        encode::encode(samples, 2, sample_rate, format)
+    }
+}
+
+fn apply_gain(samples: &mut [f32], gain_db: f64) {
+    if gain_db != 0.0 {
+        let gain = 10f64.powf(gain_db / 20.0) as f32;
+        samples.iter_mut().for_each(|s| *s *= gain);
+    }
+}
+
+// Where a render starts and how long it runs, in samples
+struct Span {
+    start: u64,
+    pre_roll: u64,
+    length: u64,               // Range plus any estimated tail
+    measure_tail: Option<u64>, // Quiet samples that end a measured tail
+    max_tail: u64,
+}
+
+impl Span {
+    fn new(project: &Project, assets: &AssetCache, sample_rate: f32, options: &RenderOptions) -> Self {
+        let range = options.range.unwrap_or(TimeRange { start: 0, end: AudioExporter::content_end(project) });
+        let length = range.len() + match options.tail {
+            TailMode::Estimated => estimated_tail(project, assets, sample_rate),
+            TailMode::Auto | TailMode::None => 0,
+        };
+        let hold = longest_echo(project).max(TAIL_HOLD_SECONDS);
+        Self {
+            start: range.start,
+            pre_roll: options.pre_roll.min(range.start),
+            length,
+            measure_tail: (options.tail == TailMode::Auto).then(|| (hold * sample_rate).ceil() as u64),
+            max_tail: (MAX_TAIL_SECONDS * sample_rate) as u64,
+        }
+    }
+}
+
+// Longest tail any track can leave behind, in samples
+fn estimated_tail(project: &Project, assets: &AssetCache, sample_rate: f32) -> u64 {
+    let bus_tail = |bus_id: u32| {
+        project.buses.iter()
+            .find(|bus| bus.id == bus_id)
+            .map(|bus| chain_tail(&bus.effects))
+            .unwrap_or(0.0)
+    };
+    let tail = project.tracks.iter()
+        .map(|track| {
+            let buses = track.output_bus.iter()
+                .chain(track.sends.iter().map(|send| &send.bus_id))
+                .map(|id| bus_tail(*id))
+                .fold(0.0, f32::max);
+            instrument_tail(track, assets, sample_rate) + chain_tail(&track.effects) + buses
+        })
+        .fold(0.0, f32::max);
+    (tail * sample_rate).ceil() as u64
+}
+
+// Longest delay time anywhere, in seconds. Repeats can leave gaps this long
+// that a measured tail must not mistake for the end.
+fn longest_echo(project: &Project) -> f32 {
+    let delay = |effects: &[Effect]| effects.iter()
+        .map(|effect| match effect {
+            Effect::Delay { time_ms, .. } => time_ms / 1000.0,
+            _ => 0.0,
+        })
+        .fold(0.0, f32::max);
+    let pads = project.tracks.iter()
+        .filter_map(|track| match &track.instrument {
+            Some(Instrument::DrumRack { pads }) => Some(pads.iter().map(|pad| delay(&pad.effects)).fold(0.0, f32::max)),
+            _ => None,
+        });
+    project.tracks.iter().map(|track| delay(&track.effects))
+        .chain(project.buses.iter().map(|bus| delay(&bus.effects)))
+        .chain(pads)
+        .fold(0.0, f32::max)
+        .min(MAX_TAIL_SECONDS)
+}
+
+// Drop what starts at or after `end` and cut off what runs past it
+fn trim_clips(track: &mut TrackData, end: u64) {
+    track.clips.retain(|clip| match clip {
+        ClipData::Audio { start, .. } | ClipData::Midi { start, .. } => *start < end,
+    });
+    for clip in &mut track.clips {
+        match clip {
+            ClipData::Audio { start, duration, .. } => *duration = (*duration).min(end - *start),
+            ClipData::Midi { start, duration, notes, .. } => {
+                *duration = (*duration).min(end - *start);
+                let len = *duration;
+                notes.retain(|note| note.start < len);
+                for note in notes.iter_mut() {
+                    note.duration = note.duration.min(len - note.start);
+                }
+            }
+        }
+    }
+}
+
+// Effects in series: each one rings on after the previous one's tail
+fn chain_tail(effects: &[Effect]) -> f32 {
+    effects.iter().map(effect_tail).sum()
+}
+
+// Seconds an effect keeps sounding after its input goes silent
+fn effect_tail(effect: &Effect) -> f32 {
+    let seconds = match *effect {
+        Effect::Delay { time_ms, feedback, .. } => {
+            let feedback = feedback.abs();
+            // Repeats until the feedback has brought them under the floor
+            let repeats = if feedback < TAIL_FLOOR { 1.0 } else { TAIL_FLOOR.ln() / feedback.min(0.9999).ln() + 1.0 };
+            repeats * time_ms / 1000.0
+        }
+        Effect::Reverb { decay, .. } => decay,
+        Effect::Compressor { release, .. } => release / 1000.0,
+        _ => 0.0,
+    };
+    if seconds.is_finite() { seconds.clamp(0.0, MAX_TAIL_SECONDS) } else { MAX_TAIL_SECONDS }
+}
+
+// Seconds a track's instrument keeps sounding after its last note ends
+fn instrument_tail(track: &TrackData, assets: &AssetCache, sample_rate: f32) -> f32 {
+    let has_notes = track.clips.iter().any(|clip| matches!(clip, ClipData::Midi { .. }));
+    if !has_notes {
+        return 0.0;
     }
+    let seconds = match &track.instrument {
+        None | Some(Instrument::Synth) => EnvelopeParams::default().release_ms / 1000.0,
+        Some(Instrument::Sampler { envelope, .. }) => envelope.release_ms / 1000.0,
+        Some(Instrument::Granular { params, .. }) => (params.size_ms + params.spray_ms) / 1000.0,
+        Some(Instrument::DrumRack { pads }) => pads.iter()
+            .map(|pad| {
+                // One-shots play their whole sample, pitched up plays shorter
+                let voice = if pad.one_shot {
+                    let frames = assets.get(&pad.asset_id).map(|(l, _)| l.len()).unwrap_or(0);
+                    frames as f32 / sample_rate / 2f32.powf(pad.pitch / 12.0)
+                } else {
+                    pad.envelope.release_ms / 1000.0
+                };
+                voice + chain_tail(&pad.effects)
+            })
+            .fold(0.0, f32::max),
+    };
+    if seconds.is_finite() { seconds.clamp(0.0, MAX_TAIL_SECONDS) } else { MAX_TAIL_SECONDS }
+}
+
+// Muted bus that swallows whatever a stem must not hear
+fn add_sink_bus(project: &mut Project) -> u32 {
+    let id = project.buses.iter().map(|bus| bus.id + 1).max().unwrap_or(0);
+    project.buses.push(BusData {
+        id,
+        name: "Stem sink".into(),
+        gain_db: 0.0,
+        pan: 0.0,
+        muted: true,
+        effects: Vec::new(),
+    });
+    id
+}
+
+// Project with only `track_id` playing. Its own output bus and drum pad
+// outputs stay, they are part of the track's sound.
+fn track_stem(project: &Project, track_id: u32, include_sends: bool) -> Project {
+    let mut stem = project.clone();
+    stem.tracks.retain(|track| track.id == track_id);
+    for track in &mut stem.tracks {
+        track.soloed = false;
+        if !include_sends {
+            track.sends.clear();
+        }
+    }
+    stem
+}
+
+// Project with only what arrives at `bus_id` playing, or with `None` only the
+// tracks and pads routed straight to master
+fn bus_stem(project: &Project, bus_id: Option<u32>) -> Project {
+    let mut stem = project.clone();
+    let sink = add_sink_bus(&mut stem);
+    for bus in &mut stem.buses {
+        if Some(bus.id) != bus_id {
+            bus.muted = true;
+        }
+    }
+    // Anything going nowhere we listen to ends up in the muted sink
+    let heard = |output: Option<u32>| stem_target(&project.buses, output) == bus_id;
+    for track in &mut stem.tracks {
+        track.soloed = false;
+        if !heard(track.output_bus) {
+            track.output_bus = Some(sink);
+        }
+        track.sends.retain(|send| bus_id == Some(send.bus_id));
+        if let Some(Instrument::DrumRack { pads }) = &mut track.instrument {
+            for pad in pads.iter_mut().filter(|pad| pad.output_bus.is_some()) {
+                if !heard(pad.output_bus) {
+                    pad.output_bus = Some(sink);
+                }
+            }
+        }
+    }
+    stem
+}
+
+// Where an output really goes: missing buses fall back to master, like the mixer does
+fn stem_target(buses: &[BusData], output: Option<u32>) -> Option<u32> {
+    output.filter(|id| buses.iter().any(|bus| bus.id == *id))
 }
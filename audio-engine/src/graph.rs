@@ -1,5 +1,6 @@
 // Basic trait for any audio processing node
 use crate::midi::MidiEvent;
+use shared::EffectParam;
 
 pub trait AudioNode {
     /// Process a block of audio.
@@ -11,6 +12,9 @@ pub trait AudioNode {
     
     // Optional event handling
     fn handle_event(&mut self, _event: MidiEvent) {}
+
+    // Automation of an effect field, `value` in real units (see shared::EffectParam::range)
+    fn set_param(&mut self, _param: EffectParam, _value: f32) {}
 }
 
 // A simple sine wave source
@@ -54,6 +58,12 @@ pub struct AudioGraph {
     nodes: Vec<Box<dyn AudioNode + Send>>, // Simplified linear chain for Phase 1
 }
 
+impl Default for AudioGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AudioGraph {
     pub fn new() -> Self {
         Self { nodes: Vec::new() }
@@ -11,6 +11,11 @@ pub mod midi;
 pub mod synth;
 pub mod modulation;
 pub mod export;
+pub mod encode;
+pub mod wav;
+pub mod soundboard;
+pub mod automation;
+pub mod warp;
 pub use processor::WasmAudioProcessor;
 
 #[wasm_bindgen]
@@ -67,6 +67,6 @@ impl MidiClip {
         self.events.push(note_off);
         
         // Keep sorted
-        self.events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        self.events.sort_by_key(|e| e.timestamp);
     }
 }
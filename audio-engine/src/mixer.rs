@@ -1,10 +1,101 @@
-use crate::nodes::{GainNode, SynthNode, CompressorNode, DelayNode, EqNode, FilterNode, BassEnhancerNode};
+use crate::nodes::{ParametricEqNode, GainNode, SynthNode, GranularNode, SamplerNode, DrumRackNode, CompressorNode, DelayNode, EqNode, FilterNode, BassEnhancerNode, BypassNode};
 use crate::graph::AudioNode;
 use crate::midi::{MidiClip, MidiEvent};
-use shared::{Project, ClipData, Effect};
+use crate::synth::voice::VoiceSource;
+use crate::synth::wavetable::Wavetable;
+use crate::soundboard::Soundboard;
+use crate::dsp::smooth::SmoothedPan;
+use crate::dsp::stretch::TimeStretcher;
+use crate::dsp::interpolation::Interpolation;
+use crate::dsp::resample::Resampler;
+use crate::warp::WarpMap;
+use crate::automation::{is_stepped, AutomationSet, AUTOMATION_STEP};
+use shared::{Project, ClipData, Effect, Instrument, MixerCommand, ParamAddress, SampleTrigger, TempoChange, TempoMap, TriggerQuantize};
+use std::sync::Arc;
 
-fn linear_to_db_approx(val: f32) -> f32 {
-    if val <= 0.0001 { -80.0 } else { 20.0 * val.log10() }
+// Buffers are allocated for blocks up to this long, longer ones grow them
+pub const MAX_BLOCK_SIZE: usize = 8192;
+
+// Decoded project assets: Asset ID -> (L, R)
+pub type AssetCache = HashMap<String, (Vec<f32>, Vec<f32>)>;
+
+// Where a library asset came from, so a new engine rate converts the
+// original again rather than a conversion of it
+struct SampleSource {
+    rate: f32,
+    original: Option<(Vec<f32>, Vec<f32>)>, // None while the library holds it unconverted
+}
+
+impl SampleSource {
+    // Bring `sample`, the library copy, to `rate` from the original
+    fn convert(&mut self, sample: &mut (Vec<f32>, Vec<f32>), rate: f32) {
+        let from = self.rate.round() as u32;
+        let to = rate.round() as u32;
+        if from == to || from == 0 {
+            if let Some(original) = self.original.take() {
+                *sample = original;
+            }
+            return;
+        }
+        let original = self.original.get_or_insert_with(|| std::mem::take(sample));
+        let resampler = Resampler::new(from, to);
+        *sample = (resampler.process(&original.0), resampler.process(&original.1));
+    }
+}
+
+/// A library asset converted to the engine rate. The sinc conversion is
+/// slow, build this off the audio thread and hand it over with
+/// `Mixer::insert_sample`.
+pub struct PreparedSample {
+    sample: (Vec<f32>, Vec<f32>),
+    source: SampleSource,
+    rate: f32, // Engine rate it was converted to
+}
+
+impl PreparedSample {
+    pub fn new(left: Vec<f32>, right: Vec<f32>, source_rate: f32, engine_rate: f32) -> Self {
+        let mut sample = (left, right);
+        let mut source = SampleSource { rate: source_rate, original: None };
+        source.convert(&mut sample, engine_rate);
+        Self { sample, source, rate: engine_rate }
+    }
+}
+
+// No time signature in the project yet, assume 4/4
+const BEATS_PER_BAR: u64 = 4;
+
+
+// Create the insert node for an effect description
+pub(crate) fn build_effect(effect: &Effect, sample_rate: f32) -> Box<dyn AudioNode + Send> {
+    match *effect {
+        Effect::Eq { low_gain, mid_gain, high_gain } => {
+            let mut node = EqNode::new(sample_rate);
+            node.set_gains(low_gain, mid_gain, high_gain);
+            Box::new(node)
+        },
+        Effect::Compressor { threshold, ratio, attack, release, makeup_gain } => {
+            let mut node = CompressorNode::new(sample_rate);
+            node.set_params(threshold, ratio, attack, release, makeup_gain);
+            Box::new(node)
+        },
+        Effect::Delay { time_ms, feedback, mix } => {
+            let max_delay = (time_ms * 2.0).max(2000.0);
+            let mut node = DelayNode::new(max_delay, sample_rate);
+            node.delay_ms = time_ms;
+            node.feedback = feedback;
+            node.mix = mix;
+            Box::new(node)
+        },
+        Effect::Reverb { .. } => Box::new(BypassNode), // Not implemented yet, keeps the slot
+        Effect::Bass { boost, cutoff, drive, width } => {
+            let mut node = BassEnhancerNode::new(sample_rate);
+            node.set_params(boost, cutoff, drive, width);
+            Box::new(node)
+        },
+        Effect::ParametricEq { ref bands } => {
+            Box::new(ParametricEqNode::with_bands(bands, sample_rate))
+        }
+    }
 }
 
 
@@ -22,6 +113,130 @@ pub struct Clip {
     pub duration: u64,   // In samples
     pub offset: u64,     // Start point within the source asset
     pub asset_id: String,
+    pub stretch: f32,    // Timeline length / source length
+    pub transpose: f32,  // Semitones
+    stretcher: Option<TimeStretcher>, // Only for clips that need it
+    warp: Option<ClipWarp>,
+}
+
+// How the playhead moves through a block, the same for every clip
+#[derive(Clone, Copy)]
+struct CursorMotion {
+    rate: f64,
+    loop_range: Option<(f64, f64)>, // Absolute sample positions
+    stutter: bool,
+}
+
+impl CursorMotion {
+    fn advance(&self, cursor: f64) -> f64 {
+        // FX: Stutter (Repeat last section)
+        // If stutter is on, we wrap the cursor within a small window centered on where we engaged it.
+        // We need 'stutter_start' state. 
+        // For MVP: Stutter = Loop 1/16th note.
+        // We will use the loop logic below.
+
+        // Normal Advance
+        let cursor = cursor + self.rate;
+        
+        // Handle Looping
+        if let Some((loop_start, loop_end)) = self.loop_range {
+            if cursor >= loop_end {
+                loop_start + (cursor - loop_end) % (loop_end - loop_start)
+            } else if cursor < loop_start {
+                loop_start
+            } else {
+                cursor
+            }
+        } else if self.stutter {
+            // Auto-Stutter: Loop 1 beat (approx 44100 / 2) relative to current time?
+            // Without 'start time' snapshot, this is hard.
+            // Alternative: Quantize cursor to grid.
+            // playhead_cursor % (44100/4) -> Sawtooth wave.
+            // This works! "Gating"
+            let beat_len = 11025.0; // ~1/4 beat at 44.1k
+            let window = cursor / beat_len;
+            let start_of_beat = window.floor() * beat_len;
+            start_of_beat + (cursor % (beat_len * 0.5)) // Repeat first half of beat
+        } else {
+            cursor
+        }
+    }
+}
+
+// Beat placement of a warped clip, so it follows tempo changes
+#[derive(Clone)]
+struct ClipWarp {
+    map: WarpMap,
+    start_beat: f64,
+    end_beat: f64,
+}
+
+impl Clip {
+    pub fn new(start_time: u64, duration: u64, offset: u64, asset_id: String) -> Self {
+        Self { start_time, duration, offset, asset_id, stretch: 1.0, transpose: 0.0, stretcher: None, warp: None }
+    }
+
+    /// Lock the clip to beats: its start and end are placed on `tempo_map`
+    /// as it is now, and the audio is read through `map` from then on
+    pub fn set_warp(&mut self, map: WarpMap, tempo_map: &TempoMap, sample_rate: f32) {
+        let sr = sample_rate as f64;
+        let start_beat = tempo_map.beat_at(self.start_time as f64 / sr);
+        let end_beat = tempo_map.beat_at((self.start_time + self.duration) as f64 / sr);
+        self.warp = Some(ClipWarp { map, start_beat, end_beat });
+        self.ensure_stretcher(sample_rate);
+    }
+
+    pub fn is_warped(&self) -> bool {
+        self.warp.is_some()
+    }
+
+    // Source position and speed (source samples per output sample) at
+    // timeline sample `pos`, None outside the clip
+    fn source_at(&self, pos: f64, rate: f32, tempo_map: &TempoMap, sample_rate: f32) -> Option<(f64, f32)> {
+        match &self.warp {
+            Some(warp) => {
+                let beat = tempo_map.beat_at(pos / sample_rate as f64);
+                if !(beat >= warp.start_beat && beat < warp.end_beat) { return None; }
+                let clip_beat = beat - warp.start_beat;
+                let beats_per_sample = tempo_map.bpm_at(beat) as f64 / 60.0 / sample_rate as f64;
+                let speed = warp.map.slope_at(clip_beat) * beats_per_sample;
+                Some((warp.map.source_at(clip_beat), speed as f32 * rate))
+            },
+            None => {
+                let (start, end) = (self.start_time as f64, (self.start_time + self.duration) as f64);
+                if !(pos >= start && pos < end) { return None; }
+                Some((self.offset as f64 + (pos - start) / self.stretch as f64, rate / self.stretch))
+            }
+        }
+    }
+
+    /// Set stretch and transpose, preparing the stretcher if they need one
+    pub fn set_stretch(&mut self, stretch: f32, transpose: f32, sample_rate: f32) {
+        self.stretch = if stretch.is_finite() && stretch > 0.0 { stretch } else { 1.0 };
+        self.transpose = if transpose.is_finite() { transpose } else { 0.0 };
+        if self.stretch != 1.0 || self.transpose != 0.0 {
+            self.ensure_stretcher(sample_rate);
+        }
+    }
+
+    fn ensure_stretcher(&mut self, sample_rate: f32) {
+        if self.stretcher.is_none() {
+            self.stretcher = Some(TimeStretcher::new(sample_rate));
+        }
+    }
+}
+
+// Post-fader copy of a track into a bus
+pub struct TrackSend {
+    pub bus_id: u32,
+    pub gain: f32,  // Linear
+    last_gain: f32, // Level at the end of the previous block, ramped from
+}
+
+impl TrackSend {
+    pub fn new(bus_id: u32, gain: f32) -> Self {
+        Self { bus_id, gain, last_gain: gain }
+    }
 }
 
 // Represents a single channel (Track)
@@ -30,11 +245,17 @@ pub struct Track {
     pub clips: Vec<Clip>,
     pub midi_clips: Vec<PlacedMidiClip>, // MIDI Clips with placement
     pub synth: Option<SynthNode>,  // Optional Synth
+    pub granular: Option<GranularNode>, // Optional granular instrument, takes priority over synth
+    pub sampler: Option<SamplerNode>,   // Optional multi-sample instrument
+    pub drum_rack: Option<DrumRackNode>, // Optional pad instrument
+    pub output_bus: Option<u32>,        // None = master
+    pub sends: Vec<TrackSend>,
     pub effects: Vec<Box<dyn AudioNode + Send>>, // Effect Chain
     pub eq_node: EqNode, // Dedicated 3-Band EQ
     pub gain_node: GainNode,
     pub filter_node: FilterNode, // DJ Filter (One-knob)
     pub pan: f32, // -1.0 to 1.0
+    pan_gains: SmoothedPan,
     pub muted: bool,
     pub soloed: bool,
     pub sample_rate: f32,
@@ -48,6 +269,8 @@ pub struct Track {
     pub playback_rate: f32, // 1.0 = normal
     pub scratch_velocity: f32, // Additive velocity
     pub playhead_cursor: f64, // Fractional sample position
+    pub key_lock: bool, // Playback rate changes tempo only, not pitch
+    pub interpolation: Interpolation, // Resampling quality for clips and instruments
     
     // Party FX
     pub fx_stutter: bool,
@@ -60,7 +283,7 @@ pub struct Track {
     pub loop_end: f64,   // Absolute sample position
     
     // Automation
-    pub automation: Vec<shared::AutomationLane>,
+    pub automation: AutomationSet,
 }
 
 impl Track {
@@ -70,11 +293,17 @@ impl Track {
             clips: Vec::new(),
             midi_clips: Vec::new(),
             synth: None,
+            granular: None,
+            sampler: None,
+            drum_rack: None,
+            output_bus: None,
+            sends: Vec::new(),
             effects: Vec::new(),
             eq_node: EqNode::new(sample_rate),
-            gain_node: GainNode::new(1.0),
+            gain_node: GainNode::new(1.0, sample_rate),
             filter_node: FilterNode::new(sample_rate),
             pan: 0.0,
+            pan_gains: SmoothedPan::new(0.0, sample_rate),
             muted: false,
             soloed: false,
             sample_rate,
@@ -84,13 +313,15 @@ impl Track {
             playback_rate: 1.0,
             scratch_velocity: 0.0,
             playhead_cursor: 0.0,
+            key_lock: false,
+            interpolation: Interpolation::default(),
             fx_stutter: false,
             fx_tape_stop: false,
             fx_filter_sweep: false,
             loop_enabled: false,
             loop_start: 0.0,
             loop_end: 0.0,
-            automation: Vec::new(),
+            automation: AutomationSet::empty(),
         }
     }
     
@@ -100,26 +331,198 @@ impl Track {
         }
     }
     
-    // Helper to apply automation at the start of a block
-    fn apply_automation(&mut self) {
-        let current_time_sec = self.playhead_cursor / self.sample_rate as f64;
-        
-        // We clone to iterate to avoid borrow checker issues with self mutation?
-        // Actually, we can't iterate self.automation and mutate self at same time easily.
-        // We need to extract the values first.
-        let values: Vec<(String, f32)> = self.automation.iter()
-            .map(|lane| (lane.target.clone(), lane.get_value_at(current_time_sec)))
-            .collect();
-            
-        for (target, value) in values {
-            match target.as_str() {
-                "gain" => self.gain_node.set_gain(shared::db_to_linear(linear_to_db_approx(value))), // Value 0-1 mapped? Assuming automation is 0-1 linear
-                "pan" => self.pan = value, // -1 to 1
-                "filter" => self.apply_filter_value(value), // Helper needed
-                _ => {}
+    pub fn enable_granular(&mut self, asset_id: String) {
+        match &mut self.granular {
+            Some(granular) => granular.asset_id = asset_id,
+            None => self.granular = Some(GranularNode::new(self.sample_rate, asset_id)),
+        }
+    }
+
+    /// Select the instrument that plays this track's MIDI clips
+    pub fn set_instrument(&mut self, instrument: &Instrument) {
+        let sample_rate = self.sample_rate;
+        match instrument {
+            Instrument::Synth => {
+                self.granular = None;
+                self.sampler = None;
+                self.drum_rack = None;
+                self.enable_synth();
+            },
+            Instrument::Granular { asset_id, params } => {
+                self.sampler = None;
+                self.drum_rack = None;
+                self.enable_granular(asset_id.clone());
+                if let Some(granular) = &mut self.granular {
+                    granular.set_params(params.clone());
+                }
+            },
+            Instrument::Sampler { zones, envelope } => {
+                self.granular = None;
+                self.drum_rack = None;
+                let sampler = self.sampler.get_or_insert_with(|| SamplerNode::new(sample_rate));
+                sampler.set_zones(zones.clone());
+                sampler.set_envelope(*envelope);
+            },
+            Instrument::DrumRack { pads } => {
+                self.granular = None;
+                self.sampler = None;
+                let rack = self.drum_rack.get_or_insert_with(|| DrumRackNode::new(sample_rate));
+                rack.set_pads(pads.clone());
+            }
+        }
+        // New nodes start at the default quality
+        self.set_interpolation(self.interpolation);
+    }
+
+    // True when MIDI clips are played by something other than the synth
+    fn has_sample_instrument(&self) -> bool {
+        self.granular.is_some() || self.sampler.is_some() || self.drum_rack.is_some()
+    }
+
+    // Event queue of the instrument that receives live notes, if not the synth
+    fn sample_instrument_queue(&mut self) -> Option<&mut Vec<MidiEvent>> {
+        if let Some(granular) = &mut self.granular {
+            Some(&mut granular.event_queue)
+        } else if let Some(sampler) = &mut self.sampler {
+            Some(&mut sampler.event_queue)
+        } else if let Some(rack) = &mut self.drum_rack {
+            Some(&mut rack.event_queue)
+        } else {
+            None
+        }
+    }
+
+    // Queue MIDI events from clips that fall inside this block
+    fn collect_midi_events(midi_clips: &[PlacedMidiClip], block_start: u64, block_end: u64, queue: &mut Vec<MidiEvent>) {
+        for clip in midi_clips {
+            let clip_start_abs = clip.start_time;
+            let clip_end_abs = clip.start_time + clip.inner.duration;
+
+            if clip_end_abs <= block_start || clip_start_abs >= block_end {
+                continue;
+            }
+
+            for event in &clip.inner.events {
+                let event_abs_time = clip.start_time + event.timestamp;
+                if event_abs_time >= block_start && event_abs_time < block_end {
+                    queue.push(*event);
+                }
+            }
+        }
+    }
+
+    pub fn set_automation(&mut self, lanes: Vec<shared::AutomationLane>) {
+        // Master/bus paths don't belong on a track lane
+        self.automation = AutomationSet::new(lanes, ParamAddress::is_track_param);
+    }
+
+    // Render this block's automation. Gain, pan and sends follow the
+    // per-sample curves during processing, effects and the DJ filter are set
+    // every `AUTOMATION_STEP` samples. Instrument parameters are set once per
+    // block, like the MIDI that plays them, and glide via the node's own
+    // parameter smoothing.
+    fn apply_automation(&mut self, samples: usize, current_time: u64) {
+        if self.automation.is_empty() { return; }
+
+        let start = self.automation_time(current_time);
+        let rate = if self.follows_playhead() { self.playback_rate.max(0.0) } else { 1.0 };
+        let step = rate as f64 / self.sample_rate as f64;
+        self.automation.record(start);
+        self.automation.render(start, step, samples);
+
+        for i in 0..self.automation.len() {
+            if let Some((target, value)) = self.automation.block_value(i) {
+                if !is_stepped(&target) {
+                    self.set_param(target, value);
+                }
+            }
+        }
+    }
+
+    // Set effect and filter lanes to their value `sample` samples into the block
+    fn apply_stepped_automation(&mut self, sample: usize) {
+        for i in 0..self.automation.len() {
+            if let Some((target, value)) = self.automation.value_at(i, sample) {
+                if is_stepped(&target) {
+                    self.set_param(target, value);
+                }
+            }
+        }
+    }
+
+    /// Resampling quality for clips and sample-based instruments
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+        if let Some(granular) = &mut self.granular {
+            granular.set_interpolation(interpolation);
+        }
+        if let Some(sampler) = &mut self.sampler {
+            sampler.set_interpolation(interpolation);
+        }
+        if let Some(rack) = &mut self.drum_rack {
+            rack.interpolation = interpolation;
+        }
+    }
+
+    /// Keep the pitch when the playback rate changes (DJ decks)
+    pub fn set_key_lock(&mut self, enabled: bool) {
+        self.key_lock = enabled;
+        if enabled {
+            for clip in &mut self.clips {
+                clip.ensure_stretcher(self.sample_rate);
             }
         }
     }
+
+    // Audio clips play from the track's own (variable speed) cursor,
+    // instruments follow the mixer transport
+    fn follows_playhead(&self) -> bool {
+        !self.clips.is_empty() && self.granular.is_none() && self.sampler.is_none() && self.drum_rack.is_none() && self.synth.is_none()
+    }
+
+    /// Lane time in seconds for this block
+    pub fn automation_time(&self, current_time: u64) -> f64 {
+        let position = if self.follows_playhead() { self.playhead_cursor } else { current_time as f64 };
+        position / self.sample_rate as f64
+    }
+
+    /// Set a track parameter from a normalized 0-1 automation value
+    pub fn apply_param(&mut self, address: ParamAddress, normalized: f32) {
+        let value = address.range().denormalize(normalized);
+        let value = if address.range().scale == shared::ParamScale::Decibel { shared::db_to_linear(value) } else { value };
+        self.set_param(address, value);
+    }
+
+    /// Set a track parameter in real units, gains as linear factors
+    pub fn set_param(&mut self, address: ParamAddress, value: f32) {
+        match address {
+            ParamAddress::TrackGain => self.gain_node.set_gain(value),
+            ParamAddress::TrackPan => self.pan = value,
+            ParamAddress::TrackFilter => self.apply_filter_value(value),
+            ParamAddress::Send { bus_id } => {
+                if let Some(send) = self.sends.iter_mut().find(|s| s.bus_id == bus_id) {
+                    send.gain = value;
+                }
+            },
+            ParamAddress::Effect { slot, param } => {
+                if let Some(effect) = self.effects.get_mut(slot as usize) {
+                    effect.set_param(param, value);
+                }
+            },
+            ParamAddress::Synth(param) => {
+                if let Some(synth) = &mut self.synth {
+                    synth.set_param(param, value);
+                }
+            },
+            ParamAddress::Granular(param) => {
+                if let Some(granular) = &mut self.granular {
+                    granular.set_param(param, value);
+                }
+            },
+            // Master and bus lanes are applied by the mixer
+            _ => {}
+        }
+    }
     
     pub fn apply_filter_value(&mut self, value: f32) {
          // Same logic as set_track_filter in Mixer
@@ -142,10 +545,10 @@ impl Track {
     }
 
     // Process a block of audio for this track
-    pub fn process(&mut self, output: &mut [&mut [f32]], scratch_l: &mut [f32], scratch_r: &mut [f32], current_time: u64, asset_cache: &std::collections::HashMap<String, (Vec<f32>, Vec<f32>)>) {
+    pub fn process(&mut self, output: &mut [&mut [f32]], scratch_l: &mut [f32], scratch_r: &mut [f32], current_time: u64, asset_cache: &AssetCache, tempo_map: &TempoMap) {
         
         // Apply Automation for this block
-        self.apply_automation();
+        self.apply_automation(output[0].len(), current_time);
 
         if self.muted {
              for channel in output.iter_mut() {
@@ -162,175 +565,163 @@ impl Track {
         }
 
         // 1. Generate Signal
-        if let Some(synth) = &mut self.synth {
+        let block_start = current_time;
+        let block_end = current_time + samples as u64;
+
+        if let Some(granular) = &mut self.granular {
+            // MIDI / Granular Path
+            Self::collect_midi_events(&self.midi_clips, block_start, block_end, &mut granular.event_queue);
+            granular.process(asset_cache, output);
+
+        } else if let Some(sampler) = &mut self.sampler {
+            // MIDI / Sampler Path
+            Self::collect_midi_events(&self.midi_clips, block_start, block_end, &mut sampler.event_queue);
+            sampler.process(asset_cache, output);
+
+        } else if let Some(rack) = &mut self.drum_rack {
+            // MIDI / Drum Rack Path
+            Self::collect_midi_events(&self.midi_clips, block_start, block_end, &mut rack.event_queue);
+            rack.process(asset_cache, output);
+
+        } else if let Some(synth) = &mut self.synth {
             // MIDI / Synth Path
-            let block_start = current_time;
-            let block_end = current_time + samples as u64;
-            
-            for clip in &self.midi_clips {
-                let clip_start_abs = clip.start_time;
-                let clip_end_abs = clip.start_time + clip.inner.duration;
-                
-                if clip_end_abs <= block_start || clip_start_abs >= block_end {
-                    continue;
-                }
-                
-                for event in &clip.inner.events {
-                    let event_abs_time = clip.start_time + event.timestamp;
-                    if event_abs_time >= block_start && event_abs_time < block_end {
-                        synth.event_queue.push(*event);
-                    }
-                }
-            }
-             synth.process(&[], output);
-             
+            synth.set_transport(tempo_map, block_start as f64 / self.sample_rate as f64);
+
+            Self::collect_midi_events(&self.midi_clips, block_start, block_end, &mut synth.event_queue);
+            synth.process(&[], output);
+
         } else {
             // Audio Path with Resampling (Variable Speed)
-            for clip in &self.clips {
-                let clip_start = clip.start_time;
-                let clip_end = clip.start_time + clip.duration;
-                
-                // Effective Rate
-                let rate = self.playback_rate + self.scratch_velocity;
+            // Effective Rate
+            let rate = self.playback_rate + self.scratch_velocity;
+
+            // If rate is tiny, effectively paused (or very slow)
+            if rate.abs() >= 0.001 {
+                // 1. Calculate Dynamic Playback Rate (FX)
+                let mut current_rate = rate;
                 
-                // If rate is tiny, effectively paused (or very slow), still process to maintain buffer
-                if rate.abs() < 0.001 {
-                    continue;
+                // FX: Tape Stop (Decelerate smoothly)
+                if self.fx_tape_stop {
+                    // Simple linear deceleration simulation
+                    // If we tracked 'tape_speed' state it would be better, 
+                    // but for stateless stutter, let's just use a fixed low rate or modulation
+                    // Actually, let's just behave like a "Brake" - gradually slow down.
+                    // Since process is stateless per block, we need state. 
+                    // For MVP Party Mode: Instant slow playback (0.5x) or complete stop?
+                    // Let's do a "Brake" effect: map playhead_cursor decimal part to slow down? 
+                    // No, let's just target 0.0 velocity over time. Be simple: Rate = 0.0 effectively.
+                    // But user wants "Vinyl Break". 
+                    // Let's rely on scratch_velocity being manipulated by the UI or set rate to decreasing.
+                    // For now: Hard Replace Rate to slowly dropping.
+                    // SIMPLIFICATION: User holds button -> Rate drops.
+                    // But we only get one property update.
+                    // Let's assume frontend drives the ramp? No, latency.
+                    // Let's just set rate to very slow constant for now, or 0? 
+                    current_rate = 0.0; // Instant Stop for now
                 }
 
-                if let Some((l_source, r_source)) = asset_cache.get(&clip.asset_id) {
-                     // LOG ONCE logic...
-                     static mut LOG_PLAY_ONCE: bool = false;
-                     unsafe {
-                         if !LOG_PLAY_ONCE {
-                             web_sys::console::log_1(&"Mixer: Playing Clip (Variable Speed)".into());
-                             LOG_PLAY_ONCE = true;
-                         }
-                     }
+                let motion = CursorMotion {
+                    rate: current_rate as f64,
+                    loop_range: (self.loop_enabled && self.loop_end > self.loop_start).then_some((self.loop_start, self.loop_end)),
+                    stutter: self.fx_stutter,
+                };
+                let block_cursor = self.playhead_cursor;
 
-                    for i in 0..samples {
-                        // 1. Calculate Dynamic Playback Rate (FX)
-                        let mut current_rate = rate;
-                        
-                        // FX: Tape Stop (Decelerate smoothly)
-                        if self.fx_tape_stop {
-                            // Simple linear deceleration simulation
-                            // If we tracked 'tape_speed' state it would be better, 
-                            // but for stateless stutter, let's just use a fixed low rate or modulation
-                            // Actually, let's just behave like a "Brake" - gradually slow down.
-                            // Since process is stateless per block, we need state. 
-                            // For MVP Party Mode: Instant slow playback (0.5x) or complete stop?
-                            // Let's do a "Brake" effect: map playhead_cursor decimal part to slow down? 
-                            // No, let's just target 0.0 velocity over time. Be simple: Rate = 0.0 effectively.
-                            // But user wants "Vinyl Break". 
-                            // Let's rely on scratch_velocity being manipulated by the UI or set rate to decreasing.
-                            // For now: Hard Replace Rate to slowly dropping.
-                            // SIMPLIFICATION: User holds button -> Rate drops.
-                            // But we only get one property update.
-                            // Let's assume frontend drives the ramp? No, latency.
-                            // Let's just set rate to very slow constant for now, or 0? 
-                            current_rate = 0.0; // Instant Stop for now
-                        }
+                // Each clip replays the block's cursor path, so the asset and
+                // transpose are looked up once per block rather than per sample
+                let (out_l, out_r) = output.split_at_mut(1);
+                for clip in &mut self.clips {
+                    let Some((l_source, r_source)) = asset_cache.get(&clip.asset_id) else { continue };
+                    let pitch = 2.0f32.powf(clip.transpose / 12.0) * if self.key_lock { 1.0 } else { current_rate };
 
-                        // 2. Advance Cursor
-                        // FX: Stutter (Repeat last section)
-                        // If stutter is on, we wrap the cursor within a small window centered on where we engaged it.
-                        // We need 'stutter_start' state. 
-                        // For MVP: Stutter = Loop 1/16th note.
-                        // We will use the loop logic below.
+                    let mut cursor = block_cursor;
+                    for (sample_l, sample_r) in out_l[0].iter_mut().zip(out_r[0].iter_mut()) {
+                        let current_pos = cursor;
+                        cursor = motion.advance(cursor);
 
-                        // Normal Advance
-                        self.playhead_cursor += current_rate as f64;
-                        
-                        // 3. Handle Looping
-                        if self.loop_enabled && self.loop_end > self.loop_start {
-                             if self.playhead_cursor >= self.loop_end {
-                                 let loop_len = self.loop_end - self.loop_start;
-                                 self.playhead_cursor = self.loop_start + (self.playhead_cursor - self.loop_end) % loop_len;
-                             } else if self.playhead_cursor < self.loop_start {
-                                 self.playhead_cursor = self.loop_start;
-                             }
-                        } else if self.fx_stutter {
-                             // Auto-Stutter: Loop 1 beat (approx 44100 / 2) relative to current time?
-                             // Without 'start time' snapshot, this is hard.
-                             // Alternative: Quantize cursor to grid.
-                             // playhead_cursor % (44100/4) -> Sawtooth wave.
-                             // This works! "Gating"
-                             let beat_len = 11025.0; // ~1/4 beat at 44.1k
-                             let window = self.playhead_cursor / beat_len;
-                             let start_of_beat = window.floor() * beat_len;
-                             self.playhead_cursor = start_of_beat + (self.playhead_cursor % (beat_len * 0.5)); // Repeat first half of beat
-                        }
-                        
-                        // Map internal cursor to global timeline for clip check?
-                        // Actually, playhead_cursor IS the timeline position (conceptually).
-                        
-                        let current_pos = self.playhead_cursor;
-                        
-                        if current_pos >= clip_start as f64 && current_pos < clip_end as f64 {
-                            let offset_in_clip = current_pos - clip_start as f64;
-                            let source_idx_f = clip.offset as f64 + offset_in_clip;
-                            
-                            // Linear Interpolation
-                            let idx = source_idx_f.floor() as usize;
-                            let frac = (source_idx_f - idx as f64) as f32;
-                            
-                            if idx + 1 < l_source.len() {
-                                let l = l_source[idx] * (1.0 - frac) + l_source[idx+1] * frac;
-                                output[0][i] += l;
-                            } else if idx < l_source.len() {
-                                output[0][i] += l_source[idx];
-                            }
-                            
-                            if idx + 1 < r_source.len() {
-                                let r = r_source[idx] * (1.0 - frac) + r_source[idx+1] * frac;
-                                output[1][i] += r;
-                            } else if idx < r_source.len() {
-                                output[1][i] += r_source[idx];
+                        // Source samples per output sample, and how fast grains are read
+                        if let Some((source_idx_f, speed)) = clip.source_at(current_pos, current_rate, tempo_map, self.sample_rate) {
+                            // Stopped tape is silent, repeating one grain or sample would drone
+                            if speed.abs() < 1e-4 { continue; }
+
+                            // Tempo and pitch apart: time-stretch
+                            if let (Some(stretcher), true) = (&mut clip.stretcher, (pitch - speed).abs() > 1e-4) {
+                                // Follow small drift, restart on jumps (loops, seeks, clip start)
+                                if (stretcher.position() - source_idx_f).abs() > stretcher.hop() as f64 {
+                                    stretcher.reset(source_idx_f);
+                                }
+                                let (l, r) = stretcher.next_frame(l_source, r_source, speed, pitch, self.interpolation);
+                                *sample_l += l;
+                                *sample_r += r;
+                                continue;
                             }
+                        
+                            // Resample at the playback speed
+                            let rate = speed as f64;
+                            *sample_l += self.interpolation.read(l_source, source_idx_f, rate);
+                            *sample_r += self.interpolation.read(r_source, source_idx_f, rate);
                         }
                     }
                 }
+
+                // 2. Advance Cursor, once for the block
+                let mut cursor = block_cursor;
+                for _ in 0..samples {
+                    cursor = motion.advance(cursor);
+                }
+                self.playhead_cursor = cursor;
             }
         }
         
         
-        // 2. Effects Chain
-        for effect in &mut self.effects {
-             // 1. Copy Output to Scratch
-             scratch_l.copy_from_slice(output[0]);
-             scratch_r.copy_from_slice(output[1]);
-             
-             // 2. Process (Input=Scratch, Output=Output)
-             // Safety: We use disjoint slices here effectively
-             let inputs = vec![&scratch_l[..], &scratch_r[..]];
-             effect.process(&inputs, output);
-        }
-
-        // 3. Apply Dedicated EQ
-        self.eq_node.process(&[], output);
-         
-        // 4. Apply DJ Filter (Stereo In-Place)
-        self.filter_node.process(&[], output);
-
-        // 4. Apply Gain
-        self.gain_node.process(&[], output);
-        
-        // 3. Apply Pan
-        let pan_clamped = self.pan.clamp(-1.0, 1.0);
-        let angle = (pan_clamped + 1.0) * std::f32::consts::PI / 4.0;
-        let gain_l = angle.cos();
-        let gain_r = angle.sin();
-        
-        for i in 0..samples {
-             output[0][i] *= gain_l;
-             output[1][i] *= gain_r;
+        // 2-4. Effects, EQ and DJ filter. With automation they run in short
+        // steps so their parameters follow the curve within the block.
+        let step = if self.automation.is_empty() { samples } else { AUTOMATION_STEP };
+        let mut pos = 0;
+        while pos < samples {
+            let end = (pos + step).min(samples);
+            self.apply_stepped_automation(end - 1);
+            let (out_l, out_r) = output.split_at_mut(1);
+            let mut io = [&mut out_l[0][pos..end], &mut out_r[0][pos..end]];
+
+            // 2. Effects Chain
+            for effect in &mut self.effects {
+                 // 1. Copy Output to Scratch
+                 scratch_l[pos..end].copy_from_slice(io[0]);
+                 scratch_r[pos..end].copy_from_slice(io[1]);
+
+                 // 2. Process (Input=Scratch, Output=Output)
+                 let inputs = [&scratch_l[pos..end], &scratch_r[pos..end]];
+                 effect.process(&inputs, &mut io);
+            }
+
+            // 3. Apply Dedicated EQ
+            self.eq_node.process(&[], &mut io);
+
+            // 4. Apply DJ Filter (Stereo In-Place)
+            self.filter_node.process(&[], &mut io);
+            pos = end;
         }
-        
-        for i in 0..samples {
-             output[0][i] *= gain_l;
-             output[1][i] *= gain_r;
+
+        // 4. Fader: gain and pan as per-channel curves in the scratch buffers,
+        // left there for the drum pads on their own outputs
+        let fader_l = &mut scratch_l[..samples];
+        let fader_r = &mut scratch_r[..samples];
+        fader_l.fill(1.0);
+        fader_r.fill(1.0);
+        match self.automation.curve(ParamAddress::TrackGain) {
+            Some(curve) => self.gain_node.process_curve(curve, &mut [&mut *fader_l, &mut *fader_r]),
+            None => { self.gain_node.process(&[], &mut [&mut *fader_l, &mut *fader_r]); }
         }
+        match self.automation.curve(ParamAddress::TrackPan) {
+            Some(curve) => self.pan_gains.process_curve(curve, fader_l, fader_r),
+            None => {
+                self.pan_gains.set_pan(self.pan);
+                self.pan_gains.process(fader_l, fader_r);
+            }
+        }
+        for (s, g) in output[0].iter_mut().zip(fader_l.iter()) { *s *= g; }
+        for (s, g) in output[1].iter_mut().zip(fader_r.iter()) { *s *= g; }
         
         // 5. Apply Crossfader (handled by Mixer::process master sum, 
         // OR we apply gain here based on mixer's crossfader position passed in?
@@ -340,13 +731,11 @@ impl Track {
         // 6. Calculate Metering (RMS & Peak)
         let mut sum_sq = 0.0;
         let mut peak = 0.0;
-        for i in 0..samples {
-            let abs_l = output[0][i].abs();
-            let abs_r = output[1][i].abs();
-            let max_val = abs_l.max(abs_r);
+        for (l, r) in output[0].iter().zip(output[1].iter()) {
+            let max_val = l.abs().max(r.abs());
             
             if max_val > peak { peak = max_val; }
-            sum_sq += output[0][i] * output[0][i] + output[1][i] * output[1][i];
+            sum_sq += l * l + r * r;
         }
         
         let rms = (sum_sq / (samples as f32 * 2.0)).sqrt();
@@ -357,6 +746,123 @@ impl Track {
     }
 }
 
+// Summing channel between tracks/drum pads and master
+pub struct Bus {
+    pub id: u32,
+    pub effects: Vec<Box<dyn AudioNode + Send>>,
+    pub gain_node: GainNode,
+    pub pan: f32, // -1.0 to 1.0
+    pan_gains: SmoothedPan,
+    pub muted: bool,
+
+    // Input summed during the block
+    buf_l: Vec<f32>,
+    buf_r: Vec<f32>,
+}
+
+impl Bus {
+    pub fn new(id: u32, sample_rate: f32) -> Self {
+        Self {
+            id,
+            effects: Vec::new(),
+            gain_node: GainNode::new(1.0, sample_rate),
+            pan: 0.0,
+            pan_gains: SmoothedPan::new(0.0, sample_rate),
+            muted: false,
+            buf_l: vec![0.0; MAX_BLOCK_SIZE],
+            buf_r: vec![0.0; MAX_BLOCK_SIZE],
+        }
+    }
+
+    fn clear(&mut self, samples: usize) {
+        if self.buf_l.len() < samples {
+            self.buf_l.resize(samples, 0.0);
+            self.buf_r.resize(samples, 0.0);
+        }
+        self.buf_l[..samples].fill(0.0);
+        self.buf_r[..samples].fill(0.0);
+    }
+
+    fn add_input(&mut self, left: &[f32], right: &[f32], gain: f32) {
+        for (o, s) in self.buf_l.iter_mut().zip(left.iter()) { *o += s * gain; }
+        for (o, s) in self.buf_r.iter_mut().zip(right.iter()) { *o += s * gain; }
+    }
+
+    // Gain moves linearly from `from` to `to` across the block
+    fn add_input_ramp(&mut self, left: &[f32], right: &[f32], from: f32, to: f32) {
+        let step = (to - from) / left.len().max(1) as f32;
+        for (i, (l, r)) in left.iter().zip(right.iter()).enumerate() {
+            let gain = from + step * (i + 1) as f32;
+            self.buf_l[i] += l * gain;
+            self.buf_r[i] += r * gain;
+        }
+    }
+
+    // Per-channel gain curves (a track's fader), scaled by `gain`
+    fn add_input_fader(&mut self, left: &[f32], right: &[f32], fader_l: &[f32], fader_r: &[f32], gain: f32) {
+        for (o, (s, g)) in self.buf_l.iter_mut().zip(left.iter().zip(fader_l)) { *o += s * g * gain; }
+        for (o, (s, g)) in self.buf_r.iter_mut().zip(right.iter().zip(fader_r)) { *o += s * g * gain; }
+    }
+
+    // Gain follows a per-sample curve (send automation)
+    fn add_input_curve(&mut self, left: &[f32], right: &[f32], gains: &[f32], scale: f32) {
+        for (i, ((l, r), g)) in left.iter().zip(right.iter()).zip(gains.iter()).enumerate() {
+            self.buf_l[i] += l * g * scale;
+            self.buf_r[i] += r * g * scale;
+        }
+    }
+
+    // Run the bus chain and add the result into `output`.
+    // Curves are per-sample gain/pan automation for this block.
+    // `automation` holds the master lanes, this bus's gain, pan and effects
+    // are read from it
+    fn process_into(&mut self, output: &mut [&mut [f32]], scratch_l: &mut [f32], scratch_r: &mut [f32], automation: &AutomationSet) {
+        if self.muted { return; }
+        let samples = output[0].len();
+        let gain_curve = automation.curve(ParamAddress::BusGain { bus_id: self.id });
+        let pan_curve = automation.curve(ParamAddress::BusPan { bus_id: self.id });
+
+        // Effects in steps, same as a track's
+        let step = if automation.is_empty() { samples } else { AUTOMATION_STEP };
+        let mut pos = 0;
+        while pos < samples {
+            let end = (pos + step).min(samples);
+            for i in 0..automation.len() {
+                if let Some((ParamAddress::BusEffect { bus_id, slot, param }, value)) = automation.value_at(i, end - 1) {
+                    if let (true, Some(effect)) = (bus_id == self.id, self.effects.get_mut(slot as usize)) {
+                        effect.set_param(param, value);
+                    }
+                }
+            }
+            let mut io = [&mut self.buf_l[pos..end], &mut self.buf_r[pos..end]];
+            for effect in &mut self.effects {
+                scratch_l[pos..end].copy_from_slice(io[0]);
+                scratch_r[pos..end].copy_from_slice(io[1]);
+                let inputs = [&scratch_l[pos..end], &scratch_r[pos..end]];
+                effect.process(&inputs, &mut io);
+            }
+            pos = end;
+        }
+
+        let mut io = [&mut self.buf_l[..samples], &mut self.buf_r[..samples]];
+        match gain_curve {
+            Some(curve) => self.gain_node.process_curve(curve, &mut io),
+            None => { self.gain_node.process(&[], &mut io); }
+        }
+
+        let [io_l, io_r] = io;
+        match pan_curve {
+            Some(curve) => self.pan_gains.process_curve(curve, io_l, io_r),
+            None => {
+                self.pan_gains.set_pan(self.pan);
+                self.pan_gains.process(io_l, io_r);
+            }
+        }
+        for (o, s) in output[0].iter_mut().zip(io_l.iter()) { *o += s; }
+        for (o, s) in output[1].iter_mut().zip(io_r.iter()) { *o += s; }
+    }
+}
+
 #[derive(Clone)]
 pub struct PlacedMidiClip {
     pub start_time: u64,
@@ -367,11 +873,14 @@ use std::collections::HashMap;
 
 pub struct Mixer {
     pub tracks: Vec<Track>,
+    pub buses: Vec<Bus>,
     pub master_gain: GainNode,
     pub sample_rate: f32,
+    pub tempo: f32,
     pub current_time: u64,
     pub is_playing: bool,
-    pub samples: HashMap<String, (Vec<f32>, Vec<f32>)>, // Asset ID -> (L, R)
+    pub samples: AssetCache,
+    sample_sources: HashMap<String, SampleSource>,
     
     // Scratch buffers for processing
     pub track_buf_l: Vec<f32>,
@@ -381,13 +890,21 @@ pub struct Mixer {
     
     pub crossfader_position: f32, // -1.0 (A) to 1.0 (B)
     
-    // Soundboard
-    pub active_samples: Vec<SampleEvent>,
-}
+    // One-shot voices for the soundboard
+    pub soundboard: Soundboard,
 
-pub struct SampleEvent {
-    pub asset_id: String,
-    pub cursor: usize,
+    // Master and bus automation
+    pub master_automation: AutomationSet,
+
+    // Resampling quality for every track and the soundboard
+    pub interpolation: Interpolation,
+
+    // Tempo changes after the start; `tempo` holds until the first one
+    pub tempo_changes: Vec<TempoChange>,
+    pub tempo_map: TempoMap,
+
+    // Skip master gain and the output clipper (stems for another mix)
+    pub master_bypass: bool,
 }
 
 impl Mixer {
@@ -395,17 +912,25 @@ impl Mixer {
     pub fn new(sample_rate: f32) -> Self {
         let mut mixer = Self {
             tracks: Vec::new(),
-            master_gain: GainNode::new(1.0),
+            buses: Vec::new(),
+            master_gain: GainNode::new(1.0, sample_rate),
             sample_rate,
+            tempo: 120.0,
             current_time: 0,
             is_playing: false,
             samples: HashMap::new(),
-            track_buf_l: vec![0.0; 8192],
-            track_buf_r: vec![0.0; 8192],
-            scratch_l: vec![0.0; 8192],
-            scratch_r: vec![0.0; 8192],
+            sample_sources: HashMap::new(),
+            track_buf_l: vec![0.0; MAX_BLOCK_SIZE],
+            track_buf_r: vec![0.0; MAX_BLOCK_SIZE],
+            scratch_l: vec![0.0; MAX_BLOCK_SIZE],
+            scratch_r: vec![0.0; MAX_BLOCK_SIZE],
             crossfader_position: 0.0,
-            active_samples: Vec::new(),
+            soundboard: Soundboard::new(sample_rate),
+            master_automation: AutomationSet::empty(),
+            interpolation: Interpolation::default(),
+            tempo_changes: Vec::new(),
+            tempo_map: TempoMap::constant(120.0),
+            master_bypass: false,
         };
         
         // Generate Default SFX
@@ -485,7 +1010,8 @@ impl Mixer {
              temp_r[i] += temp_l[i - delay_samples] * feedback;
         }
         
-        self.add_sample("horn".to_string(), temp_l, temp_r);
+        let rate = self.sample_rate;
+        self.add_sample("horn".to_string(), temp_l, temp_r, rate);
         
         // 2. Classic Dub Siren
         // Authentic Dub Siren recipe:
@@ -547,31 +1073,127 @@ impl Mixer {
             siren_r[i] += siren_l[i - delay_samples] * feedback;
         }
 
-        self.add_sample("siren".to_string(), siren_l, siren_r);
+        let rate = self.sample_rate;
+        self.add_sample("siren".to_string(), siren_l, siren_r, rate);
         // web_sys::console::log_1(&"Mixer: Default SFX Generated (Horn, Siren)".into());
     }
     
-    pub fn add_sample(&mut self, id: String, left: Vec<f32>, right: Vec<f32>) {
-        self.samples.insert(id, (left, right));
+    /// Add a sample recorded at `source_rate`, converted to the engine rate
+    /// so it plays at its own pitch. Converts right here, use
+    /// `PreparedSample` and `insert_sample` from a real-time caller.
+    pub fn add_sample(&mut self, id: String, left: Vec<f32>, right: Vec<f32>, source_rate: f32) {
+        let sample = PreparedSample::new(left, right, source_rate, self.sample_rate);
+        self.insert_sample(id, sample);
+    }
+
+    /// Add a sample converted ahead of time. Only converts if the engine
+    /// rate changed in the meantime.
+    pub fn insert_sample(&mut self, id: String, prepared: PreparedSample) {
+        let PreparedSample { mut sample, mut source, rate } = prepared;
+        if rate.round() != self.sample_rate.round() {
+            source.convert(&mut sample, self.sample_rate);
+        }
+        self.soundboard.register(&id);
+        self.samples.insert(id.clone(), sample);
+        self.sample_sources.insert(id, source);
     }
 
-    pub fn trigger_sample(&mut self, id: String) {
-        if self.samples.contains_key(&id) {
-            web_sys::console::log_1(&format!("Mixer: Adding active sample '{}'", id).into());
-            self.active_samples.push(SampleEvent {
-                asset_id: id,
-                cursor: 0,
-            });
+    /// Fire a one-shot from the sample library. Quantized triggers wait for
+    /// the next beat or bar while the transport is running.
+    pub fn trigger_sample(&mut self, id: &str, options: SampleTrigger) {
+        if !self.samples.contains_key(id) {
+            return;
+        }
+
+        let grid = match options.quantize {
+            TriggerQuantize::Off => 0.0,
+            TriggerQuantize::Beat => 1.0,
+            TriggerQuantize::Bar => BEATS_PER_BAR as f64,
+        };
+        // Next beat or bar on the tempo map, a trigger already on one starts now
+        let start_at = if self.is_playing && grid > 0.0 {
+            let sr = self.sample_rate as f64;
+            let beat = self.tempo_map.beat_at(self.current_time as f64 / sr);
+            let next = ((beat - 1e-9) / grid).ceil() * grid;
+            ((self.tempo_map.seconds_at(next) * sr).round() as u64).max(self.current_time)
         } else {
-             web_sys::console::log_1(&format!("Mixer: Sample '{}' not found in library", id).into());
+            self.current_time
+        };
+
+        self.soundboard.trigger(id, &options, start_at);
+    }
+
+    pub fn choke_sample(&mut self, id: &str) {
+        self.soundboard.choke(id);
+    }
+
+    pub fn stop_all_samples(&mut self) {
+        self.soundboard.stop_all();
+    }
+
+    /// Apply a command from the UI or backend. Commands that only change
+    /// project state on the backend are ignored here.
+    pub fn handle_command(&mut self, command: MixerCommand) {
+        match command {
+            MixerCommand::SetTrackGain { track_id, gain } => {
+                self.set_track_gain(track_id, gain);
+                self.write_automation(Some(track_id), ParamAddress::TrackGain, ParamAddress::TrackGain.range().normalize(gain));
+            },
+            MixerCommand::SetTrackPan { track_id, pan } => {
+                self.set_track_pan(track_id, pan);
+                self.write_automation(Some(track_id), ParamAddress::TrackPan, ParamAddress::TrackPan.range().normalize(pan));
+            },
+            MixerCommand::SetTrackMute { track_id, muted } => self.set_track_mute(track_id, muted),
+            MixerCommand::SetTrackSolo { track_id, soloed } => self.set_track_solo(track_id, soloed),
+            MixerCommand::NoteOn { track_id, note, velocity } => {
+                self.trigger_synth_attack(track_id, note, velocity as f32 / 127.0)
+            },
+            MixerCommand::NoteOff { track_id, note } => self.trigger_synth_release(track_id, note),
+            MixerCommand::LoadProject { mut project } => {
+                project.migrate();
+                self.load_project(&project, self.sample_rate);
+            },
+            MixerCommand::Play => self.set_playing(true),
+            MixerCommand::Stop => self.set_playing(false),
+            MixerCommand::TriggerSample { asset_id, options } => self.trigger_sample(&asset_id, options),
+            MixerCommand::ChokeSample { asset_id } => self.choke_sample(&asset_id),
+            MixerCommand::StopAllSamples => self.stop_all_samples(),
+            MixerCommand::SetParam { track_id, target, value } => {
+                if let Some(address) = ParamAddress::parse(&target) {
+                    self.set_param(track_id, address, value);
+                }
+            },
+            MixerCommand::SetAutomationMode { track_id, target, mode } => {
+                if let Some(address) = ParamAddress::parse(&target) {
+                    self.set_automation_mode(track_id, address, mode);
+                }
+            },
+            MixerCommand::ReleaseParam { track_id, target } => {
+                if let Some(address) = ParamAddress::parse(&target) {
+                    self.release_param(track_id, address);
+                }
+            },
+            _ => {}
         }
     }
 
     pub fn set_playing(&mut self, playing: bool) {
+        if playing && !self.is_playing {
+            // Same grains on every play and export from the same spot
+            for granular in self.tracks.iter_mut().filter_map(|t| t.granular.as_mut()) {
+                granular.reset_rng();
+            }
+        }
         self.is_playing = playing;
+        if !playing {
+            self.soundboard.cancel_pending(self.current_time);
+            self.finish_automation_passes();
+        }
     }
     
     pub fn seek(&mut self, time_samples: u64) {
+        self.soundboard.cancel_pending(self.current_time);
+        self.finish_automation_passes();
         self.current_time = time_samples;
         for track in &mut self.tracks {
              if let Some(_synth) = &mut track.synth {
@@ -585,10 +1207,23 @@ impl Mixer {
     
     pub fn add_track(&mut self) -> u32 {
         let id = self.tracks.len() as u32;
-        self.tracks.push(Track::new(id, self.sample_rate));
+        let mut track = Track::new(id, self.sample_rate);
+        track.interpolation = self.interpolation;
+        self.tracks.push(track);
         id
     }
     
+    /// Warped clips keep their beats, so they speed up or slow down with it
+    pub fn set_tempo(&mut self, bpm: f32) {
+        self.tempo = bpm.max(1.0);
+        self.tempo_map = TempoMap::new(self.tempo, &self.tempo_changes);
+    }
+
+    pub fn set_tempo_map(&mut self, changes: Vec<TempoChange>) {
+        self.tempo_changes = changes;
+        self.tempo_map = TempoMap::new(self.tempo, &self.tempo_changes);
+    }
+    
     pub fn set_track_gain(&mut self, track_id: u32, gain_db: f32) {
         if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
             track.gain_node.set_gain(shared::db_to_linear(gain_db));
@@ -601,6 +1236,18 @@ impl Mixer {
         }
     }
 
+    pub fn set_track_mute(&mut self, track_id: u32, muted: bool) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.muted = muted;
+        }
+    }
+
+    pub fn set_track_solo(&mut self, track_id: u32, soloed: bool) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.soloed = soloed;
+        }
+    }
+
 pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
         if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
             track.playback_rate = rate;
@@ -609,6 +1256,23 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
         }
     }
     
+    /// Resampling quality for clips, grains, samplers and one-shots.
+    /// Real-time playback can use a cheaper mode than export.
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        interpolation.prepare();
+        self.interpolation = interpolation;
+        self.soundboard.interpolation = interpolation;
+        for track in &mut self.tracks {
+            track.set_interpolation(interpolation);
+        }
+    }
+
+    pub fn set_track_key_lock(&mut self, track_id: u32, enabled: bool) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.set_key_lock(enabled);
+        }
+    }
+
     pub fn set_track_scratch(&mut self, track_id: u32, velocity: f32) {
         if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
             track.scratch_velocity = velocity;
@@ -636,6 +1300,14 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
     }
 
     pub fn start_loop_seconds(&mut self, track_id: u32, length_seconds: f32) {
+        let sample_rate = self.sample_rate as f64;
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            // Loop starts at the current playhead position
+            track.loop_start = track.playhead_cursor;
+            track.loop_end = track.playhead_cursor + length_seconds as f64 * sample_rate;
+            track.loop_enabled = true;
+        }
+    }
 
     pub fn set_track_filter(&mut self, track_id: u32, value: f32) {
         // Value is -1.0 (LPF) to 1.0 (HPF), 0.0 is neutral
@@ -673,6 +1345,225 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
 
 
 
+    /// Load a user wavetable (single-cycle or multi-frame WAV) into a track's synth
+    /// and switch its voices to the wavetable source.
+    /// Switch a track's synth to a wavetable. Build the table with
+    /// `Wavetable::from_wav` first, the mip levels take too long to make here.
+    pub fn load_wavetable(&mut self, track_id: u32, table: Arc<Wavetable>) -> Result<(), String> {
+        let track = self.tracks.iter_mut().find(|t| t.id == track_id).ok_or("Track not found")?;
+        track.enable_synth();
+        if let Some(synth) = &mut track.synth {
+            synth.set_wavetable(table);
+            synth.set_source(VoiceSource::Wavetable);
+        }
+        Ok(())
+    }
+
+    pub fn set_synth_source(&mut self, track_id: u32, source: VoiceSource) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.enable_synth();
+            if let Some(synth) = &mut track.synth {
+                synth.set_source(source);
+            }
+        }
+    }
+
+    pub fn set_wavetable_morph(&mut self, track_id: u32, morph: f32) {
+        if let Some(synth) = self.tracks.iter_mut().find(|t| t.id == track_id).and_then(|t| t.synth.as_mut()) {
+            synth.set_wavetable_morph(morph);
+        }
+    }
+
+    pub fn set_track_output_bus(&mut self, track_id: u32, bus_id: Option<u32>) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.output_bus = bus_id;
+        }
+    }
+
+    pub fn set_bus_gain(&mut self, bus_id: u32, gain_db: f32) {
+        if let Some(bus) = self.buses.iter_mut().find(|b| b.id == bus_id) {
+            bus.gain_node.set_gain(shared::db_to_linear(gain_db));
+        }
+    }
+
+    pub fn set_bus_mute(&mut self, bus_id: u32, muted: bool) {
+        if let Some(bus) = self.buses.iter_mut().find(|b| b.id == bus_id) {
+            bus.muted = muted;
+        }
+    }
+
+    /// Add, change or (with `None`) remove a track's send into a bus
+    pub fn set_track_send(&mut self, track_id: u32, bus_id: u32, level_db: Option<f32>) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            match (level_db, track.sends.iter_mut().find(|s| s.bus_id == bus_id)) {
+                (Some(db), Some(send)) => send.gain = shared::db_to_linear(db),
+                (Some(db), None) => track.sends.push(TrackSend::new(bus_id, shared::db_to_linear(db))),
+                (None, _) => track.sends.retain(|s| s.bus_id != bus_id),
+            }
+        }
+    }
+
+    /// Set any parameter from a normalized 0-1 value (UI control), capturing
+    /// it if its lane is being written. `track_id` None = master and buses.
+    pub fn set_param(&mut self, track_id: Option<u32>, address: ParamAddress, normalized: f32) {
+        match track_id {
+            Some(id) if address.is_track_param() => {
+                if let Some(track) = self.tracks.iter_mut().find(|t| t.id == id) {
+                    track.apply_param(address, normalized);
+                }
+            },
+            None if !address.is_track_param() => self.apply_master_param(address, normalized),
+            _ => return,
+        }
+        self.write_automation(track_id, address, normalized);
+    }
+
+    // Lane time for a track (its own playhead) or the master lanes
+    fn automation_time(&self, track_id: Option<u32>) -> Option<f64> {
+        let sample_rate = self.sample_rate as f64;
+        match track_id {
+            Some(id) => self.tracks.iter().find(|t| t.id == id).map(|t| t.automation_time(self.current_time)),
+            None => Some(self.current_time as f64 / sample_rate),
+        }
+    }
+
+    fn automation_set(&mut self, track_id: Option<u32>, address: ParamAddress) -> Option<&mut AutomationSet> {
+        match track_id {
+            Some(id) if address.is_track_param() => self.tracks.iter_mut().find(|t| t.id == id).map(|t| &mut t.automation),
+            None if !address.is_track_param() => Some(&mut self.master_automation),
+            _ => None,
+        }
+    }
+
+    fn write_automation(&mut self, track_id: Option<u32>, address: ParamAddress, normalized: f32) {
+        let playing = self.is_playing;
+        let Some(time) = self.automation_time(track_id) else { return };
+        if let Some(set) = self.automation_set(track_id, address) {
+            set.write(address, normalized, time, playing);
+        }
+    }
+
+    pub fn set_automation_mode(&mut self, track_id: Option<u32>, address: ParamAddress, mode: shared::AutomationMode) {
+        let Some(time) = self.automation_time(track_id) else { return };
+        if let Some(set) = self.automation_set(track_id, address) {
+            set.set_mode(address, mode, time);
+        }
+    }
+
+    /// A control was let go, ends its touch pass
+    pub fn release_param(&mut self, track_id: Option<u32>, address: ParamAddress) {
+        let Some(time) = self.automation_time(track_id) else { return };
+        if let Some(set) = self.automation_set(track_id, address) {
+            set.release(address, time);
+        }
+    }
+
+    fn finish_automation_passes(&mut self) {
+        for track in &mut self.tracks {
+            let time = track.automation_time(self.current_time);
+            track.automation.finish(time);
+        }
+        self.master_automation.finish(self.current_time as f64 / self.sample_rate as f64);
+    }
+
+    /// Automation passes recorded since the last call, for the project copy
+    pub fn take_recorded_automation(&mut self) -> Vec<shared::RecordedAutomation> {
+        let mut recorded = self.master_automation.take_recorded();
+        for track in &mut self.tracks {
+            recorded.extend(track.automation.take_recorded().into_iter().map(|pass| shared::RecordedAutomation { track_id: Some(track.id), ..pass }));
+        }
+        recorded
+    }
+
+    pub fn set_master_automation(&mut self, lanes: Vec<shared::AutomationLane>) {
+        self.master_automation = AutomationSet::new(lanes, |a| !a.is_track_param());
+    }
+
+    // Same split as Track::apply_automation: levels per sample, bus effects
+    // stepped by the bus
+    fn apply_master_automation(&mut self, samples: usize) {
+        if self.master_automation.is_empty() { return; }
+
+        let step = 1.0 / self.sample_rate as f64;
+        self.master_automation.record(self.current_time as f64 * step);
+        self.master_automation.render(self.current_time as f64 * step, step, samples);
+
+        for i in 0..self.master_automation.len() {
+            if let Some((target, value)) = self.master_automation.block_value(i) {
+                if !is_stepped(&target) {
+                    self.set_master_param(target, value);
+                }
+            }
+        }
+    }
+
+    /// Set a master or bus parameter from a normalized 0-1 automation value
+    pub fn apply_master_param(&mut self, address: ParamAddress, normalized: f32) {
+        let value = address.range().denormalize(normalized);
+        let value = if address.range().scale == shared::ParamScale::Decibel { shared::db_to_linear(value) } else { value };
+        self.set_master_param(address, value);
+    }
+
+    /// Set a master or bus parameter in real units, gains as linear factors
+    pub fn set_master_param(&mut self, address: ParamAddress, value: f32) {
+        match address {
+            ParamAddress::MasterGain => self.master_gain.set_gain(value),
+            ParamAddress::BusGain { bus_id } => {
+                if let Some(bus) = self.buses.iter_mut().find(|b| b.id == bus_id) {
+                    bus.gain_node.set_gain(value);
+                }
+            },
+            ParamAddress::BusPan { bus_id } => {
+                if let Some(bus) = self.buses.iter_mut().find(|b| b.id == bus_id) {
+                    bus.pan = value;
+                }
+            },
+            ParamAddress::BusEffect { bus_id, slot, param } => {
+                if let Some(effect) = self.buses.iter_mut()
+                    .find(|b| b.id == bus_id)
+                    .and_then(|b| b.effects.get_mut(slot as usize)) {
+                    effect.set_param(param, value);
+                }
+            },
+            // Track lanes are applied by the track
+            _ => {}
+        }
+    }
+
+    /// Swap the instrument that plays a track's MIDI clips
+    pub fn set_track_instrument(&mut self, track_id: u32, instrument: Instrument) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.set_instrument(&instrument);
+        }
+    }
+
+    pub fn trigger_synth_attack(&mut self, track_id: u32, note: u8, velocity: f32) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            // Velocity comes in as 0.0 - 1.0 from the UI
+            let vel = (velocity.clamp(0.0, 1.0) * 127.0) as u8;
+            let event = MidiEvent::note_on(0, note, vel, 0);
+            if let Some(queue) = track.sample_instrument_queue() {
+                queue.push(event);
+                return;
+            }
+            track.enable_synth();
+            if let Some(synth) = &mut track.synth {
+                synth.event_queue.push(event);
+            }
+        }
+    }
+
+    pub fn trigger_synth_release(&mut self, track_id: u32, note: u8) {
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            let event = MidiEvent::note_off(0, note, 0);
+            if let Some(queue) = track.sample_instrument_queue() {
+                queue.push(event);
+            } else if let Some(synth) = &mut track.synth {
+                synth.event_queue.push(event);
+            }
+        }
+    }
+
     pub fn set_crossfader_position(&mut self, position: f32) {
         self.crossfader_position = position.clamp(-1.0, 1.0);
     }
@@ -688,41 +1579,12 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
     }
 
     pub fn update_track_effects(&mut self, track_id: u32, effects: Vec<Effect>) {
-         if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
-            track.effects.clear();
-            for effect_data in effects {
-                 match effect_data {
-                     Effect::Eq { low_gain, mid_gain, high_gain } => {
-                         // Note: If user adds EQ via Effect Rack, it's an Extra EQ (Insert).
-                         // The Console EQ is separate.
-                         let mut node = EqNode::new(self.sample_rate);
-                         node.set_gains(low_gain, mid_gain, high_gain);
-                         track.effects.push(Box::new(node));
-                     },
-                     Effect::Compressor { threshold, ratio, attack, release, makeup_gain } => {
-                          let mut node = CompressorNode::new(self.sample_rate);
-                          node.set_params(threshold, ratio, attack, release, makeup_gain);
-                          track.effects.push(Box::new(node));
-                     },
-                     Effect::Delay { time_ms, feedback, mix } => {
-                          let max_delay = (time_ms * 2.0).max(2000.0);
-                          let mut node = DelayNode::new(max_delay, self.sample_rate);
-                          node.delay_ms = time_ms;
-                          node.feedback = feedback;
-                          node.mix = mix;
-                          track.effects.push(Box::new(node));
-                     },
-                     Effect::Reverb { .. } => {
-                          // No-op
-                     },
-                     Effect::Bass { boost, cutoff, drive, width } => {
-                          let mut node = BassEnhancerNode::new(self.sample_rate);
-                          node.set_params(boost, cutoff, drive, width);
-                          track.effects.push(Box::new(node));
-                     }
-                 }
-            }
-         }
+        let sample_rate = self.sample_rate;
+        if let Some(track) = self.tracks.iter_mut().find(|t| t.id == track_id) {
+            // Note: If user adds EQ via Effect Rack, it's an Extra EQ (Insert).
+            // The Console EQ is separate.
+            track.effects = effects.iter().map(|e| build_effect(e, sample_rate)).collect();
+        }
     }
 
     /// Process mixer into stereo output
@@ -733,6 +1595,7 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
         }
 
         let samples = output[0].len();
+        let block_start = self.current_time;
 
         if self.track_buf_l.len() < samples {
              self.track_buf_l.resize(samples, 0.0);
@@ -742,6 +1605,15 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
         }
 
         if self.is_playing {
+            self.apply_master_automation(samples);
+
+            for bus in &mut self.buses {
+                bus.clear(samples);
+            }
+
+            // Any soloed track silences every track that isn't
+            let any_solo = self.tracks.iter().any(|t| t.soloed);
+
             for track in &mut self.tracks {
                  // Use pre-allocated buffers
                  let track_slice_l = &mut self.track_buf_l[..samples];
@@ -755,7 +1627,7 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
                  let scratch_slice_r = &mut self.scratch_r[..samples];
                  
                  // Process track
-                 track.process(&mut track_io, scratch_slice_l, scratch_slice_r, self.current_time, &self.samples);
+                 track.process(&mut track_io, scratch_slice_l, scratch_slice_r, self.current_time, &self.samples, &self.tempo_map);
                  
                  // Sum into master with Crossfader Gain
                  let xf_gain = match track.crossfader_group {
@@ -776,47 +1648,78 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
                      }
                  };
                  
-                 for i in 0..samples {
-                     output[0][i] += track_io[0][i] * xf_gain;
-                     output[1][i] += track_io[1][i] * xf_gain;
+                 if any_solo && !track.soloed {
+                     continue;
+                 }
+
+                 // Route to the track's bus, or master if it has none
+                 match track.output_bus.and_then(|id| self.buses.iter_mut().find(|b| b.id == id)) {
+                     Some(bus) => bus.add_input(track_io[0], track_io[1], xf_gain),
+                     None => {
+                         for i in 0..samples {
+                             output[0][i] += track_io[0][i] * xf_gain;
+                             output[1][i] += track_io[1][i] * xf_gain;
+                         }
+                     }
+                 }
+
+                 // Post-fader sends
+                 if !track.muted {
+                     for send in &mut track.sends {
+                         let curve = track.automation.curve(ParamAddress::Send { bus_id: send.bus_id });
+                         if let Some(bus) = self.buses.iter_mut().find(|b| b.id == send.bus_id) {
+                             match curve {
+                                 Some(curve) => bus.add_input_curve(track_io[0], track_io[1], curve, xf_gain),
+                                 None => bus.add_input_ramp(track_io[0], track_io[1], send.last_gain * xf_gain, send.gain * xf_gain),
+                             }
+                         }
+                         send.last_gain = send.gain;
+                     }
+                 }
+
+                 // Drum pads with their own outputs, after the track's fader
+                 if let (Some(rack), false) = (&track.drum_rack, track.muted) {
+                     let fader_l = &self.scratch_l[..samples];
+                     let fader_r = &self.scratch_r[..samples];
+                     for (bus_id, l, r) in rack.bus_outputs() {
+                         match self.buses.iter_mut().find(|b| b.id == bus_id) {
+                             Some(bus) => bus.add_input_fader(l, r, fader_l, fader_r, xf_gain),
+                             None => {
+                                 for i in 0..samples {
+                                     output[0][i] += l[i] * fader_l[i] * xf_gain;
+                                     output[1][i] += r[i] * fader_r[i] * xf_gain;
+                                 }
+                             }
+                         }
+                     }
                  }
             }
+
+            for bus in &mut self.buses {
+                bus.process_into(output, &mut self.scratch_l[..samples], &mut self.scratch_r[..samples], &self.master_automation);
+            }
             
             // Update Time
             self.current_time += samples as u64;
+        } else {
+            self.master_automation.clear();
         }
         
         // MIX One-Shot Samples
-        // Iterate backwards to allow removal
-        for i in (0..self.active_samples.len()).rev() {
-            let mut finished = false;
-            {
-                let event = &mut self.active_samples[i];
-                // Diag log once per event per block? Too spammy.
-                // Just log when starting? we can't easily.
-                
-                if let Some((l_src, r_src)) = self.samples.get(&event.asset_id) {
-                    for s in 0..samples {
-                        if event.cursor < l_src.len() {
-                            output[0][s] += l_src[event.cursor];
-                            output[1][s] += r_src[event.cursor]; // Assuming stereo or mono dup
-                            event.cursor += 1;
-                        } else {
-                            finished = true;
-                            break;
-                        }
-                    }
-                } else {
-                    finished = true;
-                }
-            }
-            if finished {
-                self.active_samples.remove(i);
-            }
+        {
+            let (out_l, out_r) = output.split_at_mut(1);
+            self.soundboard.process(&self.samples, out_l[0], out_r[0], block_start);
         }
         
+        if self.master_bypass {
+            return;
+        }
+
         // Apply Master Gain
-        self.master_gain.process(&[], output);
+        match self.master_automation.curve(ParamAddress::MasterGain) {
+            Some(curve) => self.master_gain.process_curve(curve, output),
+            None => { self.master_gain.process(&[], output); }
+        }
         
         // Master Soft Clipper (Limiter)
         // Prevents harsh digital clipping by rounding off peaks > 1.0
@@ -831,7 +1734,18 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
     }
     
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        // The library is kept at the engine rate, convert each original again
+        if self.sample_rate.round() != sample_rate.round() {
+            let old_rate = self.sample_rate;
+            for (id, sample) in self.samples.iter_mut() {
+                // Assets put straight into `samples` are at the old rate
+                let source = self.sample_sources.entry(id.clone())
+                    .or_insert(SampleSource { rate: old_rate, original: None });
+                source.convert(sample, sample_rate);
+            }
+        }
         self.sample_rate = sample_rate;
+        self.soundboard.set_sample_rate(sample_rate);
         // Ideally we would update all tracks/effects here too, but complex.
         // For now, this ensures new tracks are correct.
     }
@@ -841,72 +1755,75 @@ pub fn set_track_playback_rate(&mut self, track_id: u32, rate: f32) {
 
 impl Mixer {
      pub fn load_project(&mut self, project: &Project, sample_rate: f32) {
+        // Positions are counted at the project rate, assets play at `sample_rate`
+        let scale = if project.sample_rate == 0 { 1.0 } else { sample_rate as f64 / project.sample_rate as f64 };
+        let at_rate = |samples: u64| (samples as f64 * scale).round() as u64;
+
         self.tracks.clear();
+        self.tempo = project.tempo.max(1.0);
+        self.tempo_changes = project.tempo_map.clone();
+        self.tempo_map = project.tempo_map();
+        self.set_master_automation(project.master_automation.clone());
+
+        self.buses = project.buses.iter().map(|bus_data| {
+            let mut bus = Bus::new(bus_data.id, sample_rate);
+            bus.gain_node.set_gain(shared::db_to_linear(bus_data.gain_db));
+            bus.pan = bus_data.pan;
+            bus.muted = bus_data.muted;
+            bus.effects = bus_data.effects.iter().map(|e| build_effect(e, sample_rate)).collect();
+            bus
+        }).collect();
         
         for track_data in &project.tracks {
             let mut track = Track::new(track_data.id, sample_rate);
+            track.interpolation = self.interpolation;
             track.gain_node.set_gain(shared::db_to_linear(track_data.gain_db));
             track.pan = track_data.pan;
             track.muted = track_data.muted;
             track.soloed = track_data.soloed;
-            track.automation = track_data.automation.clone();
+            track.set_automation(track_data.automation.clone());
+            track.sends = track_data.sends.iter()
+                .map(|send| TrackSend::new(send.bus_id, shared::db_to_linear(send.level_db)))
+                .collect();
+            track.output_bus = track_data.output_bus;
+
+            // Hydrate Instrument
+            if let Some(instrument) = &track_data.instrument {
+                track.set_instrument(instrument);
+            }
             
             // Hydrate Effects
-            track.effects.clear();
-            for effect_data in &track_data.effects {
-                 match effect_data {
-                     Effect::Eq { low_gain, mid_gain, high_gain } => {
-                         let mut node = EqNode::new(sample_rate);
-                         node.set_gains(*low_gain, *mid_gain, *high_gain);
-                         track.effects.push(Box::new(node));
-                     },
-                     Effect::Compressor { threshold, ratio, attack, release, makeup_gain } => {
-                          let mut node = CompressorNode::new(sample_rate);
-                          node.set_params(*threshold, *ratio, *attack, *release, *makeup_gain);
-                          track.effects.push(Box::new(node));
-                     },
-                     Effect::Delay { time_ms, feedback, mix } => {
-                          let max_delay = (*time_ms * 2.0).max(2000.0);
-                          let mut node = DelayNode::new(max_delay, sample_rate);
-                          node.delay_ms = *time_ms;
-                          node.feedback = *feedback;
-                          node.mix = *mix;
-                          track.effects.push(Box::new(node));
-                     },
-                     Effect::Reverb { .. } => {
-                          web_sys::console::log_1(&"Mixer: Reverb not implemented yet".into());
-                     },
-                     Effect::Bass { boost, cutoff, drive, width } => {
-                          let mut node = BassEnhancerNode::new(sample_rate);
-                          node.set_params(*boost, *cutoff, *drive, *width);
-                          track.effects.push(Box::new(node));
-                     }
-                 }
-            }
+            track.effects = track_data.effects.iter().map(|e| build_effect(e, sample_rate)).collect();
             
             // Hydrate Clips
             for clip_data in &track_data.clips {
                 match clip_data {
-                    ClipData::Audio { start, duration, offset, asset_id, .. } => {
-                       track.clips.push(Clip {
-                           start_time: *start,
-                           duration: *duration,
-                           offset: *offset,
-                           asset_id: asset_id.clone(),
-                       });
+                    ClipData::Audio { start, duration, offset, asset_id, stretch, transpose, original_bpm, warp_markers, .. } => {
+                       let offset = at_rate(*offset);
+                       let mut clip = Clip::new(at_rate(*start), at_rate(*duration), offset, asset_id.clone());
+                       clip.set_stretch(*stretch, *transpose, sample_rate);
+                       if original_bpm.is_some() || !warp_markers.is_empty() {
+                           let markers: Vec<_> = warp_markers.iter()
+                               .map(|m| shared::WarpMarker { sample: at_rate(m.sample), ..*m })
+                               .collect();
+                           let map = WarpMap::new(&markers, offset, *original_bpm, sample_rate);
+                           clip.set_warp(map, &self.tempo_map, sample_rate);
+                       }
+                       track.clips.push(clip);
                     },
                     ClipData::Midi { start, duration, notes, .. } => {
-                        track.enable_synth();
+                        if !track.has_sample_instrument() {
+                            track.enable_synth();
+                        }
                         
-                        let mut midi_clip = MidiClip::new("Midi Clip", *duration);
+                        let mut midi_clip = MidiClip::new("Midi Clip", at_rate(*duration));
                         
                         for note in notes {
-                            // Convert samples to u64
-                            midi_clip.add_note(0, note.note, note.velocity, note.start, note.duration);
+                            midi_clip.add_note(0, note.note, note.velocity, at_rate(note.start), at_rate(note.duration));
                         }
                         
                         track.midi_clips.push(PlacedMidiClip {
-                            start_time: *start,
+                            start_time: at_rate(*start),
                             inner: midi_clip,
                         });
                     }
@@ -2,7 +2,8 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ModSource {
-    Lfo(usize),
+    Lfo(usize),       // Per-voice LFO
+    GlobalLfo(usize), // Shared by all voices of a synth
     Envelope(usize),
     Velocity,
     KeyTrack,
@@ -15,6 +16,7 @@ pub enum ModTarget {
     FilterResonance,
     OscPitch(usize),
     OscWave(usize),
+    WavetableMorph,
     Gain,
     Pan,
     // Add more as needed
@@ -32,6 +34,12 @@ pub struct ModulationMatrix {
     pub connections: Vec<ModConnection>,
 }
 
+impl Default for ModulationMatrix {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ModulationMatrix {
     pub fn new() -> Self {
         Self {
@@ -49,6 +57,7 @@ impl ModulationMatrix {
         &self, 
         target_check: &ModTarget, 
         lfo_values: &[f32], 
+        global_lfo_values: &[f32],
         env_values: &[f32],
         note_values: (f32, f32) // velocity, key (0-1)
     ) -> f32 {
@@ -67,12 +76,14 @@ impl ModulationMatrix {
                 (ModTarget::Pan, ModTarget::Pan) => true,
                 (ModTarget::OscPitch(i), ModTarget::OscPitch(j)) => i == j,
                 (ModTarget::OscWave(i), ModTarget::OscWave(j)) => i == j,
+                (ModTarget::WavetableMorph, ModTarget::WavetableMorph) => true,
                 _ => false,
             };
             
             if matches {
                 let src_val = match conn.source {
                     ModSource::Lfo(i) => lfo_values.get(i).cloned().unwrap_or(0.0),
+                    ModSource::GlobalLfo(i) => global_lfo_values.get(i).cloned().unwrap_or(0.0),
                     ModSource::Envelope(i) => env_values.get(i).cloned().unwrap_or(0.0),
                     ModSource::Velocity => note_values.0,
                     ModSource::KeyTrack => note_values.1,
@@ -1,34 +1,52 @@
 use crate::graph::AudioNode;
-use crate::dsp::filter::{Biquad, FilterType};
+use crate::dsp::filter::{SmoothedBiquad, FilterType};
+use crate::dsp::smooth::{SmoothedParam, DEFAULT_SMOOTHING_MS};
 use crate::dsp::DspProcessor;
+use shared::EffectParam;
 
 pub struct BassEnhancerNode {
-    filter_l: Biquad,
-    filter_r: Biquad,
-    drive: f32, // 0-1
-    width: f32, // 0-1 (0=mono, 1=stereo)
+    filter_l: SmoothedBiquad,
+    filter_r: SmoothedBiquad,
+    drive: SmoothedParam, // 0-1
+    width: SmoothedParam, // 0-1 (0=mono, 1=stereo)
+    // Settings as given to set_params (boost dB, cutoff Hz, drive 0-100, width)
+    settings: [f32; 4],
 }
 
 impl BassEnhancerNode {
     pub fn new(sample_rate: f32) -> Self {
         Self {
-            filter_l: Biquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
-            filter_r: Biquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
-            drive: 0.0,
-            width: 1.0,
+            filter_l: SmoothedBiquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
+            filter_r: SmoothedBiquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
+            drive: SmoothedParam::linear(0.0, DEFAULT_SMOOTHING_MS, sample_rate),
+            width: SmoothedParam::linear(1.0, DEFAULT_SMOOTHING_MS, sample_rate),
+            settings: [0.0, 100.0, 0.0, 1.0],
         }
     }
     
     pub fn set_params(&mut self, boost: f32, cutoff: f32, drive: f32, width: f32) {
+        self.settings = [boost, cutoff, drive, width];
         // Boost is in dB.
         self.filter_l.set_params(cutoff, 1.2, boost);
         self.filter_r.set_params(cutoff, 1.2, boost);
-        self.drive = drive / 100.0; // Map 0-100 to 0-1
-        self.width = width;
+        self.drive.set_target(drive / 100.0); // Map 0-100 to 0-1
+        self.width.set_target(width);
     }
 }
 
 impl AudioNode for BassEnhancerNode {
+    fn set_param(&mut self, param: EffectParam, value: f32) {
+        let [mut boost, mut cutoff, mut drive, mut width] = self.settings;
+        match param {
+            EffectParam::Boost => boost = value,
+            EffectParam::Cutoff => cutoff = value,
+            EffectParam::Drive => drive = value,
+            EffectParam::Width => width = value,
+            _ => return,
+        }
+        self.set_params(boost, cutoff, drive, width);
+    }
+
     fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
         // 1. Process Filter (Boost)
         if inputs.is_empty() {
@@ -64,10 +82,12 @@ impl AudioNode for BassEnhancerNode {
         for i in 0..out_l.len() {
             let mut l = out_l[i];
             let mut r = out_r[i];
+            let drive = self.drive.next_value();
+            let width = self.width.next_value();
             
             // Drive (Soft Clip Saturation)
-            if self.drive > 0.01 {
-                let drive_gain = 1.0 + self.drive * 4.0;
+            if drive > 0.01 {
+                let drive_gain = 1.0 + drive * 4.0;
                 l = (l * drive_gain).tanh();
                 r = (r * drive_gain).tanh();
                 // Optional: Makeup gain compensation? 
@@ -75,10 +95,10 @@ impl AudioNode for BassEnhancerNode {
             }
             
             // Width (Mid/Side)
-            if self.width < 0.99 {
+            if width < 0.99 {
                 let mid = (l + r) * 0.5;
                 let side = (l - r) * 0.5;
-                let side_processed = side * self.width;
+                let side_processed = side * width;
                 l = mid + side_processed;
                 r = mid - side_processed;
             }
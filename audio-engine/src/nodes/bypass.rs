@@ -0,0 +1,14 @@
+use crate::graph::AudioNode;
+
+// Stand-in for effects without DSP yet, so fx slot numbers match the project
+pub struct BypassNode;
+
+impl AudioNode for BypassNode {
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
+        // No inputs = in place, nothing to do
+        for (input, output) in inputs.iter().zip(outputs.iter_mut()) {
+            output.copy_from_slice(&input[..output.len()]);
+        }
+        true
+    }
+}
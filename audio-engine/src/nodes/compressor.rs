@@ -1,6 +1,8 @@
 use crate::graph::AudioNode;
 use crate::dsp::dynamics::EnvelopeFollower;
 use crate::dsp::{linear_to_db, db_to_linear};
+use shared::EffectParam;
+use crate::dsp::smooth::{SmoothedParam, DEFAULT_SMOOTHING_MS};
 
 pub struct CompressorNode {
     follower: EnvelopeFollower,
@@ -11,8 +13,11 @@ pub struct CompressorNode {
     pub attack_ms: f32,
     pub release_ms: f32,
     pub makeup_gain_db: f32,
-    
-    sample_rate: f32,
+
+    // Gliding copies of the params above
+    threshold: SmoothedParam,
+    ratio_smooth: SmoothedParam,
+    makeup: SmoothedParam,
 }
 
 impl CompressorNode {
@@ -27,7 +32,9 @@ impl CompressorNode {
             attack_ms: 10.0,
             release_ms: 100.0,
             makeup_gain_db: 0.0,
-            sample_rate,
+            threshold: SmoothedParam::linear(-20.0, DEFAULT_SMOOTHING_MS, sample_rate),
+            ratio_smooth: SmoothedParam::linear(4.0, DEFAULT_SMOOTHING_MS, sample_rate),
+            makeup: SmoothedParam::linear(0.0, DEFAULT_SMOOTHING_MS, sample_rate),
         }
     }
     
@@ -43,6 +50,23 @@ impl CompressorNode {
 }
 
 impl AudioNode for CompressorNode {
+    fn set_param(&mut self, param: EffectParam, value: f32) {
+        match param {
+            EffectParam::Threshold => self.threshold_db = value,
+            EffectParam::Ratio => self.ratio = value,
+            EffectParam::MakeupGain => self.makeup_gain_db = value,
+            EffectParam::Attack => {
+                self.attack_ms = value;
+                self.follower.set_params(self.attack_ms, self.release_ms);
+            },
+            EffectParam::Release => {
+                self.release_ms = value;
+                self.follower.set_params(self.attack_ms, self.release_ms);
+            },
+            _ => {}
+        }
+    }
+
     fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
         if inputs.is_empty() { return false; }
         
@@ -62,9 +86,15 @@ impl AudioNode for CompressorNode {
         let key_l = if has_sidechain { inputs[2] } else { in_l };
         let key_r = if has_sidechain { inputs.get(3).unwrap_or(&key_l) } else { if inputs.len() > 1 { in_r } else { in_l } };
         
-        let makeup_linear = db_to_linear(self.makeup_gain_db);
+        self.threshold.set_target(self.threshold_db);
+        self.ratio_smooth.set_target(self.ratio.max(1.0));
+        self.makeup.set_target(self.makeup_gain_db);
         
         for i in 0..out_l.len() {
+            let threshold_db = self.threshold.next_value();
+            let ratio = self.ratio_smooth.next_value();
+            let makeup_db = self.makeup.next_value();
+
             // Key Signal for Envelope
             let abs_key_l = key_l[i].abs();
             let abs_key_r = key_r[i].abs();
@@ -76,15 +106,15 @@ impl AudioNode for CompressorNode {
             
             // 2. Gain Calculation
             let mut gain_change_db = 0.0;
-            if env_db > self.threshold_db {
-                let overshoot = env_db - self.threshold_db;
+            if env_db > threshold_db {
+                let overshoot = env_db - threshold_db;
                 // Ratio 4:1 means output rises 1dB for every 4dB input
                 // So reduction is 3dB for every 4dB input
                 // reduction = overshoot * (1 - 1/ratio)
-                gain_change_db = -overshoot * (1.0 - 1.0 / self.ratio);
+                gain_change_db = -overshoot * (1.0 - 1.0 / ratio);
             }
             
-            let gain_linear = db_to_linear(gain_change_db) * makeup_linear;
+            let gain_linear = db_to_linear(gain_change_db + makeup_db);
             
             // 3. Apply Gain to Main Signal
             out_l[i] = in_l[i] * gain_linear;
@@ -1,5 +1,10 @@
 use crate::graph::AudioNode;
 use crate::dsp::delay::DelayLine;
+use shared::EffectParam;
+use crate::dsp::smooth::{SmoothedParam, DEFAULT_SMOOTHING_MS};
+
+// Time changes glide like a tape delay instead of jumping the read head
+const DELAY_TIME_SMOOTHING_MS: f32 = 100.0;
 
 pub struct DelayNode {
     delay_line_l: DelayLine,
@@ -11,6 +16,12 @@ pub struct DelayNode {
     pub mix: f32,
     
     sample_rate: f32,
+    max_samples: f32,
+
+    // Gliding copies of the params above
+    delay_samples: SmoothedParam,
+    feedback_smooth: SmoothedParam,
+    mix_smooth: SmoothedParam,
 }
 
 impl DelayNode {
@@ -23,11 +34,24 @@ impl DelayNode {
             feedback: 0.4,
             mix: 0.5,
             sample_rate,
+            max_samples: max_samples as f32,
+            delay_samples: SmoothedParam::linear(300.0 / 1000.0 * sample_rate, DELAY_TIME_SMOOTHING_MS, sample_rate),
+            feedback_smooth: SmoothedParam::linear(0.4, DEFAULT_SMOOTHING_MS, sample_rate),
+            mix_smooth: SmoothedParam::linear(0.5, DEFAULT_SMOOTHING_MS, sample_rate),
         }
     }
 }
 
 impl AudioNode for DelayNode {
+    fn set_param(&mut self, param: EffectParam, value: f32) {
+        match param {
+            EffectParam::TimeMs => self.delay_ms = value,
+            EffectParam::Feedback => self.feedback = value,
+            EffectParam::Mix => self.mix = value,
+            _ => {}
+        }
+    }
+
     fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
         if inputs.is_empty() { return false; }
         
@@ -40,23 +64,29 @@ impl AudioNode for DelayNode {
         let out_l = &mut out_l_slice[0];
         let out_r = &mut rest[0];
         
-        let delay_samples = self.delay_ms / 1000.0 * self.sample_rate;
+        let delay_samples = (self.delay_ms / 1000.0 * self.sample_rate).clamp(1.0, self.max_samples - 1.0);
+        self.delay_samples.set_target(delay_samples);
+        self.feedback_smooth.set_target(self.feedback);
+        self.mix_smooth.set_target(self.mix);
         
         for i in 0..out_l.len() {
             let dry_l = in_l[i];
             let dry_r = in_r[i];
+            let delay_samples = self.delay_samples.next_value();
+            let feedback = self.feedback_smooth.next_value();
+            let mix = self.mix_smooth.next_value();
             
             // Read from delay
             let wet_l = self.delay_line_l.read(delay_samples);
             let wet_r = self.delay_line_r.read(delay_samples);
             
             // Feed back
-            self.delay_line_l.write(dry_l + wet_l * self.feedback);
-            self.delay_line_r.write(dry_r + wet_r * self.feedback);
+            self.delay_line_l.write(dry_l + wet_l * feedback);
+            self.delay_line_r.write(dry_r + wet_r * feedback);
             
             // Mix
-            out_l[i] = dry_l * (1.0 - self.mix) + wet_l * self.mix;
-            out_r[i] = dry_r * (1.0 - self.mix) + wet_r * self.mix;
+            out_l[i] = dry_l * (1.0 - mix) + wet_l * mix;
+            out_r[i] = dry_r * (1.0 - mix) + wet_r * mix;
         }
         
         true
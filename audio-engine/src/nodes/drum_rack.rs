@@ -0,0 +1,296 @@
+use crate::dsp::envelope::Envelope;
+use crate::graph::AudioNode;
+use crate::midi::{MidiEvent, MidiEventType};
+use crate::mixer::{build_effect, AssetCache, MAX_BLOCK_SIZE};
+use crate::dsp::interpolation::Interpolation;
+use shared::DrumPad;
+
+// Overlapping hits per pad before the oldest is faded out
+const VOICES_PER_PAD: usize = 4;
+// Fade applied to a choked voice so the cut doesn't click
+const CHOKE_MS: f32 = 5.0;
+// Fade-out of a voice taken for a new hit when all of a pad's are busy
+const STEAL_FADE_MS: f32 = 5.0;
+
+struct PadVoice {
+    active: bool,
+    pos: f64,
+    env: Envelope,
+    choke_gain: f32, // 1.0 until choked, then fades to 0
+    choking: bool,
+    steal_fade: u32, // Samples left of a steal fade, 0 when not stolen
+    pending: Option<u8>, // Velocity of the hit waiting for the fade
+}
+
+impl PadVoice {
+    fn start(&mut self, velocity: u8) {
+        self.active = true;
+        self.pos = 0.0;
+        self.choke_gain = 1.0;
+        self.choking = false;
+        self.steal_fade = 0;
+        self.pending = None;
+        self.env.reset();
+        self.env.trigger(velocity as f32 / 127.0);
+    }
+
+    // Stop, or hand over to the hit waiting on this voice.
+    // Returns whether the voice still plays.
+    fn stop(&mut self) -> bool {
+        match self.pending.take() {
+            Some(velocity) => {
+                self.start(velocity);
+                true
+            },
+            None => {
+                self.active = false;
+                false
+            }
+        }
+    }
+}
+
+struct Pad {
+    params: DrumPad,
+    voices: Vec<PadVoice>,
+    next_voice: usize,
+    step: f64,
+    gain_l: f32,
+    gain_r: f32,
+    effects: Vec<Box<dyn AudioNode + Send>>,
+    buf_l: Vec<f32>,
+    buf_r: Vec<f32>,
+}
+
+impl Pad {
+    fn new(params: DrumPad, sample_rate: f32) -> Self {
+        let voices = (0..VOICES_PER_PAD).map(|_| PadVoice {
+            active: false,
+            pos: 0.0,
+            env: Envelope::with_params(params.envelope, sample_rate),
+            choke_gain: 1.0,
+            choking: false,
+            steal_fade: 0,
+            pending: None,
+        }).collect();
+
+        let gain = shared::db_to_linear(params.gain_db);
+        let pan = params.pan.clamp(-1.0, 1.0);
+        Self {
+            step: 2.0f64.powf(params.pitch as f64 / 12.0),
+            // Balance, keeps stereo samples intact at centre
+            gain_l: gain * (1.0 - pan).min(1.0),
+            gain_r: gain * (1.0 + pan).min(1.0),
+            effects: params.effects.iter().map(|e| build_effect(e, sample_rate)).collect(),
+            voices,
+            next_voice: 0,
+            buf_l: vec![0.0; MAX_BLOCK_SIZE],
+            buf_r: vec![0.0; MAX_BLOCK_SIZE],
+            params,
+        }
+    }
+
+    fn is_sounding(&self) -> bool {
+        self.voices.iter().any(|v| v.active)
+    }
+}
+
+// Pad instrument: every pad plays one asset from a single MIDI note.
+// Pads routed to a bus are kept out of the track output and picked up by
+// the mixer through `bus_outputs`.
+pub struct DrumRackNode {
+    pads: Vec<Pad>,
+    sample_rate: f32,
+    block_len: usize,
+    scratch_l: Vec<f32>,
+    scratch_r: Vec<f32>,
+    pub interpolation: Interpolation,
+
+    // Internal event queue, filled by the track from its MIDI clips
+    pub event_queue: Vec<MidiEvent>,
+}
+
+impl DrumRackNode {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut node = Self {
+            pads: Vec::new(),
+            sample_rate,
+            block_len: 0,
+            scratch_l: vec![0.0; MAX_BLOCK_SIZE],
+            scratch_r: vec![0.0; MAX_BLOCK_SIZE],
+            interpolation: Interpolation::default(),
+            event_queue: Vec::with_capacity(64),
+        };
+        node.set_pads(DrumPad::default_kit());
+        node
+    }
+
+    pub fn set_pads(&mut self, pads: Vec<DrumPad>) {
+        self.pads = pads.into_iter().map(|p| Pad::new(p, self.sample_rate)).collect();
+        self.block_len = 0;
+    }
+
+    pub fn pads(&self) -> impl Iterator<Item = &DrumPad> {
+        self.pads.iter().map(|p| &p.params)
+    }
+
+    pub fn handle_event(&mut self, event: MidiEvent) {
+        match event.event_type {
+            MidiEventType::NoteOn => self.trigger(event.note, event.velocity),
+            MidiEventType::NoteOff => self.release(event.note),
+            _ => {}
+        }
+    }
+
+    fn trigger(&mut self, note: u8, velocity: u8) {
+        let steal_samples = (STEAL_FADE_MS * 0.001 * self.sample_rate).max(1.0) as u32;
+        for i in 0..self.pads.len() {
+            if self.pads[i].params.note != note { continue; }
+
+            // Cut the other pads in this choke group
+            let group = self.pads[i].params.choke_group;
+            if group != 0 {
+                for (j, other) in self.pads.iter_mut().enumerate() {
+                    if j == i || other.params.choke_group != group { continue; }
+                    for voice in other.voices.iter_mut().filter(|v| v.active) {
+                        voice.choking = true;
+                        voice.pending = None;
+                    }
+                }
+            }
+
+            let pad = &mut self.pads[i];
+            let idx = pad.next_voice;
+            pad.next_voice = (idx + 1) % pad.voices.len();
+
+            // A busy voice fades out first, the hit starts once it's silent
+            let voice = &mut pad.voices[idx];
+            if !voice.active {
+                voice.start(velocity);
+                continue;
+            }
+            if voice.steal_fade == 0 {
+                voice.steal_fade = steal_samples;
+            }
+            voice.pending = Some(velocity);
+        }
+    }
+
+    fn release(&mut self, note: u8) {
+        for pad in self.pads.iter_mut().filter(|p| p.params.note == note && !p.params.one_shot) {
+            for voice in &mut pad.voices {
+                voice.env.release();
+                // Released before its stolen voice was free, never starts
+                voice.pending = None;
+            }
+        }
+    }
+
+    /// Audio of pads routed to buses in the last block: (bus id, L, R)
+    pub fn bus_outputs(&self) -> impl Iterator<Item = (u32, &[f32], &[f32])> {
+        let len = self.block_len;
+        self.pads.iter().filter_map(move |p| {
+            p.params.output_bus.map(|bus| (bus, &p.buf_l[..len], &p.buf_r[..len]))
+        })
+    }
+
+    pub fn process(&mut self, assets: &AssetCache, outputs: &mut [&mut [f32]]) -> bool {
+        // Process internal queue first
+        for i in 0..self.event_queue.len() {
+            let event = self.event_queue[i];
+            self.handle_event(event);
+        }
+        self.event_queue.clear();
+
+        let (left, right) = outputs.split_at_mut(1);
+        let out_l = &mut left[0];
+        let out_r = &mut right[0];
+        out_l.fill(0.0);
+        out_r.fill(0.0);
+
+        let samples = out_l.len();
+        if self.scratch_l.len() < samples {
+            self.scratch_l.resize(samples, 0.0);
+            self.scratch_r.resize(samples, 0.0);
+        }
+        self.block_len = samples;
+
+        let choke_step = 1.0 / (CHOKE_MS * 0.001 * self.sample_rate);
+        let steal_samples = (STEAL_FADE_MS * 0.001 * self.sample_rate).max(1.0);
+        let interpolation = self.interpolation;
+
+        for pad in &mut self.pads {
+            let sounding = pad.is_sounding();
+            if pad.buf_l.len() < samples {
+                pad.buf_l.resize(samples, 0.0);
+                pad.buf_r.resize(samples, 0.0);
+            }
+            let buf_l = &mut pad.buf_l[..samples];
+            let buf_r = &mut pad.buf_r[..samples];
+            buf_l.fill(0.0);
+            buf_r.fill(0.0);
+
+            // Pads without inserts can skip silence; effects may still have tails
+            if !sounding && pad.effects.is_empty() {
+                continue;
+            }
+
+            if let Some((src_l, src_r)) = assets.get(&pad.params.asset_id) {
+                for voice in pad.voices.iter_mut().filter(|v| v.active) {
+                    // A voice that ends with a hit waiting carries on as that hit
+                    for (l, r) in buf_l.iter_mut().zip(buf_r.iter_mut()) {
+                        if voice.pos >= src_l.len() as f64 {
+                            if voice.stop() { continue; }
+                            break;
+                        }
+
+                        let mut env = voice.env.process();
+                        if !voice.env.is_active() {
+                            if voice.stop() { continue; }
+                            break;
+                        }
+                        if voice.choking {
+                            voice.choke_gain -= choke_step;
+                            if voice.choke_gain <= 0.0 {
+                                if voice.stop() { continue; }
+                                break;
+                            }
+                            env *= voice.choke_gain;
+                        }
+                        if voice.steal_fade > 0 {
+                            env *= voice.steal_fade as f32 / steal_samples;
+                            voice.steal_fade -= 1;
+                            if voice.steal_fade == 0 {
+                                if voice.stop() { continue; }
+                                break;
+                            }
+                        }
+
+                        *l += interpolation.read(src_l, voice.pos, pad.step) * env * pad.gain_l;
+                        *r += interpolation.read(src_r, voice.pos, pad.step) * env * pad.gain_r;
+                        voice.pos += pad.step;
+                    }
+                }
+            } else {
+                for voice in &mut pad.voices {
+                    voice.active = false;
+                }
+            }
+
+            // Per-pad inserts
+            for effect in &mut pad.effects {
+                self.scratch_l[..samples].copy_from_slice(buf_l);
+                self.scratch_r[..samples].copy_from_slice(buf_r);
+                let inputs = [&self.scratch_l[..samples], &self.scratch_r[..samples]];
+                effect.process(&inputs, &mut [&mut *buf_l, &mut *buf_r]);
+            }
+
+            if pad.params.output_bus.is_none() {
+                for (o, s) in out_l.iter_mut().zip(buf_l.iter()) { *o += s; }
+                for (o, s) in out_r.iter_mut().zip(buf_r.iter()) { *o += s; }
+            }
+        }
+
+        true
+    }
+}
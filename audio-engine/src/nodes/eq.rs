@@ -1,29 +1,33 @@
 use crate::graph::AudioNode;
-use crate::dsp::filter::{Biquad, FilterType};
+use crate::dsp::filter::{SmoothedBiquad, FilterType};
 use crate::dsp::DspProcessor;
+use shared::EffectParam;
 
 pub struct EqNode {
     // Stereo filters for 3 bands
-    low_l: Biquad, low_r: Biquad,
-    mid_l: Biquad, mid_r: Biquad,
-    high_l: Biquad, high_r: Biquad,
+    low_l: SmoothedBiquad, low_r: SmoothedBiquad,
+    mid_l: SmoothedBiquad, mid_r: SmoothedBiquad,
+    high_l: SmoothedBiquad, high_r: SmoothedBiquad,
+    gains_db: [f32; 3],
 }
 
 impl EqNode {
     pub fn new(sample_rate: f32) -> Self {
         Self {
-            low_l: Biquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
-            low_r: Biquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
+            low_l: SmoothedBiquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
+            low_r: SmoothedBiquad::new(FilterType::LowShelf, 100.0, 0.707, sample_rate),
             
-            mid_l: Biquad::new(FilterType::Peaking, 1000.0, 1.0, sample_rate),
-            mid_r: Biquad::new(FilterType::Peaking, 1000.0, 1.0, sample_rate),
+            mid_l: SmoothedBiquad::new(FilterType::Peaking, 1000.0, 1.0, sample_rate),
+            mid_r: SmoothedBiquad::new(FilterType::Peaking, 1000.0, 1.0, sample_rate),
             
-            high_l: Biquad::new(FilterType::HighShelf, 5000.0, 0.707, sample_rate),
-            high_r: Biquad::new(FilterType::HighShelf, 5000.0, 0.707, sample_rate),
+            high_l: SmoothedBiquad::new(FilterType::HighShelf, 5000.0, 0.707, sample_rate),
+            high_r: SmoothedBiquad::new(FilterType::HighShelf, 5000.0, 0.707, sample_rate),
+            gains_db: [0.0; 3],
         }
     }
     
     pub fn set_gains(&mut self, low_db: f32, mid_db: f32, high_db: f32) {
+        self.gains_db = [low_db, mid_db, high_db];
         // Update all params (simplified: fixed freq/Q for now)
         // In real app, freq/Q would be params too.
         self.low_l.set_params(100.0, 0.707, low_db);
@@ -38,25 +42,38 @@ impl EqNode {
 }
 
 impl AudioNode for EqNode {
+    fn set_param(&mut self, param: EffectParam, value: f32) {
+        let [mut low, mut mid, mut high] = self.gains_db;
+        match param {
+            EffectParam::LowGain => low = value,
+            EffectParam::MidGain => mid = value,
+            EffectParam::HighGain => high = value,
+            _ => return,
+        }
+        self.set_gains(low, mid, high);
+    }
+
     fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
         // Assumes stereo input/output
-        if inputs.is_empty() { return false; }
-        
-        let in_l = inputs[0];
-        let in_r = inputs.get(1).unwrap_or(&inputs[0]); // Mono -> Stereo fallback
-        
         let (l, r) = outputs.split_at_mut(1);
         let out_l = &mut l[0];
         let out_r = &mut r[0];
         
         for i in 0..out_l.len() {
+            // No inputs = process in place (console EQ)
+            let (in_l, in_r) = match inputs {
+                [] => (out_l[i], out_r[i]),
+                [mono] => (mono[i], mono[i]), // Mono -> Stereo fallback
+                [in_l, in_r, ..] => (in_l[i], in_r[i]),
+            };
+
             // Cascade: Low -> Mid -> High
-            let mut l = self.low_l.process_sample(in_l[i]);
+            let mut l = self.low_l.process_sample(in_l);
             l = self.mid_l.process_sample(l);
             l = self.high_l.process_sample(l);
             out_l[i] = l;
             
-            let mut r = self.low_r.process_sample(in_r[i]);
+            let mut r = self.low_r.process_sample(in_r);
             r = self.mid_r.process_sample(r);
             r = self.high_r.process_sample(r);
             out_r[i] = r;
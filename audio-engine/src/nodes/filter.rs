@@ -1,6 +1,10 @@
 use crate::graph::AudioNode;
+use crate::dsp::smooth::{SmoothedParam, DEFAULT_SMOOTHING_MS};
 use std::f32::consts::PI;
 
+// Coefficients are recalculated this often while the cutoff glides
+const COEFF_UPDATE_INTERVAL: usize = 16;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FilterType {
     LowPass,
@@ -21,6 +25,13 @@ pub struct FilterNode {
     pub resonance: f32,
     pub sample_rate: f32,
     
+    // Cutoff in octaves (log2 Hz), glides toward `cutoff`
+    octave: SmoothedParam,
+
+    // Last coefficients and the cutoff and type they were made for
+    coeffs: (f32, f32, f32, f32, f32),
+    coeffs_for: (f32, FilterType),
+
     // Stereo State
     state_l: BiquadState,
     state_r: BiquadState,
@@ -33,6 +44,9 @@ impl FilterNode {
             cutoff: 20000.0,
             resonance: 0.0,
             sample_rate,
+            octave: SmoothedParam::exponential(20000.0f32.log2(), DEFAULT_SMOOTHING_MS, sample_rate),
+            coeffs: (0.0, 0.0, 0.0, 0.0, 0.0),
+            coeffs_for: (f32::NAN, FilterType::LowPass), // Never matches, first block computes
             state_l: BiquadState::default(),
             state_r: BiquadState::default(),
         }
@@ -61,69 +75,64 @@ impl FilterNode {
     }
 }
 
-impl AudioNode for FilterNode {
-    fn process(&mut self, _inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
-        // In our Mixer track chain, "inputs" are usually empty because we write directly to outputs?
-        // Wait, looking at mixer.rs calls: 
-        // For effects: effect.process(&inputs, output); 
-        // Inputs are scratch buffers in mixer.rs.
-        
-        // Calculate Coeffs once per block
-        let cutoff_clamped = self.cutoff.clamp(20.0, self.sample_rate / 2.0 - 100.0);
+impl FilterNode {
+    fn coeffs(&self, cutoff: f32) -> (f32, f32, f32, f32, f32) {
+        let cutoff_clamped = cutoff.clamp(20.0, self.sample_rate / 2.0 - 100.0);
         let omega = 2.0 * PI * cutoff_clamped / self.sample_rate;
-        let alpha = omega.sin() / (2.0 * 0.707); 
+        let alpha = omega.sin() / (2.0 * 0.707);
         let cos_w = omega.cos();
-        
-        let (b0, b1, b2, a0, a1, a2) = match self.filter_type {
-            FilterType::LowPass => {
-                let norm = 1.0 + alpha;
-                (
-                    (1.0 - cos_w) / 2.0 / norm,
-                    (1.0 - cos_w) / norm,
-                    (1.0 - cos_w) / 2.0 / norm,
-                    1.0,
-                    -2.0 * cos_w / norm,
-                    (1.0 - alpha) / norm
-                )
-            },
-            FilterType::HighPass => {
-                let norm = 1.0 + alpha;
-                (
-                    (1.0 + cos_w) / 2.0 / norm,
-                    -(1.0 + cos_w) / norm,
-                    (1.0 + cos_w) / 2.0 / norm,
-                    1.0,
-                    -2.0 * cos_w / norm,
-                    (1.0 - alpha) / norm
-                )
-            }
-        };
+        let norm = 1.0 + alpha;
 
-        // If inputs provided, read from them. If not, process in-place on outputs?
-        // The trait signature allows both. 
-        // If inputs is empty, we assume in-place on outputs (Track::process style).
-        // But mixer.rs passes inputs for effects. 
-        // Let's support: if inputs provided, read inputs write outputs. 
-        // If inputs empty, read outputs write outputs (in-place).
-        
-        let in_place = _inputs.is_empty();
-        
-        // Left Channel
-        if let Some(out_l) = outputs.get_mut(0) {
-            let samples = out_l.len();
-            for i in 0..samples {
-                let sample = if in_place { out_l[i] } else { _inputs[0][i] };
-                out_l[i] = Self::process_biquad(&mut self.state_l, sample, b0, b1, b2, a1, a2);
-            }
+        match self.filter_type {
+            FilterType::LowPass => (
+                (1.0 - cos_w) / 2.0 / norm,
+                (1.0 - cos_w) / norm,
+                (1.0 - cos_w) / 2.0 / norm,
+                -2.0 * cos_w / norm,
+                (1.0 - alpha) / norm
+            ),
+            FilterType::HighPass => (
+                (1.0 + cos_w) / 2.0 / norm,
+                -(1.0 + cos_w) / norm,
+                (1.0 + cos_w) / 2.0 / norm,
+                -2.0 * cos_w / norm,
+                (1.0 - alpha) / norm
+            ),
         }
-        
-        // Right Channel
-        if let Some(out_r) = outputs.get_mut(1) {
-            let samples = out_r.len();
-            for i in 0..samples {
-                 let sample = if in_place { out_r[i] } else { _inputs[1][i] };
-                 out_r[i] = Self::process_biquad(&mut self.state_r, sample, b0, b1, b2, a1, a2);
+    }
+}
+
+impl AudioNode for FilterNode {
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
+        if outputs.len() < 2 { return false; }
+        self.octave.set_target(self.cutoff.max(1.0).log2());
+
+        // If inputs is empty, process in-place on outputs (Track::process style)
+        let in_place = inputs.is_empty();
+        let (l, r) = outputs.split_at_mut(1);
+        let out_l = &mut l[0];
+        let out_r = &mut r[0];
+        let samples = out_l.len();
+
+        // Coefficients per sub-block while the cutoff glides so sweeps don't
+        // zipper, once for the rest of the block when it's settled
+        let mut start = 0;
+        while start < samples {
+            let end = if self.octave.is_smoothing() { (start + COEFF_UPDATE_INTERVAL).min(samples) } else { samples };
+            let cutoff = self.octave.skip((end - start) as u32).exp2();
+            if (cutoff, self.filter_type) != self.coeffs_for {
+                self.coeffs = self.coeffs(cutoff);
+                self.coeffs_for = (cutoff, self.filter_type);
+            }
+            let (b0, b1, b2, a1, a2) = self.coeffs;
+
+            for i in start..end {
+                let sample_l = if in_place { out_l[i] } else { inputs[0][i] };
+                let sample_r = if in_place { out_r[i] } else { inputs.get(1).unwrap_or(&inputs[0])[i] };
+                out_l[i] = Self::process_biquad(&mut self.state_l, sample_l, b0, b1, b2, a1, a2);
+                out_r[i] = Self::process_biquad(&mut self.state_r, sample_r, b0, b1, b2, a1, a2);
             }
+            start = end;
         }
         
         true
@@ -1,30 +1,57 @@
 use crate::graph::AudioNode;
+use crate::dsp::smooth::{SmoothedParam, DEFAULT_SMOOTHING_MS};
 
 pub struct GainNode {
-    gain: f32,
+    gain: SmoothedParam,
 }
 
 impl GainNode {
-    pub fn new(gain: f32) -> Self {
-        Self { gain }
+    pub fn new(gain: f32, sample_rate: f32) -> Self {
+        Self { gain: SmoothedParam::linear(gain, DEFAULT_SMOOTHING_MS, sample_rate) }
     }
     
     pub fn set_gain(&mut self, gain: f32) {
-        self.gain = gain;
+        self.gain.set_target(gain);
+    }
+
+    /// In-place gain following a per-sample curve (automation) instead of the smoother
+    pub fn process_curve(&mut self, gains: &[f32], outputs: &mut [&mut [f32]]) {
+        for channel in outputs.iter_mut() {
+            for (s, g) in channel.iter_mut().zip(gains.iter()) {
+                *s *= g;
+            }
+        }
+        // Knob moves after the lane continue from where it ended
+        if let Some(&last) = gains.last() {
+            self.gain.set_immediate(last);
+        }
     }
 }
 
 impl AudioNode for GainNode {
     fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
+        // No inputs = process in place, like the other insert nodes
+        // The ramp advances once per frame, shared by all channels
+        if inputs.is_empty() {
+            let samples = outputs.first().map_or(0, |c| c.len());
+            for i in 0..samples {
+                let gain = self.gain.next_value();
+                for channel in outputs.iter_mut() {
+                    channel[i] *= gain;
+                }
+            }
+            return true;
+        }
+
         // Simple stereo gain
         // Assume inputs[0] is stereo input (interleaved or planar? Let's stick to planar for now as per web audio usually)
         // Actually, for simplicity Phase 1, let's assume inputs are channel slices.
         // But the trait signature `inputs: &[&[f32]]` implies a list of buffers.
         
         // If we have input, process it.
-        if let Some(input) = inputs.first() {
+        if let Some(_input) = inputs.first() {
              // For each output channel
-             for (i, output_channel) in outputs.iter_mut().enumerate() {
+             for (i, _output_channel) in outputs.iter_mut().enumerate() {
                  // Map input channel to output channel (or mixdown/upmix)
                  // Simple 1:1 mapping for now
                  if i < inputs.len() {
@@ -50,13 +77,14 @@ impl AudioNode for GainNode {
         // If we treat it as "List of Channels for the single Input", it works on a single connection.
         
         let channels = std::cmp::min(inputs.len(), outputs.len());
+        let samples = outputs.first().map_or(0, |c| c.len());
         
-        for ch in 0..channels {
-            let input_data = inputs[ch];
-            let output_data = &mut outputs[ch];
-            
-            for (s_in, s_out) in input_data.iter().zip(output_data.iter_mut()) {
-                *s_out = s_in * self.gain;
+        for i in 0..samples {
+            let gain = self.gain.next_value();
+            for (input, output) in inputs.iter().zip(outputs.iter_mut()).take(channels) {
+                if let Some(s_in) = input.get(i) {
+                    output[i] = s_in * gain;
+                }
             }
         }
         
@@ -1,41 +1,97 @@
-use crate::graph::AudioNode;
+use crate::dsp::interpolation::Interpolation;
+use crate::mixer::AssetCache;
+use crate::midi::{MidiEvent, MidiEventType};
 use crate::synth::granular::GranularSynth;
+use shared::{GrainWindow, GranularParam, GranularParams};
 
+// Granular instrument: MIDI notes play grains from a project asset.
+// The asset is looked up in the mixer's sample cache every block, so it can
+// be loaded after the project without rebuilding the node.
 pub struct GranularNode {
     engine: GranularSynth,
+    pub asset_id: String,
+
+    // Internal event queue, filled by the track from its MIDI clips
+    pub event_queue: Vec<MidiEvent>,
 }
 
 impl GranularNode {
-    pub fn new(sample_rate: f32) -> Self {
+    pub fn new(sample_rate: f32, asset_id: String) -> Self {
         Self {
             engine: GranularSynth::new(sample_rate, 64),
+            asset_id,
+            event_queue: Vec::with_capacity(64),
         }
     }
-    
-    pub fn set_buffer(&mut self, buffer: Vec<f32>) {
-        self.engine.set_buffer(buffer);
+
+    pub fn params(&self) -> &GranularParams {
+        &self.engine.params
     }
-    
-    pub fn set_params(&mut self, density: f32, size_ms: f32, spray_ms: f32, pos: f64) {
-        self.engine.density = density;
-        self.engine.size_ms = size_ms;
-        self.engine.spray_ms = spray_ms;
-        self.engine.playback_pos = pos;
+
+    pub fn set_params(&mut self, params: GranularParams) {
+        self.engine.set_params(params);
+    }
+
+    /// Set one parameter from automation, `value` in real units
+    pub fn set_param(&mut self, param: GranularParam, value: f32) {
+        let p = &mut self.engine.params;
+        match param {
+            GranularParam::Position => p.position = value.clamp(0.0, 1.0),
+            GranularParam::Density => p.density = value.max(0.1),
+            GranularParam::Size => p.size_ms = value.max(1.0),
+            GranularParam::Spray => p.spray_ms = value.max(0.0),
+            GranularParam::PitchJitter => p.pitch_jitter = value.max(0.0),
+            GranularParam::PanSpread => p.pan_spread = value.clamp(0.0, 1.0),
+            GranularParam::Reverse => p.reverse_prob = value.clamp(0.0, 1.0),
+            GranularParam::Window => {
+                p.window = match value as usize {
+                    0 => GrainWindow::Hann,
+                    1 => GrainWindow::Triangle,
+                    2 => GrainWindow::Tukey,
+                    _ => GrainWindow::Gaussian,
+                }
+            },
+        }
     }
-}
 
-impl AudioNode for GranularNode {
-    fn process(&mut self, _inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.engine.interpolation = interpolation;
+    }
+
+    pub fn reset_rng(&mut self) {
+        self.engine.reset_rng();
+    }
+
+    pub fn handle_event(&mut self, event: MidiEvent) {
+        match event.event_type {
+            MidiEventType::NoteOn => self.engine.note_on(event.note, event.velocity),
+            MidiEventType::NoteOff => self.engine.note_off(event.note),
+            _ => {}
+        }
+    }
+
+    pub fn process(&mut self, assets: &AssetCache, outputs: &mut [&mut [f32]]) -> bool {
+        // Process internal queue first
+        for i in 0..self.event_queue.len() {
+            let event = self.event_queue[i];
+            self.handle_event(event);
+        }
+        self.event_queue.clear();
+
         let (left, right) = outputs.split_at_mut(1);
         let out_l = &mut left[0];
         let out_r = &mut right[0];
-        
-        for i in 0..out_l.len() {
-            let (l, r) = self.engine.process();
-            out_l[i] = l;
-            out_r[i] = r;
+
+        let Some((src_l, src_r)) = assets.get(&self.asset_id) else {
+            out_l.fill(0.0);
+            out_r.fill(0.0);
+            return true;
+        };
+
+        for (l, r) in out_l.iter_mut().zip(out_r.iter_mut()) {
+            (*l, *r) = self.engine.process(src_l, src_r);
         }
-        
+
         true
     }
 }
@@ -9,13 +9,21 @@ pub mod granular;
 pub use gain::GainNode;
 pub use source::WavSourceNode;
 pub use eq::EqNode;
+pub mod parametric_eq;
+pub use parametric_eq::ParametricEqNode;
 pub use delay::DelayNode;
 pub use compressor::CompressorNode;
 pub use synth::SynthNode;
 pub use granular::GranularNode;
+pub mod sampler;
+pub use sampler::SamplerNode;
+pub mod drum_rack;
+pub use drum_rack::DrumRackNode;
 pub mod wavetable;
 pub use wavetable::WavetableNode;
 pub mod filter;
 pub use filter::FilterNode;
 pub mod bass_enhancer;
 pub use bass_enhancer::BassEnhancerNode;
+pub mod bypass;
+pub use bypass::BypassNode;
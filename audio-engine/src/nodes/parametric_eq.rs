@@ -0,0 +1,205 @@
+use crate::graph::AudioNode;
+use crate::dsp::filter::{SmoothedBiquad, FilterType};
+use crate::dsp::DspProcessor;
+use shared::{EffectParam, EqBand, EqBandType, EqChannel};
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+// 48 dB/oct cuts = four cascaded 2-pole sections
+const MAX_SECTIONS: usize = 4;
+
+struct BandFilters {
+    band: EqBand,
+    sections: usize,
+    // [channel][section], channel 0 = L or Mid, 1 = R or Side
+    filters: [[SmoothedBiquad; MAX_SECTIONS]; 2],
+}
+
+impl BandFilters {
+    fn new(band: EqBand, sample_rate: f32) -> Self {
+        let filters = std::array::from_fn(|_| {
+            std::array::from_fn(|_| SmoothedBiquad::new(FilterType::Peaking, 1000.0, FRAC_1_SQRT_2, sample_rate))
+        });
+        let mut bf = Self { band, sections: 1, filters };
+        bf.configure(band);
+        bf
+    }
+
+    fn configure(&mut self, band: EqBand) {
+        self.band = band;
+
+        let (filter_type, sections) = match band.kind {
+            EqBandType::Bell => (FilterType::Peaking, 1),
+            EqBandType::LowShelf => (FilterType::LowShelf, 1),
+            EqBandType::HighShelf => (FilterType::HighShelf, 1),
+            EqBandType::LowCut => (FilterType::HighPass, (band.slope_db as usize / 12).clamp(1, MAX_SECTIONS)),
+            EqBandType::HighCut => (FilterType::LowPass, (band.slope_db as usize / 12).clamp(1, MAX_SECTIONS)),
+            EqBandType::Notch => (FilterType::Notch, 1),
+            EqBandType::BandPass => (FilterType::BandPass, 1),
+        };
+        self.sections = sections;
+
+        for section in 0..sections {
+            let q = if sections == 1 {
+                band.q
+            } else {
+                // Butterworth section Qs for order 2n, rising with the section;
+                // band Q scales the last, most resonant one
+                let n = sections as f32;
+                let butterworth = 1.0 / (2.0 * ((2.0 * section as f32 + 1.0) * PI / (4.0 * n)).cos());
+                if section == sections - 1 { butterworth * band.q / FRAC_1_SQRT_2 } else { butterworth }
+            };
+            for channel in &mut self.filters {
+                channel[section].set_type(filter_type);
+                channel[section].set_params(band.freq, q, band.gain_db);
+            }
+        }
+    }
+
+    #[inline]
+    fn run(&mut self, channel: usize, mut x: f32) -> f32 {
+        for filter in &mut self.filters[channel][..self.sections] {
+            x = filter.process_sample(x);
+        }
+        x
+    }
+
+    fn magnitude(&self, freq: f32) -> f32 {
+        self.filters[0][..self.sections].iter().map(|f| f.magnitude(freq)).product()
+    }
+}
+
+/// N-band parametric EQ. Bands can work on both channels, left or right
+/// only, or on the mid/side signal.
+pub struct ParametricEqNode {
+    bands: Vec<BandFilters>,
+    sample_rate: f32,
+    has_mid_side: bool,
+}
+
+impl ParametricEqNode {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            bands: Vec::new(),
+            sample_rate,
+            has_mid_side: false,
+        }
+    }
+
+    pub fn with_bands(bands: &[EqBand], sample_rate: f32) -> Self {
+        let mut node = Self::new(sample_rate);
+        node.set_bands(bands);
+        node
+    }
+
+    /// Replace all bands. Filter state is kept when the band count is unchanged.
+    pub fn set_bands(&mut self, bands: &[EqBand]) {
+        if bands.len() == self.bands.len() {
+            for (state, band) in self.bands.iter_mut().zip(bands.iter()) {
+                state.configure(*band);
+            }
+        } else {
+            self.bands = bands.iter().map(|b| BandFilters::new(*b, self.sample_rate)).collect();
+        }
+        self.update_mid_side();
+    }
+
+    pub fn set_band(&mut self, index: usize, band: EqBand) {
+        if let Some(state) = self.bands.get_mut(index) {
+            state.configure(band);
+            self.update_mid_side();
+        }
+    }
+
+    pub fn bands(&self) -> impl Iterator<Item = &EqBand> {
+        self.bands.iter().map(|b| &b.band)
+    }
+
+    fn update_mid_side(&mut self) {
+        self.has_mid_side = self.bands.iter()
+            .any(|b| b.band.enabled && matches!(b.band.channel, EqChannel::Mid | EqChannel::Side));
+    }
+
+    /// Combined response of all enabled bands in dB, one value per frequency
+    pub fn frequency_response(&self, freqs: &[f32], out: &mut [f32]) {
+        for (o, &freq) in out.iter_mut().zip(freqs.iter()) {
+            let mag: f32 = self.bands.iter()
+                .filter(|b| b.band.enabled)
+                .map(|b| b.magnitude(freq))
+                .product();
+            *o = shared::linear_to_db(mag);
+        }
+    }
+
+    #[inline]
+    fn process_frame(&mut self, mut l: f32, mut r: f32) -> (f32, f32) {
+        for band in self.bands.iter_mut().filter(|b| b.band.enabled) {
+            match band.band.channel {
+                EqChannel::Stereo => {
+                    l = band.run(0, l);
+                    r = band.run(1, r);
+                },
+                EqChannel::Left => l = band.run(0, l),
+                EqChannel::Right => r = band.run(1, r),
+                EqChannel::Mid | EqChannel::Side => {}
+            }
+        }
+
+        if self.has_mid_side {
+            let mut mid = (l + r) * 0.5;
+            let mut side = (l - r) * 0.5;
+            for band in self.bands.iter_mut().filter(|b| b.band.enabled) {
+                match band.band.channel {
+                    EqChannel::Mid => mid = band.run(0, mid),
+                    EqChannel::Side => side = band.run(1, side),
+                    _ => {}
+                }
+            }
+            l = mid + side;
+            r = mid - side;
+        }
+
+        (l, r)
+    }
+}
+
+/// Response of a band list without building a node in the audio graph (UI curve)
+pub fn frequency_response(bands: &[EqBand], sample_rate: f32, freqs: &[f32], out: &mut [f32]) {
+    ParametricEqNode::with_bands(bands, sample_rate).frequency_response(freqs, out);
+}
+
+impl AudioNode for ParametricEqNode {
+    fn set_param(&mut self, param: EffectParam, value: f32) {
+        let (index, update): (u8, fn(&mut EqBand, f32)) = match param {
+            EffectParam::BandFreq(i) => (i, |b, v| b.freq = v),
+            EffectParam::BandQ(i) => (i, |b, v| b.q = v),
+            EffectParam::BandGain(i) => (i, |b, v| b.gain_db = v),
+            EffectParam::BandEnabled(i) => (i, |b, v| b.enabled = v >= 0.5),
+            _ => return,
+        };
+        if let Some(state) = self.bands.get(index as usize) {
+            let mut band = state.band;
+            update(&mut band, value);
+            self.set_band(index as usize, band);
+        }
+    }
+
+    fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> bool {
+        let (left, right) = outputs.split_at_mut(1);
+        let out_l = &mut left[0];
+        let out_r = &mut right[0];
+
+        // No inputs = process in place
+        for i in 0..out_l.len() {
+            let (l, r) = match inputs {
+                [] => (out_l[i], out_r[i]),
+                [mono] => (mono[i], mono[i]),
+                [in_l, in_r, ..] => (in_l[i], in_r[i]),
+            };
+            let (l, r) = self.process_frame(l, r);
+            out_l[i] = l;
+            out_r[i] = r;
+        }
+
+        true
+    }
+}
@@ -0,0 +1,64 @@
+use crate::dsp::envelope::EnvelopeParams;
+use crate::dsp::interpolation::Interpolation;
+use crate::mixer::AssetCache;
+use crate::midi::{MidiEvent, MidiEventType};
+use crate::synth::sampler::Sampler;
+use shared::SampleZone;
+
+// Multi-sample instrument. Zones refer to assets by ID and are resolved
+// against the mixer's sample cache while rendering.
+pub struct SamplerNode {
+    engine: Sampler,
+
+    // Internal event queue, filled by the track from its MIDI clips
+    pub event_queue: Vec<MidiEvent>,
+}
+
+impl SamplerNode {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            engine: Sampler::new(sample_rate),
+            event_queue: Vec::with_capacity(64),
+        }
+    }
+
+    pub fn zones(&self) -> &[SampleZone] {
+        &self.engine.zones
+    }
+
+    pub fn set_zones(&mut self, zones: Vec<SampleZone>) {
+        self.engine.set_zones(zones);
+    }
+
+    pub fn set_envelope(&mut self, params: EnvelopeParams) {
+        self.engine.set_envelope(params);
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.engine.interpolation = interpolation;
+    }
+
+    pub fn handle_event(&mut self, event: MidiEvent) {
+        match event.event_type {
+            MidiEventType::NoteOn => self.engine.note_on(event.note, event.velocity),
+            MidiEventType::NoteOff => self.engine.note_off(event.note),
+            _ => {}
+        }
+    }
+
+    pub fn process(&mut self, assets: &AssetCache, outputs: &mut [&mut [f32]]) -> bool {
+        // Process internal queue first
+        for i in 0..self.event_queue.len() {
+            let event = self.event_queue[i];
+            self.handle_event(event);
+        }
+        self.event_queue.clear();
+
+        let (left, right) = outputs.split_at_mut(1);
+        left[0].fill(0.0);
+        right[0].fill(0.0);
+        self.engine.render(assets, left[0], right[0]);
+
+        true
+    }
+}
@@ -39,7 +39,6 @@ impl AudioNode for WavSourceNode {
             return false;
         }
 
-        let num_channels = outputs.len();
         let samples_needed = outputs[0].len(); // Assume equal length
 
         for i in 0..samples_needed {
@@ -50,8 +49,8 @@ impl AudioNode for WavSourceNode {
             };
             
             // Write to all output channels (mono -> stereo expansion)
-            for ch in 0..num_channels {
-                outputs[ch][i] = sample_val;
+            for channel in outputs.iter_mut() {
+                channel[i] = sample_val;
             }
             
             self.position += 1;
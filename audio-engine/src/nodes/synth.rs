@@ -1,13 +1,27 @@
 use crate::graph::AudioNode;
 use crate::synth::allocator::VoiceAllocator;
-use crate::synth::voice::SynthVoice;
+use std::sync::Arc;
+use crate::synth::voice::{SynthVoice, VoiceSource, ENV_FILTER, NUM_ENVELOPES, NUM_LFOS};
+use crate::synth::wavetable::{Wavetable, DEFAULT_FRAME_SIZE};
+use crate::synth::lfo::{Lfo, LfoParams, LfoRate};
+use crate::dsp::envelope::EnvelopeParams;
 use crate::midi::{MidiEvent, MidiEventType};
 use crate::modulation::{ModulationMatrix, ModSource, ModTarget};
+use shared::{EnvelopeField, SynthParam, TempoMap};
+
+pub const NUM_GLOBAL_LFOS: usize = 2;
+
+// Global LFO values are computed this many samples at a time, longer blocks
+// are split so nothing grows on the audio thread
+const LFO_CHUNK: usize = 256;
 
 pub struct SynthNode {
     allocator: VoiceAllocator,
     voices: Vec<SynthVoice>,
-    sample_rate: f32,
+    
+    // LFOs shared by all voices (ModSource::GlobalLfo)
+    pub global_lfos: [Lfo; NUM_GLOBAL_LFOS],
+    global_lfo_buf: [[f32; NUM_GLOBAL_LFOS]; LFO_CHUNK], // Per-sample values for the current chunk
     
     // Matrix
     pub mod_matrix: ModulationMatrix,
@@ -18,24 +32,119 @@ pub struct SynthNode {
 
 impl SynthNode {
     pub fn new(sample_rate: f32, max_voices: usize) -> Self {
+        // One table shared by every voice
+        let table = Arc::new(Wavetable::new(DEFAULT_FRAME_SIZE));
         let mut voices = Vec::with_capacity(max_voices);
         for _ in 0..max_voices {
-            voices.push(SynthVoice::new(sample_rate));
+            voices.push(SynthVoice::new(sample_rate, table.clone()));
         }
         
-        // Default Matrix: Envelope -> Cutoff
+        // Default Matrix: Filter Envelope -> Cutoff
         let mut mod_matrix = ModulationMatrix::new();
-        mod_matrix.add_connection(ModSource::Envelope(0), ModTarget::FilterCutoff, 0.5); // 50% modulation
+        mod_matrix.add_connection(ModSource::Envelope(ENV_FILTER), ModTarget::FilterCutoff, 0.5); // 50% modulation
         
         Self {
             allocator: VoiceAllocator::new(max_voices),
             voices,
-            sample_rate,
+            global_lfos: [Lfo::new(sample_rate), Lfo::new(sample_rate)],
+            global_lfo_buf: [[0.0; NUM_GLOBAL_LFOS]; LFO_CHUNK],
             mod_matrix,
             event_queue: Vec::new(),
         }
     }
     
+    /// Apply envelope settings to every voice (0 = amp, 1 = filter, 2 = mod)
+    pub fn set_envelope(&mut self, index: usize, params: EnvelopeParams) {
+        if index >= NUM_ENVELOPES { return; }
+        for voice in self.voices.iter_mut() {
+            voice.envelopes[index].params = params;
+        }
+    }
+
+    pub fn set_source(&mut self, source: VoiceSource) {
+        for voice in self.voices.iter_mut() {
+            voice.source = source;
+        }
+    }
+
+    pub fn set_wavetable(&mut self, table: Arc<Wavetable>) {
+        for voice in self.voices.iter_mut() {
+            voice.wavetable.set_table(table.clone());
+        }
+    }
+
+    /// Base morph position (0-1), before modulation
+    pub fn set_wavetable_morph(&mut self, morph: f32) {
+        for voice in self.voices.iter_mut() {
+            voice.wavetable.morph = morph.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Apply LFO settings to one per-voice LFO slot on every voice
+    pub fn set_lfo(&mut self, index: usize, params: LfoParams) {
+        if index >= NUM_LFOS { return; }
+        for voice in self.voices.iter_mut() {
+            voice.lfos[index].params = params;
+        }
+    }
+
+    pub fn set_global_lfo(&mut self, index: usize, params: LfoParams) {
+        if let Some(lfo) = self.global_lfos.get_mut(index) {
+            lfo.params = params;
+        }
+    }
+
+    /// Patch parameter from automation, `value` in real units
+    pub fn set_param(&mut self, param: SynthParam, value: f32) {
+        match param {
+            SynthParam::Morph => self.set_wavetable_morph(value),
+            SynthParam::Cutoff => self.voices.iter_mut().for_each(|v| v.cutoff = value),
+            SynthParam::Resonance => self.voices.iter_mut().for_each(|v| v.resonance = value),
+            SynthParam::Envelope(index, field) => {
+                for voice in self.voices.iter_mut() {
+                    let Some(env) = voice.envelopes.get_mut(index as usize) else { return };
+                    let p = &mut env.params;
+                    match field {
+                        EnvelopeField::Delay => p.delay_ms = value,
+                        EnvelopeField::Attack => p.attack_ms = value,
+                        EnvelopeField::Hold => p.hold_ms = value,
+                        EnvelopeField::Decay => p.decay_ms = value,
+                        EnvelopeField::Sustain => p.sustain_level = value,
+                        EnvelopeField::Release => p.release_ms = value,
+                    }
+                }
+            },
+            SynthParam::LfoRate(index) => {
+                for voice in self.voices.iter_mut() {
+                    if let Some(lfo) = voice.lfos.get_mut(index as usize) {
+                        lfo.params.rate = LfoRate::Hz(value);
+                    }
+                }
+            },
+            SynthParam::GlobalLfoRate(index) => {
+                if let Some(lfo) = self.global_lfos.get_mut(index as usize) {
+                    lfo.params.rate = LfoRate::Hz(value);
+                }
+            }
+        }
+    }
+
+    /// Follow the tempo map at the transport position (in seconds), once per block
+    pub fn set_transport(&mut self, tempo_map: &TempoMap, seconds: f64) {
+        let beat = tempo_map.beat_at(seconds);
+        let tempo = tempo_map.bpm_at(beat);
+        for lfo in self.global_lfos.iter_mut() {
+            lfo.set_tempo(tempo);
+            lfo.sync_to_transport(beat);
+        }
+        for voice in self.voices.iter_mut() {
+            for lfo in voice.lfos.iter_mut() {
+                lfo.set_tempo(tempo);
+                lfo.sync_to_transport(beat);
+            }
+        }
+    }
+
     // Handle incoming MIDI events
     pub fn handle_event(&mut self, event: MidiEvent) {
         match event.event_type {
@@ -45,6 +154,9 @@ impl SynthNode {
                if idx < self.voices.len() {
                    self.voices[idx].note_on(event.note, event.velocity);
                }
+               for lfo in self.global_lfos.iter_mut() {
+                   lfo.trigger();
+               }
             },
             MidiEventType::NoteOff => {
                 if let Some(idx) = self.allocator.note_off(event.note) {
@@ -80,23 +192,36 @@ impl AudioNode for SynthNode {
         out_l.fill(0.0);
         out_r.fill(0.0);
 
-        // Mix voices
-        for (i, voice) in self.voices.iter_mut().enumerate() {
-            if voice.active {
-                for s in 0..out_l.len() {
-                    let sample = voice.process(&self.mod_matrix);
-                    
-                    // Simple Mono Mix to Stereo
-                    // TODO: Pan per voice or spread
-                    out_l[s] += sample * 0.5; // -6dB to prevent clipping
-                    out_r[s] += sample * 0.5;
+        // Run global LFOs once per sample, shared by every voice
+        let samples = out_l.len();
+        let mut start = 0;
+        while start < samples {
+            let len = (samples - start).min(LFO_CHUNK);
+            for frame in self.global_lfo_buf[..len].iter_mut() {
+                for (value, lfo) in frame.iter_mut().zip(self.global_lfos.iter_mut()) {
+                    *value = lfo.process();
                 }
-                
-                // Cleanup voice if finished
-                if !voice.active {
-                    self.allocator.voice_finished(i);
+            }
+
+            // Mix voices
+            for (i, voice) in self.voices.iter_mut().enumerate() {
+                if voice.active {
+                    for (s, lfo_values) in self.global_lfo_buf[..len].iter().enumerate() {
+                        let sample = voice.process(&self.mod_matrix, lfo_values);
+
+                        // Simple Mono Mix to Stereo
+                        // TODO: Pan per voice or spread
+                        out_l[start + s] += sample * 0.5; // -6dB to prevent clipping
+                        out_r[start + s] += sample * 0.5;
+                    }
+
+                    // Cleanup voice if finished
+                    if !voice.active {
+                        self.allocator.voice_finished(i);
+                    }
                 }
             }
+            start += len;
         }
         
         // Check if any voice is active to keep node alive
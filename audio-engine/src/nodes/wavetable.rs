@@ -13,7 +13,7 @@ impl WavetableNode {
     }
     
     pub fn set_params(&mut self, freq: f32, morph: f32) {
-        self.osc.freq = freq;
+        self.osc.set_frequency(freq);
         self.osc.morph = morph;
     }
 }
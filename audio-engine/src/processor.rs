@@ -1,7 +1,27 @@
 use wasm_bindgen::prelude::*;
 use crate::mixer::Mixer;
+use crate::synth::voice::VoiceSource;
+use crate::synth::wavetable::Wavetable;
 use shared::Project;
 use js_sys::Float32Array;
+use std::sync::Arc;
+
+/// A wavetable decoded and band-limited ahead of time. Building one is slow,
+/// do it before the next audio callback is due rather than inside
+/// `WasmAudioProcessor::load_wavetable`.
+#[wasm_bindgen]
+pub struct PreparedWavetable(Arc<Wavetable>);
+
+#[wasm_bindgen]
+impl PreparedWavetable {
+    /// Decode raw WAV bytes. Use 2048 for Serum-style tables.
+    #[wasm_bindgen(constructor)]
+    pub fn new(wav_bytes: &[u8], frame_size: usize) -> Result<PreparedWavetable, JsValue> {
+        Wavetable::from_wav(wav_bytes, frame_size)
+            .map(|table| PreparedWavetable(Arc::new(table)))
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
 
 // Placeholder for the extensive AudioWorkletProcessor trait
 #[wasm_bindgen]
@@ -13,6 +33,12 @@ pub struct WasmAudioProcessor {
     interleaved: Vec<f32>,
 }
 
+impl Default for WasmAudioProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 impl WasmAudioProcessor {
     #[wasm_bindgen(constructor)]
@@ -78,13 +104,59 @@ impl WasmAudioProcessor {
         web_sys::console::log_1(&format!("WasmAudioProcessor: Sample Rate updated to {}", rate).into());
     }
     
-    pub fn add_sample(&mut self, asset_id: String, left_channel: &[f32], right_channel: &[f32]) {
+    /// `source_rate` is the rate the channels were recorded at. Anything not
+    /// at the context rate is converted here, in the audio callback's thread,
+    /// so the frontend converts first.
+    pub fn add_sample(&mut self, asset_id: String, left_channel: &[f32], right_channel: &[f32], source_rate: f32) {
         // Clone ID for logging because add_sample consumes it
         let id_log = asset_id.clone();
-        self.mixer.add_sample(asset_id, left_channel.to_vec(), right_channel.to_vec());
+        self.mixer.add_sample(asset_id, left_channel.to_vec(), right_channel.to_vec(), source_rate);
         web_sys::console::log_1(&format!("Sample loaded: {}, {} frames", id_log, left_channel.len()).into());
     }
     
+    /// Apply a JSON-encoded `MixerCommand`
+    pub fn handle_command(&mut self, command_json: &str) {
+        match serde_json::from_str::<shared::MixerCommand>(command_json) {
+            Ok(command) => self.mixer.handle_command(command),
+            Err(e) => {
+                web_sys::console::log_1(&format!("Failed to parse command JSON: {:?}", e).into());
+            }
+        }
+    }
+
+    /// JSON list of `RecordedAutomation` passes finished since the last call.
+    /// Poll after stopping (or periodically) and forward each one to the backend.
+    pub fn take_recorded_automation(&mut self) -> String {
+        let recorded = self.mixer.take_recorded_automation();
+        serde_json::to_string(&recorded).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    pub fn trigger_sample(&mut self, asset_id: &str) {
+        self.mixer.trigger_sample(asset_id, shared::SampleTrigger::default());
+    }
+
+    pub fn choke_sample(&mut self, asset_id: &str) {
+        self.mixer.choke_sample(asset_id);
+    }
+
+    pub fn stop_all_samples(&mut self) {
+        self.mixer.stop_all_samples();
+    }
+
+    pub fn set_tempo(&mut self, bpm: f32) {
+        self.mixer.set_tempo(bpm);
+    }
+    
+    /// Tempo changes after the start, a JSON list of `TempoChange`
+    pub fn set_tempo_map(&mut self, changes_json: &str) {
+        match serde_json::from_str::<Vec<shared::TempoChange>>(changes_json) {
+            Ok(changes) => self.mixer.set_tempo_map(changes),
+            Err(e) => {
+                web_sys::console::log_1(&format!("Failed to parse tempo map JSON: {:?}", e).into());
+            }
+        }
+    }
+    
     pub fn seek_to_sample(&mut self, sample: u64) {
         self.mixer.seek(sample);
     }
@@ -93,12 +165,13 @@ impl WasmAudioProcessor {
         self.mixer.set_playing(playing);
     }
     
+    // Fader moves, recorded by lanes in a write mode
     pub fn set_track_gain(&mut self, track_id: u32, db: f32) {
-        self.mixer.set_track_gain(track_id, db);
+        self.mixer.handle_command(shared::MixerCommand::SetTrackGain { track_id, gain: db });
     }
 
     pub fn set_track_pan(&mut self, track_id: u32, pan: f32) {
-        self.mixer.set_track_pan(track_id, pan);
+        self.mixer.handle_command(shared::MixerCommand::SetTrackPan { track_id, pan });
     }
     
     pub fn set_track_eq(&mut self, track_id: u32, low: f32, mid: f32, high: f32) {
@@ -132,6 +205,19 @@ impl WasmAudioProcessor {
         self.mixer.set_track_playback_rate(track_id, rate);
     }
 
+    /// Resampling quality: "linear", "hermite", "sinc8" or "sinc32"
+    pub fn set_interpolation(&mut self, mode: &str) {
+        match crate::dsp::interpolation::Interpolation::from_name(mode) {
+            Some(interpolation) => self.mixer.set_interpolation(interpolation),
+            None => web_sys::console::log_1(&format!("Unknown interpolation mode: {}", mode).into()),
+        }
+    }
+
+    /// Key lock: the playback rate changes tempo but keeps the pitch
+    pub fn set_track_key_lock(&mut self, track_id: u32, enabled: bool) {
+        self.mixer.set_track_key_lock(track_id, enabled);
+    }
+
     pub fn set_track_scratch(&mut self, track_id: u32, velocity: f32) {
         self.mixer.set_track_scratch(track_id, velocity);
     }
@@ -152,6 +238,51 @@ impl WasmAudioProcessor {
         self.mixer.start_loop_seconds(track_id, length_seconds);
     }
 
+    /// Switch a track's synth to a wavetable built with `PreparedWavetable`
+    pub fn load_wavetable(&mut self, track_id: u32, table: &PreparedWavetable) {
+        if let Err(e) = self.mixer.load_wavetable(track_id, table.0.clone()) {
+            web_sys::console::log_1(&format!("Failed to load wavetable: {}", e).into());
+        }
+    }
+
+    pub fn set_synth_wavetable_enabled(&mut self, track_id: u32, enabled: bool) {
+        let source = if enabled { VoiceSource::Wavetable } else { VoiceSource::Analog };
+        self.mixer.set_synth_source(track_id, source);
+    }
+
+    pub fn set_wavetable_morph(&mut self, track_id: u32, morph: f32) {
+        self.mixer.set_wavetable_morph(track_id, morph);
+    }
+
+    /// Route a track to a bus. Pass -1 to send it straight to master.
+    pub fn set_track_output_bus(&mut self, track_id: u32, bus_id: i32) {
+        let bus = if bus_id < 0 { None } else { Some(bus_id as u32) };
+        self.mixer.set_track_output_bus(track_id, bus);
+    }
+
+    pub fn set_bus_gain(&mut self, bus_id: u32, db: f32) {
+        self.mixer.set_bus_gain(bus_id, db);
+    }
+
+    pub fn set_bus_mute(&mut self, bus_id: u32, muted: bool) {
+        self.mixer.set_bus_mute(bus_id, muted);
+    }
+
+    /// Post-fader send from a track into a bus. A non-finite level removes it.
+    pub fn set_track_send(&mut self, track_id: u32, bus_id: u32, level_db: f32) {
+        let level = if level_db.is_finite() { Some(level_db) } else { None };
+        self.mixer.set_track_send(track_id, bus_id, level);
+    }
+
+    pub fn set_track_instrument(&mut self, track_id: u32, instrument_json: &str) {
+        match serde_json::from_str::<shared::Instrument>(instrument_json) {
+            Ok(instrument) => self.mixer.set_track_instrument(track_id, instrument),
+            Err(e) => {
+                web_sys::console::log_1(&format!("Failed to parse instrument JSON: {:?}", e).into());
+            }
+        }
+    }
+
     pub fn trigger_synth_attack(&mut self, track_id: u32, note: u8, velocity: f32) {
         self.mixer.trigger_synth_attack(track_id, note, velocity);
     }
@@ -160,13 +291,29 @@ impl WasmAudioProcessor {
         self.mixer.trigger_synth_release(track_id, note);
     }
 
+    /// Fills `output` with the EQ curve in dB at each of `freqs`, for drawing.
+    /// `bands_json` is a list of `EqBand`. Returns the number of values written.
+    pub fn eq_frequency_response(&self, bands_json: &str, freqs: &[f32], output: &mut [f32]) -> usize {
+        match serde_json::from_str::<Vec<shared::EqBand>>(bands_json) {
+            Ok(bands) => {
+                let count = freqs.len().min(output.len());
+                crate::nodes::parametric_eq::frequency_response(&bands, self.sample_rate, &freqs[..count], &mut output[..count]);
+                count
+            },
+            Err(e) => {
+                web_sys::console::log_1(&format!("Failed to parse EQ bands JSON: {:?}", e).into());
+                0
+            }
+        }
+    }
+
     /// Fills the output array with peak values for each track.
     /// Expected size: num_tracks
     /// Returns the number of tracks written.
     pub fn read_track_meters(&self, output: &mut [f32]) -> usize {
         let count = self.mixer.tracks.len().min(output.len());
-        for i in 0..count {
-            output[i] = self.mixer.tracks[i].current_peak;
+        for (out, track) in output.iter_mut().zip(self.mixer.tracks.iter()) {
+            *out = track.current_peak;
         }
         count
     }
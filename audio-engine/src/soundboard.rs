@@ -0,0 +1,171 @@
+use crate::mixer::AssetCache;
+use crate::dsp::interpolation::Interpolation;
+use shared::SampleTrigger;
+
+// Simultaneous one-shots before the oldest is stolen
+pub const MAX_ONE_SHOT_VOICES: usize = 32;
+// Fade used by choke / stop-all so the cut doesn't click
+const STOP_FADE_MS: f32 = 5.0;
+
+struct OneShotVoice {
+    active: bool,
+    asset: usize, // Index into `asset_ids`
+    pos: f64,
+    step: f64,
+    gain_l: f32,
+    gain_r: f32,
+    start_at: u64, // Timeline sample where playback begins (quantized triggers)
+    fade: f32,     // 1.0 until stopped, then fades to 0
+    stopping: bool,
+    order: u64,    // Trigger order, for stealing the oldest voice
+}
+
+/// Fixed pool of one-shot sample voices for the soundboard.
+/// Nothing here allocates or frees on the audio thread.
+pub struct Soundboard {
+    voices: Vec<OneShotVoice>,
+    // Every asset ever registered, so voices hold an index instead of a copy
+    asset_ids: Vec<String>,
+    trigger_count: u64,
+    fade_step: f32,
+    pub interpolation: Interpolation,
+}
+
+impl Soundboard {
+    pub fn new(sample_rate: f32) -> Self {
+        let voices = (0..MAX_ONE_SHOT_VOICES).map(|_| OneShotVoice {
+            active: false,
+            asset: 0,
+            pos: 0.0,
+            step: 1.0,
+            gain_l: 1.0,
+            gain_r: 1.0,
+            start_at: 0,
+            fade: 1.0,
+            stopping: false,
+            order: 0,
+        }).collect();
+
+        let mut board = Self {
+            voices,
+            asset_ids: Vec::new(),
+            trigger_count: 0,
+            fade_step: 0.0,
+            interpolation: Interpolation::default(),
+        };
+        board.set_sample_rate(sample_rate);
+        board
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.fade_step = 1.0 / (STOP_FADE_MS * 0.001 * sample_rate).max(1.0);
+    }
+
+    /// Make `asset_id` playable. Called when the asset is added, not when triggered.
+    pub fn register(&mut self, asset_id: &str) {
+        if !self.asset_ids.iter().any(|id| id == asset_id) {
+            self.asset_ids.push(asset_id.to_string());
+        }
+    }
+
+    fn asset_index(&self, asset_id: &str) -> Option<usize> {
+        self.asset_ids.iter().position(|id| id == asset_id)
+    }
+
+    /// Start `asset_id` at timeline sample `start_at` (or right away if already past)
+    pub fn trigger(&mut self, asset_id: &str, options: &SampleTrigger, start_at: u64) {
+        let Some(asset) = self.asset_index(asset_id) else { return };
+        let idx = self.voices.iter().position(|v| !v.active)
+            .or_else(|| {
+                self.voices.iter().enumerate()
+                    .min_by_key(|(_, v)| v.order)
+                    .map(|(i, _)| i)
+            })
+            .unwrap_or(0);
+
+        let gain = shared::db_to_linear(options.gain_db);
+        let pan = options.pan.clamp(-1.0, 1.0);
+
+        self.trigger_count += 1;
+        let voice = &mut self.voices[idx];
+        voice.active = true;
+        voice.asset = asset;
+        voice.pos = options.start_offset as f64;
+        voice.step = 2.0f64.powf(options.pitch as f64 / 12.0);
+        // Balance, keeps stereo samples intact at centre
+        voice.gain_l = gain * (1.0 - pan).min(1.0);
+        voice.gain_r = gain * (1.0 + pan).min(1.0);
+        voice.start_at = start_at;
+        voice.fade = 1.0;
+        voice.stopping = false;
+        voice.order = self.trigger_count;
+    }
+
+    /// Fade out every voice playing `asset_id`
+    pub fn choke(&mut self, asset_id: &str) {
+        let Some(asset) = self.asset_index(asset_id) else { return };
+        for voice in self.voices.iter_mut().filter(|v| v.active && v.asset == asset) {
+            voice.stopping = true;
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        for voice in self.voices.iter_mut().filter(|v| v.active) {
+            voice.stopping = true;
+        }
+    }
+
+    /// Drop quantized triggers that haven't started (transport stopped or moved)
+    pub fn cancel_pending(&mut self, now: u64) {
+        for voice in self.voices.iter_mut().filter(|v| v.active && v.start_at > now) {
+            voice.active = false;
+        }
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.voices.iter().filter(|v| v.active).count()
+    }
+
+    /// Add sounding voices into the output. `block_start` is the timeline
+    /// position of the first sample, used to place quantized triggers.
+    pub fn process(&mut self, assets: &AssetCache, out_l: &mut [f32], out_r: &mut [f32], block_start: u64) {
+        let samples = out_l.len();
+        let interpolation = self.interpolation;
+
+        for voice in self.voices.iter_mut().filter(|v| v.active) {
+            let Some((src_l, src_r)) = assets.get(&self.asset_ids[voice.asset]) else {
+                voice.active = false;
+                continue;
+            };
+
+            // Waiting for its beat/bar; a stopped pending voice never starts
+            let delay = voice.start_at.saturating_sub(block_start) as usize;
+            if voice.stopping && delay > 0 {
+                voice.active = false;
+                continue;
+            }
+            if delay >= samples {
+                continue;
+            }
+
+            let len = src_l.len() as f64;
+            for (l, r) in out_l[delay..].iter_mut().zip(out_r[delay..].iter_mut()) {
+                if voice.pos >= len {
+                    voice.active = false;
+                    break;
+                }
+                if voice.stopping {
+                    voice.fade -= self.fade_step;
+                    if voice.fade <= 0.0 {
+                        voice.active = false;
+                        break;
+                    }
+                }
+
+                *l += interpolation.read(src_l, voice.pos, voice.step) * voice.gain_l * voice.fade;
+                *r += interpolation.read(src_r, voice.pos, voice.step) * voice.gain_r * voice.fade;
+                voice.pos += voice.step;
+            }
+        }
+    }
+}
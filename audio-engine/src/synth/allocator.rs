@@ -33,7 +33,7 @@ impl VoiceAllocator {
         let idx = self.voice_ages.iter().enumerate().max_by_key(|(_i, age)| *age).map(|(i, _)| i).unwrap_or(0);
         self.voice_ages[idx] = 0;
         self.voice_notes[idx] = Some(note);
-        return idx;
+        idx
     }
     
     pub fn note_off(&mut self, note: u8) -> Option<usize> {
@@ -1,87 +1,113 @@
-use std::sync::Arc;
-use crate::dsp::dynamics::EnvelopeFollower; // Reuse or use simple window
+use crate::dsp::envelope::{Envelope, EnvelopeParams};
+use crate::dsp::interpolation::Interpolation;
+use crate::dsp::rng::Rng;
+use shared::{GrainWindow, GranularParams};
+
+// Notes that can spawn grains at once
+const MAX_NOTES: usize = 8;
 
 #[derive(Clone, Debug)]
 pub struct Grain {
     pub active: bool,
-    pub start_pos_in_buffer: f64,
     pub current_pos: f64,
-    pub speed: f32,
+    pub speed: f32, // Negative plays backwards
     pub duration_samples: usize,
     pub age: usize,
     pub pan: f32,
     pub amp: f32,
+    pub window: GrainWindow,
+}
+
+impl Default for Grain {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Grain {
     pub fn new() -> Self {
         Self {
             active: false,
-            start_pos_in_buffer: 0.0,
             current_pos: 0.0,
             speed: 1.0,
             duration_samples: 0,
             age: 0,
             pan: 0.0,
             amp: 1.0,
+            window: GrainWindow::Hann,
         }
     }
-    
+
+    fn window_gain(&self, progress: f32) -> f32 {
+        use std::f32::consts::PI;
+        match self.window {
+            // 0.5 * (1 - cos(2*pi*n/N))
+            GrainWindow::Hann => 0.5 * (1.0 - (2.0 * PI * progress).cos()),
+            GrainWindow::Triangle => 1.0 - (2.0 * progress - 1.0).abs(),
+            GrainWindow::Tukey => {
+                // Cosine ramps over the outer 25% on each side
+                let edge = 0.25;
+                if progress < edge {
+                    0.5 * (1.0 - (PI * progress / edge).cos())
+                } else if progress > 1.0 - edge {
+                    0.5 * (1.0 - (PI * (1.0 - progress) / edge).cos())
+                } else {
+                    1.0
+                }
+            },
+            GrainWindow::Gaussian => {
+                let x = (progress - 0.5) / 0.15;
+                (-0.5 * x * x).exp()
+            }
+        }
+    }
+
     // Process one sample
     // Returns (L, R) tuple
-    pub fn process(&mut self, buffer: &[f32]) -> (f32, f32) {
+    pub fn process(&mut self, left: &[f32], right: &[f32], interpolation: Interpolation) -> (f32, f32) {
         if !self.active { return (0.0, 0.0); }
-        
-        let idx = self.current_pos as usize;
-        
+
         // Bounds check
-        if idx >= buffer.len() || self.age >= self.duration_samples {
+        if self.current_pos < 0.0 || self.current_pos >= left.len() as f64 || self.age >= self.duration_samples {
             self.active = false;
             return (0.0, 0.0);
         }
-        
-        // Linear Interpolation
-        let idx_f = self.current_pos;
-        let idx_i = idx_f.floor() as usize;
-        let frac = (idx_f - idx_i as f64) as f32;
-        
-        let s0 = buffer.get(idx_i).unwrap_or(&0.0);
-        let s1 = buffer.get(idx_i + 1).unwrap_or(&0.0);
-        let raw = s0 * (1.0 - frac) + s1 * frac;
-        
-        // Windowing (Hanning)
-        // 0.5 * (1 - cos(2*pi*n/N))
+
+        let rate = self.speed as f64;
+        let raw_l = interpolation.read(left, self.current_pos, rate);
+        let raw_r = interpolation.read(right, self.current_pos, rate);
+
         let progress = self.age as f32 / self.duration_samples as f32;
-        let window = 0.5 * (1.0 - (2.0 * std::f32::consts::PI * progress).cos());
-        
-        let signal = raw * window * self.amp;
-        
-        // Pan
-        // -1 (L) to 1 (R)
-        let pan_l = (1.0 - self.pan) * 0.5;
-        let pan_r = (1.0 + self.pan) * 0.5;
-        // Or constant power? keeping it simple linear for grains
-        
+        let gain = self.window_gain(progress) * self.amp;
+
+        // Balance
+        // -1 (L) to 1 (R), keeps both sides of a stereo source at centre
+        let pan_l = (1.0 - self.pan).min(1.0);
+        let pan_r = (1.0 + self.pan).min(1.0);
+
         self.age += 1;
         self.current_pos += self.speed as f64;
-        
-        (signal * pan_l, signal * pan_r)
+
+        (raw_l * gain * pan_l, raw_r * gain * pan_r)
     }
 }
 
+// A held MIDI note that keeps spawning grains at its pitch
+struct GranularNote {
+    note: u8,
+    velocity: f32,
+    env: Envelope,
+    spawn_countdown: f32, // Samples until this note's next grain
+}
+
 pub struct GranularSynth {
     pub grains: Vec<Grain>,
-    pub buffer: Arc<Vec<f32>>, // Mono buffer for now
     pub sample_rate: f32,
-    
-    // Params
-    pub density: f32, // Grains per second
-    pub size_ms: f32, // Grain size
-    pub spray_ms: f32, // Random start offset
-    pub playback_pos: f64, // Where we are scanning from
-    
-    // Scheduler state
-    grain_spawn_accum: f32,
+    pub params: GranularParams,
+    pub interpolation: Interpolation,
+
+    notes: Vec<GranularNote>,
+    rng: Rng,
 }
 
 impl GranularSynth {
@@ -90,68 +116,137 @@ impl GranularSynth {
         for _ in 0..max_grains {
             grains.push(Grain::new());
         }
-        
+
+        // Short attack/release so grains fade with the key instead of clicking
+        let env_params = EnvelopeParams {
+            attack_ms: 5.0,
+            decay_ms: 0.0,
+            sustain_level: 1.0,
+            release_ms: 300.0,
+            ..EnvelopeParams::default()
+        };
+        let notes = (0..MAX_NOTES).map(|_| GranularNote {
+            note: 0,
+            velocity: 0.0,
+            env: Envelope::with_params(env_params, sample_rate),
+            spawn_countdown: 0.0,
+        }).collect();
+
+        let params = GranularParams::default();
         Self {
             grains,
-            buffer: Arc::new(vec![0.0; 1024]), // Placeholder
             sample_rate,
-            density: 10.0,
-            size_ms: 50.0,
-            spray_ms: 0.0,
-            playback_pos: 0.0,
-            grain_spawn_accum: 0.0,
+            rng: Rng::new(params.seed),
+            params,
+            interpolation: Interpolation::default(),
+            notes,
+        }
+    }
+
+    pub fn set_params(&mut self, params: GranularParams) {
+        if params.seed != self.params.seed {
+            self.rng.reseed(params.seed);
         }
+        self.params = params;
+    }
+
+    /// Restart the random sequence (e.g. before an offline render)
+    pub fn reset_rng(&mut self) {
+        self.rng.reseed(self.params.seed);
     }
-    
-    pub fn set_buffer(&mut self, buffer: Vec<f32>) {
-        self.buffer = Arc::new(buffer);
+
+    pub fn note_on(&mut self, note: u8, velocity: u8) {
+        // Retrigger the same note, else take a free slot, else the quietest one
+        let idx = self.notes.iter().position(|n| n.note == note && n.env.is_active())
+            .or_else(|| self.notes.iter().position(|n| !n.env.is_active()))
+            .unwrap_or_else(|| {
+                self.notes.iter().enumerate()
+                    .min_by(|(_, a), (_, b)| a.velocity.total_cmp(&b.velocity))
+                    .map(|(i, _)| i)
+                    .unwrap_or(0)
+            });
+
+        let slot = &mut self.notes[idx];
+        slot.note = note;
+        slot.velocity = velocity as f32 / 127.0;
+        slot.spawn_countdown = 0.0; // Spawn the first grain immediately
+        slot.env.trigger(1.0);
     }
-    
-    pub fn process(&mut self) -> (f32, f32) {
+
+    pub fn note_off(&mut self, note: u8) {
+        for slot in self.notes.iter_mut().filter(|n| n.note == note) {
+            slot.env.release();
+        }
+    }
+
+    /// Render one stereo sample from the source asset
+    pub fn process(&mut self, left: &[f32], right: &[f32]) -> (f32, f32) {
         let mut out_l = 0.0;
         let mut out_r = 0.0;
-        
+
+        if left.is_empty() { return (0.0, 0.0); }
+
         // Spawn Grains logic
         // density = 10 -> spawns every 1/10th second = samplerate/10 samples
-        let samples_per_grain = self.sample_rate / self.density.max(0.1);
-        self.grain_spawn_accum += 1.0;
-        
-        if self.grain_spawn_accum >= samples_per_grain {
-            self.grain_spawn_accum -= samples_per_grain;
-            self.spawn_grain();
+        let samples_per_grain = self.sample_rate / self.params.density.max(0.1);
+        for i in 0..self.notes.len() {
+            if !self.notes[i].env.is_active() { continue; }
+            let level = self.notes[i].env.process();
+
+            // The fraction carries over, so density holds on average.
+            // At most one grain per sample.
+            if self.notes[i].spawn_countdown <= 0.0 {
+                self.notes[i].spawn_countdown = (self.notes[i].spawn_countdown + samples_per_grain).max(0.0);
+                let (note, velocity) = (self.notes[i].note, self.notes[i].velocity);
+                self.spawn_grain(left.len(), note, velocity * level);
+            }
+            self.notes[i].spawn_countdown -= 1.0;
         }
-        
+
         // Process Grains
         let active_count = self.grains.iter().filter(|g| g.active).count();
         let gain_comp = if active_count > 0 { 1.0 / (active_count as f32).sqrt() } else { 1.0 };
-        
+
         for grain in &mut self.grains {
-            let (l, r) = grain.process(&self.buffer);
+            let (l, r) = grain.process(left, right, self.interpolation);
             out_l += l;
             out_r += r;
         }
-        
+
         (out_l * gain_comp, out_r * gain_comp)
     }
-    
-    fn spawn_grain(&mut self) {
+
+    fn spawn_grain(&mut self, buf_len: usize, note: u8, amp: f32) {
+        let p = &self.params;
+
+        // Draw every random value up front so the sequence doesn't depend
+        // on whether a free grain was found
+        let rnd_pos = self.rng.next_bipolar();
+        let rnd_pitch = self.rng.next_bipolar();
+        let rnd_pan = self.rng.next_bipolar();
+        let rnd_reverse = self.rng.next_f32();
+
         // Find inactive grain
         if let Some(grain) = self.grains.iter_mut().find(|g| !g.active) {
             grain.active = true;
             grain.age = 0;
-            
+
             // Randomness
-            let rnd_pos = (rand::random::<f32>() * 2.0 - 1.0) * self.spray_ms * 0.001 * self.sample_rate;
-            let start = self.playback_pos + rnd_pos as f64;
-            
+            let spray = rnd_pos * p.spray_ms * 0.001 * self.sample_rate;
+            let start = p.position.clamp(0.0, 1.0) as f64 * (buf_len - 1) as f64 + spray as f64;
+
             // Limit to buffer
-            let buf_len = self.buffer.len();
             grain.current_pos = start.clamp(0.0, (buf_len - 1) as f64);
-            
-            grain.duration_samples = ((self.size_ms * 0.001) * self.sample_rate) as usize;
-            grain.speed = 1.0; // Pitch jitter could go here
-            grain.pan = (rand::random::<f32>() * 2.0) - 1.0;
-            grain.amp = 0.8;
+
+            grain.duration_samples = ((p.size_ms * 0.001) * self.sample_rate).max(1.0) as usize;
+            let semitones = note as f32 - p.root_note as f32 + rnd_pitch * p.pitch_jitter;
+            grain.speed = 2.0f32.powf(semitones / 12.0);
+            if rnd_reverse < p.reverse_prob {
+                grain.speed = -grain.speed;
+            }
+            grain.pan = rnd_pan * p.pan_spread.clamp(0.0, 1.0);
+            grain.amp = 0.8 * amp;
+            grain.window = p.window;
         }
     }
 }
@@ -1,6 +1,8 @@
 use std::f32::consts::PI;
+use serde::{Deserialize, Serialize};
+use crate::dsp::rng::Rng;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum LfoWave {
     Sine,
     Triangle,
@@ -9,82 +11,197 @@ pub enum LfoWave {
     SampleAndHold, // Random Steps
 }
 
-pub struct Lfo {
-    pub frequency: f32,
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SyncModifier {
+    Straight,
+    Dotted,  // 1.5x length
+    Triplet, // 2/3 length
+}
+
+/// Note length for tempo-synced rates, e.g. 1/8 dotted
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncDivision {
+    pub denominator: u8, // 1, 2, 4, 8, 16, 32, 64
+    pub modifier: SyncModifier,
+}
+
+impl SyncDivision {
+    pub fn new(denominator: u8, modifier: SyncModifier) -> Self {
+        Self { denominator, modifier }
+    }
+
+    /// Length of one LFO cycle in quarter-note beats
+    pub fn beats(&self) -> f32 {
+        let straight = 4.0 / self.denominator.clamp(1, 64) as f32;
+        match self.modifier {
+            SyncModifier::Straight => straight,
+            SyncModifier::Dotted => straight * 1.5,
+            SyncModifier::Triplet => straight * 2.0 / 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LfoRate {
+    Hz(f32),
+    Sync(SyncDivision),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LfoMode {
+    Free,      // Never resets; synced rates lock to the transport grid
+    Retrigger, // Phase restarts on every note
+    OneShot,   // Restarts on note, runs a single cycle then holds
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LfoParams {
     pub wave: LfoWave,
+    pub rate: LfoRate,
+    pub mode: LfoMode,
+    pub phase_offset: f32, // 0.0 to 1.0
+    pub fade_in_ms: f32,
+}
+
+impl Default for LfoParams {
+    fn default() -> Self {
+        Self {
+            wave: LfoWave::Sine,
+            rate: LfoRate::Hz(1.0),
+            mode: LfoMode::Retrigger,
+            phase_offset: 0.0,
+            fade_in_ms: 0.0,
+        }
+    }
+}
+
+pub struct Lfo {
+    pub params: LfoParams,
     pub phase: f32, // 0.0 to 1.0
-    
+
     sample_rate: f32,
-    phase_inc: f32,
-    
+    tempo: f32, // BPM, for synced rates
+
+    // Fade-in progress (0-1) since last trigger
+    fade: f32,
+    // One-shot progress through its single cycle
+    travelled: f32,
+    finished: bool,
+
     // State for S&H
     last_sh_val: f32,
-    sh_triggered: bool,
+    rng: Rng,
 }
 
 impl Lfo {
     pub fn new(sample_rate: f32) -> Self {
         Self {
-            frequency: 1.0,
-            wave: LfoWave::Sine,
+            params: LfoParams::default(),
             phase: 0.0,
             sample_rate,
-            phase_inc: 1.0 / sample_rate, // 1 Hz default
+            tempo: 120.0,
+            fade: 1.0,
+            travelled: 0.0,
+            finished: false,
             last_sh_val: 0.0,
-            sh_triggered: false,
+            rng: Rng::new(0x5EED),
         }
     }
-    
+
     pub fn set_freq(&mut self, freq: f32) {
-        self.frequency = freq;
-        self.phase_inc = freq / self.sample_rate;
+        self.params.rate = LfoRate::Hz(freq);
     }
-    
-    pub fn process(&mut self) -> f32 {
-        let val = match self.wave {
-            LfoWave::Sine => (self.phase * 2.0 * PI).sin(),
+
+    pub fn set_tempo(&mut self, bpm: f32) {
+        self.tempo = bpm.max(1.0);
+    }
+
+    /// Cycle frequency in Hz for the current rate and tempo
+    pub fn frequency(&self) -> f32 {
+        match self.params.rate {
+            LfoRate::Hz(hz) => hz,
+            LfoRate::Sync(div) => self.tempo / 60.0 / div.beats(),
+        }
+    }
+
+    /// Called on note-on. Resets phase unless the LFO is free-running.
+    pub fn trigger(&mut self) {
+        if self.params.mode != LfoMode::Free {
+            self.phase = self.params.phase_offset.rem_euclid(1.0);
+            self.travelled = 0.0;
+            self.finished = false;
+        }
+        self.fade = if self.params.fade_in_ms > 0.0 { 0.0 } else { 1.0 };
+    }
+
+    /// Lock a free-running synced LFO to the transport position (in beats)
+    pub fn sync_to_transport(&mut self, beat: f64) {
+        if self.params.mode != LfoMode::Free { return; }
+        if let LfoRate::Sync(div) = self.params.rate {
+            let cycles = beat / div.beats() as f64 + self.params.phase_offset as f64;
+            self.phase = cycles.rem_euclid(1.0) as f32;
+        }
+    }
+
+    fn wave_value(&self, phase: f32) -> f32 {
+        match self.params.wave {
+            LfoWave::Sine => (phase * 2.0 * PI).sin(),
             LfoWave::Triangle => {
-                // 0 -> 1 (up), 0.25 -> 0, 0.5 -> -1, 0.75 -> 0 ???
-                // Standard tri: 4 * abs(t - floor(t + 0.75) - 0.25) - 1 ... complicated
-                // Simple: 
                 // 0.0-0.25: 0 to 1
                 // 0.25-0.75: 1 to -1
                 // 0.75-1.0: -1 to 0
-                if self.phase < 0.25 {
-                    self.phase * 4.0
-                } else if self.phase < 0.75 {
-                    1.0 - (self.phase - 0.25) * 4.0
+                if phase < 0.25 {
+                    phase * 4.0
+                } else if phase < 0.75 {
+                    1.0 - (phase - 0.25) * 4.0
                 } else {
-                    -1.0 + (self.phase - 0.75) * 4.0
+                    -1.0 + (phase - 0.75) * 4.0
                 }
             },
             LfoWave::Saw => {
                 // 1.0 down to -1.0
-                1.0 - 2.0 * self.phase
+                1.0 - 2.0 * phase
             },
             LfoWave::Square => {
-                if self.phase < 0.5 { 1.0 } else { -1.0 }
+                if phase < 0.5 { 1.0 } else { -1.0 }
             },
-            LfoWave::SampleAndHold => {
-                // If phase wrapped recently, pick new value
-                // We detect wrap by seeing if we are near 0?
-                // Or just use the loop logic below.
-                // Assuming process is called sequentially.
-                self.last_sh_val
-            }
+            LfoWave::SampleAndHold => self.last_sh_val,
+        }
+    }
+
+    pub fn process(&mut self) -> f32 {
+        // A finished one-shot holds the end of its cycle
+        let phase = if self.finished {
+            (self.params.phase_offset - f32::EPSILON).rem_euclid(1.0)
+        } else {
+            self.phase
         };
-        
+        let mut val = self.wave_value(phase);
+
+        if self.fade < 1.0 {
+            val *= self.fade;
+            let fade_samples = self.params.fade_in_ms * 0.001 * self.sample_rate;
+            self.fade = (self.fade + 1.0 / fade_samples.max(1.0)).min(1.0);
+        }
+
+        if self.finished { return val; }
+
         // Advance Phase
-        let old_phase = self.phase;
-        self.phase += self.phase_inc;
+        let inc = self.frequency() / self.sample_rate;
+        self.phase += inc;
         if self.phase >= 1.0 {
             self.phase -= 1.0;
             // Trigger S&H on wrap
-            if let LfoWave::SampleAndHold = self.wave {
-                self.last_sh_val = (rand::random::<f32>() * 2.0) - 1.0;
+            if let LfoWave::SampleAndHold = self.params.wave {
+                self.last_sh_val = self.rng.next_bipolar();
             }
         }
-        
+
+        if self.params.mode == LfoMode::OneShot {
+            self.travelled += inc;
+            self.finished = self.travelled >= 1.0;
+        }
+
         val
     }
 }
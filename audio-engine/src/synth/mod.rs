@@ -1,8 +1,8 @@
 pub mod voice;
 pub mod allocator;
 pub mod oscillator;
-pub mod envelope;
 pub mod filter;
 pub mod lfo;
 pub mod granular;
 pub mod wavetable;
+pub mod sampler;
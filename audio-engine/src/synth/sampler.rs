@@ -0,0 +1,251 @@
+use crate::dsp::envelope::{Envelope, EnvelopeParams};
+use crate::dsp::interpolation::Interpolation;
+use crate::mixer::AssetCache;
+use shared::{LoopMode, SampleZone};
+
+const MAX_VOICES: usize = 16;
+
+// A stolen voice fades out over this long before its new note starts
+const STEAL_FADE_MS: f32 = 5.0;
+
+struct SamplerVoice {
+    active: bool,
+    note: u8,
+    zone: usize,
+    pos: f64,  // Read position in the asset
+    step: f64, // Playback speed
+    gain_l: f32,
+    gain_r: f32,
+    env: Envelope,
+    started_at: u64, // For stealing the oldest voice
+
+    // Stealing: samples left in the fade-out, then the note waiting for it
+    steal_fade: u32,
+    pending: Option<(usize, u8, u8)>, // Zone, note, velocity
+}
+
+/// Multi-sample playback engine. Zones are picked by key and velocity,
+/// zones in the same round-robin group take turns.
+pub struct Sampler {
+    pub zones: Vec<SampleZone>,
+    voices: Vec<SamplerVoice>,
+    rr_counters: [u32; 256], // Next zone per round-robin group
+    note_counter: u64,
+    steal_samples: u32,
+    pub interpolation: Interpolation,
+}
+
+impl Sampler {
+    pub fn new(sample_rate: f32) -> Self {
+        let envelope = EnvelopeParams::sampler();
+        let voices = (0..MAX_VOICES).map(|_| SamplerVoice {
+            active: false,
+            note: 0,
+            zone: 0,
+            pos: 0.0,
+            step: 1.0,
+            gain_l: 1.0,
+            gain_r: 1.0,
+            env: Envelope::with_params(envelope, sample_rate),
+            started_at: 0,
+            steal_fade: 0,
+            pending: None,
+        }).collect();
+
+        Self {
+            zones: Vec::new(),
+            voices,
+            rr_counters: [0; 256],
+            note_counter: 0,
+            steal_samples: (STEAL_FADE_MS * 0.001 * sample_rate).max(1.0) as u32,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    pub fn set_zones(&mut self, zones: Vec<SampleZone>) {
+        // Voices index into the zone list, so stop them before it changes
+        self.all_notes_off();
+        self.zones = zones;
+        self.rr_counters = [0; 256];
+    }
+
+    pub fn set_envelope(&mut self, params: EnvelopeParams) {
+        for voice in &mut self.voices {
+            voice.env.params = params;
+        }
+    }
+
+    pub fn note_on(&mut self, note: u8, velocity: u8) {
+        // Count matching zones per round-robin group
+        let mut group_size = [0u8; 256];
+        for zone in self.zones.iter().filter(|z| z.contains(note, velocity)) {
+            let g = zone.round_robin_group as usize;
+            group_size[g] = group_size[g].saturating_add(1);
+        }
+
+        let mut group_seen = [0u8; 256];
+        for i in 0..self.zones.len() {
+            if !self.zones[i].contains(note, velocity) { continue; }
+
+            let g = self.zones[i].round_robin_group as usize;
+            if g != 0 {
+                let pick = (self.rr_counters[g] % group_size[g] as u32) as u8;
+                let this = group_seen[g];
+                group_seen[g] += 1;
+                if this != pick { continue; }
+            }
+            self.start_voice(i, note, velocity);
+        }
+
+        for (counter, size) in self.rr_counters.iter_mut().zip(group_size.iter()).skip(1) {
+            if *size > 0 {
+                *counter = counter.wrapping_add(1);
+            }
+        }
+    }
+
+    fn start_voice(&mut self, zone_idx: usize, note: u8, velocity: u8) {
+        if let Some(idx) = self.voices.iter().position(|v| !v.active) {
+            self.begin(idx, zone_idx, note, velocity);
+            return;
+        }
+
+        // Steal the oldest voice, preferring one that isn't already fading out
+        let idx = self.voices.iter().enumerate()
+            .min_by_key(|(_, v)| (v.steal_fade > 0, v.started_at))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        let voice = &mut self.voices[idx];
+        if voice.steal_fade == 0 {
+            voice.steal_fade = self.steal_samples;
+        }
+        voice.pending = Some((zone_idx, note, velocity));
+    }
+
+    fn begin(&mut self, idx: usize, zone_idx: usize, note: u8, velocity: u8) {
+        let zone = &self.zones[zone_idx];
+        let semitones = note as f64 - zone.root_note as f64 + zone.fine_tune as f64 / 100.0;
+        let gain = shared::db_to_linear(zone.gain_db);
+        let pan = zone.pan.clamp(-1.0, 1.0);
+
+        self.note_counter += 1;
+        let voice = &mut self.voices[idx];
+        voice.active = true;
+        voice.note = note;
+        voice.zone = zone_idx;
+        voice.pos = zone.start as f64;
+        voice.step = 2.0f64.powf(semitones / 12.0);
+        // Balance, keeps stereo samples intact at centre
+        voice.gain_l = gain * (1.0 - pan).min(1.0);
+        voice.gain_r = gain * (1.0 + pan).min(1.0);
+        voice.started_at = self.note_counter;
+        voice.steal_fade = 0;
+        voice.pending = None;
+        voice.env.reset();
+        voice.env.trigger(velocity as f32 / 127.0);
+    }
+
+    pub fn note_off(&mut self, note: u8) {
+        for voice in self.voices.iter_mut().filter(|v| v.active) {
+            if voice.note == note {
+                voice.env.release();
+            }
+            // A note released before its stolen voice was free never starts
+            if voice.pending.is_some_and(|(_, pending, _)| pending == note) {
+                voice.pending = None;
+            }
+        }
+    }
+
+    pub fn all_notes_off(&mut self) {
+        for voice in &mut self.voices {
+            voice.active = false;
+            voice.steal_fade = 0;
+            voice.pending = None;
+            voice.env.reset();
+        }
+    }
+
+    /// Add all sounding voices into the output buffers
+    pub fn render(&mut self, assets: &AssetCache, out_l: &mut [f32], out_r: &mut [f32]) {
+        for idx in 0..self.voices.len() {
+            let mut from = 0;
+            while self.voices[idx].active && from < out_l.len() {
+                from += self.render_voice(idx, assets, &mut out_l[from..], &mut out_r[from..]);
+                // Stolen and faded out: its waiting note takes over from here
+                if let Some((zone, note, velocity)) = self.voices[idx].pending.take() {
+                    self.begin(idx, zone, note, velocity);
+                }
+            }
+        }
+    }
+
+    // Render one voice until the buffer ends, the voice stops or a steal
+    // fade finishes. Returns the samples rendered.
+    fn render_voice(&mut self, idx: usize, assets: &AssetCache, out_l: &mut [f32], out_r: &mut [f32]) -> usize {
+        let interpolation = self.interpolation;
+        let steal_samples = self.steal_samples as f32;
+        let voice = &mut self.voices[idx];
+        let Some(zone) = self.zones.get(voice.zone) else {
+            voice.active = false;
+            return 0;
+        };
+        let Some((src_l, src_r)) = assets.get(&zone.asset_id) else {
+            voice.active = false;
+            return 0;
+        };
+
+        let len = src_l.len() as u64;
+        let end = if zone.end == 0 { len } else { zone.end.min(len) } as f64;
+
+        let looping = zone.loop_mode == LoopMode::Forward
+            && zone.loop_end > zone.loop_start
+            && zone.loop_end as f64 <= end;
+        let loop_start = zone.loop_start as f64;
+        let loop_end = zone.loop_end as f64;
+        let loop_len = loop_end - loop_start;
+        // Crossfade reads from before loop_start, so it can't be longer than that
+        let xfade = (zone.crossfade as f64).min(loop_start).min(loop_len);
+
+        for (i, (l, r)) in out_l.iter_mut().zip(out_r.iter_mut()).enumerate() {
+            if !looping && voice.pos >= end {
+                voice.active = false;
+                return i;
+            }
+
+            let mut sl = interpolation.read(src_l, voice.pos, voice.step);
+            let mut sr = interpolation.read(src_r, voice.pos, voice.step);
+
+            // Blend the loop tail into the audio leading up to loop_start
+            if looping && xfade > 0.0 && voice.pos > loop_end - xfade {
+                let t = ((voice.pos - (loop_end - xfade)) / xfade) as f32;
+                let lead_in = voice.pos - loop_len;
+                sl = sl * (1.0 - t) + interpolation.read(src_l, lead_in, voice.step) * t;
+                sr = sr * (1.0 - t) + interpolation.read(src_r, lead_in, voice.step) * t;
+            }
+
+            let mut env = voice.env.process();
+            if !voice.env.is_active() {
+                voice.active = false;
+                return i;
+            }
+            if voice.steal_fade > 0 {
+                env *= voice.steal_fade as f32 / steal_samples;
+                voice.steal_fade -= 1;
+                if voice.steal_fade == 0 {
+                    voice.active = false;
+                    return i + 1;
+                }
+            }
+
+            *l += sl * env * voice.gain_l;
+            *r += sr * env * voice.gain_r;
+
+            voice.pos += voice.step;
+            if looping && voice.pos >= loop_end {
+                voice.pos -= loop_len;
+            }
+        }
+        out_l.len()
+    }
+}
@@ -1,92 +1,156 @@
+use std::sync::Arc;
 use crate::synth::lfo::Lfo;
-use crate::modulation::{ModulationMatrix, ModTarget, ModSource};
+use crate::modulation::{ModulationMatrix, ModTarget};
 use crate::synth::oscillator::Oscillator;
-use crate::synth::envelope::AdsrEnvelope;
+use crate::synth::wavetable::{Wavetable, WavetableOscillator};
+use crate::dsp::envelope::{Envelope, EnvelopeParams};
 use crate::synth::filter::SvfFilter;
 use crate::midi::note_to_freq;
 
+// Envelope slots, addressed by ModSource::Envelope(i)
+pub const ENV_AMP: usize = 0;
+pub const ENV_FILTER: usize = 1;
+pub const ENV_MOD: usize = 2;
+pub const NUM_ENVELOPES: usize = 3;
+
+// Per-voice LFOs, addressed by ModSource::Lfo(i)
+pub const NUM_LFOS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VoiceSource {
+    Analog,    // Detuned osc1 + osc2
+    Wavetable, // Morphing wavetable oscillator
+}
+
+
 pub struct SynthVoice {
     pub osc1: Oscillator,
     pub osc2: Oscillator,
-    pub env: AdsrEnvelope,
+    pub wavetable: WavetableOscillator,
+    pub source: VoiceSource,
+    pub envelopes: [Envelope; NUM_ENVELOPES],
     pub filter: SvfFilter,
-    pub lfo: Lfo,
-    
-    pub active: bool, 
+    pub lfos: [Lfo; NUM_LFOS],
+
+    // Filter settings before modulation
+    pub cutoff: f32,
+    pub resonance: f32,
+
+    pub active: bool,
     pub note: u8,
     pub velocity: f32,
 }
 
 impl SynthVoice {
-    pub fn new(sample_rate: f32) -> Self {
+    pub fn new(sample_rate: f32, table: Arc<Wavetable>) -> Self {
         let mut filter = SvfFilter::new(sample_rate);
         filter.set(2000.0, 0.7); // Default LowPass
-        
+
+        // Amp follows velocity fully, like the old single ADSR did
+        let amp_params = EnvelopeParams {
+            velocity_sens: 1.0,
+            ..EnvelopeParams::default()
+        };
+
         Self {
             osc1: Oscillator::new(sample_rate),
             osc2: Oscillator::new(sample_rate),
-            env: AdsrEnvelope::new(sample_rate),
+            wavetable: WavetableOscillator::with_table(table, sample_rate),
+            source: VoiceSource::Analog,
+            envelopes: [
+                Envelope::with_params(amp_params, sample_rate),
+                Envelope::new(sample_rate),
+                Envelope::new(sample_rate),
+            ],
             filter,
-            lfo: Lfo::new(sample_rate),
+            lfos: [Lfo::new(sample_rate), Lfo::new(sample_rate)],
+            cutoff: 2000.0,
+            resonance: 0.7,
             active: false,
             note: 0,
             velocity: 0.0,
         }
     }
-    
+
     pub fn note_on(&mut self, note: u8, velocity: u8) {
         self.note = note;
         self.velocity = velocity as f32 / 127.0;
         self.active = true;
-        
+
         let freq = note_to_freq(note);
         self.osc1.set_frequency(freq);
         self.osc2.set_frequency(freq * 1.01); // Detune
-        
-        self.env.gate(true);
-        // Reset LFO phase on note on? Usually configurable. For now yes.
-        self.lfo.phase = 0.0; 
+        self.wavetable.set_frequency(freq);
+
+        for env in self.envelopes.iter_mut() {
+            env.trigger(self.velocity);
+        }
+        // Phase reset depends on each LFO's mode
+        for lfo in self.lfos.iter_mut() {
+            lfo.trigger();
+        }
     }
-    
+
     pub fn note_off(&mut self) {
-        self.env.gate(false);
+        for env in self.envelopes.iter_mut() {
+            env.release();
+        }
     }
-    
-    pub fn process(&mut self, matrix: &ModulationMatrix) -> f32 {
+
+    /// `global_lfo_values` are this sample's outputs of the synth-wide LFOs
+    pub fn process(&mut self, matrix: &ModulationMatrix, global_lfo_values: &[f32]) -> f32 {
         if !self.active { return 0.0; }
-        
+
         // 1. Sources
-        let env_val = self.env.process();
-        let lfo_val = self.lfo.process();
-        
+        let mut env_values = [0.0; NUM_ENVELOPES];
+        for (value, env) in env_values.iter_mut().zip(self.envelopes.iter_mut()) {
+            *value = env.process();
+        }
+        let mut lfo_values = [0.0; NUM_LFOS];
+        for (value, lfo) in lfo_values.iter_mut().zip(self.lfos.iter_mut()) {
+            *value = lfo.process();
+        }
+
         // 2. Modulations
         // Cutoff
         let mod_cutoff = matrix.get_modulation_value(
-            &ModTarget::FilterCutoff, 
-            &[lfo_val], 
-            &[env_val], 
+            &ModTarget::FilterCutoff,
+            &lfo_values,
+            global_lfo_values,
+            &env_values,
             (self.velocity, self.note as f32 / 127.0)
         );
-        
+
         // Apply Modulation (Base + Amount)
-        // Base is currently hardcoded 2000.0. Ideally "Base" is a parameter.
         // For Filter, modulation is usually exponential (pitch/cutoff).
         // 20Hz * 2^(10 * mod) -> 20 to 20k
         // Let's assume mod_cutoff is -1 to 1.
-        let base_cutoff = 2000.0;
-        let final_cutoff = base_cutoff * 2.0_f32.powf(mod_cutoff * 5.0); // +/- 5 octaves
-        self.filter.set(final_cutoff.clamp(20.0, 20000.0), 0.7);
+        let final_cutoff = self.cutoff * 2.0_f32.powf(mod_cutoff * 5.0); // +/- 5 octaves
+        self.filter.set(final_cutoff.clamp(20.0, 20000.0), self.resonance);
+
 
-        
-        let osc_mix = (self.osc1.process() + self.osc2.process()) * 0.5;
+        let osc_mix = match self.source {
+            VoiceSource::Analog => (self.osc1.process() + self.osc2.process()) * 0.5,
+            VoiceSource::Wavetable => {
+                let mod_morph = matrix.get_modulation_value(
+                    &ModTarget::WavetableMorph,
+                    &lfo_values,
+                    global_lfo_values,
+                    &env_values,
+                    (self.velocity, self.note as f32 / 127.0)
+                );
+                self.wavetable.process_morph(self.wavetable.morph + mod_morph)
+            }
+        };
         let filtered = self.filter.process(osc_mix);
-        
-        let out = filtered * env_val * self.velocity;
-        
-        if !self.env.is_active() {
+
+        // Velocity is applied by the amp envelope's sensitivity
+        let out = filtered * env_values[ENV_AMP];
+
+        if !self.envelopes[ENV_AMP].is_active() {
             self.active = false;
         }
-        
+
         out
     }
 }
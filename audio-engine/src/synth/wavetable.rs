@@ -1,7 +1,25 @@
 use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::dsp::fft::fft;
+use crate::wav::decode_wav;
+
+// Serum-style wavetables use 2048-sample frames
+pub const DEFAULT_FRAME_SIZE: usize = 2048;
+
+// Serum's limit, later frames are dropped. Keeps a long file from building
+// hundreds of megabytes of mip levels.
+pub const MAX_FRAMES: usize = 256;
+
+// Shortest cycle a file may be cut into, anything shorter isn't a waveform
+pub const MIN_CYCLE_LENGTH: usize = 64;
+
+// Frames are resampled to at most this many samples
+const MAX_TABLE_SIZE: usize = DEFAULT_FRAME_SIZE * 2;
 
 pub struct Wavetable {
-    pub tables: Vec<Vec<f32>>, // 2D array: [frame][sample]
+    // 3D array: [mip level][frame][sample]
+    // Level 0 keeps every harmonic, each level above halves the harmonic count.
+    pub mips: Vec<Vec<Vec<f32>>>,
     pub table_size: usize,
 }
 
@@ -10,20 +28,20 @@ impl Wavetable {
         // Create basic morphing table: Sine -> Saw -> Square
         let mut tables = Vec::new();
         let num_frames = 8;
-        
+
         for i in 0..num_frames {
             let mut table = vec![0.0; table_size];
             let t = i as f32 / (num_frames - 1) as f32; // 0.0 to 1.0
-            
-            for s in 0..table_size {
+
+            for (s, sample) in table.iter_mut().enumerate() {
                 let phase = s as f32 / table_size as f32; // 0 to 1
                 let rad = phase * 2.0 * PI;
-                
+
                 // Generators
                 let sine = rad.sin();
                 let saw = 1.0 - 2.0 * phase;
                 let square = if phase < 0.5 { 1.0 } else { -1.0 };
-                
+
                 // Morph logic:
                 // 0.0 - 0.5: Sine -> Saw
                 // 0.5 - 1.0: Saw -> Square
@@ -34,71 +52,206 @@ impl Wavetable {
                     let blend = (t - 0.5) * 2.0;
                     saw * (1.0 - blend) + square * blend
                 };
-                
-                table[s] = val;
+
+                *sample = val;
             }
             tables.push(table);
         }
-        
-        Self {
-            tables,
-            table_size,
+
+        Self::from_frames(tables, table_size)
+    }
+
+    /// Build a wavetable from single-cycle frames of any length.
+    /// Frames are resampled to `table_size` (rounded up to a power of two,
+    /// at most 4096) and band-limited into one mip level per octave. Only
+    /// the first `MAX_FRAMES` are kept. Slow, build tables off the audio
+    /// thread and hand them over as an `Arc`.
+    pub fn from_frames(frames: Vec<Vec<f32>>, table_size: usize) -> Self {
+        let table_size = table_size.clamp(4, MAX_TABLE_SIZE).next_power_of_two();
+        let frames: Vec<Vec<f32>> = frames.iter()
+            .filter(|f| !f.is_empty())
+            .take(MAX_FRAMES)
+            .map(|f| resample_cycle(f, table_size))
+            .collect();
+        let frames = if frames.is_empty() { vec![vec![0.0; table_size]] } else { frames };
+
+        let max_harmonics = table_size / 2;
+        let num_levels = max_harmonics.trailing_zeros() as usize + 1;
+        let mut mips = vec![Vec::with_capacity(frames.len()); num_levels];
+
+        let mut re = vec![0.0; table_size];
+        let mut im = vec![0.0; table_size];
+        let mut spec_re = vec![0.0; table_size];
+        let mut spec_im = vec![0.0; table_size];
+
+        for frame in &frames {
+            spec_re.copy_from_slice(frame);
+            spec_im.fill(0.0);
+            fft(&mut spec_re, &mut spec_im, false);
+
+            for (level, level_frames) in mips.iter_mut().enumerate() {
+                let harmonics = max_harmonics >> level;
+                re.fill(0.0);
+                im.fill(0.0);
+                // Keep bins 1..=harmonics and their mirrors, drop DC
+                for h in 1..=harmonics.min(max_harmonics - 1) {
+                    re[h] = spec_re[h];
+                    im[h] = spec_im[h];
+                    re[table_size - h] = spec_re[table_size - h];
+                    im[table_size - h] = spec_im[table_size - h];
+                }
+                fft(&mut re, &mut im, true);
+                level_frames.push(re.clone());
+            }
+        }
+
+        // Normalize the whole table by the full-bandwidth peak
+        let peak = mips[0].iter().flatten().fold(0.0f32, |m, s| m.max(s.abs()));
+        if peak > 1e-6 {
+            let gain = 1.0 / peak;
+            for sample in mips.iter_mut().flatten().flatten() {
+                *sample *= gain;
+            }
+        }
+
+        Self { mips, table_size }
+    }
+
+    /// Load from WAV bytes. Files no longer than `frame_size` are treated as
+    /// a single cycle; longer files are split into `frame_size` frames.
+    /// A "clm " chunk in the file overrides `frame_size`. Frames past
+    /// `MAX_FRAMES` are dropped, frames shorter than `MIN_CYCLE_LENGTH` are
+    /// refused.
+    pub fn from_wav(bytes: &[u8], frame_size: usize) -> Result<Self, String> {
+        let wav = decode_wav(bytes)?;
+        let pcm = wav.to_mono();
+        if pcm.is_empty() {
+            return Err("Wavetable WAV has no samples".to_string());
         }
+
+        let frame_size = wav.cycle_length.unwrap_or(frame_size);
+        let frames: Vec<Vec<f32>> = if pcm.len() <= frame_size {
+            vec![pcm]
+        } else if frame_size < MIN_CYCLE_LENGTH {
+            return Err(format!("Wavetable frames of {} samples, at least {} needed", frame_size, MIN_CYCLE_LENGTH));
+        } else {
+            pcm.chunks_exact(frame_size).take(MAX_FRAMES).map(|c| c.to_vec()).collect()
+        };
+
+        Ok(Self::from_frames(frames, frame_size))
+    }
+
+    pub fn num_frames(&self) -> usize {
+        self.mips[0].len()
     }
-    
-    pub fn get_sample(&self, phase: f32, morph: f32) -> f32 {
-        let num_frames = self.tables.len();
+
+    pub fn num_levels(&self) -> usize {
+        self.mips.len()
+    }
+
+    /// Mip level that keeps all harmonics of `freq` below Nyquist
+    pub fn level_for_freq(&self, freq: f32, sample_rate: f32) -> usize {
+        let max_harmonics = (self.table_size / 2) as f32;
+        let allowed = (sample_rate * 0.5 / freq.abs().max(1.0)).max(1.0);
+        if allowed >= max_harmonics {
+            return 0;
+        }
+        let level = (max_harmonics / allowed).log2().ceil() as usize;
+        level.min(self.num_levels() - 1)
+    }
+
+    pub fn get_sample(&self, level: usize, phase: f32, morph: f32) -> f32 {
+        let tables = &self.mips[level.min(self.mips.len() - 1)];
+        let num_frames = tables.len();
         let frame_idx_f = morph.clamp(0.0, 1.0) * (num_frames - 1) as f32;
         let frame_idx = frame_idx_f.floor() as usize;
         let frame_frac = frame_idx_f - frame_idx as f32;
-        
+
         // Wrap phase
         let p = phase - phase.floor();
         let idx_f = p * self.table_size as f32;
-        let idx = idx_f.floor() as usize;
-        let frac = idx_f - idx as f32;
-        
+        let idx = (idx_f.floor() as usize) & (self.table_size - 1);
+        let frac = idx_f - idx_f.floor();
+
         // 2D Interpolation (Bilinear)
-        
-        let f1 = &self.tables[frame_idx];
-        let f2 = if frame_idx + 1 < num_frames { &self.tables[frame_idx + 1] } else { f1 };
-        
-        let s1_next = f1[(idx + 1) % self.table_size];
-        let s2_next = f2[(idx + 1) % self.table_size];
-        
-        let val1 = f1[idx] * (1.0 - frac) + s1_next * frac;
-        let val2 = f2[idx] * (1.0 - frac) + s2_next * frac;
-        
+
+        let f1 = &tables[frame_idx];
+        let f2 = if frame_idx + 1 < num_frames { &tables[frame_idx + 1] } else { f1 };
+
+        let next = (idx + 1) & (self.table_size - 1);
+
+        let val1 = f1[idx] * (1.0 - frac) + f1[next] * frac;
+        let val2 = f2[idx] * (1.0 - frac) + f2[next] * frac;
+
         val1 * (1.0 - frame_frac) + val2 * frame_frac
     }
 }
 
+// Linear resample of one cycle to `size` samples (wrapping at the end)
+fn resample_cycle(cycle: &[f32], size: usize) -> Vec<f32> {
+    if cycle.len() == size {
+        return cycle.to_vec();
+    }
+    let ratio = cycle.len() as f32 / size as f32;
+    (0..size).map(|i| {
+        let pos = i as f32 * ratio;
+        let idx = pos.floor() as usize;
+        let frac = pos - idx as f32;
+        let a = cycle[idx % cycle.len()];
+        let b = cycle[(idx + 1) % cycle.len()];
+        a + (b - a) * frac
+    }).collect()
+}
+
 pub struct WavetableOscillator {
-    pub table: Wavetable,
+    pub table: Arc<Wavetable>,
     pub phase: f32,
     pub freq: f32,
     pub sample_rate: f32,
     pub morph: f32,
+    level: usize,
 }
 
 impl WavetableOscillator {
     pub fn new(sample_rate: f32) -> Self {
-        Self {
-            table: Wavetable::new(2048),
+        Self::with_table(Arc::new(Wavetable::new(DEFAULT_FRAME_SIZE)), sample_rate)
+    }
+
+    pub fn with_table(table: Arc<Wavetable>, sample_rate: f32) -> Self {
+        let mut osc = Self {
+            table,
             phase: 0.0,
             freq: 440.0,
             sample_rate,
             morph: 0.0, // 0 to 1
-        }
+            level: 0,
+        };
+        osc.set_frequency(440.0);
+        osc
     }
-    
+
+    pub fn set_table(&mut self, table: Arc<Wavetable>) {
+        self.table = table;
+        self.set_frequency(self.freq);
+    }
+
+    pub fn set_frequency(&mut self, freq: f32) {
+        self.freq = freq;
+        self.level = self.table.level_for_freq(freq, self.sample_rate);
+    }
+
     pub fn process(&mut self) -> f32 {
-        let val = self.table.get_sample(self.phase, self.morph);
-        
+        self.process_morph(self.morph)
+    }
+
+    /// Process with a modulated morph position instead of the base one
+    pub fn process_morph(&mut self, morph: f32) -> f32 {
+        let val = self.table.get_sample(self.level, self.phase, morph);
+
         let inc = self.freq / self.sample_rate;
         self.phase += inc;
         if self.phase >= 1.0 { self.phase -= 1.0; }
-        
+
         val
     }
 }
@@ -0,0 +1,59 @@
+use shared::WarpMarker;
+
+// Assumed recording tempo of a warped clip without one
+const DEFAULT_ORIGINAL_BPM: f64 = 120.0;
+
+/// Source position of a warped clip, as a function of beats from the clip
+/// start. Linear between warp markers; outside them the audio runs at its
+/// original tempo.
+#[derive(Clone, Debug)]
+pub struct WarpMap {
+    points: Vec<(f64, f64)>, // (beat, source sample), sorted by beat
+    samples_per_beat: f64,   // Slope outside the markers
+}
+
+impl WarpMap {
+    /// Without markers, source sample `offset` is pinned to the clip start
+    pub fn new(markers: &[WarpMarker], offset: u64, original_bpm: Option<f32>, sample_rate: f32) -> Self {
+        let mut points: Vec<(f64, f64)> = markers.iter()
+            .filter(|m| m.beat.is_finite())
+            .map(|m| (m.beat, m.sample as f64))
+            .collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|a, b| a.0 == b.0);
+        if points.is_empty() {
+            points.push((0.0, offset as f64));
+        }
+
+        let first = points[0];
+        let last = points[points.len() - 1];
+        let samples_per_beat = match original_bpm.filter(|b| b.is_finite() && *b > 0.0) {
+            Some(bpm) => sample_rate as f64 * 60.0 / bpm as f64,
+            // Markers alone: keep their average tempo going
+            None if last.0 > first.0 => (last.1 - first.1) / (last.0 - first.0),
+            None => sample_rate as f64 * 60.0 / DEFAULT_ORIGINAL_BPM,
+        };
+        Self { points, samples_per_beat }
+    }
+
+    // Segment around `beat`: its start point and source samples per beat
+    fn segment(&self, beat: f64) -> ((f64, f64), f64) {
+        let i = self.points.partition_point(|p| p.0 <= beat);
+        if i == 0 || i == self.points.len() {
+            let anchor = if i == 0 { self.points[0] } else { self.points[i - 1] };
+            return (anchor, self.samples_per_beat);
+        }
+        let (a, b) = (self.points[i - 1], self.points[i]);
+        (a, (b.1 - a.1) / (b.0 - a.0))
+    }
+
+    pub fn source_at(&self, beat: f64) -> f64 {
+        let (anchor, slope) = self.segment(beat);
+        anchor.1 + (beat - anchor.0) * slope
+    }
+
+    /// Source samples per beat at `beat`
+    pub fn slope_at(&self, beat: f64) -> f64 {
+        self.segment(beat).1
+    }
+}
@@ -0,0 +1,125 @@
+// Minimal RIFF/WAVE reader for user content (wavetables, samples).
+// Handles PCM 8/16/24/32-bit, IEEE float 32/64 and WAVE_FORMAT_EXTENSIBLE.
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+pub struct WavData {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>, // Planar, one Vec per channel
+    // Frame size from a Serum-style "clm " chunk ("<!>2048 ..."), if present
+    pub cycle_length: Option<usize>,
+}
+
+impl WavData {
+    pub fn num_frames(&self) -> usize {
+        self.channels.first().map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// Average all channels into one
+    pub fn to_mono(&self) -> Vec<f32> {
+        let frames = self.num_frames();
+        let scale = 1.0 / self.channels.len().max(1) as f32;
+        (0..frames)
+            .map(|i| self.channels.iter().map(|c| c[i]).sum::<f32>() * scale)
+            .collect()
+    }
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]])
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+}
+
+pub fn decode_wav(bytes: &[u8]) -> Result<WavData, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Not a RIFF/WAVE file".to_string());
+    }
+
+    let mut format: Option<(u16, u16, u32, u16)> = None; // (tag, channels, rate, bits)
+    let mut data: Option<&[u8]> = None;
+    let mut cycle_length = None;
+
+    // Walk chunks
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = read_u32(bytes, pos + 4) as usize;
+        let body_start = pos + 8;
+        // Sizes past the end (truncated or hostile files) are cut at the end
+        let body_end = body_start.saturating_add(size).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err("fmt chunk too short".to_string());
+                }
+                let mut tag = read_u16(body, 0);
+                let channels = read_u16(body, 2);
+                let rate = read_u32(body, 4);
+                let bits = read_u16(body, 14);
+                if tag == FORMAT_EXTENSIBLE && body.len() >= 26 {
+                    // First two bytes of the SubFormat GUID hold the real format tag
+                    tag = read_u16(body, 24);
+                }
+                format = Some((tag, channels, rate, bits));
+            },
+            b"data" => data = Some(body),
+            b"clm " => {
+                // e.g. "<!>2048 10000000 wavetable (www.xferrecords.com)"
+                let text = String::from_utf8_lossy(body);
+                cycle_length = text.strip_prefix("<!>")
+                    .and_then(|rest| rest.split_whitespace().next())
+                    .and_then(|n| n.parse().ok());
+            },
+            _ => {}
+        }
+
+        // Chunks are word aligned. A size that overflows ends the walk.
+        match body_start.checked_add(size).and_then(|end| end.checked_add(size & 1)) {
+            Some(next) => pos = next,
+            None => break,
+        }
+    }
+
+    let (tag, num_channels, sample_rate, bits) = format.ok_or("Missing fmt chunk")?;
+    let data = data.ok_or("Missing data chunk")?;
+    if num_channels == 0 {
+        return Err("WAV has zero channels".to_string());
+    }
+
+    let bytes_per_sample = (bits as usize).div_ceil(8);
+    let frame_bytes = bytes_per_sample * num_channels as usize;
+    if frame_bytes == 0 {
+        return Err("Invalid bit depth".to_string());
+    }
+    let num_frames = data.len() / frame_bytes;
+
+    let decode: fn(&[u8]) -> f32 = match (tag, bits) {
+        (FORMAT_PCM, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+        (FORMAT_PCM, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        (FORMAT_PCM, 24) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0,
+        (FORMAT_PCM, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        (FORMAT_IEEE_FLOAT, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        (FORMAT_IEEE_FLOAT, 64) => |b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32,
+        _ => return Err(format!("Unsupported WAV format {} / {} bits", tag, bits)),
+    };
+
+    let mut channels = vec![Vec::with_capacity(num_frames); num_channels as usize];
+    for frame in data.chunks_exact(frame_bytes) {
+        for (ch, sample) in frame.chunks_exact(bytes_per_sample).enumerate() {
+            channels[ch].push(decode(sample));
+        }
+    }
+
+    Ok(WavData {
+        sample_rate,
+        channels,
+        cycle_length,
+    })
+}
@@ -0,0 +1,115 @@
+use audio_engine::automation::AutomationSet;
+use shared::{AutomationLane, AutomationMode, CurveType, ParamAddress};
+
+const GAIN: ParamAddress = ParamAddress::TrackGain;
+
+// A gain lane holding 0.2 from 0 to 10 seconds
+fn flat_set() -> AutomationSet {
+    let mut lane = AutomationLane::new(GAIN.to_string());
+    lane.add_point(0.0, 0.2, CurveType::Linear);
+    lane.add_point(10.0, 0.2, CurveType::Linear);
+    AutomationSet::new(vec![lane], |_| true)
+}
+
+fn value_at(set: &AutomationSet, time: f64) -> f32 {
+    set.lanes()[0].get_value_at(time)
+}
+
+fn times(set: &AutomationSet) -> Vec<f64> {
+    set.lanes()[0].points.iter().map(|p| p.time).collect()
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-4
+}
+
+// Blocks of 0.1 s from `from` to `to`, moving the control to `value` at `at`
+fn play(set: &mut AutomationSet, from: usize, to: usize, moves: &[(usize, f32)]) {
+    for block in from..to {
+        let time = block as f64 * 0.1;
+        for &(at, value) in moves {
+            if at == block {
+                set.write(GAIN, value, time, true);
+            }
+        }
+        set.record(time);
+    }
+}
+
+#[test]
+fn write_replaces_the_lane_from_the_first_block() {
+    let mut set = flat_set();
+    set.set_mode(GAIN, AutomationMode::Write, 0.0);
+    play(&mut set, 0, 50, &[(20, 0.8)]);
+    set.finish(5.0);
+
+    // Holds what was playing until the move, then the new value
+    assert!(close(value_at(&set, 1.0), 0.2));
+    assert!(close(value_at(&set, 3.0), 0.8));
+    assert!(close(value_at(&set, 5.0), 0.8));
+    assert!(close(value_at(&set, 10.0), 0.2)); // Points past the pass stay
+
+    let passes = set.take_recorded();
+    assert_eq!(passes.len(), 1);
+    assert_eq!((passes[0].start, passes[0].end), (0.0, 5.0));
+    assert!(set.take_recorded().is_empty());
+}
+
+#[test]
+fn touch_writes_only_while_held() {
+    let mut set = flat_set();
+    set.set_mode(GAIN, AutomationMode::Touch, 0.0);
+    play(&mut set, 0, 10, &[]);
+    assert!(set.take_recorded().is_empty());
+
+    play(&mut set, 10, 30, &[(10, 0.6)]);
+    set.release(GAIN, 3.0);
+    play(&mut set, 30, 50, &[]);
+    set.finish(5.0);
+
+    // Only 1..3 s is rewritten, the saved points around it stay
+    assert_eq!(times(&set), vec![0.0, 1.0, 3.0, 10.0]);
+    assert!(close(value_at(&set, 2.0), 0.6));
+    let passes = set.take_recorded();
+    assert_eq!(passes.len(), 1);
+    assert_eq!((passes[0].start, passes[0].end), (1.0, 3.0));
+}
+
+#[test]
+fn latch_keeps_writing_after_release() {
+    let mut set = flat_set();
+    set.set_mode(GAIN, AutomationMode::Latch, 0.0);
+    play(&mut set, 0, 30, &[(10, 0.6)]);
+    set.release(GAIN, 3.0);
+    play(&mut set, 30, 50, &[]);
+    set.finish(5.0);
+
+    assert_eq!(times(&set), vec![0.0, 1.0, 5.0, 10.0]);
+    assert!(close(value_at(&set, 4.5), 0.6));
+    let passes = set.take_recorded();
+    assert_eq!((passes[0].start, passes[0].end), (1.0, 5.0));
+}
+
+#[test]
+fn loops_close_the_pass_and_start_another() {
+    let mut set = flat_set();
+    set.set_mode(GAIN, AutomationMode::Latch, 0.0);
+    play(&mut set, 10, 20, &[(10, 0.6)]);
+    // Loop back to 1 s, the control moves again on the second lap
+    play(&mut set, 10, 20, &[(15, 0.9)]);
+    set.finish(2.0);
+
+    assert!(close(value_at(&set, 1.2), 0.6));
+    assert!(close(value_at(&set, 1.8), 0.9));
+    let passes = set.take_recorded();
+    assert_eq!(passes.len(), 1);
+    assert_eq!((passes[0].start, passes[0].end), (1.0, 2.0));
+}
+
+#[test]
+fn saved_modes_load_as_read() {
+    let json = r#"{"points":[],"target":"gain","mode":"Write"}"#;
+    let lane: AutomationLane = serde_json::from_str(json).unwrap();
+    assert_eq!(lane.mode, AutomationMode::Read);
+    assert!(!serde_json::to_string(&lane).unwrap().contains("mode"));
+}
@@ -0,0 +1,61 @@
+use audio_engine::dsp::envelope::EnvelopeParams;
+use audio_engine::midi::MidiEvent;
+use audio_engine::mixer::AssetCache;
+use audio_engine::nodes::DrumRackNode;
+use shared::DrumPad;
+
+fn render(rack: &mut DrumRackNode, assets: &AssetCache, frames: usize) -> Vec<f32> {
+    let (mut left, mut right) = (vec![0.0; frames], vec![0.0; frames]);
+    rack.process(assets, &mut [&mut left, &mut right]);
+    left
+}
+
+// One constant pad with a 5 ms attack, so only the steal itself could click
+fn dc_rack() -> (DrumRackNode, AssetCache) {
+    let mut assets = AssetCache::new();
+    assets.insert("dc".to_string(), (vec![1.0; 44100], vec![1.0; 44100]));
+    let mut rack = DrumRackNode::new(44100.0);
+    let envelope = EnvelopeParams { attack_ms: 5.0, ..EnvelopeParams::sampler() };
+    rack.set_pads(vec![DrumPad { asset_id: "dc".to_string(), envelope, ..DrumPad::default() }]);
+    (rack, assets)
+}
+
+fn hit(rack: &mut DrumRackNode, velocity: u8) {
+    rack.event_queue.push(MidiEvent::note_on(0, DrumPad::default().note, velocity, 0));
+}
+
+#[test]
+fn stolen_pad_voices_fade_instead_of_clicking() {
+    let (mut rack, assets) = dc_rack();
+    for _ in 0..4 {
+        hit(&mut rack, 127);
+    }
+    let settled = render(&mut rack, &assets, 441);
+    assert!((settled[440] - 4.0).abs() < 1e-3, "{}", settled[440]);
+
+    hit(&mut rack, 127);
+    let mut out = vec![settled[440]];
+    out.extend(render(&mut rack, &assets, 2205));
+    let jump = out.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max);
+    assert!(jump < 0.05, "{}", jump);
+
+    // The oldest hit dipped out and the new one has taken its place
+    let dip = out.iter().copied().fold(f32::MAX, f32::min);
+    assert!(dip < 3.5, "{}", dip);
+    assert!((out[2205] - 4.0).abs() < 1e-3, "{}", out[2205]);
+}
+
+#[test]
+fn stolen_voices_play_the_hit_waiting_on_them() {
+    let (mut rack, assets) = dc_rack();
+    for _ in 0..4 {
+        hit(&mut rack, 127);
+    }
+    render(&mut rack, &assets, 441);
+
+    // Each hit waits on its own voice, the silent one leaves three sounding
+    hit(&mut rack, 127);
+    hit(&mut rack, 0);
+    let out = render(&mut rack, &assets, 2205);
+    assert!((out[2204] - 3.0).abs() < 1e-3, "{}", out[2204]);
+}
@@ -0,0 +1,115 @@
+use audio_engine::dsp::dither::Dither;
+use audio_engine::encode::{encode, BitDepth, Container, ExportFormat};
+use symphonia::core::{audio::SampleBuffer, codecs::DecoderOptions, formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint};
+
+fn u16_at(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]])
+}
+
+// Decode a FLAC file with symphonia: sample rate, channels and interleaved words
+fn decode_flac(bytes: Vec<u8>, bits: u32) -> (u32, usize, Vec<i32>) {
+    let stream = MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes)), Default::default());
+    let mut reader = symphonia::default::get_probe()
+        .format(Hint::new().with_extension("flac"), stream, &FormatOptions::default(), &MetadataOptions::default())
+        .unwrap()
+        .format;
+    let params = reader.default_track().unwrap().codec_params.clone();
+    let mut decoder = symphonia::default::get_codecs().make(&params, &DecoderOptions::default()).unwrap();
+
+    let mut words = Vec::new();
+    while let Ok(packet) = reader.next_packet() {
+        let decoded = decoder.decode(&packet).unwrap();
+        let mut buffer = SampleBuffer::<i32>::new(decoded.capacity() as u64, *decoded.spec());
+        buffer.copy_interleaved_ref(decoded);
+        // Symphonia fills the top of the 32-bit word
+        words.extend(buffer.samples().iter().map(|w| w >> (32 - bits)));
+    }
+    (params.sample_rate.unwrap(), params.channels.unwrap().count(), words)
+}
+
+fn format(container: Container, bit_depth: BitDepth, dither: Dither) -> ExportFormat {
+    ExportFormat { container, bit_depth, dither }
+}
+
+#[test]
+fn float_wav_is_labelled_ieee_float() {
+    let samples = [0.5f32, -0.5, 0.25, -0.25];
+    let bytes = encode(&samples, 2, 44100, &format(Container::Wav, BitDepth::Float32, Dither::None)).unwrap();
+    assert_eq!(&bytes[12..16], b"fmt ");
+    assert_eq!(u16_at(&bytes, 20), 3); // WAVE_FORMAT_IEEE_FLOAT
+    assert_eq!(u16_at(&bytes, 34), 32);
+    let fact = bytes.windows(4).position(|w| w == b"fact").expect("float WAV needs a fact chunk");
+    assert_eq!(u32::from_le_bytes(bytes[fact + 8..fact + 12].try_into().unwrap()), 2);
+    let data = bytes.windows(4).position(|w| w == b"data").unwrap();
+    assert_eq!(f32::from_le_bytes(bytes[data + 8..data + 12].try_into().unwrap()), 0.5);
+}
+
+#[test]
+fn wide_pcm_uses_the_extensible_format() {
+    let bytes = encode(&[0.0; 6], 2, 48000, &format(Container::Wav, BitDepth::Int24, Dither::None)).unwrap();
+    assert_eq!(u16_at(&bytes, 20), 0xFFFE);
+    assert_eq!(u16_at(&bytes, 44), 1); // KSDATAFORMAT_SUBTYPE_PCM
+    assert_eq!(u16_at(&bytes, 32), 6); // Block align
+}
+
+#[test]
+fn dither_decorrelates_quiet_signals() {
+    // A tone at a quarter of a 16-bit step rounds to silence without dither
+    let quiet: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.05).sin() * 0.25 / 32768.0).collect();
+    let words = |dither| {
+        let bytes = encode(&quiet, 1, 44100, &format(Container::Wav, BitDepth::Int16, dither)).unwrap();
+        bytes[44..].chunks(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect::<Vec<_>>()
+    };
+    assert!(words(Dither::None).iter().all(|w| *w == 0));
+    for dither in [Dither::Tpdf, Dither::Shaped] {
+        assert!(words(dither).iter().any(|w| *w != 0));
+    }
+}
+
+#[test]
+fn flac_and_aiff_headers() {
+    let samples: Vec<f32> = (0..10000).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+    let flac = encode(&samples, 2, 44100, &format(Container::Flac, BitDepth::Int16, Dither::None)).unwrap();
+    assert_eq!(&flac[..4], b"fLaC");
+    // STREAMINFO: 20-bit rate, 3-bit channels - 1, 5-bit bits - 1, 36-bit frames
+    let info = u64::from_be_bytes(flac[18..26].try_into().unwrap());
+    assert_eq!(info >> 44, 44100);
+    assert_eq!((info >> 41) & 0x7, 1);
+    assert_eq!((info >> 36) & 0x1F, 15);
+    assert_eq!(info & 0xF_FFFF_FFFF, 5000);
+    assert!(flac.len() < samples.len() * 2, "no compression");
+
+    let aiff = encode(&samples, 2, 44100, &format(Container::Aiff, BitDepth::Float32, Dither::None)).unwrap();
+    assert_eq!(&aiff[8..12], b"AIFC");
+    assert!(aiff.windows(4).any(|w| w == b"fl32"));
+
+    let float_flac = format(Container::Flac, BitDepth::Float32, Dither::None);
+    assert!(encode(&samples, 2, 44100, &float_flac).is_err());
+}
+
+#[test]
+fn flac_decodes_bit_exact() {
+    for (depth, bits) in [(BitDepth::Int16, 16), (BitDepth::Int24, 24)] {
+        for channels in [1u16, 2] {
+            // A tone plus noise, hitting both ends of the range, over a partial last block
+            let (min, max) = (-(1i32 << (bits - 1)), (1i32 << (bits - 1)) - 1);
+            let mut noise = 1u32;
+            let words: Vec<i32> = (0..10007 * channels as usize).map(|i| {
+                noise = noise.wrapping_mul(1664525).wrapping_add(1013904223);
+                let tone = (i as f32 * 0.003).sin() * max as f32 * 0.7;
+                match i {
+                    100 => min,
+                    101 => max,
+                    _ => (tone as i32 + (noise >> 20) as i32 - 2048).clamp(min, max),
+                }
+            }).collect();
+            let scale = (1i64 << (bits - 1)) as f32;
+            let samples: Vec<f32> = words.iter().map(|w| *w as f32 / scale).collect();
+
+            let flac = encode(&samples, channels, 48000, &format(Container::Flac, depth, Dither::None)).unwrap();
+            let (rate, count, decoded) = decode_flac(flac, bits);
+            assert_eq!((rate, count), (48000, channels as usize));
+            assert!(decoded == words, "{} bit, {} channels", bits, channels);
+        }
+    }
+}
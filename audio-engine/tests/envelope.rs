@@ -0,0 +1,143 @@
+use audio_engine::dsp::envelope::{Envelope, EnvelopeParams, EnvelopeStage};
+
+// At 1 kHz every millisecond is one sample
+const SAMPLE_RATE: f32 = 1000.0;
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-4
+}
+
+fn dahdsr() -> EnvelopeParams {
+    EnvelopeParams {
+        delay_ms: 10.0,
+        attack_ms: 10.0,
+        hold_ms: 5.0,
+        decay_ms: 10.0,
+        sustain_level: 0.5,
+        release_ms: 20.0,
+        attack_curve: 0.0,
+        decay_curve: 0.0,
+        release_curve: 0.0,
+        velocity_sens: 0.0,
+        looping: false,
+    }
+}
+
+fn run(env: &mut Envelope, samples: usize) -> Vec<f32> {
+    (0..samples).map(|_| env.process()).collect()
+}
+
+#[test]
+fn stages_last_their_length() {
+    let mut env = Envelope::with_params(dahdsr(), SAMPLE_RATE);
+    env.trigger(1.0);
+    let out = run(&mut env, 40);
+    assert!(out[..10].iter().all(|s| *s == 0.0));
+    assert!(close(out[14], 0.5)); // Halfway up
+    assert!(close(out[19], 1.0));
+    assert!(out[20..25].iter().all(|s| close(*s, 1.0)));
+    assert!(close(out[29], 0.75)); // Halfway down to sustain
+    assert!(out[34..].iter().all(|s| close(*s, 0.5)));
+    assert_eq!(env.stage(), EnvelopeStage::Sustain);
+
+    env.release();
+    let out = run(&mut env, 20);
+    assert!(close(out[9], 0.25));
+    assert!(close(out[19], 0.0));
+    assert!(!env.is_active());
+}
+
+#[test]
+fn curves_bend_the_middle_not_the_ends() {
+    let mut mids = Vec::new();
+    for curve in [-1.0, 0.0, 1.0] {
+        let params = EnvelopeParams { attack_curve: curve, decay_curve: curve, ..dahdsr() };
+        let mut env = Envelope::with_params(params, SAMPLE_RATE);
+        env.trigger(1.0);
+        let out = run(&mut env, 40);
+        let (attack, decay) = (&out[10..20], &out[25..35]);
+        // Both start from where they were and land exactly on their target
+        assert!(attack[0] > 0.0 && attack[0] < 1.0, "{}: {}", curve, attack[0]);
+        assert_eq!(attack[9], 1.0, "{}", curve);
+        assert!(decay[0] < 1.0 && decay[0] > 0.5, "{}: {}", curve, decay[0]);
+        assert_eq!(decay[9], 0.5, "{}", curve);
+        assert!(attack.windows(2).all(|w| w[1] > w[0]), "{}", curve);
+        assert!(decay.windows(2).all(|w| w[1] < w[0]), "{}", curve);
+        mids.push((attack[4], decay[4]));
+    }
+    // Positive curves move fast first, negative ones slow first
+    assert!(mids[0].0 < mids[1].0 && mids[1].0 < mids[2].0, "{:?}", mids);
+    assert!(mids[0].1 > mids[1].1 && mids[1].1 > mids[2].1, "{:?}", mids);
+}
+
+#[test]
+fn velocity_scales_by_sensitivity() {
+    for (sens, velocity, peak) in [(0.0, 0.2, 1.0), (1.0, 0.2, 0.2), (0.5, 0.2, 0.6), (1.0, 1.0, 1.0)] {
+        let params = EnvelopeParams { velocity_sens: sens, ..dahdsr() };
+        let mut env = Envelope::with_params(params, SAMPLE_RATE);
+        env.trigger(velocity);
+        let out = run(&mut env, 40);
+        assert!(close(out[19], peak), "{} {}: {}", sens, velocity, out[19]);
+        assert!(close(out[39], peak * 0.5), "{} {}: {}", sens, velocity, out[39]);
+    }
+}
+
+#[test]
+fn retriggers_with_a_new_velocity_do_not_jump() {
+    let params = EnvelopeParams { velocity_sens: 1.0, ..dahdsr() };
+    let mut env = Envelope::with_params(params, SAMPLE_RATE);
+    env.trigger(1.0);
+    let held = *run(&mut env, 40).last().unwrap();
+
+    // A stolen voice carries on from where it was and heads for the new peak
+    env.trigger(0.2);
+    let out = run(&mut env, 40);
+    assert!(out[..10].iter().all(|s| close(*s, held)), "{:?}", &out[..10]);
+    assert!(out.windows(2).all(|w| (w[1] - w[0]).abs() < 0.05), "{:?}", out);
+    assert!(close(out[19], 0.2) && close(out[39], 0.1));
+}
+
+#[test]
+fn looping_cycles_until_release() {
+    let params = EnvelopeParams { looping: true, ..dahdsr() };
+    let mut env = Envelope::with_params(params, SAMPLE_RATE);
+    env.trigger(1.0);
+    let out = run(&mut env, 135);
+    assert_ne!(env.stage(), EnvelopeStage::Sustain);
+    // Attack, hold and decay repeat every 25 samples after the delay, each
+    // attack rising from the sustain level
+    for cycle in 0..5 {
+        let start = 10 + cycle * 25;
+        assert!(close(out[start + 9], 1.0), "cycle {}", cycle);
+        assert!(close(out[start + 24], 0.5), "cycle {}", cycle);
+    }
+    assert!(close(out[39], 0.75)); // Second attack, halfway up from 0.5
+
+    env.release();
+    run(&mut env, 20);
+    assert!(!env.is_active());
+}
+
+#[test]
+fn held_notes_follow_parameter_changes() {
+    let params = EnvelopeParams { attack_ms: 1000.0, ..dahdsr() };
+    let mut env = Envelope::with_params(params, SAMPLE_RATE);
+    env.trigger(1.0);
+    run(&mut env, 100);
+    assert_eq!(env.stage(), EnvelopeStage::Attack);
+
+    // A shorter attack finishes the one that's running sooner
+    env.params.attack_ms = 100.0;
+    run(&mut env, 100);
+    assert_eq!(env.stage(), EnvelopeStage::Decay);
+
+    run(&mut env, 20);
+    assert_eq!(env.stage(), EnvelopeStage::Sustain);
+    env.params.sustain_level = 0.2;
+    assert!(close(env.process(), 0.2));
+
+    env.params.release_ms = 4.0;
+    env.release();
+    run(&mut env, 4);
+    assert!(!env.is_active());
+}
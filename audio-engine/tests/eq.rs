@@ -0,0 +1,54 @@
+use audio_engine::nodes::parametric_eq::frequency_response;
+use shared::{EqBand, EqBandType};
+
+fn response_at(bands: &[EqBand], freqs: &[f32]) -> Vec<f32> {
+    let mut out = vec![0.0; freqs.len()];
+    frequency_response(bands, 48000.0, freqs, &mut out);
+    out
+}
+
+#[test]
+fn bells_and_shelves_reach_their_gain() {
+    let bell = EqBand { freq: 1000.0, gain_db: 6.0, q: 2.0, ..EqBand::default() };
+    let db = response_at(&[bell], &[1000.0, 50.0, 15000.0]);
+    assert!((db[0] - 6.0).abs() < 0.01, "{:?}", db);
+    assert!(db[1].abs() < 0.1 && db[2].abs() < 0.1, "{:?}", db);
+
+    let shelf = EqBand { kind: EqBandType::LowShelf, freq: 200.0, gain_db: -9.0, ..EqBand::default() };
+    let db = response_at(&[shelf], &[20.0, 200.0, 10000.0]);
+    assert!((db[0] + 9.0).abs() < 0.1, "{:?}", db);
+    assert!((db[1] + 4.5).abs() < 0.1, "{:?}", db); // Half the gain at the corner
+    assert!(db[2].abs() < 0.1, "{:?}", db);
+
+    // Bands add up in dB, disabled ones don't count
+    let both = response_at(&[bell, shelf], &[1000.0, 20.0]);
+    assert!((both[0] - 6.0).abs() < 0.1 && (both[1] + 9.0).abs() < 0.1, "{:?}", both);
+    let off = EqBand { enabled: false, ..bell };
+    assert!(response_at(&[off], &[1000.0])[0].abs() < 1e-4);
+}
+
+#[test]
+fn cut_slopes_stack_butterworth_sections() {
+    for slope_db in [12u8, 24, 36, 48] {
+        let cut = EqBand { kind: EqBandType::LowCut, freq: 400.0, slope_db, ..EqBand::default() };
+        let db = response_at(&[cut], &[400.0, 100.0, 8000.0]);
+        // -3 dB at the corner whatever the order, then the full slope
+        assert!((db[0] + 3.01).abs() < 0.05, "{} {:?}", slope_db, db);
+        assert!((db[1] + 2.0 * slope_db as f32).abs() < 1.0, "{} {:?}", slope_db, db);
+        assert!(db[2].abs() < 0.01, "{} {:?}", slope_db, db);
+    }
+}
+
+#[test]
+fn cut_q_adds_resonance_at_the_corner() {
+    let freqs: Vec<f32> = (0..400).map(|i| 2000.0 * 1.005f32.powi(i)).collect();
+    let cut = EqBand { kind: EqBandType::HighCut, freq: 4000.0, slope_db: 48, ..EqBand::default() };
+    let flat = response_at(&[cut], &freqs);
+    let resonant = response_at(&[EqBand { q: 4.0, ..cut }], &freqs);
+
+    let peak = |db: &[f32]| db.iter().copied().fold(f32::MIN, f32::max);
+    assert!(peak(&flat) < 0.01, "{}", peak(&flat));
+    // Scaling the already resonant section peaks near the corner
+    let (at, db) = freqs.iter().zip(&resonant).max_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+    assert!(*db > 10.0 && (3500.0..4500.0).contains(at), "{} dB at {} Hz", db, at);
+}
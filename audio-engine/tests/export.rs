@@ -0,0 +1,228 @@
+use audio_engine::export::{AudioExporter, RenderOptions, RenderProgress, StemGroup, StemOptions, TailMode};
+use audio_engine::mixer::{AssetCache, Mixer};
+use shared::{ClipData, Effect, Project, TimeRange, TrackData};
+use std::sync::Arc;
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+// Equal-power pan law at centre
+const CENTER_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+// The open DJ filter (20 kHz low-pass) shifts the phase a little
+const TOLERANCE: f32 = 0.005;
+
+fn sine(frames: usize, freq: f32) -> Vec<f32> {
+    (0..frames)
+        .map(|i| (std::f32::consts::TAU * freq * i as f32 / SAMPLE_RATE).sin() * 0.5)
+        .collect()
+}
+
+fn audio_track(id: u32, start: u64, duration: u64, effects: Vec<Effect>) -> TrackData {
+    TrackData {
+        id,
+        name: format!("Track {}", id),
+        gain_db: 0.0,
+        pan: 0.0,
+        muted: false,
+        soloed: false,
+        clips: vec![ClipData::Audio {
+            id: 1,
+            name: "tone".into(),
+            start,
+            duration,
+            offset: 0,
+            asset_id: "tone.wav".into(),
+            muted: false,
+            gain_db: 0.0,
+            stretch: 1.0,
+            transpose: 0.0,
+            original_bpm: None,
+            warp_markers: Vec::new(),
+        }],
+        effects,
+        automation: Vec::new(),
+        instrument: None,
+        output_bus: None,
+        sends: Vec::new(),
+    }
+}
+
+fn tone_assets(frames: usize) -> AssetCache {
+    let tone = sine(frames, 441.0);
+    AssetCache::from([("tone.wav".to_string(), (tone.clone(), tone))])
+}
+
+fn assert_plays_tone(out: &[f32], start: usize, tone: &[f32]) {
+    for (i, expected) in tone.iter().enumerate() {
+        let frame = start + i;
+        for channel in 0..2 {
+            let sample = out[2 * frame + channel];
+            assert!((sample - expected * CENTER_GAIN).abs() < TOLERANCE,
+                "frame {} channel {}: {} vs {}", frame, channel, sample, expected * CENTER_GAIN);
+        }
+    }
+}
+
+#[test]
+fn renders_clips_where_they_sit() {
+    let mut project = Project::new("Render");
+    project.tracks.push(audio_track(1, 1000, 4410, Vec::new()));
+    let out = AudioExporter::render_project(&project, tone_assets(4410), SAMPLE_RATE);
+
+    // Ends with the clip, nothing rings on
+    assert_eq!(out.len(), 2 * 5410);
+    assert!(out[..2 * 1000].iter().all(|s| *s == 0.0));
+    assert_plays_tone(&out, 1000, &sine(4410, 441.0));
+}
+
+#[test]
+fn clips_on_one_track_play_at_normal_speed() {
+    let mut project = Project::new("Render");
+    let mut track = audio_track(1, 0, 2205, Vec::new());
+    let second = match &track.clips[0] {
+        ClipData::Audio { id, name, asset_id, .. } => ClipData::Audio {
+            id: id + 1,
+            name: name.clone(),
+            start: 2205,
+            duration: 2205,
+            offset: 2205,
+            asset_id: asset_id.clone(),
+            muted: false,
+            gain_db: 0.0,
+            stretch: 1.0,
+            transpose: 0.0,
+            original_bpm: None,
+            warp_markers: Vec::new(),
+        },
+        _ => unreachable!(),
+    };
+    track.clips.push(second);
+    project.tracks.push(track);
+
+    let out = AudioExporter::render_project(&project, tone_assets(4410), SAMPLE_RATE);
+    assert_eq!(out.len(), 2 * 4410);
+    assert_plays_tone(&out, 0, &sine(4410, 441.0));
+}
+
+#[test]
+fn length_covers_the_delay_tail() {
+    let delay = Effect::Delay { time_ms: 100.0, feedback: 0.5, mix: 0.5 };
+    let mut project = Project::new("Render");
+    project.tracks.push(audio_track(1, 0, 4410, vec![delay]));
+    let assets = tone_assets(4410);
+
+    // Repeats until 0.5^n is under -60 dB: 10 of them, plus the first one
+    let length = AudioExporter::project_length(&project, &assets, SAMPLE_RATE);
+    let tail = ((0.001f32.ln() / 0.5f32.ln() + 1.0) * 0.1 * SAMPLE_RATE).ceil() as u64;
+    assert_eq!(length, 4410 + tail);
+
+    let out = AudioExporter::render_project(&project, assets, SAMPLE_RATE);
+    assert_eq!(out.len() as u64, 2 * length);
+    let echo = out[2 * 4410..2 * 8820].iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    assert!(echo > 0.05, "no echo after the clip: {}", echo);
+}
+
+#[test]
+fn empty_project_renders_nothing() {
+    let project = Project::new("Empty");
+    assert!(AudioExporter::render_project(&project, AssetCache::new(), SAMPLE_RATE).is_empty());
+}
+
+#[test]
+fn stems_line_up_and_sum_to_the_mix() {
+    let mut project = Project::new("Stems");
+    project.tracks.push(audio_track(1, 0, 4410, Vec::new()));
+    let mut sent = audio_track(2, 2000, 2000, Vec::new());
+    sent.sends.push(shared::SendData { bus_id: 7, level_db: -6.0 });
+    project.tracks.push(sent);
+    project.buses.push(shared::BusData {
+        id: 7,
+        name: "Echo".into(),
+        gain_db: 0.0,
+        pan: 0.0,
+        muted: false,
+        effects: vec![Effect::Delay { time_ms: 50.0, feedback: 0.3, mix: 1.0 }],
+    });
+    let mix = AudioExporter::render_project(&project, tone_assets(4410), SAMPLE_RATE);
+
+    for group in [StemGroup::Tracks, StemGroup::Buses] {
+        let options = StemOptions { group, ..Default::default() };
+        let stems = AudioExporter::render_stems(&project, tone_assets(4410), SAMPLE_RATE, options);
+        assert_eq!(stems.len(), 2);
+        assert!(stems.iter().all(|stem| stem.samples.len() == mix.len()));
+
+        let sum = stems.iter().fold(vec![0.0; mix.len()], |mut sum, stem| {
+            sum.iter_mut().zip(&stem.samples).for_each(|(s, x)| *s += x);
+            sum
+        });
+        for (i, (a, b)) in sum.iter().zip(&mix).enumerate() {
+            assert!((a - b).abs() < 1e-5, "{:?} sample {}: {} vs {}", group, i, a, b);
+        }
+    }
+}
+
+#[test]
+fn render_stops_exactly_at_the_length() {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.set_playing(true);
+    assert_eq!(AudioExporter::render(&mut mixer, 300).len(), 600);
+    assert_eq!(mixer.current_time, 300);
+}
+
+#[test]
+fn ranges_start_mid_clip_after_a_pre_roll() {
+    let mut project = Project::new("Range");
+    project.tracks.push(audio_track(1, 1000, 4410, Vec::new()));
+    let options = RenderOptions {
+        range: Some(TimeRange { start: 2000, end: 4000 }),
+        pre_roll: 1000,
+        tail: TailMode::None,
+        ..Default::default()
+    };
+    let out = AudioExporter::render_range(&project, tone_assets(4410), SAMPLE_RATE, &options);
+    assert_eq!(out.len(), 2 * 2000);
+    assert_plays_tone(&out, 0, &sine(4410, 441.0)[1000..3000]);
+}
+
+#[test]
+fn measured_tails_end_when_the_echoes_do() {
+    let delay = Effect::Delay { time_ms: 100.0, feedback: 0.5, mix: 0.5 };
+    let mut project = Project::new("Tail");
+    project.tracks.push(audio_track(1, 0, 4410, vec![delay]));
+    let options = RenderOptions { tail: TailMode::Auto, ..Default::default() };
+    let out = AudioExporter::render_range(&project, tone_assets(4410), SAMPLE_RATE, &options);
+
+    // Longer than the clip and its first echo, ends on the last audible one
+    let frames = out.len() / 2;
+    assert!(frames > 2 * 4410, "{}", frames);
+    assert!(frames < 4410 + 4 * SAMPLE_RATE as usize, "{}", frames);
+    assert!(out[out.len() - 2..].iter().any(|s| s.abs() > 0.0001));
+}
+
+#[test]
+fn selected_tracks_render_alone() {
+    let mut project = Project::new("Selection");
+    project.tracks.push(audio_track(1, 0, 4410, Vec::new()));
+    project.tracks.push(audio_track(2, 4410, 4410, Vec::new()));
+    let options = RenderOptions { tracks: Some(vec![2]), ..Default::default() };
+    let out = AudioExporter::render_range(&project, tone_assets(4410), SAMPLE_RATE, &options);
+    assert_eq!(out.len(), 2 * 8820);
+    assert!(out[..2 * 4410].iter().all(|s| *s == 0.0));
+    assert_plays_tone(&out, 4410, &sine(4410, 441.0));
+}
+
+#[test]
+fn progress_reaches_the_end_and_cancel_stops_the_render() {
+    let mut project = Project::new("Progress");
+    project.tracks.push(audio_track(1, 0, 4410, Vec::new()));
+
+    let progress = Arc::new(RenderProgress::default());
+    let options = RenderOptions { progress: Some(progress.clone()), ..Default::default() };
+    AudioExporter::render_range(&project, tone_assets(4410), SAMPLE_RATE, &options);
+    assert_eq!(progress.fraction(), 1.0);
+
+    let progress = Arc::new(RenderProgress::default());
+    progress.cancel();
+    let options = RenderOptions { progress: Some(progress), ..Default::default() };
+    assert!(AudioExporter::render_range(&project, tone_assets(4410), SAMPLE_RATE, &options).is_empty());
+}
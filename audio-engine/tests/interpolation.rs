@@ -0,0 +1,96 @@
+use audio_engine::dsp::interpolation::Interpolation;
+
+const MODES: [Interpolation; 4] = [Interpolation::Linear, Interpolation::Hermite, Interpolation::Sinc8, Interpolation::Sinc32];
+
+// Deterministic noise, the worst case for reading back whole samples
+fn noise(len: usize) -> Vec<f32> {
+    let mut state = 0x1234_5678u32;
+    (0..len).map(|_| {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (state >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+    }).collect()
+}
+
+fn sine(len: usize, cycles_per_sample: f64) -> Vec<f32> {
+    (0..len).map(|i| (std::f64::consts::TAU * cycles_per_sample * i as f64).sin() as f32).collect()
+}
+
+// RMS of a read at `rate` through the middle of `buf`
+fn read_rms(mode: Interpolation, buf: &[f32], rate: f64) -> f32 {
+    let reads: Vec<f32> = (0..1000).map(|i| mode.read(buf, 1000.0 + i as f64 * rate, rate)).collect();
+    (reads.iter().map(|s| s * s).sum::<f32>() / reads.len() as f32).sqrt()
+}
+
+#[test]
+fn whole_positions_read_the_sample() {
+    let buf = noise(256);
+    for mode in MODES {
+        for i in 0..buf.len() {
+            let value = mode.read(&buf, i as f64, 1.0);
+            assert!((value - buf[i]).abs() < 1e-5, "{:?} at {}: {} vs {}", mode, i, value, buf[i]);
+        }
+    }
+}
+
+#[test]
+fn dc_passes_at_unity_between_samples() {
+    let buf = vec![0.5; 256];
+    for mode in MODES {
+        for rate in [0.5, 1.0, 1.7, 3.0] {
+            for frac in [0.1, 0.25, 0.5, 0.73, 0.99] {
+                let value = mode.read(&buf, 128.0 + frac, rate);
+                assert!((value - 0.5).abs() < 1e-5, "{:?} x{} at +{}: {}", mode, rate, frac, value);
+            }
+        }
+    }
+}
+
+#[test]
+fn outside_the_buffer_is_silent() {
+    let buf = vec![1.0; 64];
+    for mode in MODES {
+        for pos in [-100.0, -40.0, 200.0, 1e30, -1e30, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(mode.read(&buf, pos, 1.0), 0.0, "{:?} at {}", mode, pos);
+            assert_eq!(mode.read(&buf, pos, 2.0), 0.0, "{:?} x2 at {}", mode, pos);
+        }
+        assert_eq!(mode.read(&[], 0.5, 1.0), 0.0, "{:?}", mode);
+    }
+}
+
+#[test]
+fn sinc_filters_what_would_alias_when_reading_fast() {
+    // 0.35 cycles a sample folds back at 2x, 0.05 is well inside the new band
+    let high = sine(4096, 0.35);
+    let low = sine(4096, 0.05);
+    for mode in [Interpolation::Sinc8, Interpolation::Sinc32] {
+        for rate in [2.0, 2.5] {
+            let aliased = read_rms(mode, &high, rate);
+            assert!(aliased < 0.05, "{:?} x{}: {}", mode, rate, aliased);
+            let kept = read_rms(mode, &low, rate);
+            assert!(kept > 0.65, "{:?} x{}: {}", mode, rate, kept);
+        }
+    }
+    // Without the filter it comes straight through
+    assert!(read_rms(Interpolation::Hermite, &high, 2.0) > 0.5);
+}
+
+#[test]
+fn sinc_reads_follow_the_rate_smoothly() {
+    // No step in the kernel as the rate passes 1x
+    let buf = noise(4096);
+    for mode in [Interpolation::Sinc8, Interpolation::Sinc32] {
+        let at = |rate: f64| mode.read(&buf, 2000.3, rate);
+        assert!((at(1.0) - at(1.0 + 1e-6)).abs() < 1e-4, "{:?}", mode);
+        assert!((at(1.0) - at(0.5)).abs() < 1e-6, "{:?}", mode);
+    }
+}
+
+#[test]
+fn prepared_tables_are_ready_before_the_first_read() {
+    for mode in MODES {
+        mode.prepare();
+        assert!(mode.is_prepared(), "{:?}", mode);
+    }
+    // Cheap modes have nothing to build
+    assert!(Interpolation::Linear.is_prepared() && Interpolation::Hermite.is_prepared());
+}
@@ -0,0 +1,64 @@
+use audio_engine::analysis::loudness::measure;
+use audio_engine::export::{AudioExporter, Normalize};
+use std::f32::consts::PI;
+
+// Interleaved stereo sine, both channels in phase
+fn sine(freq: f32, amplitude: f32, seconds: f32, sample_rate: f32) -> Vec<f32> {
+    (0..(seconds * sample_rate) as usize)
+        .flat_map(|i| {
+            let s = amplitude * (2.0 * PI * freq * i as f32 / sample_rate).sin();
+            [s, s]
+        })
+        .collect()
+}
+
+#[test]
+fn reference_sine_reads_minus_23_lufs() {
+    // EBU Tech 3341: 1 kHz stereo at -23 dBFS per channel reads -23 LUFS
+    let amplitude = 10f32.powf(-23.0 / 20.0);
+    for sample_rate in [44100.0, 48000.0] {
+        let stats = measure(&sine(1000.0, amplitude, 5.0, sample_rate), 2, sample_rate);
+        assert!((stats.integrated + 23.0).abs() < 0.1, "{} at {}", stats.integrated, sample_rate);
+    }
+}
+
+#[test]
+fn loudness_range_of_a_level_step() {
+    // EBU Tech 3342 case 1 (shortened): -20 then -30 dBFS is 10 LU
+    let sample_rate = 48000.0;
+    let mut samples = sine(1000.0, 10f32.powf(-20.0 / 20.0), 10.0, sample_rate);
+    samples.extend(sine(1000.0, 10f32.powf(-30.0 / 20.0), 10.0, sample_rate));
+    let stats = measure(&samples, 2, sample_rate);
+    assert!((stats.range - 10.0).abs() < 1.0, "{}", stats.range);
+}
+
+#[test]
+fn true_peak_sees_between_samples() {
+    // fs/4 sampled 45 degrees off its peaks: samples at 0.707, true peak at 1.0
+    let samples: Vec<f32> = (0..4800)
+        .flat_map(|i| {
+            let s = (PI / 2.0 * i as f32 + PI / 4.0).sin();
+            [s, s]
+        })
+        .collect();
+    let stats = measure(&samples, 2, 48000.0);
+    assert!(stats.true_peak > -0.5, "{}", stats.true_peak);
+}
+
+#[test]
+fn normalization_stops_at_the_ceiling() {
+    let sample_rate = 48000.0;
+    let quiet = sine(1000.0, 0.05, 5.0, sample_rate);
+
+    let mut to_target = quiet.clone();
+    AudioExporter::normalize(&mut to_target, sample_rate, &Normalize { target_lufs: -20.0, true_peak_ceiling: -1.0 });
+    assert!((measure(&to_target, 2, sample_rate).integrated + 20.0).abs() < 0.1);
+
+    // This sine's true peak in dBTP matches its loudness in LUFS, so -6 LUFS
+    // would pass a -9 dBTP ceiling and the ceiling wins
+    let mut to_ceiling = quiet;
+    AudioExporter::normalize(&mut to_ceiling, sample_rate, &Normalize { target_lufs: -6.0, true_peak_ceiling: -9.0 });
+    let stats = measure(&to_ceiling, 2, sample_rate);
+    assert!((stats.true_peak + 9.0).abs() < 0.1, "{}", stats.true_peak);
+    assert!(stats.integrated < -8.5, "{}", stats.integrated);
+}
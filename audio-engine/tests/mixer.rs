@@ -0,0 +1,177 @@
+use audio_engine::midi::MidiEvent;
+use audio_engine::mixer::{Bus, Mixer};
+use audio_engine::nodes::DrumRackNode;
+use shared::{AutomationLane, ClipData, CurveType, DrumPad, Effect, MixerCommand, Project, SampleTrigger, TempoChange, TrackData, TriggerQuantize};
+
+// Equal-power pan law at centre
+const CENTER_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+// A drum rack track with one constant pad on bus 1, plus a plain track
+fn rack_mixer() -> (Mixer, u32) {
+    let mut mixer = Mixer::new(44100.0);
+    mixer.add_sample("dc".to_string(), vec![0.5; 44100], vec![0.5; 44100], 44100.0);
+    mixer.buses.push(Bus::new(1, 44100.0));
+    let id = mixer.add_track();
+    mixer.add_track();
+
+    let mut rack = DrumRackNode::new(44100.0);
+    rack.set_pads(vec![DrumPad { asset_id: "dc".to_string(), output_bus: Some(1), ..DrumPad::default() }]);
+    rack.event_queue.push(MidiEvent::note_on(0, DrumPad::default().note, 127, 0));
+    mixer.tracks[id as usize].drum_rack = Some(rack);
+    mixer.set_playing(true);
+    (mixer, id)
+}
+
+// Left output after the fader and envelope have settled
+fn settled(mixer: &mut Mixer) -> f32 {
+    let (mut left, mut right) = (vec![0.0; 512], vec![0.0; 512]);
+    for _ in 0..20 {
+        mixer.process(&mut [&mut left, &mut right]);
+    }
+    left[511]
+}
+
+#[test]
+fn pads_on_buses_follow_the_track_fader() {
+    let (mut mixer, id) = rack_mixer();
+    // Through the track pan, then the bus pan
+    let unity = settled(&mut mixer);
+    assert!((unity - 0.5 * CENTER_GAIN * CENTER_GAIN).abs() < 1e-3, "{}", unity);
+
+    let (mut mixer, _) = rack_mixer();
+    mixer.set_track_gain(id, -6.0);
+    let quieter = settled(&mut mixer);
+    assert!((quieter / unity - shared::db_to_linear(-6.0)).abs() < 1e-3, "{}", quieter);
+
+    let (mut mixer, _) = rack_mixer();
+    mixer.set_track_pan(id, -1.0);
+    let (mut left, mut right) = (vec![0.0; 512], vec![0.0; 512]);
+    for _ in 0..20 {
+        mixer.process(&mut [&mut left, &mut right]);
+    }
+    assert!(right[511].abs() < 1e-4, "{}", right[511]);
+}
+
+#[test]
+fn pads_on_buses_follow_solo_and_mute() {
+    let (mut mixer, id) = rack_mixer();
+    mixer.set_track_solo(id + 1, true);
+    assert_eq!(settled(&mut mixer), 0.0);
+    mixer.set_track_solo(id, true);
+    assert!(settled(&mut mixer) > 0.1);
+
+    let (mut mixer, id) = rack_mixer();
+    mixer.set_track_mute(id, true);
+    assert_eq!(settled(&mut mixer), 0.0);
+}
+
+#[test]
+fn one_shots_play_long_asset_ids() {
+    let mut mixer = Mixer::new(44100.0);
+    let id = "f".repeat(64) + "0123"; // Longer than a content hash
+    mixer.add_sample(id.clone(), vec![0.5; 4410], vec![0.5; 4410], 44100.0);
+    mixer.master_bypass = true;
+
+    mixer.trigger_sample("not loaded", SampleTrigger::default());
+    mixer.trigger_sample(&id, SampleTrigger::default());
+    assert_eq!(mixer.soundboard.active_voices(), 1);
+    let (mut left, mut right) = (vec![0.0; 64], vec![0.0; 64]);
+    mixer.process(&mut [&mut left, &mut right]);
+    assert_eq!(left[0], 0.5);
+
+    mixer.choke_sample(&id);
+    for _ in 0..10 {
+        mixer.process(&mut [&mut left, &mut right]);
+    }
+    assert_eq!(mixer.soundboard.active_voices(), 0);
+}
+
+#[test]
+fn effect_automation_follows_the_curve_within_a_block() {
+    // Quiet enough that +24 dB stays under the master clip
+    let mut mixer = Mixer::new(44100.0);
+    mixer.add_sample("dc".to_string(), vec![0.05; 44100], vec![0.05; 44100], 44100.0);
+    let mut project = Project::new("Ramp");
+    // Makeup gain 0 to 24 dB over 0.2 s, longer than the one block played
+    let mut lane = AutomationLane::new("fx/0/makeup_gain".to_string());
+    lane.add_point(0.0, 0.0, CurveType::Linear);
+    lane.add_point(0.2, 1.0, CurveType::Linear);
+    project.tracks.push(TrackData {
+        id: 0,
+        name: "Ramp".into(),
+        gain_db: 0.0,
+        pan: 0.0,
+        muted: false,
+        soloed: false,
+        clips: vec![ClipData::Audio {
+            id: 1,
+            name: "dc".into(),
+            start: 0,
+            duration: 44100,
+            offset: 0,
+            asset_id: "dc".into(),
+            muted: false,
+            gain_db: 0.0,
+            stretch: 1.0,
+            transpose: 0.0,
+            original_bpm: None,
+            warp_markers: Vec::new(),
+        }],
+        // Ratio 1 never compresses, only the makeup gain applies
+        effects: vec![Effect::Compressor { threshold: 0.0, ratio: 1.0, attack: 10.0, release: 100.0, makeup_gain: 0.0 }],
+        automation: vec![lane],
+        instrument: None,
+        output_bus: None,
+        sends: Vec::new(),
+    });
+    mixer.handle_command(MixerCommand::LoadProject { project });
+    mixer.set_playing(true);
+
+    let (mut left, mut right) = (vec![0.0; 8192], vec![0.0; 8192]);
+    mixer.process(&mut [&mut left, &mut right]);
+    // Within the smoothing lag of the ramp, not at its end value already
+    for i in [2048, 4096, 6144] {
+        let db = 20.0 * (left[i] / (0.05 * CENTER_GAIN)).log10();
+        let expected = 24.0 * i as f32 / 8820.0;
+        assert!(db < expected && db > expected - 3.0, "sample {}: {} dB, curve {} dB", i, db, expected);
+    }
+}
+
+// Sample the one-shot first sounds at, or None within `blocks` blocks
+fn first_sound(mixer: &mut Mixer, blocks: usize) -> Option<u64> {
+    let (mut left, mut right) = (vec![0.0; 512], vec![0.0; 512]);
+    for _ in 0..blocks {
+        let block_start = mixer.current_time;
+        mixer.process(&mut [&mut left, &mut right]);
+        if let Some(i) = left.iter().position(|s| *s != 0.0) {
+            return Some(block_start + i as u64);
+        }
+    }
+    None
+}
+
+#[test]
+fn quantized_triggers_follow_the_tempo_map() {
+    // 120 BPM for 4 beats (2 s), then 60 BPM
+    let mut mixer = Mixer::new(44100.0);
+    mixer.add_sample("dc".to_string(), vec![0.5; 4410], vec![0.5; 4410], 44100.0);
+    mixer.set_tempo_map(vec![TempoChange { beat: 4.0, bpm: 60.0 }]);
+    mixer.set_playing(true);
+
+    // Beat 5.1, the next beat is 6 at 4 s
+    mixer.seek(44100 * 3 + 4410);
+    mixer.trigger_sample("dc", SampleTrigger { quantize: TriggerQuantize::Beat, ..SampleTrigger::default() });
+    assert_eq!(first_sound(&mut mixer, 400), Some(44100 * 4));
+
+    // Beat 4.5, the next bar starts on beat 8 at 6 s
+    mixer.stop_all_samples();
+    mixer.seek(44100 * 2 + 22050);
+    mixer.trigger_sample("dc", SampleTrigger { quantize: TriggerQuantize::Bar, ..SampleTrigger::default() });
+    assert_eq!(first_sound(&mut mixer, 800), Some(44100 * 6));
+
+    // On a beat already, no wait
+    mixer.stop_all_samples();
+    mixer.seek(44100 * 5);
+    mixer.trigger_sample("dc", SampleTrigger { quantize: TriggerQuantize::Beat, ..SampleTrigger::default() });
+    assert_eq!(first_sound(&mut mixer, 10), Some(44100 * 5));
+}
@@ -0,0 +1,120 @@
+use audio_engine::dsp::resample::{resample, Resampler};
+use audio_engine::mixer::{Mixer, PreparedSample};
+use shared::{ClipData, Project, TrackData};
+use std::f64::consts::PI;
+
+fn sine(freq: f64, seconds: f64, sample_rate: u32) -> Vec<f32> {
+    (0..(seconds * sample_rate as f64) as usize)
+        .map(|i| (2.0 * PI * freq * i as f64 / sample_rate as f64).sin() as f32)
+        .collect()
+}
+
+#[test]
+fn upsampled_sine_stays_on_its_curve() {
+    let output = resample(&sine(1000.0, 0.2, 44100), 44100, 48000);
+    assert_eq!(output.len(), 9600);
+    // Away from the edges, where the kernel runs off the buffer
+    let expected = sine(1000.0, 0.2, 48000);
+    let worst = output[200..9400].iter().zip(&expected[200..9400]).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+    assert!(worst < 1e-3, "{}", worst);
+}
+
+#[test]
+fn downsampling_removes_content_above_the_new_nyquist() {
+    // 30 kHz can't exist at 44.1 kHz, it would fold back to 14.1 kHz
+    let output = resample(&sine(30000.0, 0.1, 96000), 96000, 44100);
+    let peak = output[500..output.len() - 500].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    assert!(peak < 1e-3, "{}", peak);
+}
+
+#[test]
+fn awkward_ratios_keep_time() {
+    let resampler = Resampler::new(44100, 44101);
+    let output = resampler.process(&sine(440.0, 0.1, 44100));
+    assert_eq!(output.len(), resampler.output_len(4410));
+    let expected = sine(440.0, 0.1, 44101);
+    let worst = output[200..4200].iter().zip(&expected[200..4200]).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+    assert!(worst < 1e-3, "{}", worst);
+}
+
+#[test]
+fn assets_are_converted_to_the_engine_rate() {
+    let mut mixer = Mixer::new(44100.0);
+    let one_second = vec![0.0; 48000];
+    mixer.add_sample("a".to_string(), one_second.clone(), one_second, 48000.0);
+    assert_eq!(mixer.samples["a"].0.len(), 44100);
+
+    // And follow it when the context rate changes
+    mixer.set_sample_rate(48000.0);
+    assert_eq!(mixer.samples["a"].0.len(), 48000);
+}
+
+#[test]
+fn rate_changes_convert_the_original_again() {
+    let tone = sine(1000.0, 0.2, 22050);
+    let mut mixer = Mixer::new(44100.0);
+    mixer.add_sample("a".to_string(), tone.clone(), tone.clone(), 22050.0);
+    mixer.set_sample_rate(48000.0);
+    // Straight from the source, not from the 44.1 kHz copy
+    assert!(mixer.samples["a"].0 == resample(&tone, 22050, 48000));
+
+    // Back at the source rate the untouched original returns
+    mixer.set_sample_rate(22050.0);
+    assert!(mixer.samples["a"].0 == tone);
+    mixer.set_sample_rate(44100.0);
+    mixer.set_sample_rate(22050.0);
+    assert!(mixer.samples["a"].0 == tone);
+}
+
+#[test]
+fn prepared_samples_go_in_without_converting() {
+    let tone = sine(1000.0, 0.2, 48000);
+    let prepared = PreparedSample::new(tone.clone(), tone.clone(), 48000.0, 44100.0);
+    let mut mixer = Mixer::new(44100.0);
+    mixer.insert_sample("a".to_string(), prepared);
+    assert!(mixer.samples["a"].0 == resample(&tone, 48000, 44100));
+
+    // Prepared for a rate the engine has since left
+    let prepared = PreparedSample::new(tone.clone(), tone.clone(), 48000.0, 44100.0);
+    mixer.set_sample_rate(96000.0);
+    mixer.insert_sample("b".to_string(), prepared);
+    assert!(mixer.samples["b"].0 == resample(&tone, 48000, 96000));
+}
+
+#[test]
+fn project_positions_follow_the_engine_rate() {
+    let mut project = Project::new("Rates");
+    project.sample_rate = 48000;
+    project.tracks.push(TrackData {
+        id: 0,
+        name: "Track".into(),
+        gain_db: 0.0,
+        pan: 0.0,
+        muted: false,
+        soloed: false,
+        clips: vec![ClipData::Audio {
+            id: 1,
+            name: "tone".into(),
+            start: 48000,
+            duration: 96000,
+            offset: 24000,
+            asset_id: "a".into(),
+            muted: false,
+            gain_db: 0.0,
+            stretch: 1.0,
+            transpose: 0.0,
+            original_bpm: None,
+            warp_markers: Vec::new(),
+        }],
+        effects: Vec::new(),
+        automation: Vec::new(),
+        instrument: None,
+        output_bus: None,
+        sends: Vec::new(),
+    });
+
+    let mut mixer = Mixer::new(44100.0);
+    mixer.load_project(&project, 44100.0);
+    let clip = &mixer.tracks[0].clips[0];
+    assert_eq!((clip.start_time, clip.duration, clip.offset), (44100, 88200, 22050));
+}
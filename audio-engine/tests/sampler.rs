@@ -0,0 +1,53 @@
+use audio_engine::mixer::AssetCache;
+use audio_engine::synth::sampler::Sampler;
+use shared::SampleZone;
+
+fn render(sampler: &mut Sampler, assets: &AssetCache, frames: usize) -> Vec<f32> {
+    let mut left = vec![0.0; frames];
+    let mut right = vec![0.0; frames];
+    sampler.render(assets, &mut left, &mut right);
+    left
+}
+
+#[test]
+fn stolen_voices_fade_instead_of_clicking() {
+    let mut assets = AssetCache::new();
+    assets.insert("dc".to_string(), (vec![1.0; 44100], vec![1.0; 44100]));
+    let mut sampler = Sampler::new(44100.0);
+    sampler.set_zones(vec![SampleZone { asset_id: "dc".to_string(), ..SampleZone::default() }]);
+
+    // Every voice held at full level
+    for note in 40..56 {
+        sampler.note_on(note, 127);
+    }
+    let settled = render(&mut sampler, &assets, 441);
+    assert!((settled[440] - 16.0).abs() < 1e-3);
+
+    sampler.note_on(70, 127);
+    let mut out = vec![settled[440]];
+    out.extend(render(&mut sampler, &assets, 2205));
+    let jump = out.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max);
+    assert!(jump < 0.05, "{}", jump);
+
+    // The old note is gone and the new one has taken its place
+    assert!((out[2205] - 16.0).abs() < 1e-3, "{}", out[2205]);
+    let dip = out.iter().copied().fold(f32::MAX, f32::min);
+    assert!(dip < 15.5, "{}", dip);
+}
+
+#[test]
+fn notes_released_while_waiting_never_start() {
+    let mut assets = AssetCache::new();
+    assets.insert("dc".to_string(), (vec![1.0; 44100], vec![1.0; 44100]));
+    let mut sampler = Sampler::new(44100.0);
+    sampler.set_zones(vec![SampleZone { asset_id: "dc".to_string(), ..SampleZone::default() }]);
+    for note in 40..56 {
+        sampler.note_on(note, 127);
+    }
+    render(&mut sampler, &assets, 441);
+
+    sampler.note_on(70, 127);
+    sampler.note_off(70);
+    let out = render(&mut sampler, &assets, 2205);
+    assert!((out[2204] - 15.0).abs() < 1e-3, "{}", out[2204]);
+}
@@ -0,0 +1,93 @@
+use audio_engine::dsp::smooth::SmoothedParam;
+use audio_engine::graph::AudioNode;
+use audio_engine::nodes::filter::{FilterNode, FilterType};
+
+#[test]
+fn targets_before_the_first_sample_apply_at_once() {
+    let mut param = SmoothedParam::linear(0.0, 10.0, 1000.0);
+    param.set_target(0.8);
+    assert!(!param.is_smoothing());
+    assert_eq!(param.next_value(), 0.8);
+}
+
+#[test]
+fn linear_ramps_land_on_the_target() {
+    // 10 ms at 1 kHz = 10 samples
+    let mut param = SmoothedParam::linear(0.0, 10.0, 1000.0);
+    param.next_value();
+    param.set_target(1.0);
+    let ramp: Vec<f32> = (0..10).map(|_| param.next_value()).collect();
+    for (i, value) in ramp.iter().enumerate() {
+        assert!((value - (i + 1) as f32 * 0.1).abs() < 1e-6, "{:?}", ramp);
+    }
+    assert!(!param.is_smoothing());
+    assert_eq!(param.next_value(), 1.0);
+
+    // Retargeting mid-ramp starts a full ramp from where it is
+    param.set_target(0.0);
+    param.skip(5);
+    param.set_target(1.0);
+    assert!((param.skip(9) - 0.95).abs() < 1e-5);
+    assert_eq!(param.skip(1), 1.0);
+}
+
+#[test]
+fn exponential_glides_60_db_per_ramp_then_settle() {
+    let mut param = SmoothedParam::exponential(0.0, 20.0, 1000.0);
+    param.next_value();
+    param.set_target(1.0);
+    let mut value = 0.0;
+    for _ in 0..20 {
+        value = param.next_value();
+    }
+    assert!((1.0 - value - 0.001).abs() < 1e-4, "{}", value);
+
+    for _ in 0..100 {
+        param.next_value();
+    }
+    assert!(!param.is_smoothing());
+    assert_eq!(param.current(), 1.0);
+}
+
+#[test]
+fn skipping_matches_stepping() {
+    for mut param in [SmoothedParam::linear(2.0, 5.0, 48000.0), SmoothedParam::exponential(2.0, 5.0, 48000.0)] {
+        let mut stepped = param.clone();
+        param.next_value();
+        stepped.next_value();
+        param.set_target(-3.0);
+        stepped.set_target(-3.0);
+        for block in [1, 16, 37, 100, 500] {
+            let skipped = param.skip(block);
+            let mut value = 0.0;
+            for _ in 0..block {
+                value = stepped.next_value();
+            }
+            assert!((skipped - value).abs() < 1e-4, "{} {}", skipped, value);
+        }
+    }
+}
+
+#[test]
+fn settled_filters_match_a_fresh_one() {
+    // A swept filter that has settled sounds like one that started there
+    let input: Vec<f32> = (0..4096).map(|i| ((i * 7919) % 200) as f32 / 100.0 - 1.0).collect();
+    let mut swept = FilterNode::new(48000.0);
+    let mut fresh = FilterNode::new(48000.0);
+    fresh.set_params(800.0, 0.0, FilterType::LowPass);
+    swept.set_params(800.0, 0.0, FilterType::LowPass);
+    let (mut l, mut r) = (vec![0.0; 4096], vec![0.0; 4096]);
+    fresh.process(&[&input, &input], &mut [&mut l, &mut r]);
+
+    swept.set_params(5000.0, 0.0, FilterType::LowPass);
+    let (mut sl, mut sr) = (vec![0.0; 4096], vec![0.0; 4096]);
+    swept.process(&[&input, &input], &mut [&mut sl, &mut sr]);
+    swept.set_params(800.0, 0.0, FilterType::LowPass);
+    for _ in 0..4 {
+        swept.process(&[&input, &input], &mut [&mut sl, &mut sr]);
+        fresh.process(&[&input, &input], &mut [&mut l, &mut r]);
+    }
+    for (a, b) in sl.iter().zip(&l) {
+        assert!((a - b).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,110 @@
+use audio_engine::dsp::interpolation::Interpolation;
+use audio_engine::dsp::stretch::TimeStretcher;
+use audio_engine::mixer::Mixer;
+use shared::{ClipData, MixerCommand, Project, TrackData};
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+fn sine(frames: usize, freq: f32) -> Vec<f32> {
+    (0..frames)
+        .map(|i| (std::f32::consts::TAU * freq * i as f32 / SAMPLE_RATE).sin() * 0.5)
+        .collect()
+}
+
+fn stretch(speed: f32, pitch: f32, frames: usize) -> (TimeStretcher, Vec<f32>) {
+    let source = sine(3 * SAMPLE_RATE as usize, 441.0);
+    let mut stretcher = TimeStretcher::new(SAMPLE_RATE);
+    let out = (0..frames)
+        .map(|_| stretcher.next_frame(&source, &source, speed, pitch, Interpolation::Linear).0)
+        .collect();
+    (stretcher, out)
+}
+
+// Rising zero crossings per second, skipping the lead-in
+fn frequency(out: &[f32]) -> f32 {
+    let body = &out[4410..];
+    let rising = body.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+    rising as f32 * SAMPLE_RATE / body.len() as f32
+}
+
+#[test]
+fn tempo_changes_keep_the_pitch() {
+    for speed in [0.5, 1.0, 2.0] {
+        let (stretcher, out) = stretch(speed, 1.0, 44100);
+        // One second of output reads `speed` seconds of source
+        assert!((stretcher.position() - 44100.0 * speed as f64).abs() < 1e-6, "{}", speed);
+        let freq = frequency(&out);
+        assert!((freq - 441.0).abs() < 441.0 * 0.02, "{}: {} Hz", speed, freq);
+    }
+}
+
+#[test]
+fn pitch_changes_keep_the_tempo() {
+    for pitch in [0.5, 1.0, 2.0] {
+        let (stretcher, out) = stretch(1.0, pitch, 44100);
+        assert!((stretcher.position() - 44100.0).abs() < 1e-6, "{}", pitch);
+        let freq = frequency(&out);
+        assert!((freq - 441.0 * pitch).abs() < 441.0 * pitch * 0.02, "{}: {} Hz", pitch, freq);
+    }
+}
+
+fn tone_mixer(key_lock: bool) -> Mixer {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    let tone = sine(SAMPLE_RATE as usize, 441.0);
+    mixer.add_sample("tone.wav".to_string(), tone.clone(), tone, SAMPLE_RATE);
+    let mut project = Project::new("Deck");
+    project.tracks.push(TrackData {
+        id: 0,
+        name: "Deck".into(),
+        gain_db: 0.0,
+        pan: 0.0,
+        muted: false,
+        soloed: false,
+        clips: vec![ClipData::Audio {
+            id: 1,
+            name: "tone".into(),
+            start: 0,
+            duration: SAMPLE_RATE as u64,
+            offset: 0,
+            asset_id: "tone.wav".into(),
+            muted: false,
+            gain_db: 0.0,
+            stretch: 1.0,
+            transpose: 0.0,
+            original_bpm: None,
+            warp_markers: Vec::new(),
+        }],
+        effects: Vec::new(),
+        automation: Vec::new(),
+        instrument: None,
+        output_bus: None,
+        sends: Vec::new(),
+    });
+    mixer.handle_command(MixerCommand::LoadProject { project });
+    mixer.set_track_key_lock(0, key_lock);
+    mixer.set_playing(true);
+    mixer
+}
+
+fn peak(mixer: &mut Mixer, blocks: usize) -> f32 {
+    let (mut left, mut right) = (vec![0.0; 512], vec![0.0; 512]);
+    let mut peak = 0.0f32;
+    for _ in 0..blocks {
+        mixer.process(&mut [&mut left, &mut right]);
+        peak = left.iter().fold(peak, |m, s| m.max(s.abs()));
+    }
+    peak
+}
+
+#[test]
+fn tape_stop_is_silent_with_and_without_key_lock() {
+    for key_lock in [false, true] {
+        let mut mixer = tone_mixer(key_lock);
+        assert!(peak(&mut mixer, 10) > 0.1, "{}", key_lock);
+
+        mixer.set_track_fx_tape_stop(0, true);
+        // Let the fader and filter ring out before listening
+        peak(&mut mixer, 10);
+        assert!(peak(&mut mixer, 10) < 1e-3, "{}", key_lock);
+    }
+}
@@ -0,0 +1,100 @@
+use audio_engine::graph::AudioNode;
+use audio_engine::midi::MidiEvent;
+use audio_engine::mixer::Mixer;
+use audio_engine::nodes::{GranularNode, SynthNode};
+use audio_engine::synth::granular::GranularSynth;
+use audio_engine::synth::lfo::{LfoMode, LfoParams, LfoRate, SyncDivision, SyncModifier};
+use shared::{GranularParams, TempoChange, TempoMap};
+
+fn render(synth: &mut SynthNode, frames: usize, block: usize) -> Vec<f32> {
+    let mut out = Vec::with_capacity(frames);
+    let mut left = vec![0.0; block];
+    let mut right = vec![0.0; block];
+    while out.len() < frames {
+        synth.process(&[], &mut [&mut left, &mut right]);
+        out.extend_from_slice(&left);
+    }
+    out.truncate(frames);
+    out
+}
+
+#[test]
+fn long_blocks_render_like_short_ones() {
+    let mut one = SynthNode::new(44100.0, 4);
+    let mut many = SynthNode::new(44100.0, 4);
+    for synth in [&mut one, &mut many] {
+        synth.set_global_lfo(0, LfoParams { rate: LfoRate::Hz(5.0), ..LfoParams::default() });
+        synth.handle_event(MidiEvent::note_on(0, 60, 100, 0));
+    }
+    // Longer than the global LFO chunk, which has to be split
+    let a = render(&mut one, 3000, 3000);
+    let b = render(&mut many, 3000, 100);
+    assert!(a.iter().any(|s| s.abs() > 0.01));
+    assert_eq!(a, b);
+}
+
+#[test]
+fn synced_lfos_follow_the_tempo_map() {
+    // 120 BPM, halved at beat 8 (4 seconds in)
+    let map = TempoMap::new(120.0, &[TempoChange { beat: 8.0, bpm: 60.0 }]);
+    let mut synth = SynthNode::new(44100.0, 1);
+    synth.set_global_lfo(0, LfoParams {
+        rate: LfoRate::Sync(SyncDivision::new(4, SyncModifier::Straight)),
+        mode: LfoMode::Free,
+        ..LfoParams::default()
+    });
+
+    synth.set_transport(&map, 1.25);
+    assert_eq!(synth.global_lfos[0].frequency(), 2.0);
+    assert!((synth.global_lfos[0].phase - 0.5).abs() < 1e-6); // Beat 2.5
+
+    synth.set_transport(&map, 5.25);
+    assert_eq!(synth.global_lfos[0].frequency(), 1.0);
+    assert!((synth.global_lfos[0].phase - 0.25).abs() < 1e-6); // Beat 9.25
+}
+
+#[test]
+fn grain_density_keeps_its_fraction() {
+    // 44100 / 400 = 110.25 samples apart, dropping the fraction gave 397 a second
+    let mut granular = GranularSynth::new(44100.0, 64);
+    granular.set_params(GranularParams { density: 400.0, size_ms: 1.0, ..GranularParams::default() });
+    granular.note_on(60, 100);
+    let source = vec![0.5; 44100];
+    let mut spawned = 0;
+    for _ in 0..44100 {
+        granular.process(&source, &source);
+        spawned += granular.grains.iter().filter(|g| g.active && g.age == 1).count();
+    }
+    assert!((399..=401).contains(&spawned), "{}", spawned);
+}
+
+#[test]
+fn granular_tracks_repeat_on_every_play() {
+    let mut mixer = Mixer::new(44100.0);
+    let noise: Vec<f32> = (0..44100).map(|i| ((i * 7919) % 200) as f32 / 100.0 - 1.0).collect();
+    mixer.add_sample("a".to_string(), noise.clone(), noise, 44100.0);
+    let id = mixer.add_track();
+    mixer.tracks[id as usize].granular = Some(GranularNode::new(44100.0, "a".to_string()));
+
+    let mut pass = || {
+        mixer.seek(0);
+        mixer.set_playing(true);
+        let granular = mixer.tracks[id as usize].granular.as_mut().unwrap();
+        granular.event_queue.push(MidiEvent::note_on(0, 60, 100, 0));
+        let mut out = Vec::new();
+        let (mut left, mut right) = (vec![0.0; 128], vec![0.0; 128]);
+        for block in 0..400 {
+            if block == 100 {
+                let granular = mixer.tracks[id as usize].granular.as_mut().unwrap();
+                granular.event_queue.push(MidiEvent::note_off(0, 60, 0));
+            }
+            mixer.process(&mut [&mut left, &mut right]);
+            out.extend_from_slice(&left);
+        }
+        mixer.set_playing(false);
+        out
+    };
+    let first = pass();
+    assert!(first.iter().any(|s| s.abs() > 0.01));
+    assert_eq!(first, pass());
+}
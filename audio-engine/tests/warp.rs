@@ -0,0 +1,47 @@
+use audio_engine::warp::WarpMap;
+use shared::WarpMarker;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+fn marker(beat: f64, sample: u64) -> WarpMarker {
+    WarpMarker { sample, beat }
+}
+
+#[test]
+fn markers_interpolate_linearly() {
+    let map = WarpMap::new(&[marker(4.0, 80_000), marker(0.0, 1_000), marker(2.0, 41_000)], 0, Some(120.0), 44100.0);
+    assert!(close(map.source_at(0.0), 1_000.0));
+    assert!(close(map.source_at(1.0), 21_000.0));
+    assert!(close(map.source_at(2.0), 41_000.0));
+    assert!(close(map.source_at(3.0), 60_500.0));
+    assert!(close(map.slope_at(1.0), 20_000.0));
+    assert!(close(map.slope_at(3.0), 19_500.0));
+}
+
+#[test]
+fn outside_the_markers_audio_runs_at_its_tempo() {
+    // 120 BPM at 44.1 kHz is 22050 samples a beat
+    let map = WarpMap::new(&[marker(1.0, 30_000), marker(2.0, 50_000)], 0, Some(120.0), 44100.0);
+    assert!(close(map.source_at(0.0), 30_000.0 - 22_050.0));
+    assert!(close(map.source_at(4.0), 50_000.0 + 2.0 * 22_050.0));
+    assert!(close(map.slope_at(-1.0), 22_050.0));
+    assert!(close(map.slope_at(10.0), 22_050.0));
+
+    // Without a tempo the markers' average carries on
+    let map = WarpMap::new(&[marker(0.0, 0), marker(2.0, 40_000)], 0, None, 44100.0);
+    assert!(close(map.slope_at(5.0), 20_000.0));
+}
+
+#[test]
+fn no_markers_pin_the_offset_to_the_clip_start() {
+    let map = WarpMap::new(&[], 5_000, None, 48000.0);
+    assert!(close(map.source_at(0.0), 5_000.0));
+    assert!(close(map.source_at(1.0), 5_000.0 + 24_000.0)); // Assumed 120 BPM
+
+    // Duplicate and broken beats are dropped
+    let map = WarpMap::new(&[marker(1.0, 100), marker(1.0, 900), marker(f64::NAN, 0)], 0, Some(60.0), 100.0);
+    assert!(close(map.source_at(1.0), 100.0));
+    assert!(close(map.source_at(2.0), 200.0));
+}
@@ -0,0 +1,51 @@
+use audio_engine::wav::decode_wav;
+
+fn chunk(id: &[u8; 4], size: u32, body: &[u8]) -> Vec<u8> {
+    let mut out = id.to_vec();
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+// 16-bit mono fmt chunk
+fn fmt() -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&44100u32.to_le_bytes());
+    body.extend_from_slice(&88200u32.to_le_bytes());
+    body.extend_from_slice(&2u16.to_le_bytes());
+    body.extend_from_slice(&16u16.to_le_bytes());
+    chunk(b"fmt ", 16, &body)
+}
+
+fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+    let body: Vec<u8> = chunks.concat();
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(&body);
+    out
+}
+
+#[test]
+fn truncated_data_keeps_the_whole_frames() {
+    // Claims 1000 bytes, has 5
+    let data = chunk(b"data", 1000, &[0x00, 0x40, 0x00, 0xC0, 0x7F]);
+    let wav = decode_wav(&riff(&[fmt(), data])).unwrap();
+    assert_eq!(wav.channels[0], vec![0.5, -0.5]);
+}
+
+#[test]
+fn hostile_chunk_sizes_end_the_walk() {
+    let data = chunk(b"data", 4, &[0x00, 0x40, 0x00, 0xC0]);
+    for size in [u32::MAX, u32::MAX - 1, u32::MAX - 8] {
+        let junk = chunk(b"junk", size, &[1, 2, 3]);
+        let wav = decode_wav(&riff(&[fmt(), data.clone(), junk])).unwrap();
+        assert_eq!(wav.num_frames(), 2);
+    }
+
+    // Before the data chunk, everything after it is out of reach
+    let junk = chunk(b"junk", u32::MAX, &[]);
+    assert!(decode_wav(&riff(&[fmt(), junk, data])).is_err());
+}
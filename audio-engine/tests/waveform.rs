@@ -0,0 +1,95 @@
+use audio_engine::analysis::waveform::{PeakPyramid, BASE_FRAMES};
+use audio_engine::analysis::WaveformAnalyzer;
+use std::f64::consts::PI;
+
+fn sine(freq: f64, frames: usize, sample_rate: f64) -> Vec<f32> {
+    (0..frames).map(|i| (2.0 * PI * freq * i as f64 / sample_rate).sin() as f32).collect()
+}
+
+#[test]
+fn positive_audio_keeps_a_positive_min() {
+    let left = vec![0.5f32; 1000];
+    let right = vec![-0.25f32; 1000];
+    let pyramid = PeakPyramid::new(&[&left, &right]);
+    for level in 0..pyramid.level_count() {
+        for peak in pyramid.level(level, 0) {
+            assert_eq!((peak.min, peak.max), (0.5, 0.5));
+        }
+        for peak in pyramid.level(level, 1) {
+            assert_eq!((peak.min, peak.max), (-0.25, -0.25));
+        }
+    }
+}
+
+#[test]
+fn levels_halve_up_to_a_single_peak() {
+    let audio = sine(100.0, 100_000, 44100.0);
+    let pyramid = PeakPyramid::new(&[&audio]);
+    assert_eq!(pyramid.level(0, 0).len(), 100_000usize.div_ceil(BASE_FRAMES));
+    assert_eq!(pyramid.level(pyramid.level_count() - 1, 0).len(), 1);
+
+    // The top peak covers everything, RMS weighted by frames
+    let top = pyramid.level(pyramid.level_count() - 1, 0)[0];
+    let rms = (audio.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / audio.len() as f64).sqrt();
+    assert!((top.rms as f64 - rms).abs() < 1e-4, "{} {}", top.rms, rms);
+    assert!(top.max > 0.999 && top.min < -0.999);
+}
+
+#[test]
+fn queries_match_the_samples_under_each_pixel() {
+    let mut audio = sine(50.0, 200_000, 44100.0);
+    audio.iter_mut().for_each(|s| *s *= 0.5);
+    audio[123_457] = 0.9; // One-sample click
+    let pyramid = PeakPyramid::new(&[&audio]);
+
+    let (start, end, pixels) = (10_000u64, 190_000u64, 300usize);
+    let peaks = pyramid.query(0, start, end, pixels);
+    assert_eq!(peaks.len(), pixels);
+    let span = (end - start) as f64 / pixels as f64;
+    let extremes = |from: usize, to: usize| {
+        let window = &audio[from..to.min(audio.len())];
+        (window.iter().copied().fold(f32::MAX, f32::min), window.iter().copied().fold(f32::MIN, f32::max))
+    };
+    for (p, peak) in peaks.iter().enumerate() {
+        let from = start as usize + (p as f64 * span) as usize;
+        let to = start as usize + ((p + 1) as f64 * span) as usize;
+        let (min, max) = extremes(from, to);
+        assert!(peak.max >= max && peak.min <= min, "pixel {}", p);
+        // Pixels read whole stored peaks, which never reach a pixel past their edges
+        let (wide_min, wide_max) = extremes(from - span as usize, to + span as usize);
+        assert!(peak.max <= wide_max && peak.min >= wide_min, "pixel {}", p);
+    }
+    assert!(peaks.iter().any(|peak| peak.max == 0.9), "click lost");
+
+    // Past the end is silent
+    let past = pyramid.query(0, 190_000, 210_000, 4);
+    assert_eq!(past[3].max, 0.0);
+}
+
+#[test]
+fn binary_round_trip_keeps_the_envelope() {
+    let left = sine(440.0, 50_000, 48000.0);
+    let right: Vec<f32> = left.iter().map(|s| s * 0.3).collect();
+    let pyramid = PeakPyramid::new(&[&left, &right]);
+    let bytes = pyramid.to_bytes();
+    let back = PeakPyramid::from_bytes(&bytes).unwrap();
+    assert_eq!((back.channels(), back.frames(), back.level_count()), (2, 50_000, pyramid.level_count()));
+
+    for level in 0..pyramid.level_count() {
+        for c in 0..2 {
+            for (a, b) in pyramid.level(level, c).iter().zip(back.level(level, c)) {
+                // Rounded outwards, never inside the real envelope
+                assert!(b.min <= a.min && a.min - b.min < 1e-4);
+                assert!(b.max >= a.max && b.max - a.max < 1e-4);
+                assert!((a.rms - b.rms).abs() < 1e-4);
+            }
+        }
+    }
+
+    assert!(PeakPyramid::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(PeakPyramid::from_bytes(b"not peaks at all").is_err());
+
+    let mut analyzer = WaveformAnalyzer::new();
+    analyzer.insert("a", back);
+    assert_eq!(analyzer.get("a").unwrap().frames(), 50_000);
+}
@@ -0,0 +1,92 @@
+use audio_engine::dsp::fft::fft;
+use audio_engine::encode::{wav, Pcm};
+use audio_engine::synth::wavetable::{Wavetable, DEFAULT_FRAME_SIZE, MAX_FRAMES};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+fn saw(size: usize) -> Vec<f32> {
+    (0..size).map(|i| 2.0 * i as f32 / size as f32 - 1.0).collect()
+}
+
+// Mono float WAV, with a Serum-style "clm " chunk after the data
+fn wavetable_file(samples: &[f32], cycle: Option<usize>) -> Vec<u8> {
+    let mut out = wav::write(&Pcm::Float(samples), 1, 48000);
+    if let Some(cycle) = cycle {
+        let text = format!("<!>{} 10000000 wavetable", cycle);
+        let padded = text.len() + (text.len() & 1);
+        out.extend_from_slice(b"clm ");
+        out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        out.extend_from_slice(text.as_bytes());
+        out.resize(out.len() + padded - text.len(), 0);
+        let riff = out.len() as u32 - 8;
+        out[4..8].copy_from_slice(&riff.to_le_bytes());
+    }
+    out
+}
+
+// Magnitude of each harmonic in one cycle
+fn harmonics(frame: &[f32]) -> Vec<f32> {
+    let (mut re, mut im) = (frame.to_vec(), vec![0.0; frame.len()]);
+    fft(&mut re, &mut im, false);
+    (0..frame.len() / 2).map(|h| (re[h] * re[h] + im[h] * im[h]).sqrt() / frame.len() as f32).collect()
+}
+
+#[test]
+fn chosen_level_has_nothing_above_nyquist() {
+    let table = Wavetable::from_frames(vec![saw(DEFAULT_FRAME_SIZE)], DEFAULT_FRAME_SIZE);
+    let fundamental = harmonics(&table.mips[0][0])[1];
+    for freq in [20.0, 55.0, 110.0, 440.0, 1000.0, 3520.0, 9000.0, 15000.0] {
+        let level = table.level_for_freq(freq, SAMPLE_RATE);
+        let spectrum = harmonics(&table.mips[level][0]);
+        for (h, magnitude) in spectrum.iter().enumerate().skip(1) {
+            if h as f32 * freq > SAMPLE_RATE * 0.5 {
+                assert!(*magnitude < fundamental * 1e-4, "{} Hz, level {}, harmonic {}: {}", freq, level, h, magnitude);
+            }
+        }
+        // And the band below is kept, not thrown away with it
+        let top = (SAMPLE_RATE * 0.5 / freq) as usize / 2;
+        assert!(spectrum[top.clamp(1, spectrum.len() - 1)] > fundamental * 1e-3, "{} Hz", freq);
+    }
+}
+
+#[test]
+fn levels_step_once_per_octave() {
+    // 2048 samples hold 1024 harmonics, enough for 23.4 Hz at 48 kHz
+    let table = Wavetable::from_frames(vec![saw(DEFAULT_FRAME_SIZE)], DEFAULT_FRAME_SIZE);
+    assert_eq!(table.num_levels(), 11);
+    assert_eq!(table.level_for_freq(20.0, SAMPLE_RATE), 0);
+    assert_eq!(table.level_for_freq(23.0, SAMPLE_RATE), 0);
+    for octave in 0..10 {
+        let freq = 24.0 * 2f32.powi(octave);
+        assert_eq!(table.level_for_freq(freq, SAMPLE_RATE), octave as usize + 1, "{} Hz", freq);
+        assert_eq!(table.level_for_freq(-freq, SAMPLE_RATE), octave as usize + 1, "-{} Hz", freq);
+    }
+    // Past the last level everything plays the sine
+    assert_eq!(table.level_for_freq(30000.0, SAMPLE_RATE), table.num_levels() - 1);
+}
+
+#[test]
+fn long_files_stop_at_the_frame_limit() {
+    let cycle = 256;
+    let pcm: Vec<f32> = (0..cycle * (MAX_FRAMES + 44)).map(|i| ((i % cycle) as f32 / cycle as f32) - 0.5).collect();
+    let table = Wavetable::from_wav(&wavetable_file(&pcm, Some(cycle)), DEFAULT_FRAME_SIZE).unwrap();
+    assert_eq!(table.num_frames(), MAX_FRAMES);
+    assert_eq!(table.table_size, cycle);
+
+    let frames = vec![saw(64); MAX_FRAMES * 2];
+    assert_eq!(Wavetable::from_frames(frames, 64).num_frames(), MAX_FRAMES);
+}
+
+#[test]
+fn tiny_cycles_are_refused() {
+    let pcm = saw(4096);
+    assert!(Wavetable::from_wav(&wavetable_file(&pcm, Some(1)), DEFAULT_FRAME_SIZE).is_err());
+    assert!(Wavetable::from_wav(&wavetable_file(&pcm, Some(16)), DEFAULT_FRAME_SIZE).is_err());
+    assert!(Wavetable::from_wav(&wavetable_file(&pcm, None), 0).is_err());
+
+    // A file shorter than a frame is one cycle, however short
+    let table = Wavetable::from_wav(&wavetable_file(&saw(32), None), DEFAULT_FRAME_SIZE).unwrap();
+    assert_eq!((table.num_frames(), table.table_size), (1, DEFAULT_FRAME_SIZE));
+    let table = Wavetable::from_wav(&wavetable_file(&pcm, Some(2048)), DEFAULT_FRAME_SIZE).unwrap();
+    assert_eq!(table.num_frames(), 2);
+}
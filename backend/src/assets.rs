@@ -0,0 +1,311 @@
+// Asset library: uploaded audio stored under the SHA-256 of its bytes, so an
+// `asset_id` means the same sound on every client and in offline export.
+//
+// assets/<id>.<ext>   the file as uploaded
+// assets/<id>.json    metadata
+// assets/<id>.peaks   waveform peak pyramid, binary
+//
+// Other files in the folder are older assets known by file name only.
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use audio_engine::{
+    analysis::waveform::{Peak, PeakPyramid},
+    dsp::resample::Resampler,
+    encode::{wav, Pcm},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::decode::{self, DecodedAudio};
+use crate::export_handler::{MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+use crate::ws::AppState;
+
+// Where the server keeps its library
+pub const ASSETS_DIR: &str = "./assets";
+
+// Uploads past this are refused (about 45 minutes of 24-bit 96 kHz stereo)
+pub const MAX_UPLOAD_BYTES: usize = 1 << 30;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AssetInfo {
+    pub id: String,     // SHA-256 of the file, hex
+    pub name: String,   // File name it was uploaded as
+    pub format: String, // wav, aiff, flac, mp3 or ogg
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub frames: u64,
+    pub duration: f64, // Seconds
+    pub size: u64,     // Bytes of the original file
+    pub peak: f32,     // Loudest sample, linear
+}
+
+/// Ids are 64 hex digits, nothing that could walk out of the folder
+pub fn is_hash(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The folder assets are kept in
+#[derive(Clone, Debug)]
+pub struct Library {
+    root: PathBuf,
+}
+
+impl Library {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &std::path::Path {
+        &self.root
+    }
+
+    fn path(&self, id: &str, ext: &str) -> PathBuf {
+        self.root.join(format!("{}.{}", id, ext))
+    }
+
+    pub fn info(&self, id: &str) -> Option<AssetInfo> {
+        if !is_hash(id) {
+            return None;
+        }
+        let json = fs::read_to_string(self.path(id, "json")).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Decode an asset for playback: a library id, or the file name of an
+    /// older asset in the folder
+    pub fn load(&self, id: &str) -> Result<DecodedAudio, String> {
+        let file = match self.info(id) {
+            Some(info) => self.path(id, &info.format),
+            None => {
+                let name = std::path::Path::new(id).file_name().ok_or("Not an asset id")?;
+                self.root.join(name)
+            }
+        };
+        let bytes = fs::read(&file).map_err(|e| e.to_string())?;
+        decode::decode(bytes.into())
+    }
+
+    /// Decode, hash and store an upload. Uploading the same bytes again
+    /// returns the asset already there.
+    pub fn store(&self, name: &str, bytes: Bytes) -> Result<(AssetInfo, bool), (StatusCode, String)> {
+        let id: String = Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+        if let Some(existing) = self.info(&id) {
+            return Ok((existing, false));
+        }
+
+        let format = decode::sniff_format(&bytes)
+            .ok_or((StatusCode::UNSUPPORTED_MEDIA_TYPE, "Not a WAV, AIFF, FLAC, MP3 or Ogg file".to_string()))?;
+        let size = bytes.len() as u64;
+        let not_saved = |e: std::io::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("Asset not saved: {}", e));
+
+        // The file goes out first, decoding reads the same shared bytes
+        let file = self.path(&id, format);
+        fs::create_dir_all(&self.root).and_then(|_| fs::write(&file, &bytes)).map_err(not_saved)?;
+        let audio = match decode::decode(bytes) {
+            Ok(audio) => audio,
+            Err(e) => {
+                let _ = fs::remove_file(&file);
+                return Err((StatusCode::UNPROCESSABLE_ENTITY, e));
+            }
+        };
+
+        let frames = audio.frames() as u64;
+        let info = AssetInfo {
+            id: id.clone(),
+            name: name.to_string(),
+            format: format.to_string(),
+            sample_rate: audio.sample_rate,
+            channels: audio.channels.len() as u16,
+            frames,
+            duration: frames as f64 / audio.sample_rate as f64,
+            size,
+            peak: audio.channels.iter().flatten().fold(0.0f32, |peak, s| peak.max(s.abs())),
+        };
+
+        let write = || -> std::io::Result<()> {
+            fs::write(self.path(&id, "peaks"), pyramid(&audio).to_bytes())?;
+            // Metadata last, an asset only counts once it's there
+            fs::write(self.path(&id, "json"), serde_json::to_vec_pretty(&info).unwrap_or_default())
+        };
+        write().map_err(not_saved)?;
+        Ok((info, true))
+    }
+
+    /// Every asset with metadata, by name
+    pub fn list(&self) -> Vec<AssetInfo> {
+        let mut assets: Vec<AssetInfo> = fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                self.info(name.strip_suffix(".json")?)
+            })
+            .collect();
+        assets.sort_by(|a, b| a.name.cmp(&b.name));
+        assets
+    }
+
+    // Stored peaks, rebuilt from the audio when missing or unreadable
+    fn load_peaks(&self, id: &str) -> Result<PeakPyramid, String> {
+        let file = self.path(id, "peaks");
+        if let Some(pyramid) = fs::read(&file).ok().and_then(|bytes| PeakPyramid::from_bytes(&bytes).ok()) {
+            return Ok(pyramid);
+        }
+        let pyramid = pyramid(&self.load(id)?);
+        if let Err(e) = fs::write(&file, pyramid.to_bytes()) {
+            println!("Asset {}: peaks not saved: {}", id, e);
+        }
+        Ok(pyramid)
+    }
+}
+
+fn pyramid(audio: &DecodedAudio) -> PeakPyramid {
+    let channels: Vec<&[f32]> = audio.channels.iter().map(|c| c.as_slice()).collect();
+    PeakPyramid::new(&channels)
+}
+
+fn not_found(id: &str) -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, format!("No asset {}", id))
+}
+
+#[derive(Deserialize)]
+pub struct UploadParams {
+    // File name, kept for display
+    name: Option<String>,
+}
+
+/// `POST /api/assets?name=...` with the file as the body
+pub async fn upload_asset(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<UploadParams>,
+    body: Bytes,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if body.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Empty upload".to_string()));
+    }
+    let name = params.name.unwrap_or_else(|| "Untitled".to_string());
+    let (info, created) = tokio::task::spawn_blocking(move || state.library.store(&name, body))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+    if created {
+        println!("Asset stored: {} ({})", info.name, info.id);
+    }
+    Ok((if created { StatusCode::CREATED } else { StatusCode::OK }, Json(info)))
+}
+
+pub async fn list_assets(State(state): State<Arc<AppState>>) -> Json<Vec<AssetInfo>> {
+    Json(state.library.list())
+}
+
+pub async fn get_asset(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<AssetInfo>, (StatusCode, String)> {
+    state.library.info(&id).map(Json).ok_or_else(|| not_found(&id))
+}
+
+/// The file as uploaded
+pub async fn asset_file(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let info = state.library.info(&id).ok_or_else(|| not_found(&id))?;
+    let bytes = tokio::fs::read(state.library.path(&id, &info.format)).await.map_err(|_| not_found(&id))?;
+    let content_type = match info.format.as_str() {
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "aiff" => "audio/aiff",
+        "flac" => "audio/flac",
+        _ => "audio/wav",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], bytes))
+}
+
+#[derive(Deserialize)]
+pub struct PcmParams {
+    // Convert to this rate, the asset's own when missing
+    rate: Option<u32>,
+}
+
+/// Decoded audio as a 32-bit float WAV
+pub async fn asset_pcm(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(params): Query<PcmParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let info = state.library.info(&id).ok_or_else(|| not_found(&id))?;
+    let rate = params.rate.unwrap_or(info.sample_rate);
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&rate) {
+        return Err((StatusCode::BAD_REQUEST, format!("Sample rate must be {} to {} Hz", MIN_SAMPLE_RATE, MAX_SAMPLE_RATE)));
+    }
+    let bytes = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, String> {
+        let audio = state.library.load(&id)?;
+        let resampler = Resampler::new(audio.sample_rate, rate);
+        let channels: Vec<Vec<f32>> = audio.channels.iter().map(|c| resampler.process(c)).collect();
+        let frames = channels.first().map(|c| c.len()).unwrap_or(0);
+        let interleaved: Vec<f32> = (0..frames).flat_map(|i| channels.iter().map(move |c| c[i])).collect();
+        Ok(wav::write(&Pcm::Float(&interleaved), channels.len() as u16, rate))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(([(header::CONTENT_TYPE, "audio/wav")], bytes))
+}
+
+#[derive(Deserialize)]
+pub struct PeakParams {
+    // Peaks wanted, one per pixel. Without it the whole pyramid is sent.
+    pixels: Option<usize>,
+    // Frames to cover, the whole asset by default
+    start: Option<u64>,
+    end: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct Peaks {
+    start: u64,
+    end: u64,
+    channels: Vec<Vec<Peak>>,
+}
+
+// Keeps a careless query from building a huge response
+const MAX_PEAK_PIXELS: usize = 65536;
+
+/// Waveform peaks: the binary pyramid for the client to zoom through, or
+/// with `pixels`, min/max/RMS columns for one view as JSON
+pub async fn asset_peaks(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(params): Query<PeakParams>,
+) -> Result<Response, (StatusCode, String)> {
+    state.library.info(&id).ok_or_else(|| not_found(&id))?;
+    let pyramid = tokio::task::spawn_blocking(move || state.library.load_peaks(&id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let Some(pixels) = params.pixels else {
+        return Ok(([(header::CONTENT_TYPE, "application/octet-stream")], pyramid.to_bytes()).into_response());
+    };
+    if !(1..=MAX_PEAK_PIXELS).contains(&pixels) {
+        return Err((StatusCode::BAD_REQUEST, format!("pixels must be 1 to {}", MAX_PEAK_PIXELS)));
+    }
+    let start = params.start.unwrap_or(0);
+    let end = params.end.unwrap_or(pyramid.frames());
+    if end <= start {
+        return Err((StatusCode::BAD_REQUEST, "end must be after start".to_string()));
+    }
+    let channels = (0..pyramid.channels())
+        .map(|c| pyramid.query(c, start, end, pixels))
+        .collect();
+    Ok(Json(Peaks { start, end, channels }).into_response())
+}
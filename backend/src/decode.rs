@@ -0,0 +1,118 @@
+// Audio file decoding for uploads and offline export: WAV, AIFF, FLAC, MP3
+// and Ogg Vorbis through symphonia, all to planar f32
+
+use bytes::Bytes;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+pub struct DecodedAudio {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>, // Planar, one Vec per channel
+}
+
+impl DecodedAudio {
+    pub fn frames(&self) -> usize {
+        self.channels.first().map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// Left and right for the engine: mono is doubled, anything past two
+    /// channels is left out
+    pub fn into_stereo(mut self) -> (Vec<f32>, Vec<f32>) {
+        self.channels.truncate(2);
+        let left = self.channels.remove(0);
+        let right = self.channels.pop().unwrap_or_else(|| left.clone());
+        (left, right)
+    }
+}
+
+/// Container of an audio file, from its first bytes
+pub fn sniff_format(bytes: &[u8]) -> Option<&'static str> {
+    let magic = |at: usize, tag: &[u8]| bytes.get(at..at + tag.len()) == Some(tag);
+    if (magic(0, b"RIFF") && magic(8, b"WAVE")) || magic(0, b"RF64") {
+        Some("wav")
+    } else if magic(0, b"FORM") && (magic(8, b"AIFF") || magic(8, b"AIFC")) {
+        Some("aiff")
+    } else if magic(0, b"fLaC") {
+        Some("flac")
+    } else if magic(0, b"OggS") {
+        Some("ogg")
+    } else if magic(0, b"ID3") || (bytes.len() > 1 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0) {
+        // Tagged, or starting on an MPEG frame sync
+        Some("mp3")
+    } else {
+        None
+    }
+}
+
+/// Decode a whole file. Corrupt packets are skipped, like players do.
+pub fn decode(bytes: Bytes) -> Result<DecodedAudio, String> {
+    let format_name = sniff_format(&bytes).ok_or("Not a WAV, AIFF, FLAC, MP3 or Ogg file")?;
+    let mut hint = Hint::new();
+    hint.with_extension(format_name);
+
+    let stream = MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes)), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unreadable {} file: {}", format_name, e))?;
+    let mut reader = probed.format;
+
+    let track = reader.tracks().iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("No audio track")?;
+    let track_id = track.id;
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Unsupported codec: {}", e))?;
+
+    let mut channels: Vec<Vec<f32>> = Vec::new();
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(Error::ResetRequired) => break,
+            Err(e) => return Err(format!("Read failed: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(Error::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Decode failed: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        let count = spec.channels.count();
+        if count == 0 {
+            continue;
+        }
+        sample_rate = spec.rate;
+        if channels.len() != count {
+            channels.resize(count, Vec::new());
+        }
+        let buffer = match &mut buffer {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * count => buffer,
+            slot => slot.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        for frame in buffer.samples().chunks_exact(count) {
+            for (channel, sample) in channels.iter_mut().zip(frame) {
+                channel.push(*sample);
+            }
+        }
+    }
+
+    if channels.is_empty() || sample_rate == 0 {
+        return Err("No audio in file".to_string());
+    }
+    Ok(DecodedAudio { sample_rate, channels })
+}
@@ -1,51 +1,378 @@
 use axum::{
-    Json, extract::State,
+    extract::{Path, Query, State},
+    http::{header, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
 };
+use serde::Deserialize;
 use std::sync::Arc;
-use shared::Action;
-use audio_engine::{mixer::Mixer, export::AudioExporter};
+use std::time::Duration;
+use audio_engine::{
+    analysis::loudness,
+    dsp::{dither::Dither, resample::Resampler},
+    encode::{BitDepth, Container, ExportFormat},
+    export::{AudioExporter, Normalize, RenderOptions, StemGroup, StemOptions, TailMode},
+    mixer::AssetCache,
+};
+use shared::{Project, TimeRange};
+use crate::export_jobs::{progress_message, ExportFile, ExportJobs, JobStatus};
+use crate::assets::Library;
 use crate::ws::AppState;
 use std::fs;
 
+// Output rates the writers and players are happy with
+pub const MIN_SAMPLE_RATE: u32 = 8000;
+pub const MAX_SAMPLE_RATE: u32 = 384000;
+
+// Warm-up before a range that doesn't start at the top
+const DEFAULT_PRE_ROLL_SECONDS: f32 = 2.0;
+
+// Streaming services ask for at least 1 dB of headroom
+const DEFAULT_TRUE_PEAK_CEILING: f64 = -1.0;
+
+// How often running jobs report over the websocket
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+// Loudness of the export as JSON, next to the file
+const LOUDNESS_HEADER: HeaderName = HeaderName::from_static("x-export-loudness");
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    // Saved project to render, the live session when missing
+    name: Option<String>,
+    // Stems in a ZIP instead of the mix
+    stems: Option<StemGroup>,
+    // Stems: run master gain and clipper (default off)
+    master: Option<bool>,
+    // Track stems: include sends and their bus returns (default on)
+    sends: Option<bool>,
+    // wav (default), aiff or flac
+    format: Option<Container>,
+    // 16, 24, 32 or float. Default float, 24 for FLAC
+    bits: Option<BitDepth>,
+    // none, tpdf (default) or shaped
+    dither: Option<Dither>,
+    // Output sample rate, the project's rate when missing
+    rate: Option<u32>,
+    // Target integrated loudness in LUFS, no normalization when missing
+    normalize: Option<f64>,
+    // True-peak ceiling for normalization, dBTP
+    ceiling: Option<f64>,
+    // Timeline range in project samples, from the top and to the last clip
+    // when missing
+    start: Option<u64>,
+    end: Option<u64>,
+    // Render the project's loop region instead
+    #[serde(rename = "loop")]
+    loop_region: Option<bool>,
+    // Comma-separated track ids, all tracks when missing
+    tracks: Option<String>,
+    // Seconds played before the range to warm up effects
+    pre_roll: Option<f32>,
+    // auto (default), estimated or none
+    tail: Option<TailMode>,
+}
+
+// Settings resolved from the query, everything a job needs to run
+struct ExportSpec {
+    project: Project,
+    output: Output,
+    format: ExportFormat,
+    normalize: Option<Normalize>,
+    render_rate: u32,
+    output_rate: u32,
+    library: Library,
+}
+
+enum Output {
+    Mix(RenderOptions),
+    Stems(StemOptions),
+}
+
+/// Start an export job. Answers at once with its id, progress arrives over
+/// the websocket and the file at `GET /api/export/{id}`.
 pub async fn export_project(
     State(state): State<Arc<AppState>>,
-) -> Json<String> {
-    println!("Starting Export...");
-    
+    Query(params): Query<ExportParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 1. Get Project State
-    let project_guard = state.project.read().await;
-    let project_clone = project_guard.clone(); 
-    drop(project_guard); // Drop lock early
-
-    // 2. Setup Mixer (Headless)
-    let mut mixer = Mixer::new(44100.0);
-    // TODO: hydrate mixer from project_clone (tracks, clips, etc.)
-    // For now, we might need a method "Mixer::from_project(&Project)"
-    
-    // 3. Render
-    let duration_sec = 10; // TODO: Calculate from project length
-    let sample_rate = 44100;
-    let duration_samples = duration_sec * sample_rate;
-    
-    let buffer = AudioExporter::render(&mut mixer, duration_samples as u64);
-
-    // 4. Write WAV
-    fs::create_dir_all("exports").unwrap();
-    let filename = format!("exports/{}_{}.wav", project_clone.name, chrono::Utc::now().timestamp());
-    
-    let spec = hound::WavSpec {
-        channels: 2,
-        sample_rate: sample_rate as u32,
-        bits_per_sample: 32,
-        sample_format: hound::SampleFormat::Float,
+    let project = match &params.name {
+        Some(name) => load_saved_project(name)?,
+        None => state.project.read().await.clone(),
+    };
+
+    // 2. Check the settings before anything is queued
+    let container = params.format.unwrap_or_default();
+    let format = ExportFormat {
+        container,
+        bit_depth: params.bits.unwrap_or(if container == Container::Flac { BitDepth::Int24 } else { BitDepth::Float32 }),
+        dither: params.dither.unwrap_or_default(),
+    };
+    format.check().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let normalize = params.normalize.map(|target_lufs| Normalize {
+        target_lufs,
+        true_peak_ceiling: params.ceiling.unwrap_or(DEFAULT_TRUE_PEAK_CEILING),
+    });
+
+    // Render at the project's rate, its clip positions are counted in it,
+    // then convert to the requested one
+    let render_rate = project.sample_rate.clamp(MIN_SAMPLE_RATE, MAX_SAMPLE_RATE);
+    let output_rate = params.rate.unwrap_or(render_rate);
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&output_rate) {
+        return Err((StatusCode::BAD_REQUEST, format!("Sample rate must be {} to {} Hz", MIN_SAMPLE_RATE, MAX_SAMPLE_RATE)));
+    }
+
+    let mut render = render_options(&project, &params, render_rate)?;
+    let (id, progress) = state.exports.create();
+    render.progress = Some(progress);
+    let output = match params.stems {
+        None => Output::Mix(render),
+        Some(group) => {
+            let defaults = StemOptions::default();
+            Output::Stems(StemOptions {
+                group,
+                include_master: params.master.unwrap_or(defaults.include_master),
+                include_sends: params.sends.unwrap_or(defaults.include_sends),
+                render,
+            })
+        }
+    };
+    let spec = ExportSpec { project, output, format, normalize, render_rate, output_rate, library: state.library.clone() };
+    println!("Starting export {}...", id);
+
+    // 3. Render on the blocking pool, report from here
+    let state_ref = state.clone();
+    let job_state = state.clone();
+    let mut job = tokio::task::spawn_blocking(move || spec.run(&job_state.exports, id));
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(PROGRESS_INTERVAL);
+        let status = loop {
+            tokio::select! {
+                result = &mut job => break match result {
+                    Ok(Ok(file)) => JobStatus::Done(Arc::new(file)),
+                    Ok(Err(e)) => JobStatus::Failed(e),
+                    Err(e) => JobStatus::Failed(format!("Export crashed: {}", e)),
+                },
+                _ = tick.tick() => match state_ref.exports.status(id) {
+                    Some((status, fraction)) if !status.is_finished() => {
+                        let _ = state_ref.tx.send(progress_message(id, &status, fraction));
+                    }
+                    // Cancelled or forgotten, the render stops on its own
+                    _ => {}
+                },
+            }
+        };
+        // A cancelled job already said so
+        if let JobStatus::Failed(e) = &status {
+            if !state_ref.exports.status(id).is_some_and(|(status, _)| status.is_finished()) {
+                println!("Export {} failed: {}", id, e);
+            }
+        }
+        if state_ref.exports.set_status(id, status) {
+            if let Some((status, fraction)) = state_ref.exports.status(id) {
+                let _ = state_ref.tx.send(progress_message(id, &status, fraction));
+            }
+        }
+    });
+
+    let body = serde_json::json!({ "id": id, "url": format!("/api/export/{}", id) });
+    Ok((StatusCode::ACCEPTED, Json(body)))
+}
+
+/// The finished file, or where the job is at while it runs
+pub async fn download_export(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u64>,
+) -> Result<Response, (StatusCode, String)> {
+    let (status, fraction) = state.exports.status(id)
+        .ok_or((StatusCode::NOT_FOUND, format!("No export {}", id)))?;
+    match status {
+        JobStatus::Done(file) => {
+            let disposition = format!("attachment; filename=\"{}\"", file.filename.replace('"', ""));
+            Ok((
+                [
+                    (header::CONTENT_TYPE, file.content_type.to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                    (LOUDNESS_HEADER, file.report.to_string()),
+                ],
+                file.bytes.clone(),
+            ).into_response())
+        }
+        JobStatus::Failed(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+        JobStatus::Cancelled => Err((StatusCode::GONE, format!("Export {} was cancelled", id))),
+        JobStatus::Rendering | JobStatus::Encoding => {
+            let body = serde_json::json!({ "id": id, "state": status.name(), "progress": fraction });
+            Ok((StatusCode::ACCEPTED, Json(body)).into_response())
+        }
+    }
+}
+
+/// Stop a running export, or drop a finished one
+pub async fn cancel_export(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !state.exports.cancel(id) {
+        return Err((StatusCode::NOT_FOUND, format!("No export {}", id)));
+    }
+    if let Some((status, fraction)) = state.exports.status(id) {
+        let _ = state.tx.send(progress_message(id, &status, fraction));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+impl ExportSpec {
+    // Render, convert, normalize and encode. Blocking, CPU-bound.
+    fn run(self, jobs: &ExportJobs, id: u64) -> Result<ExportFile, String> {
+        let Self { project, output, format, normalize, render_rate, output_rate, library } = self;
+        let container = format.container;
+        let assets = load_assets(&library, &project, render_rate);
+        let converter = Resampler::new(render_rate, output_rate);
+        let convert = |samples: Vec<f32>| if converter.is_identity() { samples } else { converter.process_interleaved(&samples, 2) };
+        let sample_rate = output_rate as f32;
+        let encode = |samples: &[f32]| AudioExporter::encode(samples, output_rate, &format);
+        let cancelled = |render: &RenderOptions| render.progress.as_ref().is_some_and(|p| p.is_cancelled());
+        let stamp = chrono::Utc::now().timestamp();
+
+        let (filename, content_type, bytes, report) = match output {
+            Output::Mix(render) => {
+                let buffer = AudioExporter::render_range(&project, assets, render_rate as f32, &render);
+                if cancelled(&render) {
+                    return Err("Cancelled".to_string());
+                }
+                jobs.set_status(id, JobStatus::Encoding);
+                let mut buffer = convert(buffer);
+                let gain_db = normalize.map(|n| AudioExporter::normalize(&mut buffer, sample_rate, &n)).unwrap_or(0.0);
+                let report = loudness_report(gain_db, &loudness::measure(&buffer, 2, sample_rate));
+                let bytes = encode(&buffer)?;
+                (format!("{}_{}.{}", file_safe(&project.name), stamp, container.extension()), container.mime_type(), bytes, report)
+            }
+            Output::Stems(options) => {
+                let render = options.render.clone();
+                let mut stems = AudioExporter::render_stems(&project, assets, render_rate as f32, options);
+                if cancelled(&render) {
+                    return Err("Cancelled".to_string());
+                }
+                jobs.set_status(id, JobStatus::Encoding);
+                for stem in stems.iter_mut() {
+                    stem.samples = convert(std::mem::take(&mut stem.samples));
+                }
+                let gain_db = normalize.map(|n| AudioExporter::normalize_stems(&mut stems, sample_rate, &n)).unwrap_or(0.0);
+
+                // Numbered so names stay unique and keep the mixer order
+                let mut files = Vec::with_capacity(stems.len() + 1);
+                let mut measured = serde_json::Map::new();
+                for (i, stem) in stems.iter().enumerate() {
+                    let name = format!("{:02} {}.{}", i + 1, file_safe(&stem.name), container.extension());
+                    measured.insert(name.clone(), loudness_report(gain_db, &loudness::measure(&stem.samples, 2, sample_rate)));
+                    files.push((name, encode(&stem.samples)?));
+                }
+                let report = serde_json::Value::Object(measured);
+                files.push(("loudness.json".to_string(), serde_json::to_vec_pretty(&report).unwrap_or_default()));
+                let bytes = crate::zip::store(&files)?;
+                let summary = serde_json::json!({ "gain_db": gain_db });
+                (format!("{}_{}_stems.zip", file_safe(&project.name), stamp), "application/zip", bytes, summary)
+            }
+        };
+
+        // Keep a copy next to earlier exports
+        let saved = fs::create_dir_all("exports")
+            .and_then(|_| fs::write(format!("exports/{}", filename), &bytes));
+        match saved {
+            Ok(_) => println!("Export saved to exports/{}", filename),
+            Err(e) => println!("Export not saved: {}", e),
+        }
+
+        Ok(ExportFile { filename, content_type, bytes: bytes.into(), report })
+    }
+}
+
+// Range, tracks and tail from the query
+fn render_options(project: &Project, params: &ExportParams, sample_rate: u32) -> Result<RenderOptions, (StatusCode, String)> {
+    let bad_request = |message: &str| (StatusCode::BAD_REQUEST, message.to_string());
+
+    let range = if params.loop_region.unwrap_or(false) {
+        Some(project.loop_region.ok_or_else(|| bad_request("Project has no loop region"))?)
+    } else if params.start.is_some() || params.end.is_some() {
+        let end = params.end.unwrap_or_else(|| AudioExporter::content_end(project));
+        Some(TimeRange { start: params.start.unwrap_or(0), end })
+    } else {
+        None
     };
-    
-    let mut writer = hound::WavWriter::create(&filename, spec).unwrap();
-    for sample in buffer {
-        writer.write_sample(sample).unwrap();
+    if range.is_some_and(|range| range.is_empty()) {
+        return Err(bad_request("Export range is empty"));
     }
-    writer.finalize().unwrap();
 
-    println!("Export saved to {}", filename);
-    Json(filename)
+    let tracks = match &params.tracks {
+        Some(list) => {
+            let ids = list.split(',')
+                .map(|id| id.trim().parse::<u32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| bad_request("Tracks must be a comma-separated list of ids"))?;
+            if let Some(id) = ids.iter().find(|id| !project.tracks.iter().any(|track| track.id == **id)) {
+                return Err((StatusCode::BAD_REQUEST, format!("No track {}", id)));
+            }
+            Some(ids)
+        }
+        None => None,
+    };
+
+    let pre_roll = params.pre_roll.unwrap_or(DEFAULT_PRE_ROLL_SECONDS).max(0.0);
+    Ok(RenderOptions {
+        range,
+        pre_roll: (pre_roll * sample_rate as f32) as u64,
+        tracks,
+        // Measured tails end where the sound does
+        tail: params.tail.unwrap_or(TailMode::Auto),
+        progress: None,
+    })
+}
+
+// Measured loudness plus the normalization gain that was applied
+fn loudness_report(gain_db: f64, stats: &loudness::LoudnessStats) -> serde_json::Value {
+    let mut report = serde_json::to_value(stats).unwrap_or_default();
+    if let Some(fields) = report.as_object_mut() {
+        fields.insert("gain_db".to_string(), gain_db.into());
+    }
+    report
+}
+
+// Track and bus names as file names
+fn file_safe(name: &str) -> String {
+    let safe: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || " -_.".contains(c) { c } else { '_' })
+        .collect();
+    let safe = safe.trim().trim_matches('.');
+    if safe.is_empty() { "Untitled".to_string() } else { safe.to_string() }
+}
+
+fn load_saved_project(name: &str) -> Result<Project, (StatusCode, String)> {
+    let filename = format!("./projects/{}.json", name);
+    let content = fs::read_to_string(filename)
+        .map_err(|_| (StatusCode::NOT_FOUND, format!("Project '{}' not found", name)))?;
+    Project::from_json(&content)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid project '{}': {}", name, e)))
+}
+
+// Decode every asset the project uses, converted to `sample_rate`.
+// Missing or unreadable ones are left out and play as silence, like an asset
+// the browser never loaded.
+fn load_assets(library: &Library, project: &Project, sample_rate: u32) -> AssetCache {
+    let mut assets = AssetCache::new();
+    for id in project.asset_ids() {
+        match library.load(id) {
+            Ok(audio) => {
+                let resampler = Resampler::new(audio.sample_rate, sample_rate);
+                let (left, right) = audio.into_stereo();
+                let sample = if resampler.is_identity() {
+                    (left, right)
+                } else {
+                    (resampler.process(&left), resampler.process(&right))
+                };
+                assets.insert(id.to_string(), sample);
+            }
+            Err(e) => println!("Export: asset '{}' not loaded: {}", id, e),
+        }
+    }
+    assets
 }
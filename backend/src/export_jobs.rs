@@ -0,0 +1,123 @@
+// Export jobs: renders run on the blocking pool while clients follow them
+// over the websocket, then fetch the file by job id
+
+use audio_engine::export::RenderProgress;
+use bytes::Bytes;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Finished jobs kept for download, the oldest are dropped first
+const MAX_FINISHED_JOBS: usize = 16;
+
+pub struct ExportFile {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub bytes: Bytes, // Shared with every download, never copied
+    pub report: serde_json::Value, // Loudness
+}
+
+#[derive(Clone)]
+pub enum JobStatus {
+    Rendering,
+    Encoding,
+    Done(Arc<ExportFile>),
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rendering => "rendering",
+            Self::Encoding => "encoding",
+            Self::Done(_) => "done",
+            Self::Failed(_) => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Rendering | Self::Encoding)
+    }
+}
+
+struct Job {
+    progress: Arc<RenderProgress>,
+    status: JobStatus,
+}
+
+#[derive(Default)]
+pub struct ExportJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, Job>>,
+}
+
+impl ExportJobs {
+    /// Register a new job, rendering until told otherwise
+    pub fn create(&self) -> (u64, Arc<RenderProgress>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let progress = Arc::new(RenderProgress::default());
+        self.lock().insert(id, Job { progress: progress.clone(), status: JobStatus::Rendering });
+        (id, progress)
+    }
+
+    /// Status and progress (0 to 1) of a job
+    pub fn status(&self, id: u64) -> Option<(JobStatus, f32)> {
+        self.lock().get(&id).map(|job| {
+            let fraction = if matches!(job.status, JobStatus::Done(_)) { 1.0 } else { job.progress.fraction() };
+            (job.status.clone(), fraction)
+        })
+    }
+
+    /// Move a running job on. Cancelled jobs stay cancelled, false if it was.
+    pub fn set_status(&self, id: u64, status: JobStatus) -> bool {
+        let mut jobs = self.lock();
+        match jobs.get_mut(&id) {
+            Some(job) if !job.status.is_finished() => job.status = status,
+            _ => return false,
+        }
+
+        let mut finished: Vec<u64> = jobs.iter()
+            .filter(|(_, job)| job.status.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        if finished.len() > MAX_FINISHED_JOBS {
+            finished.sort_unstable();
+            for id in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+                jobs.remove(id);
+            }
+        }
+        true
+    }
+
+    /// Stop a running job, or forget a finished one. False if there's no such job.
+    pub fn cancel(&self, id: u64) -> bool {
+        let mut jobs = self.lock();
+        let Some(job) = jobs.get_mut(&id) else { return false };
+        if job.status.is_finished() {
+            jobs.remove(&id);
+        } else {
+            job.progress.cancel();
+            job.status = JobStatus::Cancelled;
+        }
+        true
+    }
+
+    // A panic while holding the lock leaves the map as it was, keep going
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Job>> {
+        self.jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Websocket message for a job, same shape as the other server messages
+pub fn progress_message(id: u64, status: &JobStatus, progress: f32) -> String {
+    let mut payload = json!({ "id": id, "state": status.name(), "progress": progress });
+    match status {
+        JobStatus::Done(file) => payload["file"] = file.filename.clone().into(),
+        JobStatus::Failed(error) => payload["error"] = error.clone().into(),
+        _ => {}
+    }
+    json!({ "type": "ExportProgress", "payload": payload }).to_string()
+}
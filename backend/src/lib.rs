@@ -0,0 +1,8 @@
+// Server modules, a library so integration tests can reach them
+
+pub mod assets;
+pub mod decode;
+pub mod export_handler;
+pub mod export_jobs;
+pub mod ws;
+pub mod zip;
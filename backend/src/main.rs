@@ -6,27 +6,33 @@ use serde::Deserialize;
 use std::fs;
 use shared::Project;
 
-mod ws;
+use backend::{assets, export_handler, ws};
 
 use std::sync::Arc;
 use tokio::sync::broadcast;
 // RwLock is used inside ws::AppState, but we invoke new here
 
-mod export_handler;
-
 #[tokio::main]
 async fn main() {
     // 1. Create Shared State
     let (tx, _rx) = broadcast::channel(100);
     let project = Arc::new(tokio::sync::RwLock::new(Project::default()));
     
-    let app_state = Arc::new(ws::AppState { tx, project });
+    let library = assets::Library::new(assets::ASSETS_DIR);
+    let app_state = Arc::new(ws::AppState { tx, project, exports: Default::default(), library });
 
     let app = Router::new()
         .route("/", get(root))
         .route("/api/projects", get(list_projects).post(save_project))
         .route("/api/projects/load", get(load_project))
+        .route("/api/assets", get(assets::list_assets).post(assets::upload_asset)
+            .layer(axum::extract::DefaultBodyLimit::max(assets::MAX_UPLOAD_BYTES)))
+        .route("/api/assets/:id", get(assets::get_asset))
+        .route("/api/assets/:id/file", get(assets::asset_file))
+        .route("/api/assets/:id/pcm", get(assets::asset_pcm))
+        .route("/api/assets/:id/peaks", get(assets::asset_peaks))
         .route("/api/export", axum::routing::post(export_handler::export_project))
+        .route("/api/export/:id", get(export_handler::download_export).delete(export_handler::cancel_export))
         // WS Route
         .route("/ws", get(ws::ws_handler))
         .with_state(app_state);
@@ -47,12 +53,10 @@ async fn list_projects() -> Json<Vec<String>> {
     
     let mut projects = Vec::new();
     if let Ok(entries) = fs::read_dir(projects_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                if let Some(name) = entry.file_name().to_str() {
-                    if name.ends_with(".json") {
-                        projects.push(name.trim_end_matches(".json").to_string());
-                    }
+        for entry in entries.flatten() {
+            if let Some(name) = entry.file_name().to_str() {
+                if name.ends_with(".json") {
+                    projects.push(name.trim_end_matches(".json").to_string());
                 }
             }
         }
@@ -83,7 +87,7 @@ struct LoadParams {
 async fn load_project(Query(params): Query<LoadParams>) -> Json<Option<Project>> {
     let filename = format!("./projects/{}.json", params.name);
     if let Ok(content) = fs::read_to_string(filename) {
-        if let Ok(project) = serde_json::from_str(&content) {
+        if let Ok(project) = Project::from_json(&content) {
             return Json(Some(project));
         }
     }
@@ -9,11 +9,15 @@ use futures::{sink::SinkExt, stream::StreamExt};
 
 use shared::Project;
 use tokio::sync::RwLock;
+use crate::assets::Library;
+use crate::export_jobs::ExportJobs;
 
 // Shared state
 pub struct AppState {
     pub tx: broadcast::Sender<String>,
     pub project: Arc<RwLock<Project>>,
+    pub exports: ExportJobs,
+    pub library: Library,
 }
 
 pub async fn ws_handler(
@@ -84,12 +88,28 @@ use shared::Action;
                                 // Find clip and move it (Simplified logic)
                                 for track in &mut project.tracks {
                                     if let Some(_pos) = track.clips.iter().position(|c| 
-                                        match c { shared::ClipData::Audio { .. } => true, _ => false } // Todo: check ID
+                                        matches!(c, shared::ClipData::Audio { .. }) // Todo: check ID
                                     ) {
                                          // Update clip start
                                     }
                                 }
                             },
+                            Action::RecordAutomation(pass) => {
+                                let lanes = match pass.track_id {
+                                    Some(id) => project.tracks.iter_mut().find(|t| t.id == id).map(|t| &mut t.automation),
+                                    None => Some(&mut project.master_automation),
+                                };
+                                if let Some(lanes) = lanes {
+                                    let index = match lanes.iter().position(|l| l.target == pass.target) {
+                                        Some(index) => index,
+                                        None => {
+                                            lanes.push(shared::AutomationLane::new(pass.target.clone()));
+                                            lanes.len() - 1
+                                        }
+                                    };
+                                    lanes[index].merge_recording(pass.start, pass.end, &pass.points);
+                                }
+                            },
                             _ => {}
                         }
                     }
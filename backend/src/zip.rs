@@ -0,0 +1,88 @@
+// Minimal ZIP writer for export bundles. Files are stored, not deflated:
+// rendered audio barely compresses and this keeps the writer dependency-free.
+// No ZIP64, so the archive and every file must stay under 4 GiB.
+
+use chrono::{Datelike, Timelike};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL: u32 = 0x06054b50;
+const VERSION: u16 = 20; // 2.0, enough for stored files
+const UTF8_NAMES: u16 = 1 << 11;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |c, &b| table[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8))
+}
+
+// MS-DOS (time, date) of now
+fn dos_timestamp() -> (u16, u16) {
+    let now = chrono::Local::now();
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let date = (((now.year().clamp(1980, 2107) - 1980) as u32) << 9) | (now.month() << 5) | now.day();
+    (time, date as u16)
+}
+
+/// Archive `files` (name, contents) into a ZIP
+pub fn store(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let (time, date) = dos_timestamp();
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, data) in files {
+        let offset = u32::try_from(out.len()).map_err(|_| "Archive too large".to_string())?;
+        let size = u32::try_from(data.len()).map_err(|_| format!("{} too large", name))?;
+        let name_len = u16::try_from(name.len()).map_err(|_| format!("{}: name too long", name))?;
+        let crc = crc32(data);
+
+        // Fields shared by the local and central headers, from "version needed" on
+        let mut common = Vec::with_capacity(26);
+        common.extend_from_slice(&VERSION.to_le_bytes());
+        common.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // Stored
+        common.extend_from_slice(&time.to_le_bytes());
+        common.extend_from_slice(&date.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes()); // Compressed
+        common.extend_from_slice(&size.to_le_bytes()); // Uncompressed
+        common.extend_from_slice(&name_len.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // Extra field
+
+        out.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        out.extend_from_slice(&common);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        central.extend_from_slice(&VERSION.to_le_bytes()); // Made by
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&0u16.to_le_bytes()); // Comment
+        central.extend_from_slice(&0u16.to_le_bytes()); // Disk
+        central.extend_from_slice(&0u16.to_le_bytes()); // Internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // External attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let count = u16::try_from(files.len()).map_err(|_| "Too many files".to_string())?;
+    let central_offset = u32::try_from(out.len()).map_err(|_| "Archive too large".to_string())?;
+    let central_size = central.len() as u32;
+    out.extend_from_slice(&central);
+
+    out.extend_from_slice(&END_OF_CENTRAL.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // This disk
+    out.extend_from_slice(&0u16.to_le_bytes()); // Disk with the directory
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&central_size.to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // Comment
+    Ok(out)
+}
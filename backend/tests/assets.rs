@@ -0,0 +1,71 @@
+use audio_engine::encode::{wav, BitDepth, Pcm};
+use axum::{body::Bytes, http::StatusCode};
+use backend::assets::{is_hash, Library};
+
+// A fresh library in a scratch folder for each test
+fn library(test: &str) -> Library {
+    let dir = std::env::temp_dir().join(format!("asset-tests-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    Library::new(dir)
+}
+
+// 16-bit stereo WAV, `seed` makes each test's file (and id) different
+fn wav_file(seed: i32, frames: i32) -> (Vec<u8>, Vec<i32>) {
+    let words: Vec<i32> = (0..frames * 2).map(|i| (i * 37 + seed * 1009) % 20000 - 10000).collect();
+    (wav::write(&Pcm::Int(words.clone(), BitDepth::Int16), 2, 48000), words)
+}
+
+#[test]
+fn ids_are_sha256_hex_only() {
+    let id = "0123456789abcdefABCDEF0123456789abcdef0123456789abcdef0123456789";
+    assert!(is_hash(id));
+    assert!(!is_hash(&id[1..]));
+    assert!(!is_hash(&format!("{}0", id)));
+    assert!(!is_hash(&format!("{}g", &id[1..])));
+    assert!(!is_hash("../../etc/passwd"));
+    assert!(library("ids").info("../../etc/passwd").is_none());
+}
+
+#[test]
+fn stored_assets_load_back() {
+    let library = library("stored");
+    let (bytes, words) = wav_file(1, 3000);
+    let (info, created) = library.store("loop.wav", bytes.clone().into()).unwrap();
+    assert!(created && is_hash(&info.id));
+    assert_eq!((info.name.as_str(), info.format.as_str()), ("loop.wav", "wav"));
+    assert_eq!((info.sample_rate, info.channels, info.frames, info.size), (48000, 2, 3000, bytes.len() as u64));
+    assert!((info.peak - 10000.0 / 32768.0).abs() < 1e-3);
+
+    // Kept as uploaded, with peaks and metadata beside it
+    let dir = library.root();
+    assert_eq!(std::fs::read(dir.join(format!("{}.wav", info.id))).unwrap(), bytes);
+    assert!(dir.join(format!("{}.peaks", info.id)).exists());
+    assert_eq!(library.info(&info.id).unwrap().frames, 3000);
+    assert_eq!(library.list().len(), 1);
+
+    let audio = library.load(&info.id).unwrap();
+    assert_eq!(audio.sample_rate, 48000);
+    for (i, word) in words.iter().enumerate() {
+        assert_eq!(audio.channels[i % 2][i / 2], *word as f32 / 32768.0, "sample {}", i);
+    }
+
+    // The same bytes again are the same asset
+    let (again, created) = library.store("copy.wav", bytes.into()).unwrap();
+    assert!(!created);
+    assert_eq!((again.id, again.name), (info.id, "loop.wav".to_string()));
+}
+
+#[test]
+fn unreadable_uploads_leave_nothing_behind() {
+    let library = library("unreadable");
+    let err = library.store("notes.txt", Bytes::from_static(b"just some text")).unwrap_err();
+    assert_eq!(err.0, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    // Looks like FLAC, isn't
+    let err = library.store("broken.flac", Bytes::from_static(b"fLaC but nothing after")).unwrap_err();
+    assert_eq!(err.0, StatusCode::UNPROCESSABLE_ENTITY);
+    let leftovers = std::fs::read_dir(library.root()).into_iter().flatten().flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".flac"))
+        .count();
+    assert_eq!(leftovers, 0);
+}
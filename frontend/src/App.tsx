@@ -52,6 +52,12 @@ function App() {
         // Initialize WebSocket Connection
         WebSocketManager.getInstance().connect();
         
+        // Passes written by the engine go into the project and to the backend
+        audioEngine.onRecordedAutomation = (pass) => {
+            useProjectStore.getState().applyRecordedAutomation(pass);
+            WebSocketManager.getInstance().send('RecordAutomation', pass);
+        };
+
         // Initialize Audio Engine
         audioEngine.init().then(() => {
             // Initial Sync
@@ -136,6 +136,11 @@ export class WebSocketManager {
                             store.updateEffect(action.track_id, action.index, action.effect);
                         }
                         break;
+                    case 'RecordAutomation':
+                        if (action.payload) {
+                            store.applyRecordedAutomation(action.payload);
+                        }
+                        break;
                     // ... Handle other cases
                 }
 
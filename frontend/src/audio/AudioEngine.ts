@@ -1,4 +1,8 @@
 import init, { WasmAudioProcessor } from '../wasm/audio-engine';
+import type { RecordedAutomation } from '../store';
+
+// How often finished automation passes are collected while playing
+const AUTOMATION_POLL_MS = 250;
 
 
 class AudioEngine {
@@ -13,6 +17,9 @@ class AudioEngine {
     private interleavedBuffer: Float32Array | null = null;
     private masterGainNode: GainNode | null = null;
 
+    // Receives every automation pass the engine records
+    public onRecordedAutomation: ((pass: RecordedAutomation) => void) | null = null;
+
     constructor() {
     }
 
@@ -89,6 +96,8 @@ class AudioEngine {
                 
                 // Sync sample rate to WASM
                 this.wasmProcessor.set_sample_rate(this.context.sampleRate);
+
+                setInterval(() => this.pollRecordedAutomation(), AUTOMATION_POLL_MS);
                 
                 this.isInitialized = true;
                 console.log(`Audio Engine Initialized. SR: ${this.context.sampleRate}. State: ${this.context.state}`);
@@ -115,22 +124,42 @@ class AudioEngine {
     }
     
     // Updated to accept AudioBuffer to store in cache
-    public loadSample(assetId: string, left: Float32Array, right: Float32Array, buffer?: AudioBuffer) {
+    public async loadSample(assetId: string, left: Float32Array, right: Float32Array, buffer?: AudioBuffer) {
         if (buffer) {
             this.sampleCache.set(assetId, buffer);
         }
 
-        if (!this.wasmProcessor || !this.isInitialized) return;
+        if (!this.wasmProcessor || !this.isInitialized || !this.context) return;
+
+        // Convert here rather than in the engine, which would stall the audio callback
+        const rate = this.context.sampleRate;
+        if (buffer && buffer.sampleRate !== rate) {
+            const converted = await this.toContextRate(buffer);
+            left = converted.getChannelData(0);
+            right = converted.numberOfChannels > 1 ? converted.getChannelData(1) : left;
+        }
+        if (!this.wasmProcessor) return;
 
         const wasBusy = this.isBusy;
         this.isBusy = true;
         try {
-           console.log(`AudioEngine: Loading Sample: ${assetId} (${left.length} frames)`);
-           this.wasmProcessor.add_sample(assetId, left, right);
+           console.log(`AudioEngine: Loading Sample: ${assetId} (${left.length} frames @ ${rate} Hz)`);
+           this.wasmProcessor.add_sample(assetId, left, right, rate);
         } finally {
             this.isBusy = wasBusy;
         }
     }
+
+    // Resample through an offline context, rendered off the main thread
+    private async toContextRate(buffer: AudioBuffer): Promise<AudioBuffer> {
+        const rate = this.context!.sampleRate;
+        const offline = new OfflineAudioContext(buffer.numberOfChannels, Math.ceil(buffer.duration * rate), rate);
+        const source = offline.createBufferSource();
+        source.buffer = buffer;
+        source.connect(offline.destination);
+        source.start();
+        return offline.startRendering();
+    }
     
     public getAudioBuffer(assetId: string): AudioBuffer | undefined {
         return this.sampleCache.get(assetId);
@@ -175,6 +204,8 @@ class AudioEngine {
         this.isBusy = true;
         try {
              this.wasmProcessor.set_playing(playing);
+             // Stopping ends every pass, hand them over right away
+             if (!playing) this.pollRecordedAutomation();
              
              // If we are starting, ensure the context is definitely running
              if (playing && this.context?.state === 'suspended') {
@@ -214,6 +245,19 @@ class AudioEngine {
         this.wasmProcessor.set_track_pan(trackId, pan);
     }
 
+    // A fader or knob was let go, ends a Touch pass. `trackId` null = master and buses
+    public releaseParam(trackId: number | null, target: string) {
+        this.wasmProcessor?.handle_command(JSON.stringify({ ReleaseParam: { track_id: trackId, target } }));
+    }
+
+    private pollRecordedAutomation() {
+        if (!this.wasmProcessor) return;
+        const passes: RecordedAutomation[] = JSON.parse(this.wasmProcessor.take_recorded_automation());
+        for (const pass of passes) {
+            this.onRecordedAutomation?.(pass);
+        }
+    }
+
     public setTrackFilter(trackId: number, val: number) {
         if (!this.wasmProcessor) return;
         this.wasmProcessor.set_track_filter(trackId, val);
@@ -271,11 +315,23 @@ class AudioEngine {
         this.wasmProcessor.start_track_loop_seconds(trackId, seconds);
     }
     
-    public triggerSample(assetId: string) {
+    public triggerSample(assetId: string, options?: SampleTriggerOptions) {
         if (this.wasmProcessor && this.context && this.context.state === 'suspended') {
             this.context.resume();
         }
-        this.wasmProcessor?.trigger_sample(assetId);
+        if (options) {
+            this.wasmProcessor?.handle_command(JSON.stringify({ TriggerSample: { asset_id: assetId, ...options } }));
+        } else {
+            this.wasmProcessor?.trigger_sample(assetId);
+        }
+    }
+
+    public chokeSample(assetId: string) {
+        this.wasmProcessor?.choke_sample(assetId);
+    }
+
+    public stopAllSamples() {
+        this.wasmProcessor?.stop_all_samples();
     }
     
     public triggerAttack(trackId: number, note: number, velocity: number) {
@@ -297,4 +353,12 @@ class AudioEngine {
     }
 }
 
+export interface SampleTriggerOptions {
+    gain_db?: number;
+    pan?: number;
+    pitch?: number; // Semitones
+    start_offset?: number; // Samples
+    quantize?: 'Off' | 'Beat' | 'Bar';
+}
+
 export const audioEngine = new AudioEngine();
@@ -293,6 +293,7 @@ const ChannelStrip = ({ track, selected, onSelect, updateTrack, setGroup }: any)
                             step={0.1}
                             value={track.gain_db}
                             onChange={(e) => updateTrack(track.id, { gain_db: parseFloat(e.target.value) })}
+                            onPointerUp={() => audioEngine.releaseParam(track.id, 'gain')}
                             className="appearance-none h-full w-full bg-transparent z-10 cursor-ns-resize opacity-0 absolute inset-0"
                             style={{ WebkitAppearance: 'slider-vertical' } as any}
                         />
@@ -33,6 +33,7 @@ export const ModulationMatrixView: React.FC<ModMatrixProps> = ({ connections, on
     const sourceToString = (s: ModSource) => {
         if (typeof s === 'string') return s;
         if ('Lfo' in s) return `LFO ${s.Lfo + 1}`;
+        if ('GlobalLfo' in s) return `Global LFO ${s.GlobalLfo + 1}`;
         if ('Envelope' in s) return `Env ${s.Envelope + 1}`;
         return 'Unknown';
     };
@@ -12,6 +12,12 @@ export interface MidiNote {
 
 export type ClipType = 'audio' | 'midi';
 
+// Source sample pinned to a beat, counted from the clip start
+export interface WarpMarker {
+    sample: number;
+    beat: number;
+}
+
 export interface ClipData {
     id: number;
     type: ClipType;
@@ -24,6 +30,10 @@ export interface ClipData {
     // Audio specific
     audioUrl?: string; // Legacy?
     asset_id?: string;
+    stretch?: number;   // Timeline length / source length, 1 = original
+    transpose?: number; // Semitones
+    original_bpm?: number | null; // Set (or add warp markers) to follow the project tempo
+    warp_markers?: WarpMarker[];
     // MIDI specific
     notes?: MidiNote[];
 }
@@ -33,7 +43,20 @@ export type Effect =
     | { type: 'Compressor'; payload: { threshold: number; ratio: number; attack: number; release: number; makeup_gain: number } }
     | { type: 'Delay'; payload: { time_ms: number; feedback: number; mix: number } }
     | { type: 'Reverb'; payload: { mix: number; decay: number } }
-    | { type: 'Bass'; payload: { boost: number; cutoff: number; drive: number; width: number } };
+    | { type: 'Bass'; payload: { boost: number; cutoff: number; drive: number; width: number } }
+    | { type: 'ParametricEq'; payload: { bands: EqBand[] } };
+
+export type EqBandType = 'Bell' | 'LowShelf' | 'HighShelf' | 'LowCut' | 'HighCut' | 'Notch' | 'BandPass';
+
+export interface EqBand {
+    kind: EqBandType;
+    freq: number;
+    q: number;
+    gain_db: number;
+    slope_db: 12 | 24 | 36 | 48; // Cuts only
+    enabled: boolean;
+    channel: 'Stereo' | 'Left' | 'Right' | 'Mid' | 'Side';
+}
 
 export interface TrackData {
     id: number;
@@ -53,9 +76,76 @@ export interface TrackData {
     playbackRate?: number;
     automation?: AutomationLane[];
     synthConfig?: SynthConfig;
+    instrument?: Instrument;
+    output_bus?: number | null; // null = master
+    sends?: SendData[];
+}
+
+export interface SendData {
+    bus_id: number;
+    level_db: number;
+}
+
+export type GrainWindow = 'Hann' | 'Triangle' | 'Tukey' | 'Gaussian';
+
+export interface GranularParams {
+    position: number;
+    density: number;
+    size_ms: number;
+    spray_ms: number;
+    pitch_jitter: number;
+    pan_spread: number;
+    window: GrainWindow;
+    reverse_prob: number;
+    root_note: number;
+    seed: number;
+}
+
+export interface SampleZone {
+    asset_id: string;
+    key_low: number;
+    key_high: number;
+    vel_low: number;
+    vel_high: number;
+    root_note: number;
+    fine_tune: number; // Cents
+    gain_db: number;
+    pan: number;
+    start: number;
+    end: number; // 0 = end of asset
+    loop_mode: 'Off' | 'Forward';
+    loop_start: number;
+    loop_end: number;
+    crossfade: number;
+    round_robin_group: number; // 0 = always plays
+}
+
+export type Instrument =
+    | { type: 'Synth' }
+    | { type: 'Granular'; asset_id: string; params?: Partial<GranularParams> }
+    | { type: 'Sampler'; zones: Partial<SampleZone>[]; envelope?: Record<string, number | boolean> }
+    | { type: 'DrumRack'; pads?: Partial<DrumPad>[] };
+
+export interface DrumPad {
+    note: number;
+    asset_id: string;
+    gain_db: number;
+    pan: number;
+    pitch: number; // Semitones
+    envelope: Record<string, number | boolean>;
+    one_shot: boolean;
+    choke_group: number; // 0 = none
+    output_bus: number | null;
+    effects: Effect[];
 }
 
-export type AutomationCurve = 'Linear' | 'Step' | { Bezier: number };
+export type AutomationCurve =
+    | 'Linear'
+    | 'Step'
+    | 'Sine'
+    | { Bezier: number }
+    | { CubicBezier: { x1: number; y1: number; x2: number; y2: number } }
+    | { Exponential: number };
 
 export interface AutomationPoint {
     time: number;
@@ -63,14 +153,27 @@ export interface AutomationPoint {
     curve: AutomationCurve;
 }
 
+export type AutomationMode = 'Read' | 'Write' | 'Touch' | 'Latch';
+
 export interface AutomationLane {
-    target: string; // "gain", "pan", "filter"
+    target: string; // "gain", "pan", "filter", "send/<bus>", "fx/<slot>/<field>", "synth/cutoff", ...
+    points: AutomationPoint[];
+    mode?: AutomationMode;
+}
+
+// A pass written by the engine, sent to the backend as `RecordAutomation`
+export interface RecordedAutomation {
+    track_id: number | null; // null = master lane
+    target: string;
+    start: number;
+    end: number;
     points: AutomationPoint[];
 }
 
 // Modulation Types
 export type ModSource = 
     | { Lfo: number } 
+    | { GlobalLfo: number } 
     | { Envelope: number } 
     | 'Velocity' 
     | 'KeyTrack';
@@ -91,10 +194,30 @@ export interface SynthConfig {
     modMatrix: ModConnection[];
 }
 
+export interface BusData {
+    id: number;
+    name: string;
+    gain_db: number;
+    pan: number;
+    muted: boolean;
+    effects?: Effect[];
+}
+
+export interface TempoChange {
+    beat: number;
+    bpm: number;
+}
+
 export interface Project {
+    version?: number; // Saved data format, see shared::PROJECT_VERSION
     name: string;
     tempo: number;
+    sample_rate?: number; // Rate clip positions are counted in, 44100 when missing
+    tempo_map?: TempoChange[]; // Changes after the start
     tracks: TrackData[];
+    buses?: BusData[];
+    master_automation?: AutomationLane[]; // "master/gain", "bus/<id>/..."
+    loop_region?: { start: number; end: number } | null; // Samples, end exclusive
 }
 
 interface ProjectState {
@@ -131,7 +254,22 @@ interface ProjectState {
     setTrackFxStutter: (trackId: number, enabled: boolean) => void;
     setTrackFxTapeStop: (trackId: number, enabled: boolean) => void;
     setTrackLoop: (trackId: number, enabled: boolean, lengthBeats?: number) => void;
+
+    // Automation
+    applyRecordedAutomation: (pass: RecordedAutomation) => void;
 }
+
+// Replace `start..end` of a lane with a recorded pass, like AutomationLane::merge_recording
+const mergeRecording = (lanes: AutomationLane[] | undefined, pass: RecordedAutomation): AutomationLane[] => {
+    const list = lanes ?? [];
+    const lane = list.find(l => l.target === pass.target) ?? { target: pass.target, points: [] };
+    const points = [
+        ...lane.points.filter(p => p.time < pass.start || p.time > pass.end),
+        ...pass.points,
+    ].sort((a, b) => a.time - b.time);
+    const merged = { ...lane, points };
+    return list.includes(lane) ? list.map(l => l === lane ? merged : l) : [...list, merged];
+};
     
 export const useProjectStore = create<ProjectState>((set) => ({
     project: initialProject,
@@ -140,6 +278,20 @@ export const useProjectStore = create<ProjectState>((set) => ({
     viewMode: 'DAW',
     
     setProject: (project) => set({ project }),
+
+    // The engine already plays the pass, only the project copy changes
+    applyRecordedAutomation: (pass) => set((state) => ({
+        project: pass.track_id === null ? {
+            ...state.project,
+            master_automation: mergeRecording(state.project.master_automation, pass),
+        } : {
+            ...state.project,
+            tracks: state.project.tracks.map(t => t.id === pass.track_id ? {
+                ...t,
+                automation: mergeRecording(t.automation, pass),
+            } : t),
+        },
+    })),
     setViewMode: (mode) => set({ viewMode: mode }),
     
     setIsPlaying: (isPlaying) => set({ isPlaying }),
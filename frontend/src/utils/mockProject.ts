@@ -1,6 +1,7 @@
 import type { Project } from '../store';
 
 export const initialProject: Project = {
+    version: 1,
     name: "New Project",
     tempo: 120,
     tracks: [
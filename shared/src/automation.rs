@@ -1,10 +1,104 @@
 use serde::{Deserialize, Serialize};
 
+// Shape of the segment from a point to the next one
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CurveType {
     Linear,
-    Step,
-    Bezier(f32), // Tension: -1.0 to 1.0 (concave to convex)
+    #[serde(alias = "Hold")]
+    Step,        // Hold this value until the next point
+    Bezier(f32), // Power curve, tension: -1.0 to 1.0 (concave to convex)
+    // Cubic Bézier through (0,0) and (1,1) with two control points, like CSS
+    // `cubic-bezier()`. x = segment time, y = share of the value change.
+    CubicBezier { x1: f32, y1: f32, x2: f32, y2: f32 },
+    Sine,             // Half-cosine S-curve, flat at both ends
+    Exponential(f32), // Curvature: > 0 starts slow, < 0 starts fast, 0 = linear
+}
+
+impl CurveType {
+    /// Progress 0-1 through the value change at segment time `t` (0-1)
+    pub fn shape(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            CurveType::Linear => t,
+            CurveType::Step => 0.0,
+            CurveType::Bezier(tension) => {
+                let exponent = if tension >= 0.0 {
+                    1.0 + tension * 4.0
+                } else {
+                    1.0 / (1.0 + tension.abs() * 4.0)
+                };
+                t.powf(exponent)
+            },
+            CurveType::CubicBezier { x1, y1, x2, y2 } => cubic_bezier(t, x1, y1, x2, y2),
+            CurveType::Sine => 0.5 - 0.5 * (t * std::f32::consts::PI).cos(),
+            CurveType::Exponential(k) => {
+                if k.abs() < 1e-3 {
+                    t
+                } else {
+                    ((k * t).exp() - 1.0) / (k.exp() - 1.0)
+                }
+            }
+        }
+    }
+}
+
+// y at the point where the curve's x equals `u`
+fn cubic_bezier(u: f32, x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+    // Control x inside 0-1 keeps x(t) monotonic, so there's one solution
+    let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+    let bez = |t: f32, p1: f32, p2: f32| {
+        let mt = 1.0 - t;
+        3.0 * mt * mt * t * p1 + 3.0 * mt * t * t * p2 + t * t * t
+    };
+    let slope = |t: f32, p1: f32, p2: f32| {
+        let mt = 1.0 - t;
+        3.0 * mt * mt * p1 + 6.0 * mt * t * (p2 - p1) + 3.0 * t * t * (1.0 - p2)
+    };
+
+    // Newton first, bisection if the slope gets too flat
+    let mut t = u;
+    for _ in 0..8 {
+        let err = bez(t, x1, x2) - u;
+        if err.abs() < 1e-6 { return bez(t, y1, y2); }
+        let d = slope(t, x1, x2);
+        if d.abs() < 1e-6 { break; }
+        t = (t - err / d).clamp(0.0, 1.0);
+    }
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    t = u;
+    for _ in 0..32 {
+        let x = bez(t, x1, x2);
+        if (x - u).abs() < 1e-6 { break; }
+        if x < u { lo = t; } else { hi = t; }
+        t = (lo + hi) * 0.5;
+    }
+    bez(t, y1, y2)
+}
+
+// Straight pieces a cut curved segment is redrawn with
+const CUT_STEPS: usize = 32;
+
+// Shapes that straight lines between the ends don't reproduce
+fn is_curved(curve: CurveType) -> bool {
+    !matches!(curve, CurveType::Linear | CurveType::Step)
+}
+
+// Value at `time` on the segment from `a` to `b`
+fn segment_value(a: AutomationPoint, b: AutomationPoint, time: f64) -> f32 {
+    let span = b.time - a.time;
+    if span <= 0.0 { return b.value; }
+    let t = ((time - a.time) / span).clamp(0.0, 1.0) as f32;
+    a.value + (b.value - a.value) * a.curve.shape(t)
+}
+
+// How a lane reacts to parameter changes while the transport runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AutomationMode {
+    #[default]
+    Read,  // Play the lane back, changes are not recorded
+    Write, // Overwrite the lane for the whole pass with the control's value
+    Touch, // Overwrite while the control is held, then read again
+    Latch, // Overwrite from the first change until the transport stops
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -17,10 +111,24 @@ pub struct AutomationPoint {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutomationLane {
     pub points: Vec<AutomationPoint>,
-    // In a real system, we might cache the last accessed index for performance
+    // Segment of the previous `value_at_cursor` lookup
     #[serde(skip)]
     pub last_index: usize, 
     pub target: String, // e.g., "volume", "pan", "fx/0/mix"
+    // Armed by the engine at runtime, saved lanes always load in Read
+    #[serde(skip)]
+    pub mode: AutomationMode,
+}
+
+/// One recorded pass over a lane, thinned. The engine merges it into its
+/// own lane and hands it out so the project copy can be updated the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAutomation {
+    pub track_id: Option<u32>, // None = master lane
+    pub target: String,
+    pub start: f64,
+    pub end: f64,
+    pub points: Vec<AutomationPoint>,
 }
 
 impl AutomationLane {
@@ -29,13 +137,15 @@ impl AutomationLane {
             points: Vec::new(),
             last_index: 0,
             target,
+            mode: AutomationMode::Read,
         }
     }
 
     pub fn add_point(&mut self, time: f64, value: f32, curve: CurveType) {
+        if time.is_nan() { return; }
         // Insert maintaining sort order
         let point = AutomationPoint { time, value, curve };
-        match self.points.binary_search_by(|p| p.time.partial_cmp(&time).unwrap()) {
+        match self.points.binary_search_by(|p| p.time.total_cmp(&time)) {
             Ok(pos) => self.points[pos] = point, // Replace if exact time match
             Err(pos) => self.points.insert(pos, point),
         }
@@ -45,50 +155,232 @@ impl AutomationLane {
         if self.points.is_empty() {
             return 0.0; // Default value logic might belong elsewhere (or return Option)
         }
+        // Index of the last point at or before `time` (NaN lands before the first point)
+        let idx = self.points.partition_point(|p| p.time <= time);
+        self.value_in_segment(idx.saturating_sub(1), time)
+    }
 
-        // Before first point
-        if time <= self.points[0].time {
-            return self.points[0].value;
+    /// Same as `get_value_at`, but walks forward from the previous lookup
+    /// instead of searching, so sequential playback is O(1) per call.
+    pub fn value_at_cursor(&mut self, time: f64) -> f32 {
+        if self.points.is_empty() {
+            return 0.0;
         }
-
-        // After last point
-        let last_idx = self.points.len() - 1;
-        if time >= self.points[last_idx].time {
-            return self.points[last_idx].value;
+        let mut idx = self.last_index.min(self.points.len() - 1);
+        if time < self.points[idx].time || time.is_nan() {
+            // Jumped backwards (seek, loop): search again
+            idx = self.points.partition_point(|p| p.time <= time).saturating_sub(1);
+        } else {
+            while idx + 1 < self.points.len() && self.points[idx + 1].time <= time {
+                idx += 1;
+            }
         }
+        self.last_index = idx;
+        self.value_in_segment(idx, time)
+    }
 
-        // Binary search for the segment
-        // We want the point P1 such that P1.time <= time < P2.time
-        // self.points is sorted.
-        let idx = match self.points.binary_search_by(|p| p.time.partial_cmp(&time).unwrap()) {
-            Ok(i) => i,
-            Err(i) => i - 1, // Insertion point is after P1, so P1 is i-1
-        };
-
-        if idx >= last_idx {
-            return self.points[last_idx].value;
+    /// Fill `out` with one value per sample, starting at `start` seconds and
+    /// advancing `step` seconds per sample. Does not allocate.
+    pub fn render(&mut self, start: f64, step: f64, out: &mut [f32]) {
+        for (i, value) in out.iter_mut().enumerate() {
+            *value = self.value_at_cursor(start + step * i as f64);
         }
+    }
 
+    // Value between point `idx` and the next one
+    fn value_in_segment(&self, idx: usize, time: f64) -> f32 {
         let p1 = &self.points[idx];
+
+        // Before first point, after last point or unknown time: hold
+        if idx + 1 >= self.points.len() || time.is_nan() || time <= p1.time {
+            return p1.value;
+        }
         let p2 = &self.points[idx + 1];
+        let span = p2.time - p1.time;
+        if span <= 0.0 {
+            return p2.value;
+        }
 
-        let t = (time - p1.time) / (p2.time - p1.time); // Normalized time 0.0 - 1.0
+        let t = ((time - p1.time) / span).clamp(0.0, 1.0) as f32; // Normalized time 0.0 - 1.0
+        p1.value + (p2.value - p1.value) * p1.curve.shape(t)
+    }
 
-        match p1.curve {
-            CurveType::Step => p1.value,
-            CurveType::Linear => p1.value + (p2.value - p1.value) * t as f32,
-            CurveType::Bezier(tension) => {
-                // Simple quadratic bezier approximation for tension
-                let t_f32 = t as f32;
-                let exponent = if tension >= 0.0 {
-                    1.0 + tension * 4.0 
-                } else {
-                    1.0 / (1.0 + tension.abs() * 4.0)
-                };
-                
-                let curved_t = t_f32.powf(exponent);
-                p1.value + (p2.value - p1.value) * curved_t
+    // --- Editing ---
+
+    pub fn remove_point(&mut self, index: usize) -> Option<AutomationPoint> {
+        (index < self.points.len()).then(|| self.points.remove(index))
+    }
+
+    /// Move a point to a new time/value, keeping its curve. A point already
+    /// at the new time is replaced. Returns the point's new index.
+    pub fn move_point(&mut self, index: usize, time: f64, value: f32) -> Option<usize> {
+        if index >= self.points.len() || time.is_nan() { return None; }
+        let curve = self.points.remove(index).curve;
+        self.add_point(time, value, curve);
+        self.points.binary_search_by(|p| p.time.total_cmp(&time)).ok()
+    }
+
+    /// Remove all points in `start..=end`. The values at both edges are
+    /// anchored first, so the lane outside the range doesn't change. A
+    /// curved segment cut by an edge keeps its shape as straight lines.
+    pub fn delete_range(&mut self, start: f64, end: f64) {
+        if start.is_nan() || end.is_nan() || start > end || self.points.is_empty() { return; }
+        let (first, last) = (self.points[0].time, self.points[self.points.len() - 1].time);
+        if end < first || start > last { return; }
+
+        // Segments running into and out of the range
+        let n = self.points.len();
+        let before = self.points.partition_point(|p| p.time < start);
+        let lead_in = (before > 0 && before < n).then(|| (self.points[before - 1], self.points[before]));
+        let after = self.points.partition_point(|p| p.time <= end);
+        let lead_out = (after < n).then(|| (self.points[after - 1], self.points[after]));
+
+        self.points.retain(|p| p.time < start || p.time > end);
+        if let Some((a, b)) = lead_in {
+            if is_curved(a.curve) && b.time > start {
+                self.add_point(a.time, a.value, CurveType::Linear);
+                self.add_cut(a, b, a.time, start);
+            }
+            self.add_point(start, segment_value(a, b, start), CurveType::Linear);
+        }
+        if let Some((a, b)) = lead_out {
+            if is_curved(a.curve) && a.time < end {
+                self.add_point(end, segment_value(a, b, end), CurveType::Linear);
+                self.add_cut(a, b, end, b.time);
+            } else {
+                self.add_point(end, segment_value(a, b, end), a.curve);
             }
         }
     }
+
+    /// The span `delete_range(start, end)` rewrites, wider than the range
+    /// where it redraws a cut curved segment
+    pub fn delete_span(&self, start: f64, end: f64) -> (f64, f64) {
+        let n = self.points.len();
+        let before = self.points.partition_point(|p| p.time < start);
+        let cut_in = before > 0 && before < n
+            && is_curved(self.points[before - 1].curve) && self.points[before].time > start;
+        let from = if cut_in { self.points[before - 1].time } else { start };
+
+        let after = self.points.partition_point(|p| p.time <= end);
+        let cut_out = after > 0 && after < n
+            && is_curved(self.points[after - 1].curve) && self.points[after - 1].time < end;
+        let to = if cut_out { self.points[after].time } else { end };
+        (from, to)
+    }
+
+    // Straight-line points following the segment `a..b` strictly inside `from..to`
+    fn add_cut(&mut self, a: AutomationPoint, b: AutomationPoint, from: f64, to: f64) {
+        for i in 1..CUT_STEPS {
+            let time = from + (to - from) * i as f64 / CUT_STEPS as f64;
+            self.add_point(time, segment_value(a, b, time), CurveType::Linear);
+        }
+    }
+
+    /// `value * scale + offset` for points in `start..=end`, clamped to 0-1
+    pub fn scale_offset(&mut self, start: f64, end: f64, scale: f32, offset: f32) {
+        for p in self.points.iter_mut().filter(|p| p.time >= start && p.time <= end) {
+            p.value = (p.value * scale + offset).clamp(0.0, 1.0);
+        }
+    }
+
+    /// Points in `start..=end`, with times relative to `start`
+    pub fn copy_range(&self, start: f64, end: f64) -> Vec<AutomationPoint> {
+        self.points.iter()
+            .filter(|p| p.time >= start && p.time <= end)
+            .map(|p| AutomationPoint { time: p.time - start, ..*p })
+            .collect()
+    }
+
+    /// Insert copied points shifted to `at`, replacing what was in that span
+    pub fn paste(&mut self, points: &[AutomationPoint], at: f64) {
+        let Some(span) = points.iter().map(|p| p.time).filter(|t| !t.is_nan()).reduce(f64::max) else { return };
+        self.points.retain(|p| p.time < at || p.time > at + span);
+        for p in points {
+            self.add_point(p.time + at, p.value, p.curve);
+        }
+    }
+
+    /// Drop points that a straight line between their neighbours reproduces
+    /// within `tolerance` (value units). Non-linear segments are kept as is.
+    pub fn thin(&mut self, tolerance: f32) {
+        self.thin_with(tolerance, &mut Vec::new());
+    }
+
+    /// `thin` with a caller's scratch buffer, doesn't allocate when `keep`
+    /// has room for every point
+    pub fn thin_with(&mut self, tolerance: f32, keep: &mut Vec<bool>) {
+        let n = self.points.len();
+        if n < 3 { return; }
+
+        // Ends of curved segments must stay, they define the shape
+        keep.clear();
+        keep.resize(n, false);
+        keep[0] = true;
+        keep[n - 1] = true;
+        for i in 0..n - 1 {
+            if self.points[i].curve != CurveType::Linear {
+                keep[i] = true;
+                keep[i + 1] = true;
+            }
+        }
+
+        // Douglas-Peucker between each pair of fixed points
+        let mut start = 0;
+        for end in 1..n {
+            if keep[end] {
+                self.simplify(start, end, tolerance, keep);
+                start = end;
+            }
+        }
+
+        let mut i = 0;
+        self.points.retain(|_| {
+            i += 1;
+            keep[i - 1]
+        });
+        self.last_index = 0;
+    }
+
+    fn simplify(&self, first: usize, last: usize, tolerance: f32, keep: &mut [bool]) {
+        if last <= first + 1 { return; }
+        let (a, b) = (&self.points[first], &self.points[last]);
+        let span = b.time - a.time;
+
+        let mut worst = (first, 0.0f32);
+        for i in first + 1..last {
+            let p = &self.points[i];
+            let t = if span > 0.0 { ((p.time - a.time) / span) as f32 } else { 0.0 };
+            let error = (p.value - (a.value + (b.value - a.value) * t)).abs();
+            if error > worst.1 {
+                worst = (i, error);
+            }
+        }
+
+        if worst.1 > tolerance {
+            keep[worst.0] = true;
+            self.simplify(first, worst.0, tolerance, keep);
+            self.simplify(worst.0, last, tolerance, keep);
+        }
+    }
+
+    /// Replace `start..=end` with a recorded pass
+    pub fn merge_recording(&mut self, start: f64, end: f64, points: &[AutomationPoint]) {
+        self.delete_range(start, end);
+        for p in points {
+            self.add_point(p.time, p.value, p.curve);
+        }
+        self.last_index = 0;
+    }
+
+    /// Lane from a recorded `(time, value)` stream (fader moves), thinned to
+    /// the points needed to stay within `tolerance`
+    pub fn from_stream(target: String, stream: &[(f64, f32)], tolerance: f32) -> Self {
+        let mut lane = Self::new(target);
+        for &(time, value) in stream {
+            lane.add_point(time, value, CurveType::Linear);
+        }
+        lane.thin(tolerance);
+        lane
+    }
 }
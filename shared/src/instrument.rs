@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use crate::Effect;
+
+// Pads in a new drum rack, starting at GM kick (C1 = 36)
+pub const DEFAULT_DRUM_PADS: usize = 16;
+const FIRST_DRUM_NOTE: u8 = 36;
+
+// Instrument that plays a track's MIDI clips
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum Instrument {
+    Synth,
+    Granular {
+        asset_id: String,
+        #[serde(default)]
+        params: GranularParams,
+    },
+    Sampler {
+        #[serde(default)]
+        zones: Vec<SampleZone>,
+        #[serde(default = "EnvelopeParams::sampler")]
+        envelope: EnvelopeParams,
+    },
+    DrumRack {
+        #[serde(default = "DrumPad::default_kit")]
+        pads: Vec<DrumPad>,
+    },
+}
+
+/// Settings for a DAHDSR envelope.
+/// Curves are -1.0 to 1.0: 0.0 is linear, positive moves fast then settles
+/// (analog-style exponential), negative starts slow.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvelopeParams {
+    pub delay_ms: f32,
+    pub attack_ms: f32,
+    pub hold_ms: f32,
+    pub decay_ms: f32,
+    pub sustain_level: f32, // 0.0 - 1.0
+    pub release_ms: f32,
+
+    pub attack_curve: f32,
+    pub decay_curve: f32,
+    pub release_curve: f32,
+
+    pub velocity_sens: f32, // 0.0 = ignore velocity, 1.0 = full scaling
+    pub looping: bool,      // Cycle Attack -> Decay while the gate is held
+}
+
+impl Default for EnvelopeParams {
+    fn default() -> Self {
+        Self {
+            delay_ms: 0.0,
+            attack_ms: 10.0,
+            hold_ms: 0.0,
+            decay_ms: 100.0,
+            sustain_level: 0.7,
+            release_ms: 200.0,
+            attack_curve: 0.0,
+            decay_curve: 0.6,
+            release_curve: 0.6,
+            velocity_sens: 0.0,
+            looping: false,
+        }
+    }
+}
+
+impl EnvelopeParams {
+    /// Plain ADSR with the other stages disabled
+    pub fn adsr(attack_ms: f32, decay_ms: f32, sustain_level: f32, release_ms: f32) -> Self {
+        Self {
+            attack_ms,
+            decay_ms,
+            sustain_level,
+            release_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Gate-style envelope for sample playback: full level while held
+    pub fn sampler() -> Self {
+        Self {
+            velocity_sens: 1.0,
+            ..Self::adsr(2.0, 0.0, 1.0, 150.0)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum GrainWindow {
+    Hann,
+    Triangle,
+    Tukey, // Flat top with cosine edges
+    Gaussian,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GranularParams {
+    pub position: f32,     // 0.0 - 1.0 through the asset
+    pub density: f32,      // Grains per second per note
+    pub size_ms: f32,
+    pub spray_ms: f32,     // Random start offset
+    pub pitch_jitter: f32, // Semitones
+    pub pan_spread: f32,   // 0.0 - 1.0
+    pub window: GrainWindow,
+    pub reverse_prob: f32, // 0.0 - 1.0
+    pub root_note: u8,     // Note that plays the asset at original pitch
+    pub seed: u64,         // RNG seed, keeps renders reproducible
+}
+
+impl Default for GranularParams {
+    fn default() -> Self {
+        Self {
+            position: 0.0,
+            density: 20.0,
+            size_ms: 80.0,
+            spray_ms: 10.0,
+            pitch_jitter: 0.0,
+            pan_spread: 0.5,
+            window: GrainWindow::Hann,
+            reverse_prob: 0.0,
+            root_note: 60,
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LoopMode {
+    Off,     // Play start -> end once
+    Forward, // Jump back to loop_start at loop_end until the voice is done
+}
+
+/// One sample mapped onto a key and velocity range.
+/// Positions are in samples of the source asset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SampleZone {
+    pub asset_id: String,
+    pub key_low: u8,
+    pub key_high: u8,
+    pub vel_low: u8,
+    pub vel_high: u8,
+    pub root_note: u8,       // Key that plays the asset at original pitch
+    pub fine_tune: f32,      // Cents
+    pub gain_db: f32,
+    pub pan: f32,            // -1.0 to 1.0
+    pub start: u64,
+    pub end: u64,            // 0 = end of asset
+    pub loop_mode: LoopMode,
+    pub loop_start: u64,
+    pub loop_end: u64,
+    pub crossfade: u64,      // Loop crossfade length
+    pub round_robin_group: u8, // 0 = always plays, else rotates with zones of the same group
+}
+
+impl Default for SampleZone {
+    fn default() -> Self {
+        Self {
+            asset_id: String::new(),
+            key_low: 0,
+            key_high: 127,
+            vel_low: 0,
+            vel_high: 127,
+            root_note: 60,
+            fine_tune: 0.0,
+            gain_db: 0.0,
+            pan: 0.0,
+            start: 0,
+            end: 0,
+            loop_mode: LoopMode::Off,
+            loop_start: 0,
+            loop_end: 0,
+            crossfade: 0,
+            round_robin_group: 0,
+        }
+    }
+}
+
+impl SampleZone {
+    pub fn contains(&self, note: u8, velocity: u8) -> bool {
+        (self.key_low..=self.key_high).contains(&note)
+            && (self.vel_low..=self.vel_high).contains(&velocity)
+    }
+}
+
+/// One pad of a drum rack, triggered by a single MIDI note
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DrumPad {
+    pub note: u8,
+    pub asset_id: String, // Empty = silent pad
+    pub gain_db: f32,
+    pub pan: f32,   // -1.0 to 1.0
+    pub pitch: f32, // Semitones
+    pub envelope: EnvelopeParams,
+    pub one_shot: bool,         // Ignore note off and play to the end
+    pub choke_group: u8,        // 0 = none, else a hit silences the group's other pads
+    pub output_bus: Option<u32>, // None = the rack's own track
+    pub effects: Vec<Effect>,   // Per-pad inserts
+}
+
+impl Default for DrumPad {
+    fn default() -> Self {
+        Self {
+            note: FIRST_DRUM_NOTE,
+            asset_id: String::new(),
+            gain_db: 0.0,
+            pan: 0.0,
+            pitch: 0.0,
+            envelope: EnvelopeParams::sampler(),
+            one_shot: true,
+            choke_group: 0,
+            output_bus: None,
+            effects: Vec::new(),
+        }
+    }
+}
+
+impl DrumPad {
+    /// Empty pads on consecutive notes from C1
+    pub fn default_kit() -> Vec<DrumPad> {
+        (0..DEFAULT_DRUM_PADS)
+            .map(|i| DrumPad { note: FIRST_DRUM_NOTE + i as u8, ..Default::default() })
+            .collect()
+    }
+}
@@ -2,6 +2,12 @@ mod project;
 pub use project::*;
 pub mod automation;
 pub use automation::*;
+mod instrument;
+pub use instrument::*;
+mod param;
+pub use param::*;
+mod tempo;
+pub use tempo::*;
 
 use serde::{Deserialize, Serialize};
 
@@ -22,6 +28,9 @@ pub enum Action {
     Play,
     Stop,
     Seek(f64), // Seconds
+
+    // Automation
+    RecordAutomation(RecordedAutomation), // Written by the engine during playback
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -49,7 +58,52 @@ pub enum MixerCommand {
     Stop,
     
     // One-Shot
-    TriggerSample { asset_id: String },
+    TriggerSample {
+        asset_id: String,
+        #[serde(default, flatten)]
+        options: SampleTrigger,
+    },
+    ChokeSample { asset_id: String }, // Fade out every voice playing this asset
+    StopAllSamples,
+
+    // Automation. `target` is a lane path (see `ParamAddress`), track_id None = master lanes
+    SetParam {
+        #[serde(default)]
+        track_id: Option<u32>,
+        target: String,
+        value: f32, // Normalized 0-1
+    },
+    SetAutomationMode {
+        #[serde(default)]
+        track_id: Option<u32>,
+        target: String,
+        mode: AutomationMode,
+    },
+    ReleaseParam { // Control let go, ends a touch pass
+        #[serde(default)]
+        track_id: Option<u32>,
+        target: String,
+    },
+}
+
+// When a soundboard trigger starts
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum TriggerQuantize {
+    #[default]
+    Off,
+    Beat,
+    Bar,
+}
+
+/// Per-trigger settings for a one-shot sample
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct SampleTrigger {
+    pub gain_db: f32,
+    pub pan: f32,          // -1.0 to 1.0
+    pub pitch: f32,        // Semitones
+    pub start_offset: u64, // Samples into the asset
+    pub quantize: TriggerQuantize,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
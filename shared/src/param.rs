@@ -0,0 +1,395 @@
+use std::fmt;
+
+// Automation addresses. Lanes store normalized 0-1 values; `ParamRange` maps
+// them to real units (dB, Hz, ms...) before they reach the engine.
+//
+// Track lanes:
+//   "gain" | "volume", "pan", "filter", "send/<bus_id>"
+//   "fx/<slot>/<field>"                 any `Effect` field, e.g. "fx/0/mix"
+//   "fx/<slot>/bands/<n>/<field>"       parametric EQ band (freq, q, gain_db, enabled)
+//   "synth/morph" | "synth/cutoff" | "synth/resonance"
+//   "synth/env/<n>/<field>"             delay_ms, attack_ms, hold_ms, decay_ms, sustain_level, release_ms
+//   "synth/lfo/<n>/rate", "synth/global_lfo/<n>/rate"
+//   "granular/<field>"
+// Master lanes (`Project::master_automation`):
+//   "master/gain", "bus/<id>/gain", "bus/<id>/pan", "bus/<id>/fx/<slot>/<field>"
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamScale {
+    Linear,
+    Exponential, // Equal ratios per step (frequencies, times)
+    Decibel,     // Linear in dB, bottom of the range is silence
+    Stepped,     // Rounded to whole numbers (enum choices)
+    Toggle,      // Off below 0.5, on above
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamRange {
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub scale: ParamScale,
+    pub unit: &'static str,
+}
+
+impl ParamRange {
+    pub const fn new(min: f32, max: f32, default: f32, scale: ParamScale, unit: &'static str) -> Self {
+        Self { min, max, default, scale, unit }
+    }
+
+    /// Normalized 0-1 lane value to real units
+    pub fn denormalize(&self, normalized: f32) -> f32 {
+        // NaN lanes fall back to the bottom of the range
+        let n = if normalized.is_nan() { 0.0 } else { normalized.clamp(0.0, 1.0) };
+        match self.scale {
+            ParamScale::Linear => self.min + (self.max - self.min) * n,
+            ParamScale::Exponential => self.min * (self.max / self.min).powf(n),
+            ParamScale::Decibel => {
+                if n <= 0.0 { f32::NEG_INFINITY } else { self.min + (self.max - self.min) * n }
+            },
+            ParamScale::Stepped => (self.min + (self.max - self.min) * n).round(),
+            ParamScale::Toggle => if n >= 0.5 { self.max } else { self.min },
+        }
+    }
+
+    /// Real value to a normalized 0-1 lane value
+    pub fn normalize(&self, value: f32) -> f32 {
+        let n = match self.scale {
+            ParamScale::Exponential => (value / self.min).ln() / (self.max / self.min).ln(),
+            ParamScale::Decibel if value <= self.min => 0.0,
+            _ => (value - self.min) / (self.max - self.min),
+        };
+        if n.is_nan() { 0.0 } else { n.clamp(0.0, 1.0) }
+    }
+}
+
+/// Effect fields, named after the `Effect` variant fields
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EffectParam {
+    LowGain,
+    MidGain,
+    HighGain,
+    Threshold,
+    Ratio,
+    Attack,
+    Release,
+    MakeupGain,
+    TimeMs,
+    Feedback,
+    Mix,
+    Decay,
+    Boost,
+    Cutoff,
+    Drive,
+    Width,
+    BandFreq(u8),
+    BandQ(u8),
+    BandGain(u8),
+    BandEnabled(u8),
+}
+
+impl EffectParam {
+    fn parse(parts: &[&str]) -> Option<Self> {
+        Some(match parts {
+            ["low_gain"] => Self::LowGain,
+            ["mid_gain"] => Self::MidGain,
+            ["high_gain"] => Self::HighGain,
+            ["threshold"] => Self::Threshold,
+            ["ratio"] => Self::Ratio,
+            ["attack"] => Self::Attack,
+            ["release"] => Self::Release,
+            ["makeup_gain"] => Self::MakeupGain,
+            ["time_ms"] => Self::TimeMs,
+            ["feedback"] => Self::Feedback,
+            ["mix"] => Self::Mix,
+            ["decay"] => Self::Decay,
+            ["boost"] => Self::Boost,
+            ["cutoff"] => Self::Cutoff,
+            ["drive"] => Self::Drive,
+            ["width"] => Self::Width,
+            ["bands", band, field] => {
+                let band = band.parse().ok()?;
+                match *field {
+                    "freq" => Self::BandFreq(band),
+                    "q" => Self::BandQ(band),
+                    "gain_db" => Self::BandGain(band),
+                    "enabled" => Self::BandEnabled(band),
+                    _ => return None,
+                }
+            },
+            _ => return None,
+        })
+    }
+
+    pub fn range(&self) -> ParamRange {
+        use ParamScale::*;
+        match self {
+            Self::LowGain | Self::MidGain | Self::HighGain => ParamRange::new(-24.0, 24.0, 0.0, Linear, "dB"),
+            Self::Threshold => ParamRange::new(-60.0, 0.0, -20.0, Linear, "dB"),
+            Self::Ratio => ParamRange::new(1.0, 20.0, 4.0, Exponential, ":1"),
+            Self::Attack => ParamRange::new(0.1, 200.0, 10.0, Exponential, "ms"),
+            Self::Release => ParamRange::new(5.0, 2000.0, 100.0, Exponential, "ms"),
+            Self::MakeupGain => ParamRange::new(0.0, 24.0, 0.0, Linear, "dB"),
+            Self::TimeMs => ParamRange::new(1.0, 2000.0, 300.0, Exponential, "ms"),
+            Self::Feedback => ParamRange::new(0.0, 0.95, 0.4, Linear, ""),
+            Self::Mix => ParamRange::new(0.0, 1.0, 0.5, Linear, ""),
+            Self::Decay => ParamRange::new(0.1, 20.0, 2.0, Exponential, "s"),
+            Self::Boost => ParamRange::new(0.0, 24.0, 6.0, Linear, "dB"),
+            Self::Cutoff => ParamRange::new(20.0, 500.0, 100.0, Exponential, "Hz"),
+            Self::Drive => ParamRange::new(0.0, 100.0, 0.0, Linear, "%"),
+            Self::Width => ParamRange::new(0.0, 1.0, 1.0, Linear, ""),
+            Self::BandFreq(_) => ParamRange::new(20.0, 20000.0, 1000.0, Exponential, "Hz"),
+            Self::BandQ(_) => ParamRange::new(0.1, 18.0, 0.707, Exponential, ""),
+            Self::BandGain(_) => ParamRange::new(-24.0, 24.0, 0.0, Linear, "dB"),
+            Self::BandEnabled(_) => ParamRange::new(0.0, 1.0, 1.0, Toggle, ""),
+        }
+    }
+}
+
+impl fmt::Display for EffectParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::LowGain => "low_gain",
+            Self::MidGain => "mid_gain",
+            Self::HighGain => "high_gain",
+            Self::Threshold => "threshold",
+            Self::Ratio => "ratio",
+            Self::Attack => "attack",
+            Self::Release => "release",
+            Self::MakeupGain => "makeup_gain",
+            Self::TimeMs => "time_ms",
+            Self::Feedback => "feedback",
+            Self::Mix => "mix",
+            Self::Decay => "decay",
+            Self::Boost => "boost",
+            Self::Cutoff => "cutoff",
+            Self::Drive => "drive",
+            Self::Width => "width",
+            Self::BandFreq(n) => return write!(f, "bands/{}/freq", n),
+            Self::BandQ(n) => return write!(f, "bands/{}/q", n),
+            Self::BandGain(n) => return write!(f, "bands/{}/gain_db", n),
+            Self::BandEnabled(n) => return write!(f, "bands/{}/enabled", n),
+        };
+        f.write_str(name)
+    }
+}
+
+/// Fields of `EnvelopeParams` that can be automated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnvelopeField {
+    Delay,
+    Attack,
+    Hold,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SynthParam {
+    Morph,
+    Cutoff,
+    Resonance,
+    Envelope(u8, EnvelopeField),
+    LfoRate(u8),
+    GlobalLfoRate(u8),
+}
+
+impl SynthParam {
+    fn parse(parts: &[&str]) -> Option<Self> {
+        Some(match parts {
+            ["morph"] => Self::Morph,
+            ["cutoff"] => Self::Cutoff,
+            ["resonance"] => Self::Resonance,
+            ["env", n, field] => {
+                let field = match *field {
+                    "delay_ms" => EnvelopeField::Delay,
+                    "attack_ms" => EnvelopeField::Attack,
+                    "hold_ms" => EnvelopeField::Hold,
+                    "decay_ms" => EnvelopeField::Decay,
+                    "sustain_level" => EnvelopeField::Sustain,
+                    "release_ms" => EnvelopeField::Release,
+                    _ => return None,
+                };
+                Self::Envelope(n.parse().ok()?, field)
+            },
+            ["lfo", n, "rate"] => Self::LfoRate(n.parse().ok()?),
+            ["global_lfo", n, "rate"] => Self::GlobalLfoRate(n.parse().ok()?),
+            _ => return None,
+        })
+    }
+
+    pub fn range(&self) -> ParamRange {
+        use ParamScale::*;
+        match self {
+            Self::Morph => ParamRange::new(0.0, 1.0, 0.0, Linear, ""),
+            Self::Cutoff => ParamRange::new(20.0, 20000.0, 2000.0, Exponential, "Hz"),
+            Self::Resonance => ParamRange::new(0.5, 10.0, 0.7, Exponential, ""),
+            Self::Envelope(_, EnvelopeField::Sustain) => ParamRange::new(0.0, 1.0, 0.7, Linear, ""),
+            Self::Envelope(_, EnvelopeField::Delay | EnvelopeField::Hold) => ParamRange::new(0.0, 5000.0, 0.0, Linear, "ms"),
+            Self::Envelope(..) => ParamRange::new(0.1, 10000.0, 100.0, Exponential, "ms"),
+            Self::LfoRate(_) | Self::GlobalLfoRate(_) => ParamRange::new(0.01, 40.0, 1.0, Exponential, "Hz"),
+        }
+    }
+}
+
+impl fmt::Display for SynthParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Morph => f.write_str("morph"),
+            Self::Cutoff => f.write_str("cutoff"),
+            Self::Resonance => f.write_str("resonance"),
+            Self::Envelope(n, field) => {
+                let name = match field {
+                    EnvelopeField::Delay => "delay_ms",
+                    EnvelopeField::Attack => "attack_ms",
+                    EnvelopeField::Hold => "hold_ms",
+                    EnvelopeField::Decay => "decay_ms",
+                    EnvelopeField::Sustain => "sustain_level",
+                    EnvelopeField::Release => "release_ms",
+                };
+                write!(f, "env/{}/{}", n, name)
+            },
+            Self::LfoRate(n) => write!(f, "lfo/{}/rate", n),
+            Self::GlobalLfoRate(n) => write!(f, "global_lfo/{}/rate", n),
+        }
+    }
+}
+
+/// Fields of `GranularParams` that can be automated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GranularParam {
+    Position,
+    Density,
+    Size,
+    Spray,
+    PitchJitter,
+    PanSpread,
+    Reverse,
+    Window,
+}
+
+impl GranularParam {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "position" => Self::Position,
+            "density" => Self::Density,
+            "size" | "size_ms" => Self::Size,
+            "spray" | "spray_ms" => Self::Spray,
+            "pitch_jitter" => Self::PitchJitter,
+            "pan_spread" => Self::PanSpread,
+            "reverse" | "reverse_prob" => Self::Reverse,
+            "window" => Self::Window,
+            _ => return None,
+        })
+    }
+
+    pub fn range(&self) -> ParamRange {
+        use ParamScale::*;
+        match self {
+            Self::Position => ParamRange::new(0.0, 1.0, 0.0, Linear, ""),
+            Self::Density => ParamRange::new(1.0, 100.0, 20.0, Linear, "grains/s"),
+            Self::Size => ParamRange::new(5.0, 500.0, 80.0, Linear, "ms"),
+            Self::Spray => ParamRange::new(0.0, 500.0, 10.0, Linear, "ms"),
+            Self::PitchJitter => ParamRange::new(0.0, 12.0, 0.0, Linear, "st"),
+            Self::PanSpread => ParamRange::new(0.0, 1.0, 0.5, Linear, ""),
+            Self::Reverse => ParamRange::new(0.0, 1.0, 0.0, Linear, ""),
+            Self::Window => ParamRange::new(0.0, 3.0, 0.0, Stepped, ""), // Hann, Triangle, Tukey, Gaussian
+        }
+    }
+}
+
+impl fmt::Display for GranularParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Position => "position",
+            Self::Density => "density",
+            Self::Size => "size",
+            Self::Spray => "spray",
+            Self::PitchJitter => "pitch_jitter",
+            Self::PanSpread => "pan_spread",
+            Self::Reverse => "reverse",
+            Self::Window => "window",
+        })
+    }
+}
+
+/// Parsed automation target, see the path list at the top of this file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamAddress {
+    TrackGain,
+    TrackPan,
+    TrackFilter, // DJ filter, -1 = low-pass sweep, 1 = high-pass sweep
+    Send { bus_id: u32 },
+    Effect { slot: u8, param: EffectParam },
+    Synth(SynthParam),
+    Granular(GranularParam),
+    MasterGain,
+    BusGain { bus_id: u32 },
+    BusPan { bus_id: u32 },
+    BusEffect { bus_id: u32, slot: u8, param: EffectParam },
+}
+
+impl ParamAddress {
+    pub fn parse(path: &str) -> Option<Self> {
+        let parts: Vec<&str> = path.split('/').collect();
+        Some(match parts.as_slice() {
+            ["gain"] | ["volume"] => Self::TrackGain,
+            ["pan"] => Self::TrackPan,
+            ["filter"] => Self::TrackFilter,
+            ["send", bus] => Self::Send { bus_id: bus.parse().ok()? },
+            ["fx", slot, rest @ ..] => Self::Effect {
+                slot: slot.parse().ok()?,
+                param: EffectParam::parse(rest)?,
+            },
+            ["synth", rest @ ..] => Self::Synth(SynthParam::parse(rest)?),
+            ["granular", name] => Self::Granular(GranularParam::parse(name)?),
+            ["master", "gain"] => Self::MasterGain,
+            ["bus", id, "gain"] => Self::BusGain { bus_id: id.parse().ok()? },
+            ["bus", id, "pan"] => Self::BusPan { bus_id: id.parse().ok()? },
+            ["bus", id, "fx", slot, rest @ ..] => Self::BusEffect {
+                bus_id: id.parse().ok()?,
+                slot: slot.parse().ok()?,
+                param: EffectParam::parse(rest)?,
+            },
+            _ => return None,
+        })
+    }
+
+    pub fn range(&self) -> ParamRange {
+        match self {
+            Self::TrackGain | Self::Send { .. } | Self::MasterGain | Self::BusGain { .. } => {
+                ParamRange::new(-60.0, 6.0, 0.0, ParamScale::Decibel, "dB")
+            },
+            Self::TrackPan | Self::TrackFilter | Self::BusPan { .. } => {
+                ParamRange::new(-1.0, 1.0, 0.0, ParamScale::Linear, "")
+            },
+            Self::Effect { param, .. } | Self::BusEffect { param, .. } => param.range(),
+            Self::Synth(param) => param.range(),
+            Self::Granular(param) => param.range(),
+        }
+    }
+
+    /// Lives on a track, as opposed to master/bus lanes
+    pub fn is_track_param(&self) -> bool {
+        !matches!(self, Self::MasterGain | Self::BusGain { .. } | Self::BusPan { .. } | Self::BusEffect { .. })
+    }
+}
+
+impl fmt::Display for ParamAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TrackGain => f.write_str("gain"),
+            Self::TrackPan => f.write_str("pan"),
+            Self::TrackFilter => f.write_str("filter"),
+            Self::Send { bus_id } => write!(f, "send/{}", bus_id),
+            Self::Effect { slot, param } => write!(f, "fx/{}/{}", slot, param),
+            Self::Synth(param) => write!(f, "synth/{}", param),
+            Self::Granular(param) => write!(f, "granular/{}", param),
+            Self::MasterGain => f.write_str("master/gain"),
+            Self::BusGain { bus_id } => write!(f, "bus/{}/gain", bus_id),
+            Self::BusPan { bus_id } => write!(f, "bus/{}/pan", bus_id),
+            Self::BusEffect { bus_id, slot, param } => write!(f, "bus/{}/fx/{}/{}", bus_id, slot, param),
+        }
+    }
+}
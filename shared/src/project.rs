@@ -1,28 +1,121 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
+// Saved data format, `Project::migrate` brings older files up to it
+pub const PROJECT_VERSION: u32 = 1;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Project {
+    // 0 for files saved before the format was versioned
+    #[serde(default)]
+    pub version: u32,
     pub name: String,
     pub tempo: f32,
+    // Rate clip starts, lengths and offsets are counted in
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: u32,
+    // Tempo changes after the start, `tempo` holds until the first one
+    #[serde(default)]
+    pub tempo_map: Vec<crate::TempoChange>,
     pub tracks: Vec<TrackData>,
+    #[serde(default)]
+    pub buses: Vec<BusData>,
+    // Lanes for master and bus parameters ("master/gain", "bus/<id>/...")
+    #[serde(default)]
+    pub master_automation: Vec<crate::AutomationLane>,
+    // Arrangement loop, also what a loop export renders
+    #[serde(default)]
+    pub loop_region: Option<TimeRange>,
+}
+
+/// Stretch of the timeline in samples, `end` exclusive
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl TimeRange {
+    pub fn len(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl Project {
     pub fn new(name: &str) -> Self {
         Self {
+            version: PROJECT_VERSION,
             name: name.to_string(),
             tempo: 120.0,
+            sample_rate: default_sample_rate(),
+            tempo_map: Vec::new(),
             tracks: Vec::new(),
+            buses: Vec::new(),
+            master_automation: Vec::new(),
+            loop_region: None,
         }
     }
 
+    /// Update data saved by an older version, once
+    pub fn migrate(&mut self) {
+        if self.version < 1 {
+            // Track lanes held raw values (linear gain, -1..1 pan and filter),
+            // they are normalized to the parameter's range now
+            use crate::ParamAddress;
+            for lane in self.tracks.iter_mut().flat_map(|t| t.automation.iter_mut()) {
+                let Some(address) = ParamAddress::parse(&lane.target) else { continue };
+                let range = address.range();
+                for point in &mut lane.points {
+                    point.value = match address {
+                        ParamAddress::TrackGain => range.normalize(crate::linear_to_db(point.value)),
+                        ParamAddress::TrackPan | ParamAddress::TrackFilter => range.normalize(point.value),
+                        _ => point.value,
+                    };
+                }
+            }
+        }
+        self.version = PROJECT_VERSION;
+    }
+
+    pub fn tempo_map(&self) -> crate::TempoMap {
+        crate::TempoMap::new(self.tempo, &self.tempo_map)
+    }
+
+    /// Every asset the project plays: clips, sampler zones, drum pads and
+    /// granular sources
+    pub fn asset_ids(&self) -> BTreeSet<&str> {
+        let mut ids = BTreeSet::new();
+        for track in &self.tracks {
+            for clip in &track.clips {
+                if let ClipData::Audio { asset_id, .. } = clip {
+                    ids.insert(asset_id.as_str());
+                }
+            }
+            match &track.instrument {
+                Some(crate::Instrument::Granular { asset_id, .. }) => { ids.insert(asset_id.as_str()); }
+                Some(crate::Instrument::Sampler { zones, .. }) => ids.extend(zones.iter().map(|z| z.asset_id.as_str())),
+                Some(crate::Instrument::DrumRack { pads }) => ids.extend(pads.iter().map(|p| p.asset_id.as_str())),
+                Some(crate::Instrument::Synth) | None => {}
+            }
+        }
+        // Empty ids are unassigned pads and zones
+        ids.remove("");
+        ids
+    }
+
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
     }
 
+    /// Parse a saved project, migrated to the current format
     pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str(json)
+        let mut project: Self = serde_json::from_str(json)?;
+        project.migrate();
+        Ok(project)
     }
 }
 
@@ -44,6 +137,33 @@ pub struct TrackData {
     pub effects: Vec<Effect>,
     #[serde(default)]
     pub automation: Vec<crate::AutomationLane>,
+    // None = default synth for MIDI clips
+    #[serde(default)]
+    pub instrument: Option<crate::Instrument>,
+    // None = master
+    #[serde(default)]
+    pub output_bus: Option<u32>,
+    #[serde(default)]
+    pub sends: Vec<SendData>,
+}
+
+// Post-fader copy of a track into a bus
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SendData {
+    pub bus_id: u32,
+    pub level_db: f32,
+}
+
+// Summing channel fed by tracks and drum pads, output goes to master
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BusData {
+    pub id: u32,
+    pub name: String,
+    pub gain_db: f32,
+    pub pan: f32,
+    pub muted: bool,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -60,6 +180,18 @@ pub enum ClipData {
         muted: bool,
         #[serde(default)]
         gain_db: f32,
+        // Timeline length / source length: 2.0 plays twice as long, same pitch
+        #[serde(default = "default_stretch")]
+        stretch: f32,
+        #[serde(default)]
+        transpose: f32, // Semitones, tempo unchanged
+        // Tempo the audio was recorded at. With this or warp markers set the
+        // clip is warped: it keeps its beats when the project tempo changes
+        // (`stretch` is then ignored)
+        #[serde(default)]
+        original_bpm: Option<f32>,
+        #[serde(default)]
+        warp_markers: Vec<crate::WarpMarker>,
     },
     Midi {
         id: u64,
@@ -74,6 +206,14 @@ pub enum ClipData {
     }
 }
 
+fn default_stretch() -> f32 {
+    1.0
+}
+
+fn default_sample_rate() -> u32 {
+    44100
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct MidiNoteData {
     pub start: u64,
@@ -113,5 +253,55 @@ pub enum Effect {
         drive: f32,
         #[serde(default)] 
         width: f32,
+    },
+    ParametricEq {
+        bands: Vec<EqBand>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum EqBandType {
+    Bell,
+    LowShelf,
+    HighShelf,
+    LowCut,  // High-pass, slope from `slope_db`
+    HighCut, // Low-pass, slope from `slope_db`
+    Notch,
+    BandPass,
+}
+
+// Which part of the stereo signal a band processes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum EqChannel {
+    Stereo,
+    Left,
+    Right,
+    Mid,
+    Side,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct EqBand {
+    pub kind: EqBandType,
+    pub freq: f32,
+    pub q: f32,
+    pub gain_db: f32,  // Bell and shelves only
+    pub slope_db: u8,  // 12, 24, 36 or 48 dB/oct for cuts
+    pub enabled: bool,
+    pub channel: EqChannel,
+}
+
+impl Default for EqBand {
+    fn default() -> Self {
+        Self {
+            kind: EqBandType::Bell,
+            freq: 1000.0,
+            q: 0.707,
+            gain_db: 0.0,
+            slope_db: 12,
+            enabled: true,
+            channel: EqChannel::Stereo,
+        }
     }
 }
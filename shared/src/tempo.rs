@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+// Used when a tempo is missing or not a number
+const FALLBACK_BPM: f64 = 120.0;
+
+/// Tempo from `beat` on, until the next change
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TempoChange {
+    pub beat: f64,
+    pub bpm: f32,
+}
+
+/// Pins a source sample of an audio clip to a beat, counted from the clip start
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WarpMarker {
+    pub sample: u64,
+    pub beat: f64,
+}
+
+/// Piecewise-constant tempo, converts between seconds and beats
+#[derive(Clone, Debug, PartialEq)]
+pub struct TempoMap {
+    // (start beat, start seconds, bpm), sorted, the first one starts at beat 0
+    segments: Vec<(f64, f64, f64)>,
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        Self::constant(FALLBACK_BPM as f32)
+    }
+}
+
+fn valid_bpm(bpm: f32) -> f64 {
+    if bpm.is_finite() { (bpm as f64).max(1.0) } else { FALLBACK_BPM }
+}
+
+impl TempoMap {
+    pub fn constant(bpm: f32) -> Self {
+        Self { segments: vec![(0.0, 0.0, valid_bpm(bpm))] }
+    }
+
+    /// `bpm` from beat 0, then each change. A change at beat 0 replaces `bpm`.
+    pub fn new(bpm: f32, changes: &[TempoChange]) -> Self {
+        let mut changes: Vec<TempoChange> = changes.iter().copied().filter(|c| c.beat.is_finite()).collect();
+        changes.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        let mut map = Self::constant(bpm);
+        for change in changes {
+            let (beat, seconds, tempo) = map.segments[map.segments.len() - 1];
+            let at = change.beat.max(0.0);
+            if at <= beat {
+                map.segments.last_mut().unwrap().2 = valid_bpm(change.bpm);
+            } else {
+                map.segments.push((at, seconds + (at - beat) * 60.0 / tempo, valid_bpm(change.bpm)));
+            }
+        }
+        map
+    }
+
+    pub fn beat_at(&self, seconds: f64) -> f64 {
+        let i = self.segments.partition_point(|s| s.1 <= seconds).saturating_sub(1);
+        let (beat, start, bpm) = self.segments[i];
+        beat + (seconds - start) * bpm / 60.0
+    }
+
+    pub fn seconds_at(&self, beat: f64) -> f64 {
+        let i = self.segments.partition_point(|s| s.0 <= beat).saturating_sub(1);
+        let (start, seconds, bpm) = self.segments[i];
+        seconds + (beat - start) * 60.0 / bpm
+    }
+
+    pub fn bpm_at(&self, beat: f64) -> f32 {
+        let i = self.segments.partition_point(|s| s.0 <= beat).saturating_sub(1);
+        self.segments[i].2 as f32
+    }
+
+    /// True when the tempo never changes
+    pub fn is_constant(&self) -> bool {
+        self.segments.len() == 1
+    }
+}
@@ -0,0 +1,219 @@
+use shared::{AutomationLane, AutomationPoint, CurveType};
+
+fn lane(points: &[(f64, f32)]) -> AutomationLane {
+    let mut lane = AutomationLane::new("gain".into());
+    for &(time, value) in points {
+        lane.add_point(time, value, CurveType::Linear);
+    }
+    lane
+}
+
+fn times(lane: &AutomationLane) -> Vec<f64> {
+    lane.points.iter().map(|p| p.time).collect()
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-4
+}
+
+#[test]
+fn curve_shapes_hit_both_ends() {
+    let curves = [
+        CurveType::Linear,
+        CurveType::Bezier(0.5),
+        CurveType::CubicBezier { x1: 0.42, y1: 0.0, x2: 0.58, y2: 1.0 },
+        CurveType::Sine,
+        CurveType::Exponential(4.0),
+        CurveType::Exponential(-4.0),
+    ];
+    for curve in curves {
+        assert!(close(curve.shape(0.0), 0.0), "{:?}", curve);
+        assert!(close(curve.shape(1.0), 1.0), "{:?}", curve);
+    }
+}
+
+#[test]
+fn step_and_hold_keep_the_first_value() {
+    assert_eq!(CurveType::Step.shape(0.99), 0.0);
+    let hold: CurveType = serde_json::from_str("\"Hold\"").unwrap();
+    assert_eq!(hold, CurveType::Step);
+}
+
+#[test]
+fn cubic_bezier_matches_known_values() {
+    // Control points on the diagonal = linear
+    let linear = CurveType::CubicBezier { x1: 0.25, y1: 0.25, x2: 0.75, y2: 0.75 };
+    for t in [0.1, 0.3, 0.5, 0.9] {
+        assert!(close(linear.shape(t), t));
+    }
+    // CSS ease-in-out is symmetric
+    let ease = CurveType::CubicBezier { x1: 0.42, y1: 0.0, x2: 0.58, y2: 1.0 };
+    assert!(close(ease.shape(0.5), 0.5));
+    assert!(ease.shape(0.1) < 0.1);
+    assert!(ease.shape(0.9) > 0.9);
+}
+
+#[test]
+fn sine_and_exponential_bend_the_right_way() {
+    assert!(close(CurveType::Sine.shape(0.5), 0.5));
+    assert!(CurveType::Sine.shape(0.25) < 0.25);
+    assert!(CurveType::Exponential(4.0).shape(0.5) < 0.5);
+    assert!(CurveType::Exponential(-4.0).shape(0.5) > 0.5);
+    assert!(close(CurveType::Exponential(0.0).shape(0.3), 0.3));
+}
+
+#[test]
+fn lane_uses_the_curve_of_the_segment_start() {
+    let mut lane = AutomationLane::new("pan".into());
+    lane.add_point(0.0, 0.0, CurveType::Sine);
+    lane.add_point(2.0, 1.0, CurveType::Linear);
+    assert!(close(lane.get_value_at(1.0), 0.5));
+    assert!(close(lane.get_value_at(0.5), CurveType::Sine.shape(0.25)));
+}
+
+#[test]
+fn nan_times_are_ignored() {
+    let mut lane = lane(&[(0.0, 0.2), (1.0, 0.8)]);
+    lane.add_point(f64::NAN, 1.0, CurveType::Linear);
+    assert_eq!(lane.points.len(), 2);
+    assert!(lane.get_value_at(f64::NAN).is_finite());
+    assert!(lane.value_at_cursor(f64::NAN).is_finite());
+    assert_eq!(lane.move_point(0, f64::NAN, 0.0), None);
+}
+
+#[test]
+fn cursor_lookup_matches_search() {
+    let mut lane = lane(&[(0.0, 0.0), (1.0, 1.0), (1.5, 0.25), (3.0, 0.75)]);
+    let reference = lane.clone();
+    // Forward, then a jump back like a loop or seek
+    for t in (0..400).chain(50..120).map(|i| i as f64 * 0.01) {
+        assert_eq!(lane.value_at_cursor(t), reference.get_value_at(t));
+    }
+}
+
+#[test]
+fn remove_and_move_points() {
+    let mut lane = lane(&[(0.0, 0.0), (1.0, 0.5), (2.0, 1.0)]);
+    let removed = lane.remove_point(1).unwrap();
+    assert_eq!(removed.time, 1.0);
+    assert!(lane.remove_point(5).is_none());
+
+    lane.add_point(1.0, 0.5, CurveType::Sine);
+    let index = lane.move_point(1, 3.0, 0.1).unwrap();
+    assert_eq!(index, 2);
+    assert_eq!(times(&lane), vec![0.0, 2.0, 3.0]);
+    assert_eq!(lane.points[2].curve, CurveType::Sine);
+
+    // Moving onto another point replaces it
+    lane.move_point(0, 2.0, 0.9);
+    assert_eq!(times(&lane), vec![2.0, 3.0]);
+    assert_eq!(lane.points[0].value, 0.9);
+}
+
+#[test]
+fn delete_range_anchors_the_edges() {
+    let mut lane = lane(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0), (4.0, 0.0)]);
+    let before = (lane.get_value_at(0.5), lane.get_value_at(3.5));
+    lane.delete_range(0.5, 3.5);
+    assert_eq!(times(&lane), vec![0.0, 0.5, 3.5, 4.0]);
+    assert!(close(lane.get_value_at(0.25), 0.25));
+    assert!(close(lane.get_value_at(0.5), before.0));
+    assert!(close(lane.get_value_at(3.5), before.1));
+    assert!(close(lane.get_value_at(3.75), 0.25));
+
+    // Outside the lane: nothing happens
+    lane.delete_range(10.0, 11.0);
+    assert_eq!(lane.points.len(), 4);
+}
+
+#[test]
+fn delete_range_keeps_cut_curves() {
+    // One exponential segment 0..10, and one ending at 10..20
+    let mut lane = AutomationLane::new("gain".into());
+    lane.add_point(0.0, 0.0, CurveType::Exponential(4.0));
+    lane.add_point(10.0, 1.0, CurveType::Exponential(-3.0));
+    lane.add_point(20.0, 0.0, CurveType::Linear);
+    let original = lane.clone();
+
+    lane.delete_range(4.0, 13.0);
+    for i in 0..=200 {
+        let time = i as f64 * 0.1;
+        if (4.0..=13.0).contains(&time) { continue; }
+        let (want, got) = (original.get_value_at(time), lane.get_value_at(time));
+        assert!((want - got).abs() < 2e-3, "{} s: {} vs {}", time, got, want);
+    }
+
+    // Reported as rewritten up to the ends of both cut segments
+    assert_eq!(original.delete_span(4.0, 13.0), (0.0, 20.0));
+    assert_eq!(original.delete_span(10.0, 10.0), (10.0, 10.0));
+
+    // Cutting on a point keeps the segment leaving it as it was
+    let mut lane = original.clone();
+    lane.delete_range(10.0, 12.0);
+    assert_eq!(times(&lane)[0], 0.0);
+    assert_eq!(lane.points[0].curve, CurveType::Exponential(4.0));
+}
+
+#[test]
+fn scale_offset_clamps_selection() {
+    let mut lane = lane(&[(0.0, 0.2), (1.0, 0.4), (2.0, 0.6), (3.0, 0.8)]);
+    lane.scale_offset(1.0, 2.0, 2.0, 0.1);
+    let values: Vec<f32> = lane.points.iter().map(|p| p.value).collect();
+    assert!(close(values[0], 0.2));
+    assert!(close(values[1], 0.9));
+    assert!(close(values[2], 1.0));
+    assert!(close(values[3], 0.8));
+}
+
+#[test]
+fn copy_paste_with_time_shift() {
+    let source = lane(&[(1.0, 0.1), (2.0, 0.9), (5.0, 0.5)]);
+    let clip = source.copy_range(1.0, 2.0);
+    assert_eq!(clip.iter().map(|p| p.time).collect::<Vec<_>>(), vec![0.0, 1.0]);
+
+    let mut target = lane(&[(0.0, 0.0), (10.5, 0.3), (20.0, 1.0)]);
+    target.paste(&clip, 10.0);
+    // The point inside the pasted span is overwritten
+    assert_eq!(times(&target), vec![0.0, 10.0, 11.0, 20.0]);
+    assert_eq!(target.points[1].value, 0.1);
+
+    target.paste(&[] as &[AutomationPoint], 3.0);
+    assert_eq!(target.points.len(), 4);
+}
+
+#[test]
+fn thin_removes_redundant_points() {
+    // Points on a straight line collapse to the ends
+    let stream: Vec<(f64, f32)> = (0..=100).map(|i| (i as f64 * 0.01, i as f32 * 0.01)).collect();
+    let mut line = lane(&stream);
+    line.thin(0.001);
+    assert_eq!(times(&line), vec![0.0, 1.0]);
+
+    // A corner survives
+    let mut corner = lane(&[(0.0, 0.0), (0.5, 0.5), (1.0, 1.0), (1.5, 0.5), (2.0, 0.0)]);
+    corner.thin(0.01);
+    assert_eq!(times(&corner), vec![0.0, 1.0, 2.0]);
+}
+
+#[test]
+fn thin_keeps_curved_segments() {
+    let mut lane = lane(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]);
+    lane.points[1].curve = CurveType::Sine;
+    lane.thin(0.5);
+    assert_eq!(times(&lane), vec![0.0, 1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn from_stream_follows_the_recording() {
+    let stream: Vec<(f64, f32)> = (0..=200)
+        .map(|i| {
+            let t = i as f64 * 0.01;
+            (t, (t as f32 * 3.0).sin() * 0.5 + 0.5)
+        })
+        .collect();
+    let lane = AutomationLane::from_stream("pan".into(), &stream, 0.01);
+    assert!(lane.points.len() < stream.len() / 4);
+    for &(t, v) in &stream {
+        assert!((lane.get_value_at(t) - v).abs() <= 0.01 + 1e-4);
+    }
+}
@@ -0,0 +1,82 @@
+use shared::{ParamAddress, ParamRange, ParamScale, Project, PROJECT_VERSION};
+
+#[test]
+fn paths_parse_and_print_back() {
+    let paths = [
+        "gain", "pan", "filter", "send/3",
+        "fx/0/mix", "fx/2/threshold", "fx/1/bands/4/freq", "fx/1/bands/0/enabled",
+        "synth/morph", "synth/cutoff", "synth/env/1/attack_ms", "synth/env/0/sustain_level",
+        "synth/lfo/2/rate", "synth/global_lfo/0/rate",
+        "granular/density", "granular/pitch_jitter",
+        "master/gain", "bus/7/gain", "bus/7/pan", "bus/7/fx/0/feedback",
+    ];
+    for path in paths {
+        let address = ParamAddress::parse(path).unwrap_or_else(|| panic!("{}", path));
+        assert_eq!(address.to_string(), path);
+        assert_eq!(ParamAddress::parse(&address.to_string()), Some(address));
+    }
+
+    // Aliases print as the canonical name
+    assert_eq!(ParamAddress::parse("volume").unwrap().to_string(), "gain");
+    assert_eq!(ParamAddress::parse("granular/size_ms").unwrap().to_string(), "granular/size");
+
+    for bad in ["", "gain/1", "send/x", "fx/0/nope", "fx/0/bands/1/slope", "synth/env/0", "bus/1/width", "master"] {
+        assert_eq!(ParamAddress::parse(bad), None, "{}", bad);
+    }
+    assert!(ParamAddress::parse("bus/1/gain").is_some_and(|a| !a.is_track_param()));
+}
+
+#[test]
+fn normalized_values_round_trip() {
+    let ranges = [
+        ParamRange::new(-1.0, 1.0, 0.0, ParamScale::Linear, ""),
+        ParamRange::new(20.0, 20000.0, 1000.0, ParamScale::Exponential, "Hz"),
+        ParamRange::new(-60.0, 6.0, 0.0, ParamScale::Decibel, "dB"),
+    ];
+    for range in ranges {
+        for i in 1..=20 {
+            let n = i as f32 / 20.0;
+            let back = range.normalize(range.denormalize(n));
+            assert!((back - n).abs() < 1e-4, "{:?} {} {}", range.scale, n, back);
+        }
+        assert_eq!(range.denormalize(-1.0), range.denormalize(0.0));
+        assert_eq!(range.denormalize(2.0), range.max);
+        assert_eq!(range.denormalize(f32::NAN), range.denormalize(0.0));
+        assert_eq!(range.normalize(range.max * 10.0), 1.0);
+    }
+
+    let db = ParamAddress::TrackGain.range();
+    assert_eq!(db.denormalize(0.0), f32::NEG_INFINITY); // Bottom is silence
+    assert_eq!(db.normalize(-200.0), 0.0);
+
+    let stepped = ParamRange::new(0.0, 3.0, 0.0, ParamScale::Stepped, "");
+    assert_eq!(stepped.denormalize(0.4), 1.0);
+    let toggle = ParamRange::new(0.0, 1.0, 0.0, ParamScale::Toggle, "");
+    assert_eq!((toggle.denormalize(0.49), toggle.denormalize(0.5)), (0.0, 1.0));
+}
+
+#[test]
+fn old_track_lanes_are_normalized_once() {
+    // Saved before lanes were normalized: no version, raw values
+    let lane = |target: &str, value: f32| {
+        format!(r#"{{ "target": "{}", "points": [{{ "time": 0.0, "value": {}, "curve": "Linear" }}] }}"#, target, value)
+    };
+    let lanes = [lane("pan", 0.0), lane("filter", -1.0), lane("volume", 1.0), lane("fx/0/mix", 0.3)].join(",");
+    let json = format!(r#"{{
+        "name": "old", "tempo": 120.0,
+        "tracks": [{{ "id": 0, "name": "t", "gain_db": 0.0, "pan": 0.0, "muted": false, "soloed": false,
+                      "clips": [], "effects": [], "automation": [{}] }}]
+    }}"#, lanes);
+
+    let loaded = Project::from_json(&json).unwrap();
+    assert_eq!(loaded.version, PROJECT_VERSION);
+    let values = |project: &Project| -> Vec<f32> {
+        project.tracks[0].automation.iter().map(|l| l.points[0].value).collect()
+    };
+    let unity = ParamAddress::TrackGain.range().normalize(0.0);
+    assert_eq!(values(&loaded), vec![0.5, 0.0, unity, 0.3]);
+
+    // Current files stay as they are
+    let again = Project::from_json(&loaded.to_json().unwrap()).unwrap();
+    assert_eq!(values(&again), values(&loaded));
+}
@@ -0,0 +1,53 @@
+use shared::{TempoChange, TempoMap};
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn constant_tempo_is_linear() {
+    let map = TempoMap::constant(120.0);
+    assert!(map.is_constant());
+    assert!(close(map.beat_at(3.0), 6.0));
+    assert!(close(map.seconds_at(6.0), 3.0));
+    assert_eq!(map.bpm_at(100.0), 120.0);
+}
+
+#[test]
+fn changes_bend_the_curve_at_their_beat() {
+    // 120 BPM for 8 beats (4 s), then 60 BPM, then 240 BPM from beat 12 (8 s)
+    let map = TempoMap::new(120.0, &[
+        TempoChange { beat: 12.0, bpm: 240.0 },
+        TempoChange { beat: 8.0, bpm: 60.0 },
+    ]);
+    assert!(!map.is_constant());
+    for (beat, seconds) in [(0.0, 0.0), (4.0, 2.0), (8.0, 4.0), (10.0, 6.0), (12.0, 8.0), (16.0, 9.0)] {
+        assert!(close(map.seconds_at(beat), seconds), "beat {}", beat);
+        assert!(close(map.beat_at(seconds), beat), "{} s", seconds);
+    }
+    assert_eq!(map.bpm_at(7.99), 120.0);
+    assert_eq!(map.bpm_at(8.0), 60.0);
+    assert_eq!(map.bpm_at(20.0), 240.0);
+}
+
+#[test]
+fn beats_and_seconds_round_trip() {
+    let map = TempoMap::new(97.0, &[TempoChange { beat: 3.5, bpm: 141.0 }, TempoChange { beat: 9.25, bpm: 73.0 }]);
+    for i in 0..200 {
+        let beat = i as f64 * 0.1;
+        assert!(close(map.beat_at(map.seconds_at(beat)), beat), "beat {}", beat);
+    }
+}
+
+#[test]
+fn bad_changes_fall_back() {
+    // A change at beat 0 replaces the start tempo, broken values are ignored or clamped
+    let map = TempoMap::new(f32::NAN, &[
+        TempoChange { beat: 0.0, bpm: 90.0 },
+        TempoChange { beat: f64::NAN, bpm: 300.0 },
+        TempoChange { beat: 4.0, bpm: 0.0 },
+    ]);
+    assert_eq!(map.bpm_at(0.0), 90.0);
+    assert_eq!(map.bpm_at(4.0), 1.0);
+    assert_eq!(TempoMap::constant(f32::INFINITY), TempoMap::default());
+}